@@ -0,0 +1,806 @@
+//! An entry point for embedding the compiler.
+//!
+//! [`compile`] takes sources that are already in memory and returns the
+//! compiled library together with its diagnostics as plain values. Unlike
+//! [`crate::cli::run`], it never reads files or writes to stdout or stderr.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::compat::{self, CompatChange};
+use crate::compiler::{self, Compiler, ReferenceKind, Referent};
+use crate::completion::{self, Completion};
+use crate::decomposition::{self, DecomposedDeclaration, Decomposition};
+use crate::diagnostics::{self, Error, ErrorKind, Label};
+use crate::experimental_flags::ExperimentalFlags;
+use crate::flat_ast::Root;
+use crate::formatter;
+use crate::hover::{self, Hover};
+use crate::json_generator::JsonRoot;
+use crate::lexer::Lexer;
+use crate::linter::{self, LintOptions};
+use crate::names::OwnedQualifiedName;
+use crate::outline::{self, OutlineSymbol};
+use crate::parser::Parser;
+use crate::projection;
+use crate::raw_ast;
+use crate::rename::{self};
+use crate::reporter::{Reporter, json_range};
+use crate::semantic_tokens::{self, SemanticToken};
+use crate::source_file::{ColumnUnit, SourceFile};
+use crate::source_span::SourceSpan;
+use crate::timeline::Timeline;
+use crate::token::TokenKind;
+use crate::versioning_types::{Version, VersionRange, VersionSelection};
+
+/// The `(filename, contents)` pairs to compile. The filenames are only used
+/// to identify the files in diagnostics.
+#[derive(Debug, Clone, Default)]
+pub struct Sources {
+    /// Files of the libraries the main library depends on, in any order.
+    pub dependencies: Vec<(String, String)>,
+    /// Files of the library being compiled.
+    pub library: Vec<(String, String)>,
+}
+
+impl Sources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_file(&mut self, filename: impl Into<String>, contents: impl Into<String>) {
+        self.library.push((filename.into(), contents.into()));
+    }
+
+    pub fn add_dependency_file(
+        &mut self,
+        filename: impl Into<String>,
+        contents: impl Into<String>,
+    ) {
+        self.dependencies.push((filename.into(), contents.into()));
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CompileOptions {
+    pub version_selection: VersionSelection,
+    pub experimental_flags: ExperimentalFlags,
+    /// Treat warnings as errors, like `--werror`.
+    pub werror: bool,
+    /// Don't warn about references to deprecated elements, like
+    /// `--allow-deprecated`.
+    pub allow_deprecated: bool,
+    /// JSON IR of already-compiled dependency libraries, like
+    /// `--dependency-ir`.
+    pub dependency_irs: Vec<serde_json::Value>,
+    /// The directories `--library-path` searched for each library it didn't
+    /// find, noted on errors about unknown libraries.
+    pub searched_library_paths: HashMap<String, Vec<String>>,
+}
+
+/// A range in a source file, with 1-based lines and 0-based characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub filename: String,
+    pub start_line: usize,
+    pub start_char: usize,
+    pub end_line: usize,
+    pub end_char: usize,
+}
+
+impl Location {
+    pub(crate) fn new(span: &SourceSpan<'_>) -> Self {
+        let (start_line, start_char, end_line, end_char) = json_range(span);
+        Self {
+            filename: span.source_file.filename().to_string(),
+            start_line,
+            start_char,
+            end_line,
+            end_char,
+        }
+    }
+}
+
+/// An owned copy of a [`diagnostics::Diagnostic`], which outlives the sources
+/// it refers to.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub error: Error,
+    pub message: String,
+    pub location: Option<Location>,
+    /// Secondary locations, each with its own message.
+    pub labels: Vec<(String, Location)>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    fn new(diag: &diagnostics::Diagnostic<'_>) -> Self {
+        Self {
+            error: diag.def.clone(),
+            message: diag.message.clone(),
+            location: diag.span.as_ref().map(Location::new),
+            labels: diag
+                .labels
+                .iter()
+                .map(|label| (label.message.clone(), Location::new(&label.span)))
+                .collect(),
+            notes: diag.notes.clone(),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.error.kind()
+    }
+
+    /// The `fi-NNNN` identifier of the diagnostic.
+    pub fn id(&self) -> String {
+        self.error.format_id().to_string()
+    }
+
+    /// The diagnostic with its locations found again in `source_files`, so
+    /// that a [`Reporter`] can render it. Locations in other files, like the
+    /// generated one, are left out.
+    pub(crate) fn to_reported<'a>(
+        &self,
+        source_files: &'a [SourceFile],
+    ) -> diagnostics::Diagnostic<'a> {
+        diagnostics::Diagnostic {
+            def: self.error.clone(),
+            message: self.message.clone(),
+            span: self
+                .location
+                .as_ref()
+                .and_then(|location| span_at(source_files, location)),
+            labels: self
+                .labels
+                .iter()
+                .filter_map(|(message, location)| {
+                    Some(Label::new(
+                        span_at(source_files, location)?,
+                        message.clone(),
+                    ))
+                })
+                .collect(),
+            notes: self.notes.clone(),
+        }
+    }
+}
+
+/// The span of `source_files` at `location`.
+fn span_at<'a>(source_files: &'a [SourceFile], location: &Location) -> Option<SourceSpan<'a>> {
+    let source = source_files
+        .iter()
+        .find(|s| s.filename() == location.filename)?;
+    let start = source.offset_at(
+        location.start_line,
+        location.start_char + 1,
+        ColumnUnit::Byte,
+    )?;
+    let end = source.offset_at(location.end_line, location.end_char + 1, ColumnUnit::Byte)?;
+    Some(SourceSpan::new(source.data().get(start..end)?, source))
+}
+
+/// A place where the library refers to a declaration, a member or another
+/// library.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub location: Location,
+    pub kind: ReferenceKind,
+    pub referent: Referent,
+}
+
+impl Reference {
+    fn new(reference: &compiler::Reference<'_>) -> Self {
+        Self {
+            location: Location::new(&reference.span),
+            kind: reference.kind,
+            referent: reference.referent.clone(),
+        }
+    }
+}
+
+/// A replacement for the text in a range of a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub location: Location,
+    pub new_text: String,
+}
+
+pub struct RenameOutput {
+    /// The edits to make: those in the renamed library in source order, then
+    /// those in each dependent library. Empty if the rename isn't possible.
+    pub edits: Vec<TextEdit>,
+    /// Why the rename isn't possible: an invalid new name, or one that
+    /// collides with another name. Problems already in the sources aren't
+    /// included.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub struct DecomposeOutput {
+    /// The library at every version, or `None` if compilation failed at
+    /// any of them.
+    pub decomposition: Option<Decomposition>,
+    /// Errors and warnings from every version, each reported once.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub struct CompatOutput {
+    /// The changes from the old library to the new one, or `None` if either
+    /// failed to compile.
+    pub changes: Option<Vec<CompatChange>>,
+    /// Errors and warnings from both compilations, each reported once.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub struct TimelineOutput {
+    /// Every declaration and member, even if compilation failed at some
+    /// version.
+    pub timeline: Timeline,
+    /// Errors and warnings from every version, each reported once.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub struct ProjectOutput {
+    /// Each file of the library as it is at the selected version, by
+    /// filename, or `None` if compilation failed.
+    pub files: Option<Vec<(String, String)>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub struct CompileOutput {
+    /// The compiled library, or `None` if compilation failed.
+    pub root: Option<Root>,
+    /// Errors and warnings, in the order they were reported.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Every reference in the library's files, in source order. These are
+    /// recorded even if compilation failed, for the names that resolved.
+    pub references: Vec<Reference>,
+}
+
+impl CompileOutput {
+    pub fn succeeded(&self) -> bool {
+        self.root.is_some()
+    }
+
+    /// The innermost reference at a 1-based line and 0-based character of a
+    /// file, like the `dep` in `dep.Thing` or the whole name elsewhere in it.
+    /// A position just past the end of a name counts as on it.
+    pub fn reference_at(
+        &self,
+        filename: &str,
+        line: usize,
+        character: usize,
+    ) -> Option<&Reference> {
+        self.references
+            .iter()
+            .filter(|r| {
+                let l = &r.location;
+                l.filename == filename
+                    && (l.start_line, l.start_char) <= (line, character)
+                    && (line, character) <= (l.end_line, l.end_char)
+            })
+            .min_by_key(|r| (r.location.end_line, r.location.end_char))
+    }
+
+    /// All references to a declaration, member or library.
+    pub fn references_to<'a>(
+        &'a self,
+        referent: &'a Referent,
+    ) -> impl Iterator<Item = &'a Reference> + 'a {
+        self.references
+            .iter()
+            .filter(move |r| r.referent == *referent)
+    }
+
+    /// The JSON IR of the compiled library, as written by `--json`.
+    pub fn json(&self) -> Option<JsonRoot> {
+        self.root.as_ref().map(JsonRoot::from)
+    }
+}
+
+/// Compiles `sources` into a library. Problems in the sources are returned as
+/// diagnostics; an `Err` means the options themselves were invalid, such as a
+/// malformed or duplicate dependency IR.
+pub fn compile(sources: &Sources, options: &CompileOptions) -> Result<CompileOutput, String> {
+    compile_then(sources, options, |_, _, _, _| {})
+}
+
+/// Compiles `sources` like [`compile`], then lints the library. Lint findings
+/// are returned as warnings among the diagnostics. The raw AST checks run
+/// even if compilation fails.
+pub fn lint(
+    sources: &Sources,
+    options: &CompileOptions,
+    lint_options: &LintOptions,
+) -> Result<CompileOutput, String> {
+    compile_then(sources, options, |files, root, source_files, compiler| {
+        linter::lint(files, root, source_files, compiler.reporter, lint_options)
+    })
+}
+
+/// Compiles `sources` like [`compile`], then summarizes the declaration or
+/// member at a 1-based `line` and 0-based byte `character` of `filename`:
+/// either its name, or a reference to it. Returns `None` if there is nothing
+/// there, such as whitespace, a keyword or a builtin type.
+pub fn hover(
+    sources: &Sources,
+    options: &CompileOptions,
+    filename: &str,
+    line: usize,
+    character: usize,
+) -> Result<Option<Hover>, String> {
+    let mut result = None;
+    compile_then(sources, options, |_, _, _, compiler| {
+        result = hover::hover(compiler, filename, line, character);
+    })?;
+    Ok(result)
+}
+
+/// Compiles `sources` like [`compile`], then finds the edits that rename
+/// `name`, a declaration like `fuchsia.example/Color` or a member like
+/// `fuchsia.example/Color.RED`, to `new_name`: where it is declared, and where
+/// the library refers to it. Each of `dependents` is the sources of another
+/// library, with the library of `sources` among its dependencies; its
+/// references to `name` are edited too. Returns an `Err` if `name` isn't
+/// declared in `sources`.
+pub fn rename(
+    sources: &Sources,
+    dependents: &[Sources],
+    options: &CompileOptions,
+    name: &str,
+    new_name: &str,
+) -> Result<RenameOutput, String> {
+    let name = OwnedQualifiedName::parse(name);
+    let rename_in = |sources: &Sources| -> Result<Option<RenameOutput>, String> {
+        let mut result = None;
+        compile_then(sources, options, |_, _, _, compiler| {
+            let start = compiler.reporter.diagnostics().len();
+            let Some(spans) = rename::rename(compiler, &name, new_name) else {
+                return;
+            };
+            let edits = spans
+                .iter()
+                .map(|span| TextEdit {
+                    location: Location::new(span),
+                    new_text: new_name.to_string(),
+                })
+                .collect();
+            let diagnostics = compiler.reporter.diagnostics()[start..]
+                .iter()
+                .map(Diagnostic::new)
+                .collect();
+            result = Some(RenameOutput { edits, diagnostics });
+        })?;
+        Ok(result)
+    };
+    let mut output =
+        rename_in(sources)?.ok_or_else(|| format!("unknown declaration or member {}", name))?;
+    if !output.diagnostics.is_empty() {
+        return Ok(output);
+    }
+    for dependent in dependents {
+        // A library that doesn't import the declaration has nothing to edit.
+        let Some(dependent_output) = rename_in(dependent)? else {
+            continue;
+        };
+        for edit in dependent_output.edits {
+            if !output.edits.contains(&edit) {
+                output.edits.push(edit);
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Compiles `sources` at every version of the library's platform, and splits
+/// each declaration into the ranges of versions over which it doesn't change.
+/// If `options` select several versions of the library's platform, the
+/// library as compiled with them is the `LEGACY` range; other versions
+/// selected for it are ignored. Those for other platforms apply to every
+/// compilation.
+pub fn decompose(sources: &Sources, options: &CompileOptions) -> Result<DecomposeOutput, String> {
+    let source_files = source_files(sources);
+    let reporter = new_reporter(options);
+    let mut dep_files = parse_files(&source_files, &reporter);
+    let main_files: Vec<_> = dep_files
+        .split_off(sources.dependencies.len())
+        .into_iter()
+        .flatten()
+        .collect();
+    let dep_files: Vec<_> = dep_files.into_iter().flatten().collect();
+    let source_refs: Vec<&SourceFile> = source_files.iter().collect();
+
+    // Compiling with `options` covers the versions it selects, and finds the
+    // library's platform and where each of its declarations changes.
+    let mut compiler = new_compiler(&reporter, options)?;
+    let json = compiler
+        .compile(&main_files, &dep_files, &source_refs)
+        .ok()
+        .map(|root| JsonRoot::from(&root));
+    let mut diagnostics = Vec::new();
+    add_diagnostics(
+        &mut diagnostics,
+        reporter.diagnostics().iter().map(Diagnostic::new).collect(),
+    );
+    let Some(json) = json else {
+        return Ok(DecomposeOutput {
+            decomposition: None,
+            diagnostics,
+        });
+    };
+    let library = compiler.library_name.to_string();
+    let Some(platform) = compiler.platform.clone() else {
+        let range = VersionRange::new(Version::NEG_INF, Version::POS_INF);
+        let pieces = pieces(&json, |_, _| Some(range));
+        return Ok(DecomposeOutput {
+            decomposition: Some(Decomposition::new(None, None, pieces)),
+            diagnostics,
+        });
+    };
+
+    let selected = options.version_selection.lookup(&platform);
+    let legacy =
+        (selected == Version::LEGACY).then(|| options.version_selection.lookup_all(&platform));
+    let ranges = decomposition::ranges(&compiler.version_points, legacy.is_some());
+    let splits = &compiler.decl_splits;
+    let piece_of = |name: &str, range: VersionRange| {
+        splits
+            .get(name)
+            .map(|split| decomposition::piece(&split.points, &ranges, range))
+    };
+    let selected_range = ranges.iter().copied().find(|r| r.contains(selected));
+    let mut all_pieces = match selected_range {
+        Some(range) => pieces(&json, |name, decl| {
+            Some(piece_of(&decomposition::owner(&library, name, decl), range).unwrap_or(range))
+        }),
+        None => Vec::new(),
+    };
+    // Every other range only needs the declarations that change where it
+    // starts, and aren't covered by the selected range.
+    for &range in &ranges {
+        if range.contains(selected) {
+            continue;
+        }
+        let changed: HashSet<&String> = splits
+            .keys()
+            .filter(|name| {
+                piece_of(name, range)
+                    .is_some_and(|piece| piece.lower == range.lower && !piece.contains(selected))
+            })
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+        let compile_only = changed
+            .iter()
+            .flat_map(|name| std::iter::once(*name).chain(&splits[*name].references))
+            .cloned()
+            .collect();
+        let mut range_options = options.clone();
+        range_options.version_selection =
+            decomposition::selection_for(&options.version_selection, &platform, range);
+        let range_reporter = new_reporter(options);
+        let mut range_compiler = new_compiler(&range_reporter, &range_options)?;
+        range_compiler.compile_only = Some(compile_only);
+        let range_json = range_compiler
+            .compile(&main_files, &dep_files, &source_refs)
+            .ok()
+            .map(|root| JsonRoot::from(&root));
+        add_diagnostics(
+            &mut diagnostics,
+            range_reporter
+                .diagnostics()
+                .iter()
+                .map(Diagnostic::new)
+                .collect(),
+        );
+        let Some(range_json) = range_json else {
+            return Ok(DecomposeOutput {
+                decomposition: None,
+                diagnostics,
+            });
+        };
+        all_pieces.extend(pieces(&range_json, |name, decl| {
+            let owner = decomposition::owner(&library, name, decl);
+            changed
+                .contains(&owner)
+                .then(|| piece_of(&owner, range).unwrap_or(range))
+        }));
+    }
+    let decomposition = Decomposition::new(Some(platform), legacy, all_pieces);
+    Ok(DecomposeOutput {
+        decomposition: Some(decomposition),
+        diagnostics,
+    })
+}
+
+/// The declarations of `root` over the ranges `range_of` gives them, skipping
+/// those it gives none.
+fn pieces(
+    root: &JsonRoot,
+    range_of: impl Fn(&str, &serde_json::Value) -> Option<VersionRange>,
+) -> Vec<DecomposedDeclaration> {
+    decomposition::json_declarations(root)
+        .into_iter()
+        .filter_map(|(name, kind, json)| {
+            let range = range_of(&name, &json)?;
+            Some(DecomposedDeclaration {
+                name,
+                kind,
+                range,
+                json,
+            })
+        })
+        .collect()
+}
+
+/// One of the two libraries [`compat`] compares.
+#[derive(Clone, Copy)]
+pub enum CompatLibrary<'a> {
+    /// The sources, compiled with these options.
+    Compiled(&'a CompileOptions),
+    /// JSON IR from an earlier compilation, like a `--json` output.
+    Ir(&'a serde_json::Value),
+}
+
+/// Compiles or reads the `old` and then the `new` library, usually `sources`
+/// at different versions, and reports what changed between them. An `Err`
+/// means the options or the JSON IR were invalid.
+pub fn compat(
+    sources: &Sources,
+    old: CompatLibrary<'_>,
+    new: CompatLibrary<'_>,
+) -> Result<CompatOutput, String> {
+    let mut diagnostics = Vec::new();
+    let mut roots = Vec::new();
+    for library in [old, new] {
+        let root = match library {
+            CompatLibrary::Compiled(options) => {
+                let output = compile(sources, options)?;
+                add_diagnostics(&mut diagnostics, output.diagnostics);
+                output.root
+            }
+            CompatLibrary::Ir(ir) => {
+                let reporter = Reporter::new();
+                let mut compiler = Compiler::new(&reporter);
+                compiler.add_dependency_ir(ir)?;
+                Some(
+                    compiler
+                        .precompiled_library(ir)
+                        .ok_or_else(|| "malformed JSON IR".to_string())?,
+                )
+            }
+        };
+        let Some(root) = root else {
+            return Ok(CompatOutput {
+                changes: None,
+                diagnostics,
+            });
+        };
+        roots.push(root);
+    }
+    Ok(CompatOutput {
+        changes: Some(compat::check(&roots[0], &roots[1])),
+        diagnostics,
+    })
+}
+
+/// Compiles `sources` at the version selected in `options`, and writes each
+/// file of the library with only what is available at that version.
+pub fn project(sources: &Sources, options: &CompileOptions) -> Result<ProjectOutput, String> {
+    let mut files = None;
+    let output = compile_then(sources, options, |main_files, root, _, compiler| {
+        if root.is_none() {
+            return;
+        }
+        files = Some(
+            main_files
+                .iter()
+                .map(|file| {
+                    let source = file.element.span().source_file;
+                    (
+                        source.filename().to_string(),
+                        projection::project(source, file, compiler),
+                    )
+                })
+                .collect(),
+        );
+    })?;
+    Ok(ProjectOutput {
+        files,
+        diagnostics: output.diagnostics,
+    })
+}
+
+/// Compiles `sources` at every version of the library's platform, and lists
+/// when each of its declarations and members is available. Like
+/// [`decompose`], the versions selected in `options` for the library's own
+/// platform are ignored.
+pub fn timeline(sources: &Sources, options: &CompileOptions) -> Result<TimelineOutput, String> {
+    let mut timeline = Timeline::new(String::new(), None);
+    let mut platform = None;
+    let mut points = BTreeSet::new();
+    let output = compile_then(sources, options, |_, _, _, compiler| {
+        timeline = Timeline::new(
+            compiler.library_name.to_string(),
+            compiler.platform.as_ref().map(|p| p.name().to_string()),
+        );
+        timeline.add(compiler);
+        platform = compiler.platform.clone();
+        points = compiler.version_points.clone();
+    })?;
+    let mut diagnostics = Vec::new();
+    add_diagnostics(&mut diagnostics, output.diagnostics);
+
+    if let Some(platform) = platform {
+        for range in decomposition::ranges(&points, false) {
+            let mut range_options = options.clone();
+            range_options.version_selection =
+                decomposition::selection_for(&options.version_selection, &platform, range);
+            let output = compile_then(sources, &range_options, |_, _, _, compiler| {
+                timeline.add(compiler);
+            })?;
+            add_diagnostics(&mut diagnostics, output.diagnostics);
+        }
+    }
+    timeline.finish();
+    Ok(TimelineOutput {
+        timeline,
+        diagnostics,
+    })
+}
+
+/// Adds each of `new` that isn't already in `diagnostics`.
+fn add_diagnostics(diagnostics: &mut Vec<Diagnostic>, new: Vec<Diagnostic>) {
+    for diag in new {
+        if !diagnostics
+            .iter()
+            .any(|d| d.message == diag.message && d.location == diag.location)
+        {
+            diagnostics.push(diag);
+        }
+    }
+}
+
+/// Offers completions at a 1-based `line` and 0-based byte `character` of
+/// `filename`. The sources are only parsed, not compiled, so this works on
+/// files with errors in them.
+pub fn complete(
+    sources: &Sources,
+    filename: &str,
+    line: usize,
+    character: usize,
+) -> Vec<Completion> {
+    let source_files: Vec<SourceFile> = sources
+        .dependencies
+        .iter()
+        .chain(&sources.library)
+        .map(|(filename, contents)| SourceFile::new(filename.clone(), contents.clone()))
+        .collect();
+    let Some(source) = source_files.iter().find(|s| s.filename() == filename) else {
+        return Vec::new();
+    };
+    let Some(offset) = source.offset_at(line, character + 1, ColumnUnit::Byte) else {
+        return Vec::new();
+    };
+    let reporter = Reporter::new();
+    let files: Vec<_> = parse_files(&source_files, &reporter)
+        .into_iter()
+        .flatten()
+        .collect();
+    completion::complete(source, &files, offset)
+}
+
+fn compile_then(
+    sources: &Sources,
+    options: &CompileOptions,
+    then: impl for<'a> FnOnce(&[raw_ast::File<'a>], Option<&Root>, &[&'a SourceFile], &Compiler<'_, 'a>),
+) -> Result<CompileOutput, String> {
+    let source_files = source_files(sources);
+    let reporter = new_reporter(options);
+    let mut dep_files = parse_files(&source_files, &reporter);
+    let main_files: Vec<_> = dep_files
+        .split_off(sources.dependencies.len())
+        .into_iter()
+        .flatten()
+        .collect();
+    let dep_files: Vec<_> = dep_files.into_iter().flatten().collect();
+
+    let mut compiler = new_compiler(&reporter, options)?;
+    let source_refs: Vec<&SourceFile> = source_files.iter().collect();
+    let root = compiler.compile(&main_files, &dep_files, &source_refs).ok();
+    let main_sources = &source_refs[sources.dependencies.len()..];
+    then(&main_files, root.as_ref(), main_sources, &compiler);
+
+    let diagnostics = reporter.diagnostics().iter().map(Diagnostic::new).collect();
+    let references = compiler.references.iter().map(Reference::new).collect();
+    Ok(CompileOutput {
+        root,
+        diagnostics,
+        references,
+    })
+}
+
+/// The dependencies' source files, then the library's.
+fn source_files(sources: &Sources) -> Vec<SourceFile> {
+    sources
+        .dependencies
+        .iter()
+        .chain(&sources.library)
+        .map(|(filename, contents)| SourceFile::new(filename.clone(), contents.clone()))
+        .collect()
+}
+
+fn new_reporter<'a>(options: &CompileOptions) -> Reporter<'a> {
+    let mut reporter = Reporter::new();
+    reporter.warnings_as_errors = options.werror;
+    reporter
+}
+
+/// A compiler set up with `options`. An `Err` means a dependency IR was
+/// invalid.
+fn new_compiler<'node, 'a>(
+    reporter: &'a Reporter<'a>,
+    options: &CompileOptions,
+) -> Result<Compiler<'node, 'a>, String> {
+    let mut compiler = Compiler::new(reporter);
+    compiler.version_selection = options.version_selection.clone();
+    compiler.experimental_flags = options.experimental_flags.clone();
+    compiler.allow_deprecated_references = options.allow_deprecated;
+    compiler.searched_library_paths = options.searched_library_paths.clone();
+    for ir in &options.dependency_irs {
+        compiler.add_dependency_ir(ir)?;
+    }
+    Ok(compiler)
+}
+
+/// Formats a single file in the canonical style. Syntax errors are returned
+/// as diagnostics.
+pub fn format(filename: &str, contents: &str) -> Result<String, Vec<Diagnostic>> {
+    let source = SourceFile::new(filename.to_string(), contents.to_string());
+    let reporter = Reporter::new();
+    formatter::format_source(&source, &reporter)
+        .ok_or_else(|| reporter.diagnostics().iter().map(Diagnostic::new).collect())
+}
+
+/// Classifies every token of a single file for semantic highlighting. The
+/// file is parsed to tell how its identifiers are used, as far as it parses;
+/// syntax errors are otherwise ignored.
+pub fn semantic_tokens(filename: &str, contents: &str) -> Vec<SemanticToken> {
+    let source = SourceFile::new(filename.to_string(), contents.to_string());
+    let reporter = Reporter::new();
+    let file = parse_files(std::slice::from_ref(&source), &reporter)
+        .pop()
+        .flatten();
+    semantic_tokens::semantic_tokens(&source, file.as_ref())
+}
+
+/// The outline of a single file: its library, its declarations and their
+/// members. Returns `None` if the file doesn't parse far enough to have a
+/// library declaration.
+pub fn outline(filename: &str, contents: &str) -> Option<OutlineSymbol> {
+    let source = SourceFile::new(filename.to_string(), contents.to_string());
+    let reporter = Reporter::new();
+    let file = parse_files(std::slice::from_ref(&source), &reporter)
+        .pop()
+        .flatten()?;
+    outline::outline(&file)
+}
+
+/// Parses every source file, so that each file's syntax errors are reported
+/// even when an earlier file has some. A file the parser can't recover from
+/// yields `None`; its errors are left in `reporter`, and the remaining files
+/// can still be compiled.
+pub(crate) fn parse_files<'a>(
+    source_files: &'a [SourceFile],
+    reporter: &'a Reporter<'a>,
+) -> Vec<Option<raw_ast::File<'a>>> {
+    source_files
+        .iter()
+        .map(|source| {
+            let mut lexer = Lexer::new(source, reporter);
+            let mut parser = Parser::new(&mut lexer, reporter);
+            parser.consume_token(TokenKind::StartOfFile)?;
+            parser.parse_file()
+        })
+        .collect()
+}
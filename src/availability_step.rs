@@ -1,26 +1,71 @@
+use std::collections::{BTreeSet, HashMap};
+
 use crate::compiler::Compiler;
+use crate::decomposition::DeclSplit;
 use crate::raw_ast;
 use crate::step::Step;
 use crate::versioning_types::Platform;
-use crate::versioning_types::{Availability, InheritStatus, InitArgs, Version};
+use crate::versioning_types::{
+    Availability, AvailabilityState, Ending, InheritStatus, InitArgs, Version,
+};
 
 use crate::diagnostics::Error;
-use crate::names::OwnedQualifiedName;
 use crate::raw_ast::AttributeProvenance;
 use crate::raw_ast::Layout;
 use crate::raw_ast::RawDecl;
 use crate::source_span::SourceSpan;
 use crate::token::TokenSubkind;
-pub struct AvailabilityStep;
+use crate::tree_visitor::{TreeVisitor, walk_attribute};
+/// A member of a declaration or layout: its attributes, name and pointer,
+/// and the layouts declared inline in its type.
+type Member<'a, 'src> = (
+    Option<&'a raw_ast::AttributeList<'src>>,
+    &'a str,
+    usize,
+    Vec<&'a Layout<'src>>,
+);
+
+/// The `@available` argument each bound of an element's availability comes
+/// from, on the element itself or on the ancestor it inherits the bound from.
+#[derive(Clone, Default)]
+struct Origins<'src> {
+    added: Option<Origin<'src>>,
+    deprecated: Option<Origin<'src>>,
+    removed: Option<Origin<'src>>,
+}
+
+#[derive(Clone)]
+struct Origin<'src> {
+    /// The argument's name: `removed` or `replaced` for the removal.
+    name: &'static str,
+    value: String,
+    span: SourceSpan<'src>,
+}
+
+impl<'src> Origin<'src> {
+    fn new(name: &'static str, (_, value, span): (&str, String, SourceSpan<'src>)) -> Self {
+        Self { name, value, span }
+    }
+}
+
+/// Computes the availability of every element of every version, before
+/// [`ConsumeStep`] consumes the declarations in the selected one.
+///
+/// [`ConsumeStep`]: crate::consume_step::ConsumeStep
+pub struct AvailabilityStep<'node, 'src> {
+    pub main_files: &'node [raw_ast::File<'src>],
+    pub dependency_files: &'node [raw_ast::File<'src>],
+}
 
-impl AvailabilityStep {
+impl AvailabilityStep<'_, '_> {
     fn compile_attr<'src>(
         compiler: &Compiler<'_, 'src>,
         attr: &raw_ast::Attribute<'src>,
         parent_avail: &Availability,
+        parent_origins: &Origins<'src>,
         decl_kind: &str, // e.g. "library", "struct", "modifier", "alias", etc
         item_name: &str,
-    ) -> Option<Availability> {
+    ) -> Option<(Availability, Origins<'src>)> {
         let mut added = None;
         let mut deprecated = None;
         let mut removed = None;
@@ -29,6 +74,7 @@ impl AvailabilityStep {
         let mut added_arg = None;
         let mut deprecated_arg = None;
         let mut removed_arg = None;
+        let mut replaced_arg = None;
 
         for arg in &attr.args {
             let arg_name = arg.name.as_ref().map(|n| n.data()).unwrap_or("value");
@@ -92,6 +138,7 @@ impl AvailabilityStep {
                         arg.element.span(),
                     );
                 }
+                replaced_arg = Some((arg_name, val_str.clone(), arg.element.span()));
                 if decl_kind == "modifier" {
                     compiler.reporter.fail(
                         Error::ErrInvalidModifierAvailableArgument(arg_name.into()),
@@ -205,15 +252,34 @@ impl AvailabilityStep {
                     InheritStatus::AfterParentRemoved => "after",
                     _ => "",
                 };
-                let parent_what = match status {
-                    InheritStatus::BeforeParentAdded => "added",
-                    InheritStatus::AfterParentDeprecated => "deprecated",
-                    InheritStatus::AfterParentRemoved => "removed",
-                    _ => "",
+                let (parent_what, parent_origin, parent_version) = match status {
+                    InheritStatus::BeforeParentAdded => {
+                        ("added", &parent_origins.added, parent_avail.added())
+                    }
+                    InheritStatus::AfterParentDeprecated => (
+                        "deprecated",
+                        &parent_origins.deprecated,
+                        parent_avail.deprecated(),
+                    ),
+                    InheritStatus::AfterParentRemoved => {
+                        ("removed", &parent_origins.removed, parent_avail.removed())
+                    }
+                    _ => ("", &None, None),
+                };
+                // A bound the parent has always comes from an argument, but
+                // fall back to the version in case it doesn't.
+                let (parent_name, parent_val, parent_location) = match parent_origin {
+                    Some(origin) => (
+                        origin.name,
+                        origin.value.clone(),
+                        origin.span.position_str(),
+                    ),
+                    None => (
+                        parent_what,
+                        parent_version.map(|v| v.to_string()).unwrap_or_default(),
+                        String::new(),
+                    ),
                 };
-                let parent_name = parent_what;
-                let parent_val = "unknown"; // We omit finding the actual parent value for simplicity
-                let parent_span = "unknown_location";
 
                 let span =
                     unsafe { std::mem::transmute::<SourceSpan<'_>, SourceSpan<'_>>(child_span) };
@@ -221,9 +287,9 @@ impl AvailabilityStep {
                     Error::ErrAvailabilityConflictsWithParent(
                         flyweights::FlyStr::new(format!("{}", &child_name)),
                         flyweights::FlyStr::new(format!("{}", &child_val)),
-                        flyweights::FlyStr::new(format!("{}", &parent_name)),
-                        flyweights::FlyStr::new(format!("{}", &parent_val)),
-                        flyweights::FlyStr::new(format!("{}", &parent_span)),
+                        flyweights::FlyStr::new(parent_name),
+                        flyweights::FlyStr::new(parent_val),
+                        flyweights::FlyStr::new(parent_location),
                         flyweights::FlyStr::new(format!("{}", &child_name)),
                         flyweights::FlyStr::new(format!("{}", &when)),
                         flyweights::FlyStr::new(format!("{}", &parent_what)),
@@ -233,22 +299,246 @@ impl AvailabilityStep {
             }
         };
 
-        report(added_arg, result.added);
-        report(deprecated_arg, result.deprecated);
-        report(removed_arg, result.removed);
+        report(added_arg.clone(), result.added);
+        report(deprecated_arg.clone(), result.deprecated);
+        report(removed_arg.clone(), result.removed);
+
+        // After a conflict, the element keeps its parent's availability.
+        if !result.is_ok() {
+            return None;
+        }
+        let removal = removed_arg
+            .map(|arg| Origin::new("removed", arg))
+            .or_else(|| replaced_arg.map(|arg| Origin::new("replaced", arg)));
+        let origins = Origins {
+            added: added_arg
+                .map(|arg| Origin::new("added", arg))
+                .or_else(|| parent_origins.added.clone()),
+            deprecated: deprecated_arg
+                .map(|arg| Origin::new("deprecated", arg))
+                .or_else(|| parent_origins.deprecated.clone()),
+            removed: removal.or_else(|| parent_origins.removed.clone()),
+        };
+        Some((initial, origins))
+    }
+
+    /// When several versions are selected, the library is compiled at
+    /// [`Version::LEGACY`]. An element removed at some point, rather than
+    /// replaced, is kept there if it is available at any selected version, so
+    /// that each element is as it is at the latest selected version that has
+    /// it.
+    fn select_legacy(avail: &mut Availability, selected: &BTreeSet<Version>) {
+        if selected.len() > 1
+            && avail.state() == AvailabilityState::Inherited
+            && avail.ending() == Ending::Removed
+            && selected.iter().any(|&v| avail.set().contains(v))
+        {
+            avail.set_legacy();
+        }
+    }
+
+    /// The members of `decl`, including the methods and composed protocols of
+    /// a protocol and the properties of a resource.
+    fn decl_members<'a, 'src>(decl: &RawDecl<'a, 'src>) -> Vec<Member<'a, 'src>> {
+        match *decl {
+            RawDecl::Struct(d) => Self::struct_members(d),
+            RawDecl::Table(d) => Self::table_members(d),
+            RawDecl::Union(d) => Self::union_members(d),
+            RawDecl::Enum(d) => Self::enum_members(d),
+            RawDecl::Bits(d) => Self::bits_members(d),
+            RawDecl::Protocol(d) => d
+                .composed_protocols
+                .iter()
+                .map(|c| {
+                    let name = c.protocol_name.components.last().map_or("", |n| n.data());
+                    (
+                        c.attributes.as_deref(),
+                        name,
+                        c.element.span().data.as_ptr() as usize,
+                        Vec::new(),
+                    )
+                })
+                .chain(d.methods.iter().map(|m| {
+                    let payloads = [&m.request_payload, &m.response_payload, &m.error_payload];
+                    let layouts = payloads
+                        .into_iter()
+                        .flatten()
+                        .flat_map(|layout| match layout {
+                            Layout::TypeConstructor(type_ctor) => Self::inline_layouts(type_ctor),
+                            _ => vec![layout],
+                        })
+                        .collect();
+                    (
+                        m.attributes.as_deref(),
+                        m.name.data(),
+                        m.element.span().data.as_ptr() as usize,
+                        layouts,
+                    )
+                }))
+                .collect(),
+            RawDecl::Service(d) => d
+                .members
+                .iter()
+                .map(|m| {
+                    (
+                        m.attributes.as_deref(),
+                        m.name.data(),
+                        m.element.span().data.as_ptr() as usize,
+                        Vec::new(),
+                    )
+                })
+                .collect(),
+            RawDecl::Resource(d) => d
+                .properties
+                .iter()
+                .map(|p| {
+                    (
+                        p.attributes.as_deref(),
+                        p.name.data(),
+                        p.element.span().data.as_ptr() as usize,
+                        Vec::new(),
+                    )
+                })
+                .collect(),
+            RawDecl::Type(d) => Self::layout_members(&d.layout),
+            _ => Vec::new(),
+        }
+    }
+
+    fn layout_members<'a, 'src>(layout: &'a Layout<'src>) -> Vec<Member<'a, 'src>> {
+        match layout {
+            Layout::Struct(l) => Self::struct_members(l),
+            Layout::Table(l) => Self::table_members(l),
+            Layout::Union(l) => Self::union_members(l),
+            Layout::Enum(l) => Self::enum_members(l),
+            Layout::Bits(l) => Self::bits_members(l),
+            Layout::TypeConstructor(_) => Vec::new(),
+        }
+    }
+
+    fn struct_members<'a, 'src>(d: &'a raw_ast::StructDeclaration<'src>) -> Vec<Member<'a, 'src>> {
+        d.members
+            .iter()
+            .map(|m| {
+                (
+                    m.attributes.as_deref(),
+                    m.name.data(),
+                    m.element.span().data.as_ptr() as usize,
+                    Self::inline_layouts(&m.type_ctor),
+                )
+            })
+            .collect()
+    }
+
+    fn table_members<'a, 'src>(d: &'a raw_ast::TableDeclaration<'src>) -> Vec<Member<'a, 'src>> {
+        d.members
+            .iter()
+            .map(|m| {
+                (
+                    m.attributes.as_deref(),
+                    m.name.as_ref().map(|n| n.data()).unwrap_or(""),
+                    m.element.span().data.as_ptr() as usize,
+                    m.type_ctor.iter().flat_map(Self::inline_layouts).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn union_members<'a, 'src>(d: &'a raw_ast::UnionDeclaration<'src>) -> Vec<Member<'a, 'src>> {
+        d.members
+            .iter()
+            .map(|m| {
+                (
+                    m.attributes.as_deref(),
+                    m.name.as_ref().map(|n| n.data()).unwrap_or(""),
+                    m.element.span().data.as_ptr() as usize,
+                    m.type_ctor.iter().flat_map(Self::inline_layouts).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn enum_members<'a, 'src>(d: &'a raw_ast::EnumDeclaration<'src>) -> Vec<Member<'a, 'src>> {
+        d.members
+            .iter()
+            .map(|m| {
+                (
+                    m.attributes.as_deref(),
+                    m.name.data(),
+                    m.element.span().data.as_ptr() as usize,
+                    Vec::new(),
+                )
+            })
+            .collect()
+    }
+
+    fn bits_members<'a, 'src>(d: &'a raw_ast::BitsDeclaration<'src>) -> Vec<Member<'a, 'src>> {
+        d.members
+            .iter()
+            .map(|m| {
+                (
+                    m.attributes.as_deref(),
+                    m.name.data(),
+                    m.element.span().data.as_ptr() as usize,
+                    Vec::new(),
+                )
+            })
+            .collect()
+    }
+
+    /// The layouts declared inline in `type_ctor`, like the struct in
+    /// `vector<struct { ... }>`.
+    fn inline_layouts<'a, 'src>(
+        type_ctor: &'a raw_ast::TypeConstructor<'src>,
+    ) -> Vec<&'a Layout<'src>> {
+        let mut layouts = match &type_ctor.layout {
+            raw_ast::LayoutParameter::Inline(layout) => vec![&**layout],
+            raw_ast::LayoutParameter::Type(inner) => Self::inline_layouts(inner),
+            _ => Vec::new(),
+        };
+        layouts.extend(type_ctor.parameters.iter().flat_map(Self::inline_layouts));
+        layouts
+    }
+
+    /// The element of a layout declared inline, which is consumed as a
+    /// declaration of its own when it is a method's payload.
+    fn layout_ptr(layout: &Layout<'_>) -> Option<usize> {
+        let element = match layout {
+            Layout::Struct(l) => &l.element,
+            Layout::Table(l) => &l.element,
+            Layout::Union(l) => &l.element,
+            Layout::Enum(l) => &l.element,
+            Layout::Bits(l) => &l.element,
+            Layout::TypeConstructor(_) => return None,
+        };
+        Some(element.span().data.as_ptr() as usize)
+    }
 
-        Some(initial)
+    /// The availability of an element that has no `@available` of its own,
+    /// or an invalid one: the parent's, where a removal is only inherited.
+    fn inherited(parent: &Availability) -> Availability {
+        let mut avail = Availability::new();
+        avail.init(InitArgs {
+            added: None,
+            deprecated: None,
+            removed: None,
+            replaced: false,
+        });
+        avail.inherit(parent);
+        avail
     }
 
     fn extract_availability<'src>(
         compiler: &Compiler<'_, 'src>,
         attrs: Option<&raw_ast::AttributeList<'src>>,
         parent_avail: &Availability,
+        parent_origins: &Origins<'src>,
         decl_kind: &str,
         has_library_avail: bool,
         item_name: &str,
-    ) -> Availability {
-        let mut avail = parent_avail.clone();
+    ) -> (Availability, Origins<'src>) {
+        let mut avail = Self::inherited(parent_avail);
+        let mut origins = parent_origins.clone();
         if let Some(attrs) = attrs {
             for attr in &attrs.attributes {
                 if attr.name.data() == "available"
@@ -263,363 +553,409 @@ impl AvailabilityStep {
                             .fail(Error::ErrMissingLibraryAvailability, attr.element.span());
                         // Continue to avoid multiple errors
                     }
-                    if let Some(parsed) =
-                        Self::compile_attr(compiler, attr, parent_avail, decl_kind, item_name)
-                    {
-                        avail = parsed;
+                    if let Some(parsed) = Self::compile_attr(
+                        compiler,
+                        attr,
+                        parent_avail,
+                        parent_origins,
+                        decl_kind,
+                        item_name,
+                    ) {
+                        (avail, origins) = parsed;
                     }
                 }
             }
         }
-        avail
+        (avail, origins)
+    }
+
+    /// Reports modifiers of the same kind, like `strict` and `flexible`, that
+    /// are both available at some version.
+    fn check_modifiers<'src>(
+        compiler: &Compiler<'_, 'src>,
+        decl: &RawDecl<'_, 'src>,
+        decl_avail: &Availability,
+        decl_origins: &Origins<'src>,
+        is_main: bool,
+        has_library_avail: bool,
+    ) {
+        decl.for_each_modifier_list(|modifiers| {
+            let mut by_kind: std::collections::HashMap<
+                u8,
+                Vec<(&raw_ast::Modifier<'src>, Availability)>,
+            > = std::collections::HashMap::new();
+
+            for modifier in modifiers {
+                // Only extract availability for main library modifiers so we don't report errors twice
+                let kind_str = if is_main {
+                    "modifier"
+                } else {
+                    "dependency_modifier"
+                };
+                let item_name = modifier.element.span().data;
+                let (avail, _) = Self::extract_availability(
+                    compiler,
+                    modifier.attributes.as_ref(),
+                    decl_avail,
+                    decl_origins,
+                    kind_str,
+                    has_library_avail || !is_main,
+                    item_name,
+                );
+
+                let kind = match modifier.subkind {
+                    TokenSubkind::Strict | TokenSubkind::Flexible => 1,
+                    TokenSubkind::Open | TokenSubkind::Ajar | TokenSubkind::Closed => 2,
+                    TokenSubkind::Resource => 3,
+                    _ => 0,
+                };
+
+                let same_kind = by_kind.entry(kind).or_default();
+                for (other_mod, other_avail) in same_kind.iter() {
+                    if avail.set().overlap(&other_avail.set()) {
+                        if modifier.subkind == other_mod.subkind {
+                            compiler.reporter.fail(
+                                Error::ErrDuplicateModifier(flyweights::FlyStr::new(
+                                    format!("{}", &modifier.element.span().data.to_string())
+                                        .into_boxed_str(),
+                                )),
+                                modifier.element.span(),
+                            );
+                        } else {
+                            compiler.reporter.fail(
+                                Error::ErrConflictingModifier(
+                                    flyweights::FlyStr::new(
+                                        format!("{}", &modifier.element.span().data.to_string())
+                                            .into_boxed_str(),
+                                    ),
+                                    flyweights::FlyStr::new(
+                                        format!("{}", &other_mod.element.span().data.to_string())
+                                            .into_boxed_str(),
+                                    ),
+                                ),
+                                modifier.element.span(),
+                            );
+                        }
+                        break;
+                    }
+                }
+                same_kind.push((modifier, avail));
+            }
+        });
     }
 }
 
-impl<'node, 'src> Step<'node, 'src> for AvailabilityStep {
+impl<'node, 'src> Step<'node, 'src> for AvailabilityStep<'node, 'src> {
     fn run(&mut self, compiler: &mut Compiler<'node, 'src>) {
-        let mut decl_availability = std::collections::HashMap::new();
-
-        // Need to parse Availability for each file's library_decl, but we only have `raw_decls`.
-        // We can just get it from `compiler.library_decl` and `compiler.all_files`?
-        // Wait, `all_files` isn't accessible. But `all_ast_files` is in `Compiler`? No it is not.
-        // Let's just avoid failing on dependencies and use unbounded for them.
-        let main_lib_prefix = compiler
-            .library_decl
-            .as_ref()
-            .map(|l| format!("{}/", l.path))
-            .unwrap_or_default();
-
-        // Wait, we can extract the library prefix from the declaration's name string!
-        // We'll just define the main library availability.
-        let mut platform_name = compiler
-            .library_decl
-            .as_ref()
+        // Each file can add attributes to the library declaration.
+        let library_attrs = self
+            .main_files
+            .iter()
+            .filter_map(|file| file.library_decl.as_deref())
+            .flat_map(|decl| decl.attributes.iter().flat_map(|list| &list.attributes));
+        let library_decl = self
+            .main_files
+            .iter()
+            .find_map(|file| file.library_decl.as_deref());
+        let library_path = library_decl.map(|l| l.path.to_string()).unwrap_or_default();
+        let mut platform_name = library_decl
             .map(|l| l.path.components[0].data().to_string())
             .unwrap_or_else(|| "unversioned".to_string());
 
-        let mut main_lib_avail = Availability::unbounded();
+        let mut library_avail = Availability::unbounded();
+        let mut library_origins = Origins::default();
         let mut has_library_avail = false;
-        if let Some(lib_decl) = &compiler.library_decl
-            && let Some(attrs) = &lib_decl.attributes
-        {
-            for attr in &attrs.attributes {
-                if attr.name.data() == "available" {
-                    has_library_avail = true;
-                    for arg in &attr.args {
-                        let arg_name = arg.name.as_ref().map(|n| n.data()).unwrap_or("value");
-                        let val_str = match &arg.value {
-                            raw_ast::Constant::Literal(lit) => lit.literal.value.clone(),
-                            raw_ast::Constant::Identifier(id) => id.identifier.to_string(),
-                            _ => "".to_string(),
-                        };
-                        if arg_name == "platform" {
-                            platform_name = val_str.trim_matches('"').to_string();
-                        }
-                    }
-                    if let Some(parsed) = Self::compile_attr(
-                        compiler,
-                        attr,
-                        &Availability::unbounded(),
-                        "library",
-                        &platform_name,
-                    ) {
-                        main_lib_avail = parsed;
-                    }
+        let mut library_points = BTreeSet::new();
+        for attr in library_attrs {
+            if attr.name.data() != "available" {
+                continue;
+            }
+            has_library_avail = true;
+            library_points.extend(available_points(attr));
+            for arg in &attr.args {
+                let arg_name = arg.name.as_ref().map(|n| n.data()).unwrap_or("value");
+                let val_str = match &arg.value {
+                    raw_ast::Constant::Literal(lit) => lit.literal.value.clone(),
+                    raw_ast::Constant::Identifier(id) => id.identifier.to_string(),
+                    _ => "".to_string(),
+                };
+                if arg_name == "platform" {
+                    platform_name = val_str.trim_matches('"').to_string();
                 }
             }
+            if let Some(parsed) = Self::compile_attr(
+                compiler,
+                attr,
+                &Availability::unbounded(),
+                &Origins::default(),
+                "library",
+                &platform_name,
+            ) {
+                (library_avail, library_origins) = parsed;
+            }
         }
-        let final_lib_avail = main_lib_avail;
-
         let platform = Platform::parse(&platform_name).unwrap_or_else(Platform::unversioned);
         let selected_version = compiler.version_selection.lookup(&platform);
+        let selected_versions = compiler.version_selection.lookup_all(&platform);
+        Self::select_legacy(&mut library_avail, &selected_versions);
+        if has_library_avail {
+            compiler.platform = Some(platform.clone());
+        }
+        let mut allow_unused_imports = !library_avail.set().contains(selected_version);
 
-        for (name, decl) in &compiler.raw_decls {
-            let is_main =
-                name.to_string().starts_with(&main_lib_prefix) || main_lib_prefix.is_empty();
-            let parent_avail = if is_main {
-                &final_lib_avail
-            } else {
-                &Availability::unbounded()
-            };
+        // Declarations that reuse a name are only consumed in the versions
+        // where they are available, so look at the files instead.
+        let mut version_points = BTreeSet::new();
+        let mut collector = VersionPointCollector {
+            points: &mut version_points,
+        };
+        for file in self.main_files {
+            collector.visit_file(file);
+        }
 
-            // We only report errors for the main library's elements compilation.
-            // But compile_attr always reports. To hack around this:
-            // What if we don't compile availability for dependencies?
-            // Actually, we must. But we don't have their lib_avail so they might fail if they inherit.
-            // unbounded() allows them to pass inherit.
-            let item_name = name.declaration();
-            let avail = if is_main {
-                Self::extract_availability(
-                    compiler,
-                    decl.attributes(),
-                    parent_avail,
-                    "declaration",
-                    has_library_avail,
-                    item_name,
-                )
+        // Dependencies are compiled at every version of their own; their
+        // elements only inherit from themselves.
+        let unbounded = Availability::unbounded();
+        let no_origins = Origins::default();
+        let main = self.main_files.iter().map(|file| (file, true));
+        let dependencies = self.dependency_files.iter().map(|file| (file, false));
+        for (file, is_main) in main.chain(dependencies) {
+            let (parent_avail, parent_origins) = if is_main {
+                (&library_avail, &library_origins)
             } else {
-                Self::extract_availability(
+                (&unbounded, &no_origins)
+            };
+            for decl in file.raw_decls() {
+                let item_name = decl.name().map(|n| n.data()).unwrap_or("");
+                let (mut decl_avail, decl_origins) = Self::extract_availability(
                     compiler,
                     decl.attributes(),
                     parent_avail,
-                    "dependency",
-                    true,
+                    parent_origins,
+                    if is_main { "declaration" } else { "dependency" },
+                    has_library_avail || !is_main,
                     item_name,
-                )
-            };
-            decl_availability.insert(name.to_string(), avail);
-        }
-        compiler.decl_availability = decl_availability
-            .clone()
-            .into_iter()
-            .map(|(k, v)| (OwnedQualifiedName::from(k), v))
-            .collect();
-
-        let mut any_decl_removed = false;
-
-        compiler.raw_decls.retain(|name, _| {
-            if let Some(avail) = decl_availability.get(&name.to_string())
-                && !avail.set().contains(selected_version)
-            {
-                any_decl_removed = true;
-                return false;
-            }
-            true
-        });
-
-        let mut allow_unused_imports = false;
-        if any_decl_removed || !final_lib_avail.set().contains(selected_version) {
-            allow_unused_imports = true;
-        }
-
-        let mut member_availability_additions = std::collections::HashMap::new();
-
-        // Validate modifiers
-        for (name, decl) in &compiler.raw_decls {
-            let decl_avail = decl_availability.get(&name.to_string()).unwrap();
-            decl.for_each_modifier_list(|modifiers| {
-                let mut by_kind: std::collections::HashMap<
-                    u8,
-                    Vec<(&raw_ast::Modifier<'src>, Availability)>,
-                > = std::collections::HashMap::new();
-
-                for modifier in modifiers {
-                    // Only extract availability for main library modifiers so we don't report errors twice
-                    let decl_is_main = name.to_string().starts_with(&main_lib_prefix)
-                        || main_lib_prefix.is_empty();
-                    let kind_str = if decl_is_main {
-                        "modifier"
-                    } else {
-                        "dependency_modifier"
-                    };
-                    let item_name = modifier.element.span().data;
-                    let avail = Self::extract_availability(
-                        compiler,
-                        modifier.attributes.as_ref(),
-                        decl_avail,
-                        kind_str,
-                        has_library_avail || !decl_is_main,
-                        item_name,
-                    );
-
-                    let kind = match modifier.subkind {
-                        TokenSubkind::Strict | TokenSubkind::Flexible => 1,
-                        TokenSubkind::Open | TokenSubkind::Ajar | TokenSubkind::Closed => 2,
-                        TokenSubkind::Resource => 3,
-                        _ => 0,
-                    };
-
-                    let same_kind = by_kind.entry(kind).or_default();
-                    for (other_mod, other_avail) in same_kind.iter() {
-                        if avail.set().overlap(&other_avail.set()) {
-                            if modifier.subkind == other_mod.subkind {
-                                compiler.reporter.fail(
-                                    Error::ErrDuplicateModifier(flyweights::FlyStr::new(
-                                        format!("{}", &modifier.element.span().data.to_string())
-                                            .into_boxed_str(),
-                                    )),
-                                    modifier.element.span(),
-                                );
-                            } else {
-                                compiler.reporter.fail(
-                                    Error::ErrConflictingModifier(
-                                        flyweights::FlyStr::new(
-                                            format!(
-                                                "{}",
-                                                &modifier.element.span().data.to_string()
-                                            )
-                                            .into_boxed_str(),
-                                        ),
-                                        flyweights::FlyStr::new(
-                                            format!(
-                                                "{}",
-                                                &other_mod.element.span().data.to_string()
-                                            )
-                                            .into_boxed_str(),
-                                        ),
-                                    ),
-                                    modifier.element.span(),
-                                );
-                            }
-                            break;
-                        }
+                );
+                if is_main {
+                    Self::select_legacy(&mut decl_avail, &selected_versions);
+                    if !decl_avail.set().contains(selected_version) {
+                        allow_unused_imports = true;
                     }
-                    same_kind.push((modifier, avail));
                 }
-            });
-
-            // Visit struct / table / union / enum / bits / protocol members and extract availability
-            let mut visit_member = |attributes: Option<&raw_ast::AttributeList<'src>>,
-                                    item_name: &str,
-                                    member_ptr: usize| {
-                let decl_is_main =
-                    name.to_string().starts_with(&main_lib_prefix) || main_lib_prefix.is_empty();
-                let kind_str = if decl_is_main {
-                    "member"
-                } else {
-                    "dependency_member"
-                };
-                let avail = Self::extract_availability(
+                compiler.raw_decl_availability.insert(
+                    decl.element().span().data.as_ptr() as usize,
+                    decl_avail.clone(),
+                );
+                Self::check_modifiers(
                     compiler,
-                    attributes,
-                    decl_avail,
-                    kind_str,
-                    has_library_avail || !decl_is_main,
-                    item_name,
+                    &decl,
+                    &decl_avail,
+                    &decl_origins,
+                    is_main,
+                    has_library_avail,
                 );
-                let platform = Platform::parse(compiler.library_name.versioning_platform())
-                    .unwrap_or_else(Platform::unversioned);
-                if !avail
-                    .set()
-                    .contains(compiler.version_selection.lookup(&platform))
-                {
-                    allow_unused_imports = true;
-                }
-                member_availability_additions.insert(member_ptr, avail);
-            };
 
-            match decl {
-                RawDecl::Struct(d) => {
-                    for m in &d.members {
-                        visit_member(
-                            m.attributes.as_deref(),
-                            m.name.data(),
-                            m.element.span().data.as_ptr() as usize,
-                        );
-                    }
-                }
-                RawDecl::Table(d) => {
-                    for m in &d.members {
-                        visit_member(
-                            m.attributes.as_deref(),
-                            m.name.as_ref().map(|n| n.data()).unwrap_or(""),
-                            m.element.span().data.as_ptr() as usize,
-                        );
-                    }
-                }
-                RawDecl::Union(d) => {
-                    for m in &d.members {
-                        visit_member(
-                            m.attributes.as_deref(),
-                            m.name.as_ref().map(|n| n.data()).unwrap_or(""),
-                            m.element.span().data.as_ptr() as usize,
-                        );
-                    }
-                }
-                RawDecl::Enum(d) => {
-                    for m in &d.members {
-                        visit_member(
-                            m.attributes.as_deref(),
-                            m.name.data(),
-                            m.element.span().data.as_ptr() as usize,
-                        );
-                    }
-                }
-                RawDecl::Bits(d) => {
-                    for m in &d.members {
-                        visit_member(
-                            m.attributes.as_deref(),
-                            m.name.data(),
-                            m.element.span().data.as_ptr() as usize,
-                        );
-                    }
-                }
-                RawDecl::Protocol(d) => {
-                    for m in &d.methods {
-                        visit_member(
-                            m.attributes.as_deref(),
-                            m.name.data(),
-                            m.element.span().data.as_ptr() as usize,
-                        );
-                    }
-                }
-                RawDecl::Service(d) => {
-                    for m in &d.members {
-                        visit_member(
-                            m.attributes.as_deref(),
-                            m.name.data(),
-                            m.element.span().data.as_ptr() as usize,
+                // Members of a layout declared inline in a member's type
+                // inherit from that member.
+                let mut pending = vec![(Self::decl_members(&decl), decl_avail, decl_origins)];
+                while let Some((members, parent_avail, parent_origins)) = pending.pop() {
+                    for (attributes, item_name, member_ptr, layouts) in members {
+                        let (mut avail, origins) = Self::extract_availability(
+                            compiler,
+                            attributes,
+                            &parent_avail,
+                            &parent_origins,
+                            if is_main {
+                                "member"
+                            } else {
+                                "dependency_member"
+                            },
+                            has_library_avail || !is_main,
+                            item_name,
                         );
-                    }
-                }
-                RawDecl::Type(d) => match &d.layout {
-                    Layout::Struct(l) => {
-                        for m in &l.members {
-                            visit_member(
-                                m.attributes.as_deref(),
-                                m.name.data(),
-                                m.element.span().data.as_ptr() as usize,
-                            );
-                        }
-                    }
-                    Layout::Table(l) => {
-                        for m in &l.members {
-                            visit_member(
-                                m.attributes.as_deref(),
-                                m.name.as_ref().map(|n| n.data()).unwrap_or(""),
-                                m.element.span().data.as_ptr() as usize,
-                            );
-                        }
-                    }
-                    Layout::Union(l) => {
-                        for m in &l.members {
-                            visit_member(
-                                m.attributes.as_deref(),
-                                m.name.as_ref().map(|n| n.data()).unwrap_or(""),
-                                m.element.span().data.as_ptr() as usize,
-                            );
+                        if is_main {
+                            Self::select_legacy(&mut avail, &selected_versions);
                         }
-                    }
-                    Layout::Enum(l) => {
-                        for m in &l.members {
-                            visit_member(
-                                m.attributes.as_deref(),
-                                m.name.data(),
-                                m.element.span().data.as_ptr() as usize,
-                            );
+                        if !avail.set().contains(selected_version) {
+                            allow_unused_imports = true;
                         }
-                    }
-                    Layout::Bits(l) => {
-                        for m in &l.members {
-                            visit_member(
-                                m.attributes.as_deref(),
-                                m.name.data(),
-                                m.element.span().data.as_ptr() as usize,
-                            );
+                        compiler
+                            .member_availability
+                            .insert(member_ptr, avail.clone());
+                        for layout in layouts {
+                            if let Some(ptr) = Self::layout_ptr(layout) {
+                                compiler.raw_decl_availability.insert(ptr, avail.clone());
+                            }
+                            pending.push((
+                                Self::layout_members(layout),
+                                avail.clone(),
+                                origins.clone(),
+                            ));
                         }
                     }
-                    _ => {}
-                },
-                _ => {}
+                }
             }
         }
 
         if allow_unused_imports {
             compiler.allow_unused_imports = true;
         }
+        compiler.decl_splits = split_decls(self.main_files, &library_path, &library_points);
+        compiler.version_points = version_points;
+    }
+}
+
+/// The versions named by `attr` if it is an `@available` attribute or a
+/// modifier's availability.
+fn available_points(attr: &raw_ast::Attribute<'_>) -> Vec<Version> {
+    if attr.name.data() != "available"
+        && attr.provenance != AttributeProvenance::ModifierAvailability
+    {
+        return Vec::new();
+    }
+    attr.args
+        .iter()
+        .filter(|arg| {
+            let arg_name = arg.name.as_ref().map(|n| n.data()).unwrap_or("value");
+            matches!(arg_name, "added" | "deprecated" | "removed" | "replaced")
+        })
+        .filter_map(|arg| match &arg.value {
+            raw_ast::Constant::Literal(lit) => Version::parse(&lit.literal.value),
+            raw_ast::Constant::Identifier(id) => Version::parse(&id.identifier.to_string()),
+            _ => None,
+        })
+        .collect()
+}
 
-        for (k, v) in member_availability_additions {
-            compiler.member_availability.insert(k, v);
+/// Collects the versions named by every `@available` attribute and modifier
+/// availability, whether or not the element is in the selected version.
+struct VersionPointCollector<'p> {
+    points: &'p mut BTreeSet<Version>,
+}
+
+impl<'a> TreeVisitor<'a> for VersionPointCollector<'_> {
+    fn visit_attribute(&mut self, node: &raw_ast::Attribute<'a>) {
+        self.points.extend(available_points(node));
+    }
+}
+
+/// Collects what a declaration's compiled form can depend on: the versions
+/// named by the availability of it and its members, and every name it
+/// mentions, as the components of a compound identifier.
+#[derive(Default)]
+struct SplitCollector {
+    points: BTreeSet<Version>,
+    names: Vec<Vec<String>>,
+}
+
+impl SplitCollector {
+    fn visit_decl<'a>(&mut self, decl: &RawDecl<'_, 'a>) {
+        match *decl {
+            RawDecl::Struct(d) => self.visit_structdeclaration(d),
+            RawDecl::Enum(d) => self.visit_enumdeclaration(d),
+            RawDecl::Bits(d) => self.visit_bitsdeclaration(d),
+            RawDecl::Union(d) => self.visit_uniondeclaration(d),
+            RawDecl::Table(d) => self.visit_tabledeclaration(d),
+            RawDecl::Protocol(d) => self.visit_protocoldeclaration(d),
+            RawDecl::Service(d) => self.visit_servicedeclaration(d),
+            RawDecl::Resource(d) => self.visit_resourcedeclaration(d),
+            RawDecl::Const(d) => self.visit_constdeclaration(d),
+            RawDecl::Alias(d) => self.visit_aliasdeclaration(d),
+            RawDecl::Type(d) => self.visit_typedeclaration(d),
+        }
+    }
+
+    /// The qualified names `components` can refer to: a declaration of
+    /// `library`, possibly followed by a member, or one of another library.
+    fn candidates(library: &str, components: &[String]) -> Vec<String> {
+        let mut names = vec![format!("{}/{}", library, components[0])];
+        for split in 1..components.len() {
+            names.push(format!(
+                "{}/{}",
+                components[..split].join("."),
+                components[split]
+            ));
+        }
+        names
+    }
+}
+
+impl<'a> TreeVisitor<'a> for SplitCollector {
+    fn visit_attribute(&mut self, node: &raw_ast::Attribute<'a>) {
+        self.points.extend(available_points(node));
+        walk_attribute(self, node);
+    }
+
+    fn visit_compoundidentifier(&mut self, node: &raw_ast::CompoundIdentifier<'a>) {
+        let components: Vec<String> = node
+            .components
+            .iter()
+            .map(|c| c.data().to_string())
+            .collect();
+        if !components.is_empty() {
+            self.names.push(components);
+        }
+    }
+}
+
+/// Splits each named declaration of the main library, in every version,
+/// into the ranges over which it compiles the same way: it changes where it
+/// or one of its members is added, deprecated or removed, where the library
+/// is, and where any declaration it refers to, directly or not, changes.
+/// Declarations that reuse a name are split as one.
+fn split_decls(
+    main_files: &[raw_ast::File<'_>],
+    library: &str,
+    library_points: &BTreeSet<Version>,
+) -> HashMap<String, DeclSplit> {
+    let mut collected: HashMap<String, SplitCollector> = HashMap::new();
+    for decl in main_files.iter().flat_map(|file| file.raw_decls()) {
+        let Some(name) = decl.name() else {
+            continue;
+        };
+        let collector = collected
+            .entry(format!("{}/{}", library, name.data()))
+            .or_default();
+        collector.visit_decl(&decl);
+    }
+
+    let mut splits: HashMap<String, DeclSplit> = collected
+        .iter()
+        .map(|(name, collector)| {
+            let references = collector
+                .names
+                .iter()
+                .flat_map(|components| SplitCollector::candidates(library, components))
+                .filter(|candidate| candidate != name && collected.contains_key(candidate))
+                .collect();
+            let mut points = collector.points.clone();
+            points.extend(library_points);
+            (name.clone(), DeclSplit { points, references })
+        })
+        .collect();
+
+    // Follow references until nothing more is reached.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let names: Vec<String> = splits.keys().cloned().collect();
+        for name in names {
+            let references = splits[&name].references.clone();
+            let mut points = splits[&name].points.clone();
+            let mut reached = references.clone();
+            for reference in &references {
+                let split = &splits[reference];
+                points.extend(&split.points);
+                reached.extend(split.references.iter().cloned());
+            }
+            reached.remove(name.as_str());
+            let split = splits.get_mut(&name).unwrap();
+            if points.len() != split.points.len() || reached.len() != split.references.len() {
+                split.points = points;
+                split.references = reached;
+                changed = true;
+            }
         }
     }
+    splits
 }
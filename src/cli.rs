@@ -1,18 +1,23 @@
-use clap::{ArgAction, Parser as ClapParser};
-use std::collections::BTreeSet;
+use clap::{ArgAction, Args, Parser as ClapParser, Subcommand};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
+use crate::api::{self, CompatLibrary, CompileOptions, Sources, parse_files};
+use crate::compat::Compatibility;
 use crate::compiler::Compiler;
-use crate::experimental_flags::ExperimentalFlags;
+use crate::diagnostics::ErrorKind;
+use crate::experimental_flags::{ExperimentalFlag, ExperimentalFlags};
+use crate::formatter::format_source;
+use crate::index_json_generator::IndexRoot;
 use crate::json_generator::JsonRoot;
-use crate::lexer::Lexer;
-use crate::parser::Parser;
+use crate::library_path::{LibraryPath, LibrarySearch, library_header};
+use crate::linter::LintOptions;
+use crate::lsp;
 use crate::raw_ast;
 use crate::reporter::Reporter;
 use crate::source_file::SourceFile;
-use crate::token::TokenKind;
 use crate::versioning_types::{Platform, Version, VersionSelection};
 
 #[derive(ClapParser, Debug, Default)]
@@ -21,6 +26,14 @@ pub struct Cli {
     #[arg(long, value_name = "JSON_PATH")]
     pub json: Option<String>,
 
+    #[arg(long, value_name = "JSON_INDEX_PATH")]
+    pub json_index: Option<String>,
+
+    /// Compile every `--files` group as its own library, in dependency order,
+    /// writing `<DIR>/<library>.fidl.json` for each.
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: Option<String>,
+
     #[arg(long, value_name = "PLATFORM:VERSION[,VERSION]...")]
     pub available: Vec<String>,
 
@@ -33,9 +46,24 @@ pub struct Cli {
     #[arg(long, value_name = "FLAG_NAME")]
     pub experimental: Vec<String>,
 
+    /// Root directory to search for the sources of imported libraries that
+    /// aren't provided with `--files`. May be repeated.
+    #[arg(long, value_name = "DIR")]
+    pub library_path: Vec<String>,
+
+    /// JSON IR of an already-compiled dependency library, used instead of its
+    /// sources. May be repeated.
+    #[arg(long, value_name = "JSON_IR_PATH")]
+    pub dependency_ir: Vec<String>,
+
     #[arg(long)]
     pub werror: bool,
 
+    /// Don't warn about references to elements that are deprecated at the
+    /// selected version.
+    #[arg(long)]
+    pub allow_deprecated: bool,
+
     #[arg(long, value_name = "[text|json]", default_value = "text", value_parser(["text", "json"]))]
     pub format: String,
 
@@ -55,6 +83,418 @@ pub struct Cli {
 
     #[arg(long, action = ArgAction::Help, help = "Print help (see more with '--help')")]
     pub help: Option<bool>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print FIDL files in the canonical style.
+    Format(FormatArgs),
+    /// Report what changed between two versions of a FIDL library, and
+    /// whether each change breaks source or ABI compatibility.
+    Compat(CompatArgs),
+    /// Check a FIDL library against the style guide and API rubric.
+    Lint(LintArgs),
+    /// Run a language server over stdin and stdout.
+    Lsp,
+    /// Print a FIDL library as it is at one version, without the elements
+    /// that aren't available then or their `@available` attributes.
+    Project(ProjectArgs),
+    /// List when each declaration and member of a FIDL library is available,
+    /// and flag removals without a deprecation window.
+    Timeline(TimelineArgs),
+}
+
+#[derive(Args, Debug, Default)]
+pub struct FormatArgs {
+    /// Fail if any file isn't formatted, instead of printing the files.
+    #[arg(long)]
+    pub check: bool,
+
+    /// Rewrite the files that aren't formatted.
+    #[arg(short, long, conflicts_with = "check")]
+    pub in_place: bool,
+
+    #[arg(value_name = "FIDL_FILE", required = true)]
+    pub files: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct CompatArgs {
+    /// Versions to compile the old library at. May be repeated.
+    #[arg(long, value_name = "PLATFORM:VERSION[,VERSION]...")]
+    pub old_available: Vec<String>,
+
+    /// Versions to compile the new library at. May be repeated.
+    #[arg(long, value_name = "PLATFORM:VERSION[,VERSION]...")]
+    pub new_available: Vec<String>,
+
+    /// JSON IR of the old library, used instead of compiling the files.
+    #[arg(long, value_name = "JSON_IR_PATH")]
+    pub old_ir: Option<String>,
+
+    /// JSON IR of the new library, used instead of compiling the files.
+    #[arg(long, value_name = "JSON_IR_PATH")]
+    pub new_ir: Option<String>,
+
+    /// Root directory to search for the sources of imported libraries. May
+    /// be repeated.
+    #[arg(long, value_name = "DIR")]
+    pub library_path: Vec<String>,
+
+    /// JSON IR of an already-compiled dependency library. May be repeated.
+    #[arg(long, value_name = "JSON_IR_PATH")]
+    pub dependency_ir: Vec<String>,
+
+    #[arg(long, value_name = "[text|json]", default_value = "text", value_parser(["text", "json"]))]
+    pub format: String,
+
+    /// The files of the library, for whichever of the old and new libraries
+    /// isn't given as JSON IR.
+    #[arg(value_name = "FIDL_FILE")]
+    pub files: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct LintArgs {
+    /// Root directory to search for the sources of imported libraries. May
+    /// be repeated.
+    #[arg(long, value_name = "DIR")]
+    pub library_path: Vec<String>,
+
+    /// JSON IR of an already-compiled dependency library. May be repeated.
+    #[arg(long, value_name = "JSON_IR_PATH")]
+    pub dependency_ir: Vec<String>,
+
+    /// Turn off a check, by name or by `fi-NNNN` id. May be repeated.
+    #[arg(long, value_name = "CHECK")]
+    pub exclude_check: Vec<String>,
+
+    /// Allowed first component of the library name, replacing the default
+    /// `fuchsia`, `fidl` and `test`. May be repeated.
+    #[arg(long, value_name = "PREFIX")]
+    pub library_prefix: Vec<String>,
+
+    #[arg(long, value_name = "[text|json]", default_value = "text", value_parser(["text", "json"]))]
+    pub format: String,
+
+    /// The files of the library to lint.
+    #[arg(value_name = "FIDL_FILE", required = true)]
+    pub files: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct ProjectArgs {
+    /// The version to print the library at. May be repeated for other
+    /// platforms.
+    #[arg(long, value_name = "PLATFORM:VERSION", required = true)]
+    pub available: Vec<String>,
+
+    /// Root directory to search for the sources of imported libraries. May
+    /// be repeated.
+    #[arg(long, value_name = "DIR")]
+    pub library_path: Vec<String>,
+
+    /// JSON IR of an already-compiled dependency library. May be repeated.
+    #[arg(long, value_name = "JSON_IR_PATH")]
+    pub dependency_ir: Vec<String>,
+
+    /// Write each file to this directory instead of printing it.
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: Option<String>,
+
+    /// The files of the library.
+    #[arg(value_name = "FIDL_FILE", required = true)]
+    pub files: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct TimelineArgs {
+    /// Root directory to search for the sources of imported libraries. May
+    /// be repeated.
+    #[arg(long, value_name = "DIR")]
+    pub library_path: Vec<String>,
+
+    /// JSON IR of an already-compiled dependency library. May be repeated.
+    #[arg(long, value_name = "JSON_IR_PATH")]
+    pub dependency_ir: Vec<String>,
+
+    #[arg(long, value_name = "[markdown|json]", default_value = "markdown", value_parser(["markdown", "json"]))]
+    pub format: String,
+
+    /// The files of the library.
+    #[arg(value_name = "FIDL_FILE", required = true)]
+    pub files: Vec<String>,
+}
+
+/// Runs a subcommand, which doesn't take the `--files` groups.
+pub fn run_command(command: &Command) -> Result<(), String> {
+    match command {
+        Command::Format(args) => run_format(args),
+        Command::Compat(args) => run_compat(args),
+        Command::Lint(args) => run_lint(args),
+        Command::Lsp => lsp::run(std::io::stdin().lock(), std::io::stdout().lock()),
+        Command::Project(args) => run_project(args),
+        Command::Timeline(args) => run_timeline(args),
+    }
+}
+
+fn run_format(args: &FormatArgs) -> Result<(), String> {
+    let source_files = read_source_files(&args.files)?;
+    let mut unformatted = Vec::new();
+    for source in &source_files {
+        let reporter = Reporter::new();
+        let Some(formatted) = format_source(source, &reporter) else {
+            reporter.print_reports();
+            return Err(format!("Could not format {}", source.filename()));
+        };
+        if args.check {
+            if formatted != source.data() {
+                unformatted.push(source.filename());
+            }
+        } else if args.in_place {
+            if formatted != source.data() {
+                fs::write(source.filename(), formatted)
+                    .map_err(|e| format!("Error writing file {}: {}", source.filename(), e))?;
+            }
+        } else {
+            print!("{}", formatted);
+        }
+    }
+    if !unformatted.is_empty() {
+        return Err(format!("Not formatted: {}", unformatted.join(", ")));
+    }
+    Ok(())
+}
+
+fn run_compat(args: &CompatArgs) -> Result<(), String> {
+    let old_ir = args.old_ir.as_deref().map(read_ir).transpose()?;
+    let new_ir = args.new_ir.as_deref().map(read_ir).transpose()?;
+    let (sources, options) = if old_ir.is_some() && new_ir.is_some() {
+        (Sources::new(), CompileOptions::default())
+    } else if args.files.is_empty() {
+        return Err("No files provided".to_string());
+    } else {
+        read_sources(&args.library_path, &args.dependency_ir, &args.files)?
+    };
+    let old_options = CompileOptions {
+        version_selection: parse_available(&args.old_available)?,
+        ..options.clone()
+    };
+    let new_options = CompileOptions {
+        version_selection: parse_available(&args.new_available)?,
+        ..options
+    };
+    let output = api::compat(
+        &sources,
+        old_ir
+            .as_ref()
+            .map_or(CompatLibrary::Compiled(&old_options), CompatLibrary::Ir),
+        new_ir
+            .as_ref()
+            .map_or(CompatLibrary::Compiled(&new_options), CompatLibrary::Ir),
+    )?;
+    print_diagnostics(&sources, &output.diagnostics, args.format == "json")?;
+    let Some(changes) = output.changes else {
+        return Err("Compilation failed".to_string());
+    };
+
+    if args.format == "json" {
+        let json = serde_json::to_string_pretty(&changes).map_err(|e| e.to_string())?;
+        println!("{}", json);
+    } else {
+        for change in &changes {
+            println!("{}", change);
+        }
+    }
+    let breaking = changes
+        .iter()
+        .filter(|c| c.compatibility != Compatibility::Safe)
+        .count();
+    if breaking > 0 {
+        return Err(format!("Found {} breaking change(s)", breaking));
+    }
+    Ok(())
+}
+
+fn run_lint(args: &LintArgs) -> Result<(), String> {
+    let mut lint_options = LintOptions::default();
+    for check in &args.exclude_check {
+        lint_options.excluded.insert(check.parse()?);
+    }
+    if !args.library_prefix.is_empty() {
+        lint_options.library_prefixes = args.library_prefix.clone();
+    }
+
+    let (sources, options) = read_sources(&args.library_path, &args.dependency_ir, &args.files)?;
+    let output = api::lint(&sources, &options, &lint_options)?;
+    // The findings are printed like compiler diagnostics, to stderr in
+    // either format, but unlike them, any finding fails the command.
+    let source_files = source_files(&sources);
+    let reporter = Reporter::new();
+    for diag in &output.diagnostics {
+        reporter.report(diag.to_reported(&source_files));
+    }
+    print_reports(&reporter, args.format == "json");
+    let findings = output.diagnostics.len();
+    if findings > 0 {
+        return Err(format!("Found {} problem(s)", findings));
+    }
+    Ok(())
+}
+
+fn run_timeline(args: &TimelineArgs) -> Result<(), String> {
+    let (sources, options) = read_sources(&args.library_path, &args.dependency_ir, &args.files)?;
+    let output = api::timeline(&sources, &options)?;
+    print_diagnostics(&sources, &output.diagnostics, args.format == "json")?;
+    let timeline = &output.timeline;
+    if args.format == "json" {
+        let json = serde_json::to_string_pretty(timeline).map_err(|e| e.to_string())?;
+        println!("{}", json);
+    } else {
+        print!("{}", timeline.markdown());
+    }
+    let problems: usize = timeline.problems().map(|e| e.problems.len()).sum();
+    if problems > 0 {
+        return Err(format!("Found {} problem(s)", problems));
+    }
+    Ok(())
+}
+
+fn run_project(args: &ProjectArgs) -> Result<(), String> {
+    let (sources, mut options) =
+        read_sources(&args.library_path, &args.dependency_ir, &args.files)?;
+    options.version_selection = parse_available(&args.available)?;
+    let output = api::project(&sources, &options)?;
+    print_diagnostics(&sources, &output.diagnostics, false)?;
+    let Some(files) = output.files else {
+        return Err("Compilation failed".to_string());
+    };
+    if let Some(out_dir) = &args.out_dir {
+        let filenames: Vec<&str> = files.iter().map(|(f, _)| f.as_str()).collect();
+        for ((_, contents), name) in files.iter().zip(out_paths(&filenames)?) {
+            let path = Path::new(out_dir).join(name);
+            path.parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(&path, contents))
+                .map_err(|e| format!("Error writing file {}: {}", path.display(), e))?;
+        }
+        return Ok(());
+    }
+    for (filename, contents) in &files {
+        if files.len() > 1 {
+            print!("// {}\n{}", filename, contents);
+        } else {
+            print!("{}", contents);
+        }
+    }
+    Ok(())
+}
+
+/// Where to write each of `filenames` under `--out-dir`: its path relative to
+/// the deepest directory that has all of them, so that files with the same
+/// name in different directories stay apart.
+fn out_paths(filenames: &[&str]) -> Result<Vec<PathBuf>, String> {
+    let dirs: Vec<Vec<Component>> = filenames
+        .iter()
+        .map(|f| {
+            Path::new(f)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .components()
+                .collect()
+        })
+        .collect();
+    let common = dirs
+        .iter()
+        .skip(1)
+        .fold(dirs.first().map_or(0, Vec::len), |n, dir| {
+            n.min(dirs[0].iter().zip(dir).take_while(|(a, b)| a == b).count())
+        });
+    let mut written: HashMap<PathBuf, &str> = HashMap::new();
+    let mut paths = Vec::new();
+    for filename in filenames {
+        let path: PathBuf = Path::new(filename).components().skip(common).collect();
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(format!("Cannot write {} under --out-dir", filename));
+        }
+        if let Some(other) = written.insert(path.clone(), filename) {
+            return Err(format!(
+                "Both {} and {} would be written to {}",
+                other,
+                filename,
+                path.display()
+            ));
+        }
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Reads a library and the sources of its dependencies, found as for
+/// `--library-path`, for the [`api`] functions.
+fn read_sources(
+    library_path: &[String],
+    dependency_ir: &[String],
+    files: &[String],
+) -> Result<(Sources, CompileOptions), String> {
+    let cli = Cli {
+        library_path: library_path.to_vec(),
+        dependency_ir: dependency_ir.to_vec(),
+        ..Default::default()
+    };
+    let dependency_irs = read_dependency_irs(&cli)?;
+    let (mut groups, searched_library_paths) =
+        resolve_library_paths(&cli, &[files.to_vec()], &dependency_irs)?;
+    let main_filenames = groups.pop().unwrap_or_default();
+    let mut sources = Sources::new();
+    for source in read_source_files(&groups.concat())? {
+        sources.add_dependency_file(source.filename(), source.data());
+    }
+    for source in read_source_files(&main_filenames)? {
+        sources.add_file(source.filename(), source.data());
+    }
+    let options = CompileOptions {
+        dependency_irs,
+        searched_library_paths,
+        ..Default::default()
+    };
+    Ok((sources, options))
+}
+
+/// The files of `sources`, dependencies first, as the [`api`] compiles them.
+fn source_files(sources: &Sources) -> Vec<SourceFile> {
+    sources
+        .dependencies
+        .iter()
+        .chain(&sources.library)
+        .map(|(filename, contents)| SourceFile::new(filename.clone(), contents.clone()))
+        .collect()
+}
+
+/// Prints the diagnostics of an [`api`] function like the main command does,
+/// but to stderr, so that stdout only has the subcommand's output. Fails if
+/// any of them is an error.
+fn print_diagnostics(
+    sources: &Sources,
+    diagnostics: &[api::Diagnostic],
+    json_diagnostics: bool,
+) -> Result<(), String> {
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+    let source_files = source_files(sources);
+    let reporter = Reporter::new();
+    for diag in diagnostics {
+        reporter.report(diag.to_reported(&source_files));
+    }
+    print_reports(&reporter, json_diagnostics);
+    if diagnostics.iter().any(|d| d.kind() == ErrorKind::Error) {
+        return Err("Compilation failed".to_string());
+    }
+    Ok(())
 }
 
 pub fn run(cli: &Cli, source_managers: &[Vec<String>]) -> Result<(), String> {
@@ -65,11 +505,11 @@ pub fn run(cli: &Cli, source_managers: &[Vec<String>]) -> Result<(), String> {
 
     let json_path = &cli.json;
     let _warnings_as_errors = cli.werror;
-    let _format = &cli.format;
+    let json_diagnostics = cli.format == "json";
     let _expected_library_name = &cli.name;
     let mut _expected_platform: Option<String> = None;
     let mut _expected_version_added: Option<String> = None;
-    let mut version_selection = VersionSelection::new();
+    let version_selection = parse_available(&cli.available)?;
     let dep_file_path = &cli.depfile;
 
     if let Some(ref arg) = cli.versioned {
@@ -80,32 +520,25 @@ pub fn run(cli: &Cli, source_managers: &[Vec<String>]) -> Result<(), String> {
         }
     }
 
-    for arg in &cli.available {
-        let parts: Vec<&str> = arg.splitn(2, ':').collect();
-        if parts.len() != 2 {
-            return Err(format!("Invalid syntax for --available: {}", arg));
-        }
-        if let Some(platform) = Platform::parse(parts[0]) {
-            let mut versions = BTreeSet::new();
-            for v_str in parts[1].split(',') {
-                if let Some(v) = Version::parse(v_str) {
-                    versions.insert(v);
-                } else {
-                    return Err(format!("Invalid version in --available: {}", v_str));
-                }
-            }
-            if !version_selection.insert(platform, versions) {
-                return Err(format!("Duplicate platform in --available: {}", parts[0]));
-            }
-        } else {
-            return Err(format!("Invalid platform in --available: {}", parts[0]));
-        }
-    }
-
     if source_managers.is_empty() {
         return Err("No files provided".to_string());
     }
 
+    let dependency_irs = read_dependency_irs(cli)?;
+    let (source_managers, searched_library_paths) =
+        resolve_library_paths(cli, source_managers, &dependency_irs)?;
+
+    if let Some(out_dir) = &cli.out_dir {
+        return run_build(
+            cli,
+            &source_managers,
+            out_dir,
+            version_selection,
+            dependency_irs,
+            searched_library_paths,
+        );
+    }
+
     let mut sm = source_managers.to_vec();
     let main_filenames = sm.pop().unwrap();
     let mut dep_filenames = Vec::new();
@@ -120,56 +553,39 @@ pub fn run(cli: &Cli, source_managers: &[Vec<String>]) -> Result<(), String> {
     let mut filenames = dep_filenames.clone();
     filenames.extend(main_filenames.clone());
 
-    let mut source_files = Vec::new();
-    for filename in &filenames {
-        let content = match fs::read_to_string(filename) {
-            Ok(c) => c,
-            Err(e) => {
-                return Err(format!("Error reading file {}: {}", filename, e));
-            }
-        };
-        source_files.push(SourceFile::new(filename.to_string(), content));
-    }
+    let source_files = read_source_files(&filenames)?;
 
     let mut reporter = Reporter::new();
     reporter.warnings_as_errors = _warnings_as_errors;
-    let mut files = Vec::new();
-
-    for source in &source_files {
-        let mut lexer = Lexer::new(source, &reporter);
-        let mut parser = Parser::new(&mut lexer, &reporter);
-
-        parser
-            .consume_token(TokenKind::StartOfFile)
-            .expect("Failed to consume StartOfFile");
-
-        match parser.parse_file() {
-            Some(file) => files.push(file),
-            None => {
-                return Err(format!("Failed to parse file: {}", source.filename()));
-            }
-        }
-    }
+    let mut dep_files = parse_files(&source_files, &reporter);
+    let main_files: Vec<_> = dep_files
+        .split_off(dep_filenames.len())
+        .into_iter()
+        .flatten()
+        .collect();
+    let dep_files: Vec<_> = dep_files.into_iter().flatten().collect();
 
     let mut compiler = Compiler::new(&reporter);
     compiler.version_selection = version_selection;
-    let mut flags = ExperimentalFlags::new();
-    for f in &cli.experimental {
-        if let Ok(flag) = f.parse() {
-            flags.enable_flag(flag);
-        } else {
-            return Err(format!("Unknown experimental flag: {}", f));
-        }
+    let flags = parse_experimental_flags(cli)?;
+    if cli.json_index.is_some() && !flags.is_enabled(ExperimentalFlag::OutputIndexJson) {
+        return Err(
+            "--json-index requires --experimental output_index_json to be enabled".to_string(),
+        );
     }
     compiler.experimental_flags = flags;
+    compiler.allow_deprecated_references = cli.allow_deprecated;
+    for ir in &dependency_irs {
+        compiler.add_dependency_ir(ir)?;
+    }
+    compiler.searched_library_paths = searched_library_paths;
     let source_refs: Vec<&SourceFile> = source_files.iter().collect();
-    let (dep_files, main_files) = files.split_at(dep_filenames.len());
-    let json_root = match compiler.compile(main_files, dep_files, &source_refs) {
+    let json_root = match compiler.compile(&main_files, &dep_files, &source_refs) {
         Ok(root) => {
-            reporter.print_reports();
+            print_reports(&reporter, json_diagnostics);
 
             let mut provided_libraries = std::collections::BTreeSet::new();
-            for file in dep_files {
+            for file in &dep_files {
                 if let Some(decl) = &file.library_decl {
                     provided_libraries.insert(decl.path.to_string());
                 }
@@ -179,7 +595,7 @@ pub fn run(cli: &Cli, source_managers: &[Vec<String>]) -> Result<(), String> {
                 String,
                 std::collections::BTreeSet<String>,
             > = std::collections::HashMap::new();
-            for file in files.iter() {
+            for file in dep_files.iter().chain(&main_files) {
                 if let Some(decl) = &file.library_decl {
                     let lib_name = decl.path.to_string();
                     let entry = reachability.entry(lib_name).or_default();
@@ -229,7 +645,7 @@ pub fn run(cli: &Cli, source_managers: &[Vec<String>]) -> Result<(), String> {
             root
         }
         Err(e) => {
-            reporter.print_reports();
+            print_reports(&reporter, json_diagnostics);
             return Err(format!("Compilation failed: {}\n", e));
         }
     };
@@ -303,26 +719,362 @@ pub fn run(cli: &Cli, source_managers: &[Vec<String>]) -> Result<(), String> {
     let json_string = serde_json::to_string_pretty(&serialized_root).unwrap();
 
     if let Some(out_path) = json_path {
-        if let Some(p) = Path::new(&out_path).parent() {
-            fs::create_dir_all(p).unwrap_or(());
+        write_json_file(out_path, &json_string)?;
+    }
+
+    if let Some(index_path) = &cli.json_index {
+        let index = IndexRoot::from(&compiler);
+        write_json_file(index_path, &serde_json::to_string_pretty(&index).unwrap())?;
+    }
+
+    if let Some(dep_path) = dep_file_path {
+        let mut f = fs::File::create(dep_path).unwrap();
+        let outputs: Vec<&str> = [json_path, &cli.json_index]
+            .into_iter()
+            .filter_map(|p| p.as_deref())
+            .collect();
+        if !outputs.is_empty() {
+            let input_files = filenames
+                .iter()
+                .chain(&cli.dependency_ir)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(f, "{} : {}", outputs.join(" "), input_files).unwrap();
         }
-        let mut f = match fs::File::create(out_path) {
-            Ok(f) => f,
-            Err(_e) => {
-                return Err(format!("Could not open file: {}\n", out_path));
-            }
+    }
+
+    Ok(())
+}
+
+/// Compiles each `--files` group as its own library, in the topological order
+/// of their `using` declarations. Each library's JSON IR is written to
+/// `<out_dir>/<library>.fidl.json` and handed to the libraries that depend on
+/// it as a precompiled dependency, so no library is compiled twice.
+fn run_build(
+    cli: &Cli,
+    source_managers: &[Vec<String>],
+    out_dir: &str,
+    version_selection: VersionSelection,
+    external_irs: Vec<serde_json::Value>,
+    searched_library_paths: SearchedLibraryPaths,
+) -> Result<(), String> {
+    for (flag, set) in [
+        ("--json", cli.json.is_some()),
+        ("--json-index", cli.json_index.is_some()),
+        ("--name", cli.name.is_some()),
+        ("--versioned", cli.versioned.is_some()),
+    ] {
+        if set {
+            return Err(format!("{} cannot be combined with --out-dir", flag));
+        }
+    }
+    let flags = parse_experimental_flags(cli)?;
+
+    let groups = source_managers
+        .iter()
+        .map(|filenames| read_source_files(filenames))
+        .collect::<Result<Vec<_>, _>>()?;
+    let reporters: Vec<Reporter> = groups
+        .iter()
+        .map(|_| {
+            let mut reporter = Reporter::new();
+            reporter.warnings_as_errors = cli.werror;
+            reporter
+        })
+        .collect();
+    let mut libraries = Vec::new();
+    for (sources, reporter) in groups.iter().zip(&reporters) {
+        let files: Vec<_> = parse_files(sources, reporter)
+            .into_iter()
+            .flatten()
+            .collect();
+        let Some(name) = files
+            .iter()
+            .find_map(|f| f.library_decl.as_ref())
+            .map(|decl| decl.path.to_string())
+        else {
+            print_reports(reporter, cli.format == "json");
+            return Err(format!(
+                "Missing library declaration in {}",
+                sources.first().map_or("", |s| s.filename())
+            ));
         };
-        f.write_all(json_string.as_bytes()).unwrap();
-        f.write_all(b"\n").unwrap();
+        if libraries.iter().any(|(n, _)| *n == name) {
+            return Err(format!(
+                "Library {} is provided by more than one --files group",
+                name
+            ));
+        }
+        libraries.push((name, files));
     }
 
-    if let Some(dep_path) = dep_file_path {
+    let names: Vec<&str> = libraries.iter().map(|(n, _)| n.as_str()).collect();
+    let dependencies: Vec<BTreeSet<usize>> = libraries
+        .iter()
+        .map(|(_, files)| {
+            files
+                .iter()
+                .flat_map(|f| &f.using_decls)
+                .filter_map(|u| {
+                    let path = u.using_path.to_string();
+                    names.iter().position(|n| *n == path)
+                })
+                .collect()
+        })
+        .collect();
+    let order = topological_order(&names, &dependencies)?;
+
+    let mut compiled: Vec<Option<serde_json::Value>> = vec![None; libraries.len()];
+    let mut outputs = Vec::new();
+    for i in order {
+        let (name, files) = &libraries[i];
+        let reporter = &reporters[i];
+        let mut compiler = Compiler::new(reporter);
+        compiler.version_selection = version_selection.clone();
+        compiler.experimental_flags = flags.clone();
+        compiler.allow_deprecated_references = cli.allow_deprecated;
+        compiler.searched_library_paths = searched_library_paths.clone();
+        for ir in &external_irs {
+            compiler.add_dependency_ir(ir)?;
+        }
+        for dep in transitive_dependencies(i, &dependencies) {
+            if let Some(ir) = &compiled[dep] {
+                compiler.add_dependency_ir(ir)?;
+            }
+        }
+        let source_refs: Vec<&SourceFile> = groups[i].iter().collect();
+        let result = compiler.compile(files, &[], &source_refs);
+        print_reports(reporter, cli.format == "json");
+        let root = result.map_err(|e| format!("Compilation of {} failed: {}\n", name, e))?;
+
+        let serialized_root = JsonRoot::from(&root);
+        let out_path = Path::new(out_dir).join(format!("{}.fidl.json", name));
+        let out_path = out_path.to_string_lossy().to_string();
+        write_json_file(
+            &out_path,
+            &serde_json::to_string_pretty(&serialized_root).unwrap(),
+        )?;
+        compiled[i] = Some(serde_json::to_value(&serialized_root).unwrap());
+        outputs.push(out_path);
+    }
+
+    if let Some(dep_path) = &cli.depfile {
         let mut f = fs::File::create(dep_path).unwrap();
-        if let Some(jp) = json_path {
-            let input_files = filenames.join(" ");
-            writeln!(f, "{} : {}", jp, input_files).unwrap();
+        let input_files = source_managers
+            .iter()
+            .flatten()
+            .chain(&cli.dependency_ir)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(f, "{} : {}", outputs.join(" "), input_files).unwrap();
+    }
+
+    Ok(())
+}
+
+/// The directories searched for each library that couldn't be found.
+type SearchedLibraryPaths = HashMap<String, Vec<String>>;
+
+/// Adds a `--files` group for each imported library that isn't otherwise
+/// provided, using the sources found under `--library-path`, until every
+/// import is either resolved or known to be missing. Returns the groups (the
+/// found libraries first, so the last group is still the main library) and,
+/// for each missing library, the directories that were searched.
+fn resolve_library_paths(
+    cli: &Cli,
+    source_managers: &[Vec<String>],
+    dependency_irs: &[serde_json::Value],
+) -> Result<(Vec<Vec<String>>, SearchedLibraryPaths), String> {
+    let mut searched = HashMap::new();
+    if cli.library_path.is_empty() {
+        return Ok((source_managers.to_vec(), searched));
+    }
+    let library_path = LibraryPath::new(&cli.library_path);
+
+    let mut provided: HashSet<String> = dependency_irs
+        .iter()
+        .filter_map(|ir| ir.get("name")?.as_str().map(str::to_string))
+        .collect();
+    let mut imported = Vec::new();
+    let scan =
+        |filenames: &[String], provided: &mut HashSet<String>, imported: &mut Vec<String>| {
+            for source in read_source_files(filenames)? {
+                if let Some((name, usings)) = library_header(&source) {
+                    provided.insert(name);
+                    imported.extend(usings);
+                }
+            }
+            Ok::<_, String>(())
+        };
+    for group in source_managers {
+        scan(group, &mut provided, &mut imported)?;
+    }
+
+    let mut groups = Vec::new();
+    while let Some(library) = imported.pop() {
+        if provided.contains(&library) || searched.contains_key(&library) {
+            continue;
+        }
+        match library_path.find(&library)? {
+            LibrarySearch::Found(filenames) => {
+                provided.insert(library);
+                scan(&filenames, &mut provided, &mut imported)?;
+                groups.push(filenames);
+            }
+            LibrarySearch::NotFound(dirs) => {
+                searched.insert(library, dirs);
+            }
+        }
+    }
+    groups.extend(source_managers.iter().cloned());
+    Ok((groups, searched))
+}
+
+/// Orders libraries so that each comes after the libraries it depends on,
+/// keeping the command-line order where the graph allows.
+fn topological_order(
+    names: &[&str],
+    dependencies: &[BTreeSet<usize>],
+) -> Result<Vec<usize>, String> {
+    fn visit(
+        i: usize,
+        names: &[&str],
+        dependencies: &[BTreeSet<usize>],
+        path: &mut Vec<usize>,
+        done: &mut [bool],
+        order: &mut Vec<usize>,
+    ) -> Result<(), String> {
+        if done[i] {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|&p| p == i) {
+            let cycle: Vec<&str> = path[start..]
+                .iter()
+                .chain([&i])
+                .map(|&p| names[p])
+                .collect();
+            return Err(format!(
+                "There is an includes-cycle in libraries: {}",
+                cycle.join(" -> ")
+            ));
+        }
+        path.push(i);
+        for &dep in &dependencies[i] {
+            visit(dep, names, dependencies, path, done, order)?;
+        }
+        path.pop();
+        done[i] = true;
+        order.push(i);
+        Ok(())
+    }
+
+    let mut done = vec![false; names.len()];
+    let mut order = Vec::new();
+    for i in 0..names.len() {
+        visit(
+            i,
+            names,
+            dependencies,
+            &mut Vec::new(),
+            &mut done,
+            &mut order,
+        )?;
+    }
+    Ok(order)
+}
+
+fn transitive_dependencies(i: usize, dependencies: &[BTreeSet<usize>]) -> BTreeSet<usize> {
+    let mut result = BTreeSet::new();
+    let mut worklist: Vec<usize> = dependencies[i].iter().copied().collect();
+    while let Some(dep) = worklist.pop() {
+        if result.insert(dep) {
+            worklist.extend(&dependencies[dep]);
+        }
+    }
+    result
+}
+
+/// Parses `--available` arguments like `fuchsia:10,HEAD`.
+fn parse_available(args: &[String]) -> Result<VersionSelection, String> {
+    let mut version_selection = VersionSelection::new();
+    for arg in args {
+        let parts: Vec<&str> = arg.splitn(2, ':').collect();
+        if parts.len() != 2 {
+            return Err(format!("Invalid syntax for --available: {}", arg));
+        }
+        if let Some(platform) = Platform::parse(parts[0]) {
+            let mut versions = BTreeSet::new();
+            for v_str in parts[1].split(',') {
+                if let Some(v) = Version::parse(v_str) {
+                    versions.insert(v);
+                } else {
+                    return Err(format!("Invalid version in --available: {}", v_str));
+                }
+            }
+            if !version_selection.insert(platform, versions) {
+                return Err(format!("Duplicate platform in --available: {}", parts[0]));
+            }
+        } else {
+            return Err(format!("Invalid platform in --available: {}", parts[0]));
+        }
+    }
+    Ok(version_selection)
+}
+
+fn read_source_files(filenames: &[String]) -> Result<Vec<SourceFile>, String> {
+    filenames
+        .iter()
+        .map(|filename| {
+            fs::read_to_string(filename)
+                .map(|content| SourceFile::new(filename.to_string(), content))
+                .map_err(|e| format!("Error reading file {}: {}", filename, e))
+        })
+        .collect()
+}
+
+fn parse_experimental_flags(cli: &Cli) -> Result<ExperimentalFlags, String> {
+    let mut flags = ExperimentalFlags::new();
+    for f in &cli.experimental {
+        if let Ok(flag) = f.parse() {
+            flags.enable_flag(flag);
+        } else {
+            return Err(format!("Unknown experimental flag: {}", f));
         }
     }
+    Ok(flags)
+}
+
+fn read_dependency_irs(cli: &Cli) -> Result<Vec<serde_json::Value>, String> {
+    cli.dependency_ir.iter().map(|path| read_ir(path)).collect()
+}
 
+fn read_ir(ir_path: &str) -> Result<serde_json::Value, String> {
+    let content = fs::read_to_string(ir_path)
+        .map_err(|e| format!("Error reading file {}: {}", ir_path, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Error parsing JSON IR {}: {}", ir_path, e))
+}
+
+fn print_reports(reporter: &Reporter, json_diagnostics: bool) {
+    if json_diagnostics {
+        reporter.print_reports_json();
+    } else {
+        reporter.print_reports();
+    }
+}
+
+fn write_json_file(out_path: &str, json_string: &str) -> Result<(), String> {
+    if let Some(p) = Path::new(out_path).parent() {
+        fs::create_dir_all(p).unwrap_or(());
+    }
+    let mut f = match fs::File::create(out_path) {
+        Ok(f) => f,
+        Err(_e) => {
+            return Err(format!("Could not open file: {}\n", out_path));
+        }
+    };
+    f.write_all(json_string.as_bytes()).unwrap();
+    f.write_all(b"\n").unwrap();
     Ok(())
 }
@@ -0,0 +1,791 @@
+//! Compatibility between two versions of a library.
+//!
+//! [`check`] compares the compiled declarations of an old and a new version
+//! of a library, such as two `--available` selections of one source or two IR
+//! snapshots, and classifies each difference by what it breaks. Declarations
+//! are matched by name. Table and union members and protocol methods are
+//! matched by ordinal, since that is what peers see on the wire, and bits and
+//! enum members by name.
+//!
+//! A change to a declaration isn't reported again for every type that refers
+//! to it, except where it changes the layout of a struct.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::compiler::protocols::compute_method_ordinal;
+use crate::flat_ast::{
+    AliasDeclaration, BitsDeclaration, ConstDeclaration, Constant, DeclarationKind,
+    EnumDeclaration, ExperimentalResourceDeclaration, NewTypeDeclaration, ProtocolDeclaration,
+    ProtocolMethod, Root, ServiceDeclaration, StructDeclaration, TableDeclaration, Type, TypeKind,
+    TypeShape, UnionDeclaration,
+};
+
+/// What a change breaks, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compatibility {
+    /// Neither code written against the old version nor peers using it
+    /// notice.
+    Safe,
+    /// Peers using the old version still interoperate, but code written
+    /// against it may not compile.
+    SourceBreaking,
+    /// Peers using the old version can no longer interoperate.
+    AbiBreaking,
+}
+
+impl fmt::Display for Compatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Safe => write!(f, "safe"),
+            Self::SourceBreaking => write!(f, "source-breaking"),
+            Self::AbiBreaking => write!(f, "ABI-breaking"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompatChange {
+    pub compatibility: Compatibility,
+    /// The declaration or member that changed, like `fuchsia.example/Color`
+    /// or `fuchsia.example/Color.RED`.
+    pub name: String,
+    pub message: String,
+}
+
+impl fmt::Display for CompatChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.compatibility, self.name, self.message)
+    }
+}
+
+/// The changes from `old` to `new`, both the same library, by declaration
+/// name.
+pub fn check(old: &Root, new: &Root) -> Vec<CompatChange> {
+    let old_decls = declarations(old);
+    let new_decls = declarations(new);
+    let mut checker = Checker {
+        changes: Vec::new(),
+    };
+    for (name, old_decl) in &old_decls {
+        let Some(new_decl) = new_decls.get(name) else {
+            let compatibility = if old_decl.kind() == DeclarationKind::Protocol {
+                Compatibility::AbiBreaking
+            } else {
+                Compatibility::SourceBreaking
+            };
+            checker.report(compatibility, name, format!("{} removed", old_decl.kind()));
+            continue;
+        };
+        if old_decl.kind() != new_decl.kind() {
+            checker.report(
+                Compatibility::AbiBreaking,
+                name,
+                format!("changed from {} to {}", old_decl.kind(), new_decl.kind()),
+            );
+            continue;
+        }
+        match (old_decl, new_decl) {
+            (Declaration::Struct(old), Declaration::Struct(new)) => {
+                checker.check_struct(name, old, new)
+            }
+            (Declaration::Table(old), Declaration::Table(new)) => {
+                // Tables are always flexible.
+                checker.check_ordinal_members(
+                    name,
+                    "flexible table",
+                    false,
+                    old.members
+                        .iter()
+                        .map(|m| (m.ordinal, m.name.as_ref(), m.type_.as_ref())),
+                    new.members
+                        .iter()
+                        .map(|m| (m.ordinal, m.name.as_ref(), m.type_.as_ref())),
+                );
+            }
+            (Declaration::Union(old), Declaration::Union(new))
+            | (Declaration::Overlay(old), Declaration::Overlay(new)) => {
+                checker.check_strictness(name, old.strict, new.strict);
+                checker.check_ordinal_members(
+                    name,
+                    &format!("{} {}", strictness(old.strict), old_decl.kind()),
+                    old.strict && new.strict,
+                    old.members
+                        .iter()
+                        .map(|m| (m.ordinal, m.name.as_ref(), m.type_.as_ref())),
+                    new.members
+                        .iter()
+                        .map(|m| (m.ordinal, m.name.as_ref(), m.type_.as_ref())),
+                );
+            }
+            (Declaration::Enum(old), Declaration::Enum(new)) => {
+                if old.type_ != new.type_ {
+                    checker.report(
+                        Compatibility::AbiBreaking,
+                        name,
+                        format!("changed type from {} to {}", old.type_, new.type_),
+                    );
+                }
+                checker.check_strictness(name, old.strict, new.strict);
+                checker.check_valued_members(
+                    name,
+                    old.strict && new.strict,
+                    old.members.iter().map(|m| (m.name.as_ref(), &m.value)),
+                    new.members.iter().map(|m| (m.name.as_ref(), &m.value)),
+                );
+            }
+            (Declaration::Bits(old), Declaration::Bits(new)) => {
+                checker.check_type(Compatibility::AbiBreaking, name, &old.type_, &new.type_);
+                checker.check_strictness(name, old.strict, new.strict);
+                checker.check_valued_members(
+                    name,
+                    old.strict && new.strict,
+                    old.members.iter().map(|m| (m.name.as_ref(), &m.value)),
+                    new.members.iter().map(|m| (m.name.as_ref(), &m.value)),
+                );
+            }
+            (Declaration::Protocol(old), Declaration::Protocol(new)) => {
+                checker.check_protocol(name, old, new)
+            }
+            (Declaration::Service(old), Declaration::Service(new)) => {
+                checker.check_service(name, old, new)
+            }
+            (Declaration::Const(old), Declaration::Const(new)) => {
+                checker.check_const(name, old, new)
+            }
+            (Declaration::Alias(old), Declaration::Alias(new)) => {
+                checker.check_type(Compatibility::SourceBreaking, name, &old.type_, &new.type_)
+            }
+            (Declaration::ExperimentalResource(old), Declaration::ExperimentalResource(new)) => {
+                checker.check_type(Compatibility::AbiBreaking, name, &old.type_, &new.type_)
+            }
+            (Declaration::NewType(old), Declaration::NewType(new)) => {
+                checker.check_type(Compatibility::AbiBreaking, name, &old.type_, &new.type_)
+            }
+            _ => {}
+        }
+        if let (Some(old_resource), Some(new_resource)) = (old_decl.resource(), new_decl.resource())
+            && old_resource != new_resource
+        {
+            let message = if new_resource {
+                "became a resource type"
+            } else {
+                "is no longer a resource type"
+            };
+            checker.report(Compatibility::SourceBreaking, name, message.to_string());
+        }
+    }
+    for (name, decl) in &new_decls {
+        if !old_decls.contains_key(name) {
+            checker.report(Compatibility::Safe, name, format!("{} added", decl.kind()));
+        }
+    }
+    checker.changes.sort_by(|a, b| a.name.cmp(&b.name));
+    checker.changes
+}
+
+/// A declaration of the library, of any kind.
+#[derive(Clone, Copy)]
+enum Declaration<'a> {
+    Alias(&'a AliasDeclaration),
+    Bits(&'a BitsDeclaration),
+    Const(&'a ConstDeclaration),
+    Enum(&'a EnumDeclaration),
+    ExperimentalResource(&'a ExperimentalResourceDeclaration),
+    NewType(&'a NewTypeDeclaration),
+    Protocol(&'a ProtocolDeclaration),
+    Service(&'a ServiceDeclaration),
+    Struct(&'a StructDeclaration),
+    Table(&'a TableDeclaration),
+    Union(&'a UnionDeclaration),
+    Overlay(&'a UnionDeclaration),
+}
+
+impl Declaration<'_> {
+    fn kind(&self) -> DeclarationKind {
+        match self {
+            Self::Alias(_) => DeclarationKind::Alias,
+            Self::Bits(_) => DeclarationKind::Bits,
+            Self::Const(_) => DeclarationKind::Const,
+            Self::Enum(_) => DeclarationKind::Enum,
+            Self::ExperimentalResource(_) => DeclarationKind::ExperimentalResource,
+            Self::NewType(_) => DeclarationKind::NewType,
+            Self::Protocol(_) => DeclarationKind::Protocol,
+            Self::Service(_) => DeclarationKind::Service,
+            Self::Struct(_) => DeclarationKind::Struct,
+            Self::Table(_) => DeclarationKind::Table,
+            Self::Union(_) => DeclarationKind::Union,
+            Self::Overlay(_) => DeclarationKind::Overlay,
+        }
+    }
+
+    /// Whether a layout is a resource type, or `None` for other declarations.
+    fn resource(&self) -> Option<bool> {
+        match self {
+            Self::Struct(d) => Some(d.resource),
+            Self::Table(d) => Some(d.resource),
+            Self::Union(d) | Self::Overlay(d) => Some(d.resource),
+            _ => None,
+        }
+    }
+}
+
+/// The library's own declarations by name.
+fn declarations(root: &Root) -> BTreeMap<String, Declaration<'_>> {
+    let mut result = BTreeMap::new();
+    let mut insert = |name: &dyn fmt::Display, decl| {
+        result.insert(name.to_string(), decl);
+    };
+    for d in &root.alias_declarations {
+        insert(&d.name, Declaration::Alias(d));
+    }
+    for d in &root.bits_declarations {
+        insert(&d.name, Declaration::Bits(d));
+    }
+    for d in &root.const_declarations {
+        insert(&d.name, Declaration::Const(d));
+    }
+    for d in &root.enum_declarations {
+        insert(&d.name, Declaration::Enum(d));
+    }
+    for d in &root.experimental_resource_declarations {
+        insert(&d.name, Declaration::ExperimentalResource(d));
+    }
+    for d in &root.new_type_declarations {
+        insert(&d.name, Declaration::NewType(d));
+    }
+    for d in &root.protocol_declarations {
+        insert(&d.name, Declaration::Protocol(d));
+    }
+    for d in &root.service_declarations {
+        insert(&d.name, Declaration::Service(d));
+    }
+    for d in &root.struct_declarations {
+        insert(&d.name, Declaration::Struct(d));
+    }
+    for d in &root.table_declarations {
+        insert(&d.name, Declaration::Table(d));
+    }
+    for d in &root.union_declarations {
+        insert(&d.name, Declaration::Union(d));
+    }
+    for d in root.overlay_declarations.iter().flatten() {
+        insert(&d.name, Declaration::Overlay(d));
+    }
+    result
+}
+
+struct Checker {
+    changes: Vec<CompatChange>,
+}
+
+impl Checker {
+    fn report(&mut self, compatibility: Compatibility, name: &str, message: String) {
+        self.changes.push(CompatChange {
+            compatibility,
+            name: name.to_string(),
+            message,
+        });
+    }
+
+    fn check_strictness(&mut self, name: &str, old: bool, new: bool) {
+        if old != new {
+            self.report(
+                Compatibility::SourceBreaking,
+                name,
+                format!("changed from {} to {}", strictness(old), strictness(new)),
+            );
+        }
+    }
+
+    fn check_type(&mut self, compatibility: Compatibility, name: &str, old: &Type, new: &Type) {
+        if !same_type(old, new) {
+            self.report(
+                compatibility,
+                name,
+                format!("changed type from {} to {}", describe(old), describe(new)),
+            );
+        }
+    }
+
+    fn check_struct(&mut self, name: &str, old: &StructDeclaration, new: &StructDeclaration) {
+        let count = self.changes.len();
+        if old.members.len() == new.members.len() {
+            for (o, n) in old.members.iter().zip(&new.members) {
+                let member_name = format!("{}.{}", name, o.name);
+                if !same_type(&o.type_, &n.type_) {
+                    self.report(
+                        Compatibility::AbiBreaking,
+                        &member_name,
+                        format!(
+                            "changed from {} {} to {} {}",
+                            o.name,
+                            describe(&o.type_),
+                            n.name,
+                            describe(&n.type_)
+                        ),
+                    );
+                } else if o.name != n.name {
+                    self.report(
+                        Compatibility::SourceBreaking,
+                        &member_name,
+                        format!("renamed to {}", n.name),
+                    );
+                }
+            }
+        } else {
+            for o in &old.members {
+                if !new.members.iter().any(|n| n.name == o.name) {
+                    self.report(
+                        Compatibility::AbiBreaking,
+                        &format!("{}.{}", name, o.name),
+                        "member removed".to_string(),
+                    );
+                }
+            }
+            for n in &new.members {
+                if !old.members.iter().any(|o| o.name == n.name) {
+                    self.report(
+                        Compatibility::AbiBreaking,
+                        &format!("{}.{}", name, n.name),
+                        "member added".to_string(),
+                    );
+                }
+            }
+        }
+        // A layout that changed only through the types of the members.
+        if self.changes.len() == count && old.type_shape != new.type_shape {
+            self.report(
+                Compatibility::AbiBreaking,
+                name,
+                format!(
+                    "layout changed from {} to {}",
+                    describe_shape(&old.type_shape),
+                    describe_shape(&new.type_shape)
+                ),
+            );
+        }
+    }
+
+    /// Tables, unions and overlays, whose members are matched by ordinal.
+    /// Each member is its ordinal, name and type, which is `None` if the
+    /// ordinal is reserved.
+    fn check_ordinal_members<'a>(
+        &mut self,
+        name: &str,
+        kind: &str,
+        strict: bool,
+        old: impl Iterator<Item = (u32, &'a str, Option<&'a Type>)>,
+        new: impl Iterator<Item = (u32, &'a str, Option<&'a Type>)>,
+    ) {
+        let old_members: BTreeMap<u32, (&str, Option<&Type>)> = old
+            .map(|(ordinal, name, ty)| (ordinal, (name, ty)))
+            .collect();
+        let new_members: BTreeMap<u32, (&str, Option<&Type>)> = new
+            .map(|(ordinal, name, ty)| (ordinal, (name, ty)))
+            .collect();
+        for (ordinal, &(old_name, old_type)) in &old_members {
+            let Some(old_type) = old_type else {
+                // A reserved ordinal was used before, so peers of that
+                // version may still send it.
+                if let Some(&(new_name, Some(new_type))) = new_members.get(ordinal) {
+                    self.report(
+                        Compatibility::AbiBreaking,
+                        &format!("{}.{}", name, new_name),
+                        format!(
+                            "reserved ordinal {} reused for {} {}",
+                            ordinal,
+                            new_name,
+                            describe(new_type)
+                        ),
+                    );
+                }
+                continue;
+            };
+            let member_name = format!("{}.{}", name, old_name);
+            let Some(&(new_name, Some(new_type))) = new_members.get(ordinal) else {
+                let compatibility = if strict {
+                    Compatibility::AbiBreaking
+                } else {
+                    Compatibility::SourceBreaking
+                };
+                self.report(
+                    compatibility,
+                    &member_name,
+                    format!("member removed from {}", kind),
+                );
+                continue;
+            };
+            if !same_type(old_type, new_type) {
+                let message = if old_name == new_name {
+                    format!(
+                        "changed type from {} to {}",
+                        describe(old_type),
+                        describe(new_type)
+                    )
+                } else {
+                    format!(
+                        "ordinal {} reused: was {} {}, now {} {}",
+                        ordinal,
+                        old_name,
+                        describe(old_type),
+                        new_name,
+                        describe(new_type)
+                    )
+                };
+                self.report(Compatibility::AbiBreaking, &member_name, message);
+            } else if old_name != new_name {
+                self.report(
+                    Compatibility::SourceBreaking,
+                    &member_name,
+                    format!("renamed to {}", new_name),
+                );
+            }
+        }
+        for (ordinal, &(new_name, new_type)) in &new_members {
+            if new_type.is_none() || old_members.contains_key(ordinal) {
+                continue;
+            }
+            let compatibility = if strict {
+                Compatibility::AbiBreaking
+            } else {
+                Compatibility::Safe
+            };
+            self.report(
+                compatibility,
+                &format!("{}.{}", name, new_name),
+                format!("member added to {}", kind),
+            );
+        }
+    }
+
+    /// Bits and enums, whose members are matched by name. Each member is its
+    /// name and value.
+    fn check_valued_members<'a>(
+        &mut self,
+        name: &str,
+        strict: bool,
+        old: impl Iterator<Item = (&'a str, &'a Constant)>,
+        new: impl Iterator<Item = (&'a str, &'a Constant)>,
+    ) {
+        let old_members: Vec<(&str, &str)> = old.map(|(n, v)| (n, value(v))).collect();
+        let new_members: Vec<(&str, &str)> = new.map(|(n, v)| (n, value(v))).collect();
+        let has = |members: &[(&str, &str)], name: &str| members.iter().any(|m| m.0 == name);
+        for &(old_name, old_value) in &old_members {
+            let member_name = format!("{}.{}", name, old_name);
+            if let Some(&(_, new_value)) = new_members.iter().find(|n| n.0 == old_name) {
+                if old_value != new_value {
+                    self.report(
+                        Compatibility::AbiBreaking,
+                        &member_name,
+                        format!("value changed from {} to {}", old_value, new_value),
+                    );
+                }
+            } else if let Some(&(new_name, _)) = new_members
+                .iter()
+                .find(|n| n.1 == old_value && !has(&old_members, n.0))
+            {
+                self.report(
+                    Compatibility::SourceBreaking,
+                    &member_name,
+                    format!("renamed to {}", new_name),
+                );
+            } else {
+                let compatibility = if strict {
+                    Compatibility::AbiBreaking
+                } else {
+                    Compatibility::SourceBreaking
+                };
+                self.report(
+                    compatibility,
+                    &member_name,
+                    format!("member removed from {} type", strictness(strict)),
+                );
+            }
+        }
+        for &(new_name, new_value) in &new_members {
+            let renamed = old_members
+                .iter()
+                .any(|o| o.1 == new_value && !has(&new_members, o.0));
+            if renamed || has(&old_members, new_name) {
+                continue;
+            }
+            let compatibility = if strict {
+                Compatibility::AbiBreaking
+            } else {
+                Compatibility::Safe
+            };
+            self.report(
+                compatibility,
+                &format!("{}.{}", name, new_name),
+                format!("member added to {} type", strictness(strict)),
+            );
+        }
+    }
+
+    fn check_protocol(&mut self, name: &str, old: &ProtocolDeclaration, new: &ProtocolDeclaration) {
+        if old.openness != new.openness {
+            self.report(
+                Compatibility::SourceBreaking,
+                name,
+                format!("changed from {} to {}", old.openness, new.openness),
+            );
+        }
+        for o in &old.methods {
+            let method_name = format!("{}.{}", name, o.name);
+            if let Some(n) = new.methods.iter().find(|n| n.ordinal == o.ordinal) {
+                self.check_method(&method_name, o, n);
+                if n.name != o.name {
+                    let selector = format!("{}.{}", name, n.name);
+                    let message = if n.ordinal != compute_method_ordinal(&selector) {
+                        format!("renamed to {}, keeping its ordinal with @selector", n.name)
+                    } else {
+                        format!("renamed to {}", n.name)
+                    };
+                    self.report(Compatibility::SourceBreaking, &method_name, message);
+                }
+            } else if let Some(n) = new.methods.iter().find(|n| n.name == o.name) {
+                self.report(
+                    Compatibility::AbiBreaking,
+                    &method_name,
+                    format!("ordinal changed from {} to {}", o.ordinal, n.ordinal),
+                );
+            } else {
+                self.report(
+                    Compatibility::AbiBreaking,
+                    &method_name,
+                    "method removed".to_string(),
+                );
+            }
+        }
+        for n in &new.methods {
+            if !old
+                .methods
+                .iter()
+                .any(|o| o.ordinal == n.ordinal || o.name == n.name)
+            {
+                // Servers of the protocol have to handle the new method.
+                self.report(
+                    Compatibility::SourceBreaking,
+                    &format!("{}.{}", name, n.name),
+                    "method added".to_string(),
+                );
+            }
+        }
+    }
+
+    fn check_method(&mut self, name: &str, old: &ProtocolMethod, new: &ProtocolMethod) {
+        if old.kind != new.kind {
+            self.report(
+                Compatibility::AbiBreaking,
+                name,
+                format!("changed from {} to {}", method_kind(old), method_kind(new)),
+            );
+            return;
+        }
+        if old.strict != new.strict {
+            self.report(
+                Compatibility::SourceBreaking,
+                name,
+                format!(
+                    "changed from {} to {}",
+                    strictness(old.strict),
+                    strictness(new.strict)
+                ),
+            );
+        }
+        if old.has_error != new.has_error {
+            let message = if new.has_error {
+                "error syntax added"
+            } else {
+                "error syntax removed"
+            };
+            self.report(Compatibility::AbiBreaking, name, message.to_string());
+        }
+        for (old_payload, new_payload, payload) in [
+            (
+                &old.maybe_request_payload,
+                &new.maybe_request_payload,
+                "request",
+            ),
+            (
+                &old.maybe_response_payload,
+                &new.maybe_response_payload,
+                "response",
+            ),
+        ] {
+            match (old_payload, new_payload) {
+                (None, None) => {}
+                (Some(o), Some(n)) => {
+                    if !same_type(o, n) {
+                        self.report(
+                            Compatibility::AbiBreaking,
+                            name,
+                            format!(
+                                "{} payload changed from {} to {}",
+                                payload,
+                                describe(o),
+                                describe(n)
+                            ),
+                        );
+                    }
+                }
+                (None, Some(_)) => self.report(
+                    Compatibility::AbiBreaking,
+                    name,
+                    format!("{} payload added", payload),
+                ),
+                (Some(_), None) => self.report(
+                    Compatibility::AbiBreaking,
+                    name,
+                    format!("{} payload removed", payload),
+                ),
+            }
+        }
+    }
+
+    fn check_service(&mut self, name: &str, old: &ServiceDeclaration, new: &ServiceDeclaration) {
+        for o in &old.members {
+            let member_name = format!("{}.{}", name, o.name);
+            match new.members.iter().find(|n| n.name == o.name) {
+                Some(n) => {
+                    self.check_type(Compatibility::AbiBreaking, &member_name, &o.type_, &n.type_)
+                }
+                None => self.report(
+                    Compatibility::AbiBreaking,
+                    &member_name,
+                    "member removed".to_string(),
+                ),
+            }
+        }
+        for n in &new.members {
+            if !old.members.iter().any(|o| o.name == n.name) {
+                self.report(
+                    Compatibility::Safe,
+                    &format!("{}.{}", name, n.name),
+                    "member added".to_string(),
+                );
+            }
+        }
+    }
+
+    fn check_const(&mut self, name: &str, old: &ConstDeclaration, new: &ConstDeclaration) {
+        self.check_type(Compatibility::SourceBreaking, name, &old.type_, &new.type_);
+        if old.value.value != new.value.value {
+            self.report(
+                Compatibility::Safe,
+                name,
+                format!(
+                    "value changed from {} to {}",
+                    old.value.value, new.value.value
+                ),
+            );
+        }
+    }
+}
+
+fn strictness(strict: bool) -> &'static str {
+    if strict { "strict" } else { "flexible" }
+}
+
+fn method_kind(method: &ProtocolMethod) -> &str {
+    match method.kind.as_str() {
+        "oneway" => "one-way",
+        "twoway" => "two-way",
+        kind => kind,
+    }
+}
+
+/// The value of a bits or enum member, without the quotes it has in the IR.
+fn value(constant: &Constant) -> &str {
+    constant.value.trim_matches('"')
+}
+
+/// Whether two types are the same on the wire and in source, ignoring the
+/// shapes of the declarations they refer to, which are checked on their own.
+fn same_type(old: &Type, new: &Type) -> bool {
+    old.kind() == new.kind()
+        && subtype(old) == subtype(new)
+        && old.identifier() == new.identifier()
+        && old.nullable() == new.nullable()
+        && old.protocol() == new.protocol()
+        && role(old) == role(new)
+        && old.element_count() == new.element_count()
+        && old.maybe_element_count() == new.maybe_element_count()
+        && old.rights() == new.rights()
+        && old.resource_identifier() == new.resource_identifier()
+        && match (old.element_type(), new.element_type()) {
+            (Some(o), Some(n)) => same_type(o, n),
+            (o, n) => o.is_none() && n.is_none(),
+        }
+}
+
+fn subtype(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Primitive(t) => Some(t.subtype.to_string()),
+        Type::Handle(t) => t.subtype.clone(),
+        Type::Request(t) => t.subtype.clone(),
+        Type::Internal(t) => Some(t.subtype.clone()),
+        _ => None,
+    }
+}
+
+fn role(ty: &Type) -> Option<&str> {
+    match ty {
+        Type::Endpoint(t) => t.role.as_deref(),
+        _ => None,
+    }
+}
+
+/// A type as it would be written in FIDL, roughly.
+fn describe(ty: &Type) -> String {
+    let base = match ty {
+        Type::Primitive(t) => t.subtype.to_string(),
+        Type::Internal(t) => t.subtype.clone(),
+        Type::Vector(t) => format!("vector<{}>", describe(&t.element_type)),
+        Type::Array(t) => format!("array<{}, {}>", describe(&t.element_type), t.element_count),
+        Type::Endpoint(t) => format!(
+            "{}_end:{}",
+            t.role.as_deref().unwrap_or(""),
+            t.protocol.as_deref().unwrap_or("")
+        ),
+        Type::Handle(t) => match &t.subtype {
+            Some(subtype) => format!("handle:{}", subtype.to_uppercase()),
+            None => "handle".to_string(),
+        },
+        _ => match ty.identifier() {
+            Some(identifier) => identifier,
+            None => kind_name(ty.kind()).to_string(),
+        },
+    };
+    if ty.nullable() {
+        format!("{}:optional", base)
+    } else {
+        base
+    }
+}
+
+fn kind_name(kind: TypeKind) -> &'static str {
+    match kind {
+        TypeKind::Primitive => "primitive",
+        TypeKind::String => "string",
+        TypeKind::StringArray => "string_array",
+        TypeKind::Unknown => "unknown",
+        TypeKind::Vector => "vector",
+        TypeKind::Array => "array",
+        TypeKind::Endpoint => "endpoint",
+        TypeKind::Handle => "handle",
+        TypeKind::Identifier => "identifier",
+        TypeKind::Struct => "struct",
+        TypeKind::Request => "request",
+        TypeKind::ExperimentalPointer => "experimental_pointer",
+        TypeKind::Internal => "internal",
+    }
+}
+
+fn describe_shape(shape: &TypeShape) -> String {
+    format!("{} bytes aligned to {}", shape.inline_size, shape.alignment)
+}
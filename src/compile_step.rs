@@ -10,6 +10,11 @@ impl<'node, 'src> Step<'node, 'src> for CompileStep {
             if compiler.anonymous_structs.contains::<str>(name.as_ref()) {
                 continue;
             }
+            if let Some(only) = &compiler.compile_only
+                && !only.contains(&name.to_string())
+            {
+                continue;
+            }
             compiler.compile_decl_by_name(name.as_ref());
         }
     }
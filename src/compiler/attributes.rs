@@ -251,6 +251,24 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
         true
     }
 
+    /// Whether `decl` is in the selected version, going by the availability
+    /// [`AvailabilityStep`] computed for it. When several versions are
+    /// selected, that is [`Version::LEGACY`], where a declaration that is
+    /// removed rather than replaced is kept if it is available at any of them.
+    ///
+    /// [`AvailabilityStep`]: crate::availability_step::AvailabilityStep
+    pub fn is_decl_active(&self, decl: &RawDecl<'_, '_>) -> bool {
+        let ptr = decl.element().span().data.as_ptr() as usize;
+        let Some(avail) = self.raw_decl_availability.get(&ptr) else {
+            return true;
+        };
+        let platform = Platform::parse(self.library_name.versioning_platform())
+            .unwrap_or_else(Platform::unversioned);
+        avail
+            .set()
+            .contains(self.version_selection.lookup(&platform))
+    }
+
     pub fn is_member_active(&self, member_ptr: usize) -> bool {
         if let Some(avail) = self.member_availability.get(&member_ptr) {
             let platform = Platform::parse(self.library_name.versioning_platform())
@@ -61,7 +61,7 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                 }
 
                 if let Some((type_full_name, maybe_member)) =
-                    self.resolve_constant_decl(&name.to_string(), library_name)
+                    self.resolve_constant_identifier(&id.identifier, library_name)
                     && let Some(decl) = self.raw_decls.get::<str>(type_full_name.as_ref())
                 {
                     if let Some(member_name) = maybe_member {
@@ -104,6 +104,14 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                     }
                 }
 
+                if let Some(c) = self.resolve_precompiled_constant(&name, library_name) {
+                    return Some(if c.kind == "string" {
+                        serde_json::Value::from(c.value).to_string()
+                    } else {
+                        format!("\"{}\"", c.value)
+                    });
+                }
+
                 self.eval_constant_value(constant, library_name)
                     .map(|v| format!("\"{}\"", v))
             }
@@ -132,7 +140,7 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                 }
 
                 if let Some((type_full_name, maybe_member)) =
-                    self.resolve_constant_decl(&name.to_string(), library_name)
+                    self.resolve_constant_identifier(&id.identifier, library_name)
                     && let Some(decl) = self.raw_decls.get::<str>(type_full_name.as_ref())
                 {
                     if maybe_member.is_some() {
@@ -163,7 +171,8 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                         };
                     }
                 }
-                None
+                self.resolve_precompiled_constant(&name, library_name)
+                    .map(|c| c.kind)
             }
             raw_ast::Constant::BinaryOperator(_) => Some("numeric"),
         }
@@ -206,7 +215,7 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                 }
 
                 if let Some((type_full_name, maybe_member)) =
-                    self.resolve_constant_decl(&name.to_string(), library_name)
+                    self.resolve_constant_identifier(&id.identifier, library_name)
                     && let Some(decl) = self.raw_decls.get::<str>(type_full_name.as_ref())
                 {
                     if let Some(member_name) = maybe_member {
@@ -241,7 +250,17 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                     }
                 }
 
-                None
+                let c = self.resolve_precompiled_constant(&name, library_name)?;
+                match c.kind {
+                    "bool" => Some(if c.value == "true" { 1 } else { 0 }),
+                    "numeric" => c
+                        .value
+                        .parse::<i64>()
+                        .ok()
+                        .map(|v| v as u64)
+                        .or_else(|| c.value.parse::<u64>().ok()),
+                    _ => None,
+                }
             }
             raw_ast::Constant::BinaryOperator(binop) => {
                 let left = self.eval_constant_value(&binop.left, library_name)?;
@@ -378,7 +397,7 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
 
                 let mut full_name = id_str.clone();
                 if let Some((type_full_name, maybe_member)) =
-                    self.resolve_constant_decl(&id_str, library_name)
+                    self.resolve_constant_identifier(&id.identifier, library_name)
                 {
                     if let Some(member) = maybe_member {
                         full_name = format!("{}.{}", type_full_name, member);
@@ -550,7 +569,7 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
 
                         let mut full_name = name.clone();
                         if let Some((type_full_name, maybe_member)) =
-                            self.resolve_constant_decl(&name.to_string(), library_name)
+                            self.resolve_constant_identifier(&id.identifier, library_name)
                         {
                             if let Some(member) = maybe_member {
                                 full_name = format!("{}.{}", type_full_name, member);
@@ -561,6 +580,34 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                             full_name = format!("{}/{}", library_name, name);
                         }
 
+                        if let Some(c) = self.resolve_precompiled_constant(&name, library_name) {
+                            let c_layout = c.subtype.unwrap_or(c.kind);
+                            if c.kind == "string"
+                                || (subtype == "bool") != (c_layout == "bool")
+                                || subtype.starts_with("float") != c_layout.starts_with("float")
+                            {
+                                self.reporter.fail(
+                                    Error::ErrTypeCannotBeConvertedToType(
+                                        flyweights::FlyStr::new(name.to_string()),
+                                        flyweights::FlyStr::new(c_layout.to_string()),
+                                        flyweights::FlyStr::new(subtype.to_string()),
+                                    ),
+                                    span,
+                                );
+                            } else if c.kind == "numeric"
+                                && !self.check_numeric_bounds(c.value, subtype)
+                            {
+                                self.reporter.fail(
+                                    Error::ErrConstantOverflowsType(
+                                        flyweights::FlyStr::new(c.value.to_string()),
+                                        flyweights::FlyStr::new(subtype.to_string()),
+                                    ),
+                                    span,
+                                );
+                            }
+                            return;
+                        }
+
                         let decl_info = self
                             .raw_decls
                             .get::<str>(full_name.as_ref())
@@ -826,7 +873,7 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                     let name = id.identifier.to_string();
                     let mut full_name = name.clone();
                     if let Some((type_full_name, maybe_member)) =
-                        self.resolve_constant_decl(&name.to_string(), library_name)
+                        self.resolve_constant_identifier(&id.identifier, library_name)
                     {
                         if let Some(member) = maybe_member {
                             full_name = format!("{}.{}", type_full_name, member);
@@ -843,6 +890,12 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                     {
                         valid = true;
                     }
+                    if self
+                        .resolve_precompiled_constant(&name, library_name)
+                        .is_some_and(|c| c.kind == "string")
+                    {
+                        valid = true;
+                    }
                     if !valid {
                         self.reporter.fail(
                             Error::ErrTypeCannotBeConvertedToType(
@@ -893,7 +946,7 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                         let mut type_full_name = "".to_string();
                         let mut member_name_str = "".to_string();
                         if let Some((type_full, maybe_member)) =
-                            self.resolve_constant_decl(&name.to_string(), library_name)
+                            self.resolve_constant_identifier(&id.identifier, library_name)
                         {
                             type_full_name = type_full.to_string();
                             if let Some(m) = maybe_member {
@@ -949,6 +1002,15 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                                         _ => {}
                                     }
                                 }
+                                if !found_member
+                                    && self
+                                        .precompiled_decls
+                                        .contains_key::<str>(type_full_name.as_ref())
+                                {
+                                    found_member = self
+                                        .resolve_precompiled_constant(&name, library_name)
+                                        .is_some();
+                                }
                                 if !found_member {
                                     self.reporter.fail(
                                         Error::ErrCouldNotResolveMember(flyweights::FlyStr::new(
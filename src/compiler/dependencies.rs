@@ -1,5 +1,6 @@
 use super::RawDecl;
 use crate::diagnostics::Error;
+use crate::diagnostics::Label;
 use crate::flat_ast;
 use crate::flat_ast::DeclarationKind;
 use crate::flat_ast::*;
@@ -191,6 +192,13 @@ pub(crate) fn get_dependencies<'node, 'src>(
             }
         }
         RawDecl::Protocol(p) => {
+            for compose in &p.composed_protocols {
+                let name = compose.protocol_name.to_string();
+                deps.push(match name.rsplit_once('.') {
+                    Some((library, protocol)) => format!("{}/{}", library, protocol),
+                    None => format!("{}/{}", library_name, name),
+                });
+            }
             for m in &p.methods {
                 let _method_name_camel = format!(
                     "{}{}",
@@ -492,9 +500,48 @@ impl<'node, 'src> super::Compiler<'node, 'src> {
                     first_decl.element().span()
                 };
 
-                reporter.fail(
+                // The primary span is on the declaration that closes the cycle;
+                // point at every other declaration along the way.
+                let labels = cycle_names
+                    .iter()
+                    .filter(|cname| cname.as_str() != name)
+                    .filter_map(|cname| {
+                        let decl = decls.get::<str>(cname.as_ref())?;
+                        let cname_fqn = OwnedQualifiedName::parse(cname);
+                        Some(Label::new(
+                            decl.element().span(),
+                            format!("'{}' is part of the cycle", cname_fqn.declaration()),
+                        ))
+                    })
+                    .collect();
+
+                // Only a cycle through the members of a layout can be broken
+                // by making a reference optional; const, alias and compose
+                // cycles can't.
+                let through_layout = cycle_names.iter().any(|cname| {
+                    matches!(
+                        decl_kinds.get::<str>(cname.as_ref()),
+                        Some(
+                            DeclarationKind::Struct
+                                | DeclarationKind::Table
+                                | DeclarationKind::Union
+                                | DeclarationKind::Overlay
+                        )
+                    )
+                });
+                let notes = if through_layout {
+                    vec![
+                        "make one of the references optional or boxed to break the cycle"
+                            .to_string(),
+                    ]
+                } else {
+                    vec![]
+                };
+                reporter.fail_with(
                     Error::ErrIncludeCycle(flyweights::FlyStr::new(format!("{}", &cycle_str))),
                     span,
+                    labels,
+                    notes,
                 );
                 return;
             }
@@ -0,0 +1,927 @@
+//! Loading of dependency libraries from precompiled JSON IR.
+//!
+//! Instead of lexing, parsing and compiling a dependency's `.fidl` files, the
+//! compiler can be handed the JSON IR that an earlier invocation produced for
+//! it. The declarations it contains are registered in `decl_kinds`, `shapes`
+//! and `dependency_declarations`, and in `precompiled_decls` for lookups that
+//! need more than a kind and a shape (constant values, enum members and so on).
+
+use crate::compiler::Compiler;
+use crate::flat_ast::{
+    AliasDeclaration, Attribute, AttributeArg, BitsDeclaration, BitsMember, ConstDeclaration,
+    Constant, DeclBase, DeclarationKind, DependencyDeclaration, EnumDeclaration, EnumMember,
+    ExperimentalMaybeFromAlias, ExperimentalResourceDeclaration, FieldShape, Literal, Location,
+    NewTypeDeclaration, Openness, PartialTypeCtor, PrimitiveSubtype, ProtocolCompose,
+    ProtocolDeclaration, ProtocolMethod, ResourceProperty, Root, ServiceDeclaration, ServiceMember,
+    StructDeclaration, StructMember, TableDeclaration, TableMember, Type, TypeShape,
+    UnionDeclaration, UnionMember,
+};
+use crate::names::{OwnedLibraryName, OwnedQualifiedName};
+
+/// A dependency declaration loaded from JSON IR.
+#[derive(Clone, Debug)]
+pub struct PrecompiledDecl {
+    pub kind: DeclarationKind,
+    pub resource: Option<bool>,
+    pub type_shape: Option<TypeShape>,
+    /// The declaration's IR. Declarations that were only listed in the IR's
+    /// `library_dependencies` (i.e. transitive dependencies) don't have one.
+    pub ir: Option<serde_json::Value>,
+}
+
+/// The value of a `const` declaration, or of a bits or enum member, from a
+/// precompiled dependency.
+pub(crate) struct PrecompiledConstant<'a> {
+    /// `"numeric"`, `"string"` or `"bool"`, as in [`Compiler::infer_constant_type`].
+    pub kind: &'static str,
+    /// The primitive subtype of a numeric or bool constant, e.g. `"uint32"`.
+    pub subtype: Option<&'a str>,
+    pub value: &'a str,
+}
+
+const DECLARATION_LISTS: [(&str, DeclarationKind); 12] = [
+    ("bits_declarations", DeclarationKind::Bits),
+    ("const_declarations", DeclarationKind::Const),
+    ("enum_declarations", DeclarationKind::Enum),
+    (
+        "experimental_resource_declarations",
+        DeclarationKind::ExperimentalResource,
+    ),
+    ("protocol_declarations", DeclarationKind::Protocol),
+    ("service_declarations", DeclarationKind::Service),
+    ("struct_declarations", DeclarationKind::Struct),
+    ("table_declarations", DeclarationKind::Table),
+    ("union_declarations", DeclarationKind::Union),
+    ("overlay_declarations", DeclarationKind::Overlay),
+    ("alias_declarations", DeclarationKind::Alias),
+    ("new_type_declarations", DeclarationKind::NewType),
+];
+
+fn parse_kind(kind: &str) -> Option<DeclarationKind> {
+    Some(match kind {
+        "bits" => DeclarationKind::Bits,
+        "const" => DeclarationKind::Const,
+        "enum" => DeclarationKind::Enum,
+        "experimental_resource" => DeclarationKind::ExperimentalResource,
+        "protocol" => DeclarationKind::Protocol,
+        "service" => DeclarationKind::Service,
+        "struct" => DeclarationKind::Struct,
+        "table" => DeclarationKind::Table,
+        "union" => DeclarationKind::Union,
+        "overlay" => DeclarationKind::Overlay,
+        "alias" => DeclarationKind::Alias,
+        "new_type" => DeclarationKind::NewType,
+        _ => return None,
+    })
+}
+
+fn parse_type_shape(value: &serde_json::Value) -> Option<TypeShape> {
+    let u32_field = |name: &str| value.get(name)?.as_u64().map(|v| v as u32);
+    Some(TypeShape {
+        inline_size: u32_field("inline_size")?,
+        alignment: u32_field("alignment")?,
+        depth: u32_field("depth")?,
+        max_handles: u32_field("max_handles")?,
+        max_out_of_line: u32_field("max_out_of_line")?,
+        has_padding: value.get("has_padding")?.as_bool()?,
+        has_flexible_envelope: value.get("has_flexible_envelope")?.as_bool()?,
+    })
+}
+
+fn enum_type_shape(subtype: &str) -> TypeShape {
+    let (inline_size, alignment) = match subtype {
+        "uint8" | "int8" => (1, 1),
+        "uint16" | "int16" => (2, 2),
+        "uint32" | "int32" => (4, 4),
+        "uint64" | "int64" => (8, 8),
+        _ => (4, 4),
+    };
+    TypeShape {
+        inline_size,
+        alignment,
+        depth: 0,
+        max_handles: 0,
+        max_out_of_line: 0,
+        has_padding: false,
+        has_flexible_envelope: false,
+    }
+}
+
+fn alias_from_ir(value: &serde_json::Value) -> Option<ExperimentalMaybeFromAlias> {
+    Some(ExperimentalMaybeFromAlias {
+        name: value.get("name")?.as_str()?.to_string(),
+        args: vec![],
+        nullable: value
+            .get("nullable")
+            .and_then(|n| n.as_bool())
+            .unwrap_or(false),
+    })
+}
+
+fn location_from_ir(value: &serde_json::Value) -> Option<Location> {
+    let usize_field = |name: &str| value.get(name)?.as_u64().map(|v| v as usize);
+    Some(Location {
+        filename: value.get("filename")?.as_str()?.to_string(),
+        line: usize_field("line")?,
+        column: usize_field("column")?,
+        length: usize_field("length")?,
+    })
+}
+
+fn constant_from_ir(value: &serde_json::Value) -> Option<Constant> {
+    Some(Constant {
+        kind: value.get("kind")?.as_str()?.to_string(),
+        value: value.get("value")?.to_string(),
+        expression: value.get("expression")?.to_string(),
+        identifier: value
+            .get("identifier")
+            .and_then(|i| i.as_str())
+            .map(str::to_string),
+        literal: match value.get("literal") {
+            Some(literal) => Some(Literal {
+                kind: literal.get("kind")?.as_str()?.to_string(),
+                value: literal.get("value")?.to_string(),
+                expression: literal.get("expression")?.to_string(),
+            }),
+            None => None,
+        },
+    })
+}
+
+fn attributes_from_ir(value: &serde_json::Value) -> Option<Vec<Attribute>> {
+    let Some(attributes) = value.get("maybe_attributes") else {
+        return Some(vec![]);
+    };
+    attributes
+        .as_array()?
+        .iter()
+        .map(|attr| {
+            Some(Attribute {
+                name: attr.get("name")?.as_str()?.to_string(),
+                arguments: attr
+                    .get("arguments")?
+                    .as_array()?
+                    .iter()
+                    .map(|arg| {
+                        Some(AttributeArg {
+                            name: arg.get("name")?.as_str()?.to_string(),
+                            type_: arg.get("type")?.as_str()?.to_string(),
+                            value: constant_from_ir(arg.get("value")?)?,
+                            location: location_from_ir(arg.get("location")?)?,
+                        })
+                    })
+                    .collect::<Option<_>>()?,
+                location: location_from_ir(attr.get("location")?)?,
+            })
+        })
+        .collect()
+}
+
+/// The name, location, deprecation and attributes shared by declarations and
+/// their members.
+fn decl_base_from_ir(value: &serde_json::Value) -> Option<DeclBase> {
+    Some(DeclBase {
+        name: value.get("name")?.as_str()?.to_string().into(),
+        location: location_from_ir(value.get("location")?)?,
+        deprecated: value
+            .get("deprecated")
+            .and_then(|d| d.as_bool())
+            .unwrap_or(false),
+        maybe_attributes: attributes_from_ir(value)?,
+    })
+}
+
+/// Like [`decl_base_from_ir`], for a table or union member, which has no
+/// name or location if it is reserved.
+fn member_base_from_ir(value: &serde_json::Value) -> Option<DeclBase> {
+    if value.get("reserved").and_then(|r| r.as_bool()) != Some(true) {
+        return decl_base_from_ir(value);
+    }
+    Some(DeclBase {
+        name: String::new().into(),
+        location: Location {
+            filename: String::new(),
+            line: 0,
+            column: 0,
+            length: 0,
+        },
+        deprecated: false,
+        maybe_attributes: attributes_from_ir(value)?,
+    })
+}
+
+fn partial_type_ctor_from_ir(value: &serde_json::Value) -> Option<PartialTypeCtor> {
+    Some(PartialTypeCtor {
+        name: value.get("name")?.as_str()?.to_string(),
+        args: value
+            .get("args")?
+            .as_array()?
+            .iter()
+            .map(partial_type_ctor_from_ir)
+            .collect::<Option<_>>()?,
+        nullable: value
+            .get("nullable")
+            .and_then(|n| n.as_bool())
+            .unwrap_or(false),
+        maybe_size: match value.get("maybe_size") {
+            Some(size) => Some(constant_from_ir(size)?),
+            None => None,
+        },
+        handle_rights: match value.get("handle_rights") {
+            Some(rights) => Some(constant_from_ir(rights)?),
+            None => None,
+        },
+    })
+}
+
+fn naming_context_from_ir(value: &serde_json::Value) -> Option<Vec<String>> {
+    value
+        .get("naming_context")?
+        .as_array()?
+        .iter()
+        .map(|c| c.as_str().map(str::to_string))
+        .collect()
+}
+
+impl<'node, 'src> Compiler<'node, 'src> {
+    /// Registers the declarations of a dependency library from its JSON IR.
+    /// Must be called before [`Compiler::compile`].
+    pub fn add_dependency_ir(&mut self, ir: &serde_json::Value) -> Result<(), String> {
+        let library = ir
+            .get("name")
+            .and_then(|n| n.as_str())
+            .ok_or_else(|| "dependency IR has no library name".to_string())?;
+        let library = OwnedLibraryName::new(library.to_string());
+        if !self.precompiled_libraries.insert(library.clone()) {
+            return Err(format!("duplicate dependency IR for library {}", library));
+        }
+
+        for (list, kind) in DECLARATION_LISTS {
+            for decl in ir
+                .get(list)
+                .and_then(|l| l.as_array())
+                .into_iter()
+                .flatten()
+            {
+                let name = decl
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .ok_or_else(|| format!("declaration in {} of {} has no name", list, library))?;
+                let (resource, type_shape) = match kind {
+                    DeclarationKind::Struct
+                    | DeclarationKind::Table
+                    | DeclarationKind::Union
+                    | DeclarationKind::Overlay => (
+                        decl.get("resource").and_then(|r| r.as_bool()),
+                        decl.get("type_shape_v2").and_then(parse_type_shape),
+                    ),
+                    DeclarationKind::Enum => (
+                        None,
+                        decl.get("type")
+                            .and_then(|t| t.as_str())
+                            .map(enum_type_shape),
+                    ),
+                    DeclarationKind::Bits => (
+                        None,
+                        decl.get("type")
+                            .and_then(|t| t.get("type_shape_v2"))
+                            .and_then(parse_type_shape),
+                    ),
+                    _ => (None, None),
+                };
+                self.register_precompiled_decl(
+                    &library,
+                    name,
+                    PrecompiledDecl {
+                        kind,
+                        resource,
+                        type_shape,
+                        ir: Some(decl.clone()),
+                    },
+                );
+            }
+        }
+
+        // The IR also summarizes the declarations it uses from its own
+        // dependencies. Register those too, unless their library's IR was (or
+        // will be) provided in full.
+        for dep in ir
+            .get("library_dependencies")
+            .and_then(|d| d.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let Some(dep_name) = dep.get("name").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            let dep_library = OwnedLibraryName::new(dep_name.to_string());
+            let Some(decls) = dep.get("declarations").and_then(|d| d.as_object()) else {
+                continue;
+            };
+            for (name, decl) in decls {
+                if self.precompiled_decls.contains_key::<str>(name.as_ref()) {
+                    continue;
+                }
+                let Some(kind) = decl
+                    .get("kind")
+                    .and_then(|k| k.as_str())
+                    .and_then(parse_kind)
+                else {
+                    continue;
+                };
+                self.register_precompiled_decl(
+                    &dep_library,
+                    name,
+                    PrecompiledDecl {
+                        kind,
+                        resource: decl.get("resource").and_then(|r| r.as_bool()),
+                        type_shape: decl.get("type_shape_v2").and_then(parse_type_shape),
+                        ir: None,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn register_precompiled_decl(
+        &mut self,
+        library: &OwnedLibraryName,
+        name: &str,
+        decl: PrecompiledDecl,
+    ) {
+        let qualified = OwnedQualifiedName::from(name.to_string());
+        // A full declaration replaces a summary from another library's IR.
+        if let Some(existing) = self.precompiled_decls.get(&qualified)
+            && existing.ir.is_some()
+        {
+            return;
+        }
+        self.decl_kinds.insert(qualified.clone(), decl.kind);
+        if let Some(shape) = &decl.type_shape {
+            self.shapes.insert(qualified.clone(), shape.clone());
+        }
+        // Anonymous payload layouts aren't listed among the library's
+        // dependency declarations, but synthesized result types are, as when
+        // compiling the dependency from source.
+        let unlisted = decl.ir.as_ref().is_some_and(|ir| {
+            let flag = |name: &str| ir.get(name).and_then(|f| f.as_bool()) == Some(true);
+            ir.get("naming_context")
+                .and_then(|c| c.as_array())
+                .is_some_and(|c| c.len() > 1)
+                && !flag("is_result")
+                && !flag("is_empty_success_struct")
+        });
+        if unlisted {
+            self.precompiled_decls.insert(qualified, decl);
+            return;
+        }
+        let type_shape = match decl.kind {
+            DeclarationKind::Const
+            | DeclarationKind::Alias
+            | DeclarationKind::Protocol
+            | DeclarationKind::Service => None,
+            _ => decl.type_shape.clone(),
+        };
+        self.dependency_declarations
+            .entry(library.clone())
+            .or_default()
+            .insert(
+                name.to_string(),
+                DependencyDeclaration {
+                    kind: decl.kind,
+                    resource: decl.resource,
+                    type_shape,
+                },
+            );
+        self.precompiled_decls.insert(qualified, decl);
+    }
+
+    /// Returns the IR of a declaration loaded from a precompiled dependency.
+    pub fn precompiled_ir(&self, name: &str) -> Option<&serde_json::Value> {
+        self.precompiled_decls.get(name)?.ir.as_ref()
+    }
+
+    /// Returns the attributes of a declaration loaded from a precompiled
+    /// dependency, or of its member named `member`.
+    pub(crate) fn precompiled_attributes(
+        &self,
+        name: &str,
+        member: Option<&str>,
+    ) -> Option<Vec<Attribute>> {
+        let ir = self.precompiled_ir(name)?;
+        let ir = match member {
+            Some(member) => ir
+                .get("members")?
+                .as_array()?
+                .iter()
+                .find(|m| m.get("name").and_then(|n| n.as_str()) == Some(member))?,
+            None => ir,
+        };
+        attributes_from_ir(ir)
+    }
+
+    /// Resolves a constant reference such as `dep.LIMIT` or `dep.Color.RED`
+    /// to a value from a precompiled dependency.
+    pub(crate) fn resolve_precompiled_constant(
+        &self,
+        name: &str,
+        library_name: &str,
+    ) -> Option<PrecompiledConstant<'_>> {
+        let (full_name, member) = self.resolve_constant_decl(name, library_name)?;
+        let decl = self.precompiled_decls.get::<str>(full_name.as_ref())?;
+        let ir = decl.ir.as_ref()?;
+        match (decl.kind, member) {
+            (DeclarationKind::Const, None) => {
+                let ty = ir.get("type")?;
+                let (kind, subtype) = match ty.get("kind_v2")?.as_str()? {
+                    "string" => ("string", None),
+                    "primitive" => {
+                        let subtype = ty.get("subtype")?.as_str()?;
+                        let kind = if subtype == "bool" { "bool" } else { "numeric" };
+                        (kind, Some(subtype))
+                    }
+                    _ => return None,
+                };
+                Some(PrecompiledConstant {
+                    kind,
+                    subtype,
+                    value: ir.get("value")?.get("value")?.as_str()?,
+                })
+            }
+            (DeclarationKind::Enum | DeclarationKind::Bits, Some(member)) => {
+                let subtype = match decl.kind {
+                    DeclarationKind::Enum => ir.get("type")?.as_str()?,
+                    _ => ir.get("type")?.get("subtype")?.as_str()?,
+                };
+                let value = ir
+                    .get("members")?
+                    .as_array()?
+                    .iter()
+                    .find(|m| m.get("name").and_then(|n| n.as_str()) == Some(member.as_str()))?
+                    .get("value")?
+                    .get("value")?
+                    .as_str()?;
+                Some(PrecompiledConstant {
+                    kind: "numeric",
+                    subtype: Some(subtype),
+                    value,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Reconstructs a protocol from a precompiled dependency, so that it can
+    /// be composed.
+    pub(crate) fn precompiled_protocol(&self, name: &str) -> Option<ProtocolDeclaration> {
+        let decl = self.precompiled_decls.get(name)?;
+        if decl.kind != DeclarationKind::Protocol {
+            return None;
+        }
+        let ir = decl.ir.as_ref()?;
+        let openness = match ir.get("openness")?.as_str()? {
+            "ajar" => Openness::Ajar,
+            "closed" => Openness::Closed,
+            _ => Openness::Open,
+        };
+        let composed_protocols = ir
+            .get("composed_protocols")?
+            .as_array()?
+            .iter()
+            .map(|c| decl_base_from_ir(c).map(|base| ProtocolCompose { base }))
+            .collect::<Option<_>>()?;
+        let methods = ir
+            .get("methods")?
+            .as_array()?
+            .iter()
+            .map(|m| {
+                let flag = |name: &str| m.get(name).and_then(|f| f.as_bool()).unwrap_or(false);
+                let payload = |name: &str| m.get(name).and_then(|t| self.type_from_ir(t));
+                Some(ProtocolMethod {
+                    base: decl_base_from_ir(m)?,
+                    kind: m.get("kind")?.as_str()?.to_string(),
+                    ordinal: m.get("ordinal")?.as_u64()?,
+                    strict: flag("strict"),
+                    has_request: flag("has_request"),
+                    maybe_request_payload: payload("maybe_request_payload"),
+                    has_response: flag("has_response"),
+                    maybe_response_payload: payload("maybe_response_payload"),
+                    is_composed: flag("is_composed"),
+                    has_error: flag("has_error"),
+                    maybe_response_success_type: payload("maybe_response_success_type"),
+                    maybe_response_err_type: payload("maybe_response_err_type"),
+                })
+            })
+            .collect::<Option<_>>()?;
+        let base = decl_base_from_ir(ir)?;
+        Some(ProtocolDeclaration::new(
+            base.name,
+            base.location,
+            base.deprecated,
+            base.maybe_attributes,
+            openness,
+            composed_protocols,
+            methods,
+            None,
+        ))
+    }
+
+    /// Reconstructs a struct from a precompiled dependency, e.g. the payload
+    /// of a composed method.
+    pub(crate) fn precompiled_struct(&self, name: &str) -> Option<StructDeclaration> {
+        let decl = self.precompiled_decls.get(name)?;
+        if decl.kind != DeclarationKind::Struct {
+            return None;
+        }
+        let ir = decl.ir.as_ref()?;
+        let members = ir
+            .get("members")?
+            .as_array()?
+            .iter()
+            .map(|m| {
+                let field_shape = m.get("field_shape_v2")?;
+                Some(StructMember {
+                    type_: self.type_from_ir(m.get("type")?)?,
+                    experimental_maybe_from_alias: match m.get("experimental_maybe_from_alias") {
+                        Some(alias) => Some(alias_from_ir(alias)?),
+                        None => None,
+                    },
+                    base: decl_base_from_ir(m)?,
+                    maybe_default_value: match m.get("maybe_default_value") {
+                        Some(value) => Some(constant_from_ir(value)?),
+                        None => None,
+                    },
+                    field_shape: FieldShape {
+                        offset: field_shape.get("offset")?.as_u64()? as u32,
+                        padding: field_shape.get("padding")?.as_u64()? as u32,
+                    },
+                })
+            })
+            .collect::<Option<_>>()?;
+        let naming_context = ir
+            .get("naming_context")?
+            .as_array()?
+            .iter()
+            .map(|c| c.as_str().map(str::to_string))
+            .collect::<Option<_>>()?;
+        let base = decl_base_from_ir(ir)?;
+        Some(StructDeclaration::new(
+            base.name,
+            base.location,
+            base.deprecated,
+            base.maybe_attributes,
+            naming_context,
+            members,
+            decl.resource.unwrap_or(false),
+            ir.get("is_empty_success_struct")
+                .and_then(|e| e.as_bool())
+                .unwrap_or(false),
+            decl.type_shape.clone()?,
+        ))
+    }
+
+    /// Reconstructs a type from its IR, e.g. the `type` of a precompiled alias.
+    pub(crate) fn type_from_ir(&self, ir: &serde_json::Value) -> Option<Type> {
+        let str_field = |name: &str| ir.get(name).and_then(|v| v.as_str()).map(str::to_string);
+        let u32_field = |name: &str| ir.get(name).and_then(|v| v.as_u64()).map(|v| v as u32);
+        let nullable = ir
+            .get("nullable")
+            .and_then(|n| n.as_bool())
+            .unwrap_or(false);
+        let element_type =
+            || -> Option<Box<Type>> { Some(Box::new(self.type_from_ir(ir.get("element_type")?)?)) };
+        let mut ty = match ir.get("kind_v2")?.as_str()? {
+            "primitive" => Type::primitive(str_field("subtype")?.parse::<PrimitiveSubtype>().ok()?),
+            "string" => Type::string(u32_field("maybe_element_count"), nullable, None),
+            "string_array" => Type::string_array(u32_field("element_count")),
+            "vector" => Type::vector(
+                element_type()?,
+                u32_field("maybe_element_count"),
+                nullable,
+                None,
+            ),
+            "array" => Type::array(element_type()?, u32_field("element_count")?, None),
+            "endpoint" => Type::endpoint(
+                str_field("protocol"),
+                str_field("role"),
+                nullable,
+                str_field("protocol_transport"),
+            ),
+            "handle" => Type::handle(
+                str_field("subtype"),
+                u32_field("rights"),
+                u32_field("obj_type"),
+                nullable,
+                str_field("resource_identifier"),
+            ),
+            "identifier" => {
+                let identifier = str_field("identifier")?;
+                let resource = self
+                    .precompiled_decls
+                    .get::<str>(identifier.as_ref())
+                    .is_some_and(|d| {
+                        d.resource == Some(true) || d.kind == DeclarationKind::Protocol
+                    });
+                Type::identifier_type(
+                    Some(identifier),
+                    nullable,
+                    parse_type_shape(ir.get("type_shape_v2")?)?,
+                    resource,
+                )
+            }
+            "internal" => Type::internal(str_field("subtype")?),
+            _ => return None,
+        };
+        if let Some(shape) = ir.get("type_shape_v2").and_then(parse_type_shape) {
+            ty.type_shape = shape;
+        }
+        if let Some(alias) = ir.get("experimental_maybe_from_alias") {
+            ty.experimental_maybe_from_alias = Some(ExperimentalMaybeFromAlias {
+                name: alias.get("name")?.as_str()?.to_string(),
+                args: vec![],
+                nullable: alias
+                    .get("nullable")
+                    .and_then(|n| n.as_bool())
+                    .unwrap_or(false),
+            });
+        }
+        Some(ty)
+    }
+
+    /// Reconstructs a whole library from its JSON IR, which must have been
+    /// added with [`Compiler::add_dependency_ir`]. Declarations keep their
+    /// names, members, types and shapes, but not what only the IR of their
+    /// own library has, like the declaration order.
+    pub(crate) fn precompiled_library(&self, ir: &serde_json::Value) -> Option<Root> {
+        let list = |name: &str| -> Vec<&serde_json::Value> {
+            ir.get(name)
+                .and_then(|l| l.as_array())
+                .into_iter()
+                .flatten()
+                .collect()
+        };
+        let flag = |value: &serde_json::Value, name: &str| {
+            value.get(name).and_then(|f| f.as_bool()).unwrap_or(false)
+        };
+        let ty = |value: &serde_json::Value| self.type_from_ir(value.get("type")?);
+        let alias = |value: &serde_json::Value| match value.get("experimental_maybe_from_alias") {
+            Some(alias) => alias_from_ir(alias).map(Some),
+            None => Some(None),
+        };
+        let union = |d: &serde_json::Value| {
+            let base = decl_base_from_ir(d)?;
+            let members = d
+                .get("members")?
+                .as_array()?
+                .iter()
+                .map(|m| {
+                    Some(UnionMember {
+                        ordinal: m.get("ordinal")?.as_u64()? as u32,
+                        reserved: m.get("reserved").and_then(|r| r.as_bool()),
+                        type_: m.get("type").and_then(|t| self.type_from_ir(t)),
+                        experimental_maybe_from_alias: alias(m)?,
+                        base: member_base_from_ir(m)?,
+                    })
+                })
+                .collect::<Option<_>>()?;
+            Some(UnionDeclaration::new(
+                base.name,
+                base.location,
+                base.deprecated,
+                base.maybe_attributes,
+                naming_context_from_ir(d)?,
+                members,
+                flag(d, "strict"),
+                flag(d, "resource"),
+                d.get("is_result").and_then(|r| r.as_bool()),
+                parse_type_shape(d.get("type_shape_v2")?)?,
+            ))
+        };
+
+        let bits_declarations = list("bits_declarations")
+            .into_iter()
+            .map(|d| {
+                let base = decl_base_from_ir(d)?;
+                let members = d
+                    .get("members")?
+                    .as_array()?
+                    .iter()
+                    .map(|m| {
+                        Some(BitsMember {
+                            base: decl_base_from_ir(m)?,
+                            value: constant_from_ir(m.get("value")?)?,
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                Some(BitsDeclaration::new(
+                    base.name,
+                    base.location,
+                    base.deprecated,
+                    base.maybe_attributes,
+                    naming_context_from_ir(d)?,
+                    ty(d)?,
+                    d.get("mask")?.as_str()?.to_string(),
+                    members,
+                    flag(d, "strict"),
+                ))
+            })
+            .collect::<Option<_>>()?;
+        let const_declarations = list("const_declarations")
+            .into_iter()
+            .map(|d| {
+                let base = decl_base_from_ir(d)?;
+                Some(ConstDeclaration::new(
+                    base.name,
+                    base.location,
+                    base.deprecated,
+                    base.maybe_attributes,
+                    ty(d)?,
+                    constant_from_ir(d.get("value")?)?,
+                ))
+            })
+            .collect::<Option<_>>()?;
+        let enum_declarations = list("enum_declarations")
+            .into_iter()
+            .map(|d| {
+                let base = decl_base_from_ir(d)?;
+                let members = d
+                    .get("members")?
+                    .as_array()?
+                    .iter()
+                    .map(|m| {
+                        Some(EnumMember {
+                            base: decl_base_from_ir(m)?,
+                            value: constant_from_ir(m.get("value")?)?,
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                Some(EnumDeclaration::new(
+                    base.name,
+                    base.location,
+                    base.deprecated,
+                    base.maybe_attributes,
+                    naming_context_from_ir(d)?,
+                    d.get("type")?.as_str()?.to_string(),
+                    members,
+                    flag(d, "strict"),
+                    d.get("maybe_unknown_value").and_then(|v| v.as_u64()),
+                ))
+            })
+            .collect::<Option<_>>()?;
+        let experimental_resource_declarations = list("experimental_resource_declarations")
+            .into_iter()
+            .map(|d| {
+                let base = decl_base_from_ir(d)?;
+                let properties = d
+                    .get("properties")?
+                    .as_array()?
+                    .iter()
+                    .map(|p| {
+                        Some(ResourceProperty {
+                            name: p.get("name")?.as_str()?.to_string(),
+                            location: location_from_ir(p.get("location")?)?,
+                            deprecated: flag(p, "deprecated"),
+                            type_: ty(p)?,
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                Some(ExperimentalResourceDeclaration::new(
+                    base.name,
+                    base.location,
+                    base.deprecated,
+                    base.maybe_attributes,
+                    ty(d)?,
+                    properties,
+                ))
+            })
+            .collect::<Option<_>>()?;
+        let service_declarations = list("service_declarations")
+            .into_iter()
+            .map(|d| {
+                let base = decl_base_from_ir(d)?;
+                let members = d
+                    .get("members")?
+                    .as_array()?
+                    .iter()
+                    .map(|m| {
+                        Some(ServiceMember {
+                            type_: ty(m)?,
+                            base: decl_base_from_ir(m)?,
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                Some(ServiceDeclaration::new(
+                    base.name,
+                    base.location,
+                    base.deprecated,
+                    base.maybe_attributes,
+                    members,
+                ))
+            })
+            .collect::<Option<_>>()?;
+        let table_declarations = list("table_declarations")
+            .into_iter()
+            .map(|d| {
+                let base = decl_base_from_ir(d)?;
+                let members = d
+                    .get("members")?
+                    .as_array()?
+                    .iter()
+                    .map(|m| {
+                        Some(TableMember {
+                            ordinal: m.get("ordinal")?.as_u64()? as u32,
+                            reserved: m.get("reserved").and_then(|r| r.as_bool()),
+                            type_: m.get("type").and_then(|t| self.type_from_ir(t)),
+                            experimental_maybe_from_alias: alias(m)?,
+                            base: member_base_from_ir(m)?,
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                Some(TableDeclaration::new(
+                    base.name,
+                    base.location,
+                    base.deprecated,
+                    base.maybe_attributes,
+                    naming_context_from_ir(d)?,
+                    members,
+                    flag(d, "strict"),
+                    flag(d, "resource"),
+                    parse_type_shape(d.get("type_shape_v2")?)?,
+                ))
+            })
+            .collect::<Option<_>>()?;
+        let alias_declarations = list("alias_declarations")
+            .into_iter()
+            .map(|d| {
+                let base = decl_base_from_ir(d)?;
+                Some(AliasDeclaration::new(
+                    base.name,
+                    base.location,
+                    base.deprecated,
+                    base.maybe_attributes,
+                    partial_type_ctor_from_ir(d.get("partial_type_ctor")?)?,
+                    ty(d)?,
+                ))
+            })
+            .collect::<Option<_>>()?;
+        let new_type_declarations = list("new_type_declarations")
+            .into_iter()
+            .map(|d| {
+                let base = decl_base_from_ir(d)?;
+                Some(NewTypeDeclaration::new(
+                    base.name,
+                    base.location,
+                    base.deprecated,
+                    base.maybe_attributes,
+                    ty(d)?,
+                    alias(d)?,
+                ))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Root {
+            name: ir.get("name")?.as_str()?.to_string(),
+            platform: ir
+                .get("platform")
+                .and_then(|p| p.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            available: None,
+            maybe_attributes: attributes_from_ir(ir)?,
+            experiments: Vec::new(),
+            library_dependencies: Vec::new(),
+            bits_declarations,
+            const_declarations,
+            enum_declarations,
+            experimental_resource_declarations,
+            protocol_declarations: list("protocol_declarations")
+                .into_iter()
+                .map(|d| self.precompiled_protocol(d.get("name")?.as_str()?))
+                .collect::<Option<_>>()?,
+            service_declarations,
+            struct_declarations: list("struct_declarations")
+                .into_iter()
+                .map(|d| self.precompiled_struct(d.get("name")?.as_str()?))
+                .collect::<Option<_>>()?,
+            external_struct_declarations: Vec::new(),
+            table_declarations,
+            union_declarations: list("union_declarations")
+                .into_iter()
+                .map(union)
+                .collect::<Option<_>>()?,
+            overlay_declarations: match ir.get("overlay_declarations") {
+                Some(_) => Some(
+                    list("overlay_declarations")
+                        .into_iter()
+                        .map(union)
+                        .collect::<Option<_>>()?,
+                ),
+                None => None,
+            },
+            alias_declarations,
+            new_type_declarations,
+            declaration_order: Vec::new(),
+        })
+    }
+}
@@ -1,5 +1,5 @@
 use indexmap::IndexMap;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use crate::attribute_schema;
 use crate::attribute_schema::AttributeSchemaMap;
@@ -7,8 +7,11 @@ use crate::availability_step::AvailabilityStep;
 use crate::canonical_names::CanonicalNames;
 use crate::compile_step::CompileStep;
 use crate::consume_step::ConsumeStep;
+use crate::decomposition::DeclSplit;
+use crate::deprecation_step::DeprecationStep;
 use crate::diagnostics::Error;
 use crate::diagnostics::ErrorKind;
+use crate::diagnostics::Label;
 use crate::experimental_flags::ExperimentalFlag;
 use crate::experimental_flags::ExperimentalFlags;
 use crate::flat_ast::*;
@@ -17,6 +20,7 @@ use crate::names::{OwnedLibraryName, OwnedQualifiedName};
 use crate::raw_ast;
 use crate::raw_ast::LibraryDeclaration;
 pub use crate::raw_ast::RawDecl;
+use crate::replacement_step::ReplacementStep;
 use crate::reporter::Reporter;
 use crate::resolve_step::ResolveStep;
 use crate::source_file::{SourceFile, VirtualSourceFile};
@@ -24,16 +28,20 @@ use crate::source_span::SourceSpan;
 use crate::step::Step;
 use crate::token::TokenSubkind;
 use crate::versioning_types::Availability;
-use crate::versioning_types::VersionSelection;
+use crate::versioning_types::{Platform, Version, VersionSelection};
+pub use dependency_ir::PrecompiledDecl;
 pub use protocols::compute_method_ordinal;
+pub use references::{Reference, ReferenceKind, Referent};
 
 pub(crate) mod aliases;
 pub(crate) mod attributes;
 pub(crate) mod bits;
 pub(crate) mod constants;
 pub(crate) mod dependencies;
+pub(crate) mod dependency_ir;
 pub(crate) mod enums;
 pub(crate) mod protocols;
+pub(crate) mod references;
 pub(crate) mod resources;
 pub(crate) mod services;
 pub(crate) mod structs;
@@ -68,6 +76,21 @@ impl std::fmt::Display for MemberKind {
     }
 }
 
+impl MemberKind {
+    /// The kind of the members of a declaration of the given kind.
+    pub fn of(kind: DeclarationKind) -> Self {
+        match kind {
+            DeclarationKind::Struct => Self::StructMember,
+            DeclarationKind::Table => Self::TableField,
+            DeclarationKind::Union | DeclarationKind::Overlay => Self::UnionMember,
+            DeclarationKind::Protocol => Self::Method,
+            DeclarationKind::Service => Self::ServiceMember,
+            DeclarationKind::ExperimentalResource => Self::ResourceProperty,
+            _ => Self::Member,
+        }
+    }
+}
+
 pub fn to_camel_case(s: &str) -> String {
     let mut camel = String::new();
     let mut capitalize_next = true;
@@ -105,8 +128,31 @@ pub struct Compiler<'node, 'src> {
 
     pub declaration_order: Vec<String>,
     pub decl_availability: HashMap<OwnedQualifiedName, Availability>,
+    /// The availability of every declaration in the source, keyed by where
+    /// it starts, including those that aren't in the selected version and
+    /// the layouts declared inline in members. Set by [`AvailabilityStep`];
+    /// [`ConsumeStep`] only consumes the declarations available in the
+    /// selected version, and keeps their availability in `decl_availability`.
+    pub raw_decl_availability: HashMap<usize, Availability>,
+    /// The availability of every member in the source, keyed by where it
+    /// starts, like `raw_decl_availability`.
     pub member_availability: HashMap<usize, Availability>,
     pub version_selection: VersionSelection,
+    /// The platform the main library is versioned by, or `None` if it is
+    /// unversioned. Set by [`AvailabilityStep`].
+    pub platform: Option<Platform>,
+    /// The versions at which an element of the main library is added,
+    /// deprecated or removed, including elements that aren't in the selected
+    /// version. Between two of them, the library compiles the same way; see
+    /// [`crate::decomposition`].
+    pub version_points: BTreeSet<Version>,
+    /// Where each declaration of the main library, by fully qualified name,
+    /// changes across every version. Set by [`AvailabilityStep`].
+    pub decl_splits: HashMap<String, DeclSplit>,
+    /// If set, only these declarations of the main library are compiled,
+    /// along with whatever they need; [`crate::api::decompose`] uses it to
+    /// compile just the declarations that change at a version.
+    pub compile_only: Option<HashSet<String>>,
     pub compiling_shapes: HashSet<OwnedQualifiedName>,
     /// A mapping of imported library dependencies to their compiled declarations.
     ///
@@ -131,6 +177,21 @@ pub struct Compiler<'node, 'src> {
     pub library_imports: HashMap<OwnedLibraryName, raw_ast::UsingDeclaration<'src>>,
     pub used_imports: std::cell::RefCell<HashSet<OwnedLibraryName>>,
     pub allow_unused_imports: bool,
+    /// Don't warn about references to deprecated elements. See
+    /// [`DeprecationStep`].
+    pub allow_deprecated_references: bool,
+    /// Dependency libraries loaded from precompiled JSON IR rather than from
+    /// source. See [`Compiler::add_dependency_ir`].
+    pub precompiled_libraries: HashSet<OwnedLibraryName>,
+    pub precompiled_decls: HashMap<OwnedQualifiedName, PrecompiledDecl>,
+    /// For each imported library that couldn't be found via `--library-path`,
+    /// the directories that were searched. Reported with `ErrUnknownLibrary`.
+    pub searched_library_paths: HashMap<String, Vec<String>>,
+    /// Where the main library refers to declarations and libraries, in
+    /// source order. See the [`references`] module.
+    pub references: Vec<Reference<'src>>,
+    /// The references recorded so far during compilation, possibly repeated.
+    recorded_references: std::cell::RefCell<Vec<Reference<'src>>>,
 }
 
 impl<'node, 'src> Compiler<'node, 'src> {
@@ -148,8 +209,13 @@ impl<'node, 'src> Compiler<'node, 'src> {
 
             declaration_order: Vec::new(),
             decl_availability: HashMap::new(),
+            raw_decl_availability: HashMap::new(),
             member_availability: HashMap::new(),
             version_selection: VersionSelection::new(),
+            platform: None,
+            version_points: BTreeSet::new(),
+            decl_splits: HashMap::new(),
+            compile_only: None,
             compiling_shapes: HashSet::new(),
             dependency_declarations: BTreeMap::new(),
             inline_names: HashMap::new(),
@@ -162,6 +228,12 @@ impl<'node, 'src> Compiler<'node, 'src> {
             library_imports: HashMap::new(),
             used_imports: std::cell::RefCell::new(HashSet::new()),
             allow_unused_imports: false,
+            allow_deprecated_references: false,
+            precompiled_libraries: HashSet::new(),
+            precompiled_decls: HashMap::new(),
+            searched_library_paths: HashMap::new(),
+            references: Vec::new(),
+            recorded_references: std::cell::RefCell::new(Vec::new()),
         }
     }
 
@@ -186,7 +258,7 @@ impl<'node, 'src> Compiler<'node, 'src> {
         if !full_name.contains('/') {
             full_name = format!("{}/{}", library_name, name);
         }
-        if self.raw_decls.contains_key::<str>(full_name.as_ref()) {
+        if self.is_known_decl(&full_name) {
             return Some((full_name, None));
         }
 
@@ -195,7 +267,7 @@ impl<'node, 'src> Compiler<'node, 'src> {
             if !type_full_name.contains('/') {
                 let local_fqn = crate::names::OwnedLibraryName::new(library_name.to_string())
                     .with_declaration(type_name);
-                if self.raw_decls.contains_key(&local_fqn) {
+                if self.is_known_decl(&local_fqn.as_string()) {
                     type_full_name = local_fqn.as_string();
                 } else if let Some((lib_prefix, rest)) = type_name.split_once('.') {
                     let mut actual_lib = lib_prefix.to_string();
@@ -206,7 +278,7 @@ impl<'node, 'src> Compiler<'node, 'src> {
                         actual_lib = import.using_path.to_string();
                     }
                     let dep_fqn = OwnedLibraryName::new(actual_lib).with_declaration(rest);
-                    if self.raw_decls.contains_key(&dep_fqn) {
+                    if self.is_known_decl(&dep_fqn.as_string()) {
                         type_full_name = dep_fqn.as_string();
                     }
                 } else if let Some(import) = self.library_imports.get(type_name) {
@@ -215,24 +287,47 @@ impl<'node, 'src> Compiler<'node, 'src> {
                         .insert(OwnedLibraryName::new(type_name.to_string()));
                     let dep_fqn = OwnedLibraryName::new(import.using_path.to_string())
                         .with_declaration(member_name);
-                    if self.raw_decls.contains_key(&dep_fqn) {
+                    if self.is_known_decl(&dep_fqn.as_string()) {
                         return Some((dep_fqn.as_string(), None));
                     }
                 }
             }
-            if self.raw_decls.contains_key::<str>(type_full_name.as_ref()) {
+            if self.is_known_decl(&type_full_name) {
                 return Some((type_full_name, Some(member_name.to_string())));
             }
 
             let imported_name =
                 OwnedLibraryName::new(type_name.to_string()).with_declaration(member_name);
-            if self.raw_decls.contains_key(&imported_name) {
+            if self.is_known_decl(&imported_name.as_string()) {
                 return Some((imported_name.as_string(), None));
             }
         }
         None
     }
 
+    /// Resolves `id`, a constant in `library_name`, like
+    /// [`Compiler::resolve_constant_decl`], and records the reference.
+    pub(crate) fn resolve_constant_identifier(
+        &self,
+        id: &raw_ast::CompoundIdentifier<'_>,
+        library_name: &str,
+    ) -> Option<(String, Option<String>)> {
+        let (decl, member) = self.resolve_constant_decl(&id.to_string(), library_name)?;
+        let name = OwnedQualifiedName::parse(&decl);
+        let name = match &member {
+            Some(member) => name.with_member(member),
+            None => name,
+        };
+        self.record_reference(id, library_name, ReferenceKind::Constant, name);
+        Some((decl, member))
+    }
+
+    /// Whether `full_name` is declared in a compiled library or a precompiled
+    /// dependency.
+    pub(crate) fn is_known_decl(&self, full_name: &str) -> bool {
+        self.raw_decls.contains_key(full_name) || self.precompiled_decls.contains_key(full_name)
+    }
+
     pub fn verify_used_imports(&self) {
         if self.allow_unused_imports {
             return;
@@ -272,7 +367,14 @@ impl<'node, 'src> Compiler<'node, 'src> {
     ) -> Result<Root, String> {
         self.source_files = source_files.to_vec();
 
-        // 1. Consume
+        // 1. Availability, of every element in every version
+        let mut avail = AvailabilityStep {
+            main_files,
+            dependency_files,
+        };
+        avail.run(self);
+
+        // 2. Consume the declarations in the selected version
         let mut consume = ConsumeStep {
             main_files,
             dependency_files,
@@ -280,19 +382,24 @@ impl<'node, 'src> Compiler<'node, 'src> {
         consume.run(self);
         self.verify_attributes();
 
-        // 2. Resolve
-        // 1.5. Availability
-        let mut avail = AvailabilityStep;
-        avail.run(self);
-
+        // 3. Resolve
         let mut resolve = ResolveStep;
         resolve.run(self);
 
-        // 3. Compile
+        // 4. Compile
         let mut compile = CompileStep;
         compile.run(self);
 
-        self.verify_used_imports();
+        // Replacements are checked across versions, with the shapes of the
+        // types compiled in the selected one.
+        let mut replacement = ReplacementStep { main_files };
+        replacement.run(self);
+
+        if self.compile_only.is_none() {
+            self.verify_used_imports();
+        }
+        self.finish_references();
+        DeprecationStep { main_files }.run(self);
         // Fixup max_handles for resources in cycles
         for decl in self.declarations.structs_mut() {
             if decl.resource && decl.type_shape.depth == u32::MAX {
@@ -606,7 +713,11 @@ impl<'node, 'src> Compiler<'node, 'src> {
                             let is_struct = self
                                 .declarations
                                 .structs()
-                                .any(|s| s.name.as_string() == id);
+                                .any(|s| s.name.as_string() == id)
+                                || self
+                                    .precompiled_decls
+                                    .get::<str>(id.as_ref())
+                                    .is_some_and(|d| d.kind == DeclarationKind::Struct);
                             if is_struct {
                                 external_names.insert(id);
                             }
@@ -635,11 +746,18 @@ impl<'node, 'src> Compiler<'node, 'src> {
                         }
                     }
                 }
-                self.declarations
+                let mut external_structs: Vec<_> = self
+                    .declarations
                     .structs()
-                    .filter(|d| external_names.contains(&d.name.as_string()))
+                    .filter(|d| external_names.remove(&d.name.as_string()))
                     .cloned()
-                    .collect()
+                    .collect();
+                external_structs.extend(
+                    external_names
+                        .iter()
+                        .filter_map(|name| self.precompiled_struct(name)),
+                );
+                external_structs
             },
             table_declarations: self
                 .declarations
@@ -1529,9 +1647,13 @@ impl<'node, 'src> Compiler<'node, 'src> {
             let kind_str = kind.to_string();
             let prev_kind_str = prev_kind.to_string();
             let prev_site_str = prev_site.position_str();
+            let labels = vec![Label::new(
+                prev_site,
+                format!("previous {} '{}' declared here", prev_kind_str, prev_raw),
+            )];
 
             if is_exact {
-                self.reporter.fail(
+                self.reporter.fail_with(
                     Error::ErrNameCollision(
                         flyweights::FlyStr::new(format!("{}", &kind_str)),
                         flyweights::FlyStr::new(format!("{}", &raw_name)),
@@ -1539,10 +1661,12 @@ impl<'node, 'src> Compiler<'node, 'src> {
                         flyweights::FlyStr::new(format!("{}", &prev_site_str)),
                     ),
                     span,
+                    labels,
+                    vec![],
                 );
             } else {
                 let canon = attribute_schema::canonicalize(&raw_name);
-                self.reporter.fail(
+                self.reporter.fail_with(
                     Error::ErrNameCollisionCanonical(
                         flyweights::FlyStr::new(format!("{}", &kind_str)),
                         flyweights::FlyStr::new(format!("{}", &raw_name)),
@@ -1552,6 +1676,11 @@ impl<'node, 'src> Compiler<'node, 'src> {
                         flyweights::FlyStr::new(format!("{}", &canon)),
                     ),
                     span,
+                    labels,
+                    vec![format!(
+                        "'{}' and '{}' both have the canonical form '{}'",
+                        raw_name, prev_raw, canon
+                    )],
                 );
             }
         }
@@ -1784,7 +1913,16 @@ impl<'node, 'src> Compiler<'node, 'src> {
                 if is_collision {
                     let prev_decl = self.raw_decls.get::<str>(full_name.as_ref()).unwrap();
                     let prev_kind = prev_decl.kind().to_string();
-                    let prev_site = prev_decl.element().span().position_str();
+                    let prev_span: SourceSpan =
+                        unsafe { std::mem::transmute(prev_decl.element().span()) };
+                    let prev_site = prev_span.position_str();
+                    let labels = vec![Label::new(
+                        prev_span,
+                        format!(
+                            "previous {} '{}' declared here",
+                            prev_kind, final_short_name
+                        ),
+                    )];
                     let kind = default_name.strip_prefix("inline_").unwrap_or(default_name);
                     let span_transmuted: SourceSpan =
                         unsafe { std::mem::transmute(type_ctor.element.span()) };
@@ -1796,7 +1934,7 @@ impl<'node, 'src> Compiler<'node, 'src> {
                         {
                             let gen_span_transmuted: SourceSpan =
                                 unsafe { std::mem::transmute(gen_attr.element.span()) };
-                            self.reporter.fail(
+                            self.reporter.fail_with(
                                 Error::ErrNameCollision(
                                     flyweights::FlyStr::new(format!("{}", &kind.to_string())),
                                     flyweights::FlyStr::new(
@@ -1809,9 +1947,11 @@ impl<'node, 'src> Compiler<'node, 'src> {
                                     flyweights::FlyStr::new(format!("{}", &prev_site)),
                                 ),
                                 gen_span_transmuted,
+                                labels,
+                                vec![],
                             );
                         } else {
-                            self.reporter.fail(
+                            self.reporter.fail_with(
                                 Error::ErrNameCollision(
                                     flyweights::FlyStr::new(format!("{}", &kind.to_string())),
                                     flyweights::FlyStr::new(
@@ -1824,10 +1964,12 @@ impl<'node, 'src> Compiler<'node, 'src> {
                                     flyweights::FlyStr::new(format!("{}", &prev_site)),
                                 ),
                                 span_transmuted,
+                                labels,
+                                vec![],
                             );
                         }
                     } else {
-                        self.reporter.fail(
+                        self.reporter.fail_with(
                             Error::ErrNameCollision(
                                 flyweights::FlyStr::new(format!("{}", &kind.to_string())),
                                 flyweights::FlyStr::new(
@@ -1837,6 +1979,8 @@ impl<'node, 'src> Compiler<'node, 'src> {
                                 flyweights::FlyStr::new(format!("{}", &prev_site)),
                             ),
                             span_transmuted,
+                            labels,
+                            vec![],
                         );
                     }
                 }
@@ -2375,9 +2519,11 @@ impl<'node, 'src> Compiler<'node, 'src> {
                 };
 
                 let mut protocol = "".to_string();
+                let mut protocol_id = None;
 
                 if let Some(constraint) = type_ctor.constraints.first() {
                     if let raw_ast::Constant::Identifier(id) = constraint {
+                        protocol_id = Some(&id.identifier);
                         let proto_name = id.identifier.to_string();
                         if let Some((lib_prefix, rest)) = proto_name.split_once('.') {
                             let mut actual_lib = lib_prefix.to_string();
@@ -2396,6 +2542,7 @@ impl<'node, 'src> Compiler<'node, 'src> {
                     }
                 } else if let Some(param) = type_ctor.parameters.first() {
                     if let raw_ast::LayoutParameter::Identifier(id) = &param.layout {
+                        protocol_id = Some(id);
                         let proto_name = id.to_string();
                         if let Some((lib_prefix, rest)) = proto_name.split_once('.') {
                             let mut actual_lib = lib_prefix.to_string();
@@ -2423,6 +2570,17 @@ impl<'node, 'src> Compiler<'node, 'src> {
                     );
                 }
 
+                if let Some(id) = protocol_id
+                    && self.is_known_decl(&protocol)
+                {
+                    self.record_reference(
+                        id,
+                        library_name,
+                        ReferenceKind::Type,
+                        OwnedQualifiedName::parse(&protocol),
+                    );
+                }
+
                 let mut transport = None;
                 if !protocol.is_empty() {
                     let mut is_protocol = false;
@@ -2516,6 +2674,18 @@ impl<'node, 'src> Compiler<'node, 'src> {
                             },
                             _ => {}
                         }
+                    } else if let Some(kind) = inner_type
+                        .identifier()
+                        .filter(|id| self.precompiled_decls.contains_key::<str>(id.as_ref()))
+                        .and_then(|id| self.decl_kinds.get::<str>(id.as_ref()).copied())
+                    {
+                        match kind {
+                            DeclarationKind::Enum
+                            | DeclarationKind::Bits
+                            | DeclarationKind::Table => is_nor_opt = true,
+                            DeclarationKind::Struct => is_struct = true,
+                            _ => {}
+                        }
                     } else {
                         is_nor_opt = inner_type.kind() == TypeKind::Array
                             || inner_type.kind() == TypeKind::Primitive;
@@ -2572,6 +2742,16 @@ impl<'node, 'src> Compiler<'node, 'src> {
                 } else {
                     format!("{}/{}", library_name, name)
                 };
+                if let raw_ast::LayoutParameter::Identifier(id) = &type_ctor.layout
+                    && self.is_known_decl(&full_name)
+                {
+                    self.record_reference(
+                        id,
+                        library_name,
+                        ReferenceKind::Type,
+                        OwnedQualifiedName::parse(&full_name),
+                    );
+                }
 
                 if !nullable && !self.skip_eager_compile {
                     self.compile_decl_by_name(&full_name);
@@ -2801,6 +2981,36 @@ impl<'node, 'src> Compiler<'node, 'src> {
                     }
                 }
 
+                if nullable
+                    && self
+                        .precompiled_decls
+                        .contains_key::<str>(full_name.as_ref())
+                    && let Some(kind) = self.decl_kinds.get::<str>(full_name.as_ref()).copied()
+                {
+                    match kind {
+                        DeclarationKind::Struct => {
+                            self.reporter.fail(
+                                Error::ErrStructCannotBeOptional(flyweights::FlyStr::new(
+                                    name.as_str(),
+                                )),
+                                type_ctor.element.span(),
+                            );
+                            nullable = false;
+                        }
+                        DeclarationKind::Table
+                        | DeclarationKind::Enum
+                        | DeclarationKind::Bits
+                        | DeclarationKind::Service => {
+                            self.reporter.fail(
+                                Error::ErrCannotBeOptional(flyweights::FlyStr::new(name.as_str())),
+                                type_ctor.element.span(),
+                            );
+                            nullable = false;
+                        }
+                        _ => {}
+                    }
+                }
+
                 if nullable && let Some(decl) = self.raw_decls.get::<str>(full_name.as_ref()) {
                     let is_struct = match decl {
                         RawDecl::Struct(_) => true,
@@ -2882,9 +3092,63 @@ impl<'node, 'src> Compiler<'node, 'src> {
                         RawDecl::Protocol(_) => true,
                         _ => false,
                     }
+                } else if let Some(decl) = self.precompiled_decls.get::<str>(full_name.as_ref()) {
+                    decl.resource == Some(true) || decl.kind == DeclarationKind::Protocol
                 } else {
                     false
                 };
+                if let Some(decl) = self.precompiled_decls.get::<str>(full_name.as_ref())
+                    && decl.kind == DeclarationKind::Alias
+                    && let Some(mut resolved_type) = decl
+                        .ir
+                        .as_ref()
+                        .and_then(|ir| ir.get("type"))
+                        .and_then(|ty| self.type_from_ir(ty))
+                {
+                    if let Some(c) = actual_constraints.first() {
+                        let size = self.eval_constant_usize(c, library_name);
+                        match (&resolved_type, size) {
+                            (Type::Vector(v), Some(size)) if v.maybe_element_count.is_none() => {
+                                resolved_type = Type::vector(
+                                    v.element_type.clone(),
+                                    Some(size as u32),
+                                    v.nullable,
+                                    None,
+                                );
+                            }
+                            (Type::String(s), Some(size)) if s.maybe_element_count.is_none() => {
+                                resolved_type = Type::string(Some(size as u32), s.nullable, None);
+                            }
+                            (Type::Vector(_) | Type::String(_), _) => {
+                                self.reporter.fail(
+                                    Error::ErrCannotConstrainTwice(flyweights::FlyStr::new(
+                                        name.as_str(),
+                                    )),
+                                    type_ctor.element.start_token.span,
+                                );
+                            }
+                            _ => {
+                                self.reporter.fail(
+                                    Error::ErrTooManyConstraints(
+                                        flyweights::FlyStr::new(name.to_string()),
+                                        0,
+                                        actual_constraints.len(),
+                                    ),
+                                    type_ctor.element.start_token.span,
+                                );
+                            }
+                        }
+                    }
+                    resolved_type.outer_alias = Some(ExperimentalMaybeFromAlias {
+                        name: full_name.clone(),
+                        args: vec![],
+                        nullable,
+                    });
+                    if nullable {
+                        resolved_type.set_nullable(true);
+                    }
+                    return resolved_type;
+                }
                 if let Some(shape) = self.shapes.get::<str>(&full_name) {
                     Type::identifier_type(
                         Some(full_name.clone()),
@@ -1,4 +1,4 @@
-use crate::compiler::{CanonicalNames, Compiler, DeclarationKind, MemberKind};
+use crate::compiler::{CanonicalNames, Compiler, DeclarationKind, MemberKind, ReferenceKind};
 use crate::diagnostics::Error;
 use crate::experimental_flags::ExperimentalFlag;
 use crate::flat_ast::{
@@ -82,6 +82,9 @@ impl<'node, 'src> Compiler<'node, 'src> {
 
         let mut compiled_composed = vec![];
         for composed in &decl.composed_protocols {
+            if !self.is_member_active(composed.element.span().data.as_ptr() as usize) {
+                continue;
+            }
             let mut composed_name = composed.protocol_name.to_string();
             if let Some((lib_prefix, type_name)) = composed_name.rsplit_once('.') {
                 let mut actual_lib = lib_prefix.to_string();
@@ -99,8 +102,18 @@ impl<'node, 'src> Compiler<'node, 'src> {
                 format!("{}/{}", library_name, composed_name)
             };
 
+            if self.is_known_decl(&full_composed_name) {
+                self.record_reference(
+                    &composed.protocol_name,
+                    library_name,
+                    ReferenceKind::Compose,
+                    OwnedQualifiedName::parse(&full_composed_name),
+                );
+            }
             self.compile_decl_by_name(&full_composed_name);
 
+            let precompiled = self.precompiled_protocol(&full_composed_name);
+
             if has_no_resource {
                 let mut composed_has_no_resource = false;
                 if let Some(p) = self
@@ -110,6 +123,9 @@ impl<'node, 'src> Compiler<'node, 'src> {
                 {
                     composed_has_no_resource =
                         p.maybe_attributes.iter().any(|a| a.name == "no_resource");
+                } else if let Some(p) = &precompiled {
+                    composed_has_no_resource =
+                        p.maybe_attributes.iter().any(|a| a.name == "no_resource");
                 } else if let Some(RawDecl::Protocol(p)) =
                     self.raw_decls.get::<str>(full_composed_name.as_ref())
                 {
@@ -145,6 +161,9 @@ impl<'node, 'src> Compiler<'node, 'src> {
             {
                 composed_openness = p.openness;
                 parent_methods = p.methods.clone();
+            } else if let Some(p) = precompiled {
+                composed_openness = p.openness;
+                parent_methods = p.methods;
             } else if let Some(RawDecl::Protocol(p)) =
                 self.raw_decls.get::<str>(full_composed_name.as_ref())
             {
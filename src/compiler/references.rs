@@ -0,0 +1,121 @@
+//! Records where the main library refers to declarations and libraries.
+//!
+//! The resolver records each reference as it resolves the name: type
+//! constructors in [`Compiler::resolve_type`], constants in
+//! [`Compiler::resolve_constant_identifier`], and `compose` clauses and
+//! `using` declarations where they are compiled. A reference through a library
+//! name or `using` alias, like `dep.Thing`, is recorded twice: once for the
+//! whole name, referring to the declaration, and once for the library part,
+//! referring to the library. Names are often resolved more than once, so
+//! [`Compiler::finish_references`] puts them in source order and drops the
+//! repeats.
+
+use crate::compiler::Compiler;
+use crate::flat_ast::DeclarationKind;
+use crate::names::{OwnedLibraryName, OwnedQualifiedName};
+use crate::raw_ast::{CompoundIdentifier, Identifier, SourceElement};
+use crate::source_span::SourceSpan;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    Type,
+    Constant,
+    Compose,
+    Library,
+}
+
+/// What a reference refers to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Referent {
+    /// A declaration, or a member like `Color.RED`.
+    Declaration(OwnedQualifiedName),
+    Library(OwnedLibraryName),
+}
+
+impl std::fmt::Display for Referent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Referent::Declaration(name) => write!(f, "{}", name),
+            Referent::Library(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference<'src> {
+    pub span: SourceSpan<'src>,
+    pub kind: ReferenceKind,
+    pub referent: Referent,
+}
+
+fn span_of<'src>(components: &[Identifier<'src>]) -> SourceSpan<'src> {
+    let first = &components[0].element.start_token;
+    let last = &components[components.len() - 1].element.end_token;
+    SourceElement::new(first.clone(), last.clone()).span()
+}
+
+impl<'node, 'src> Compiler<'node, 'src> {
+    /// Records that `id`, a name in `library_name`, resolved to `name`, a
+    /// declaration or member. If `id` starts with a library name or `using`
+    /// alias, that part is recorded as a reference to the library. Only
+    /// references in the main library are kept.
+    pub(crate) fn record_reference(
+        &self,
+        id: &CompoundIdentifier<'_>,
+        library_name: &str,
+        kind: ReferenceKind,
+        name: OwnedQualifiedName,
+    ) {
+        if library_name != self.library_name.to_string() {
+            return;
+        }
+        // Every name the compiler resolves is in one of its source files.
+        let components: &[Identifier<'src>] = unsafe { std::mem::transmute(&id.components[..]) };
+        let kind = match self.decl_kinds.get(&name) {
+            Some(DeclarationKind::Const) if kind == ReferenceKind::Type => ReferenceKind::Constant,
+            _ => kind,
+        };
+        let library = name.library().to_owned();
+        let prefix_len = components
+            .len()
+            .saturating_sub(1 + name.member().is_some() as usize);
+        let mut references = self.recorded_references.borrow_mut();
+        if prefix_len > 0 && library != self.library_name {
+            references.push(Reference {
+                span: span_of(&components[..prefix_len]),
+                kind: ReferenceKind::Library,
+                referent: Referent::Library(library),
+            });
+        }
+        references.push(Reference {
+            span: span_of(components),
+            kind,
+            referent: Referent::Declaration(name),
+        });
+    }
+
+    /// Records that `span`, a `using` path in the main library, refers to
+    /// `library`.
+    pub(crate) fn record_library_reference(&self, span: SourceSpan<'src>, library: &str) {
+        self.recorded_references.borrow_mut().push(Reference {
+            span,
+            kind: ReferenceKind::Library,
+            referent: Referent::Library(OwnedLibraryName::new(library.to_string())),
+        });
+    }
+
+    /// Moves the recorded references to [`Compiler::references`], in source
+    /// order and without repeats.
+    pub(crate) fn finish_references(&mut self) {
+        let mut references = self.recorded_references.take();
+        let file_index = |span: &SourceSpan<'_>| {
+            self.source_files
+                .iter()
+                .position(|file| std::ptr::eq(*file, span.source_file))
+        };
+        let key = |span: &SourceSpan<'_>| (span.data.as_ptr() as usize, span.data.len());
+        references.sort_by_key(|r| (file_index(&r.span), key(&r.span)));
+        references.dedup_by_key(|r| key(&r.span));
+        self.references = references;
+    }
+}
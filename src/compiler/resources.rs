@@ -80,6 +80,9 @@ impl<'node, 'src> Compiler<'node, 'src> {
         let mut has_subtype = false;
 
         for prop in &decl.properties {
+            if !self.is_member_active(prop.element.span().data.as_ptr() as usize) {
+                continue;
+            }
             let prop_name = prop.name.data().to_string();
 
             self.check_canonical_insert(
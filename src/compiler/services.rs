@@ -21,6 +21,9 @@ impl<'node, 'src> Compiler<'node, 'src> {
         let mut first_member_with_that_transport = String::new();
 
         for member in &decl.members {
+            if !self.is_member_active(member.element.span().data.as_ptr() as usize) {
+                continue;
+            }
             let ctx = NamingContext::create(name).enter_member(member.name.data());
             let type_obj = self.resolve_type(&member.type_ctor, library_name, Some(ctx));
             let member_name = member.name.data().to_string();
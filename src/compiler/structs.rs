@@ -13,7 +13,7 @@ impl<'node, 'src> Compiler<'node, 'src> {
         name: &str,
         decl: &'node raw_ast::StructDeclaration<'src>,
         library_name: &str,
-        name_element: Option<&raw_ast::SourceElement<'_>>,
+        name_element: Option<&raw_ast::SourceElement<'src>>,
         naming_context: Option<std::rc::Rc<NamingContext<'src>>>,
         inherited_attributes: Option<&raw_ast::AttributeList<'_>>,
     ) -> StructDeclaration {
@@ -61,6 +61,8 @@ impl<'node, 'src> Compiler<'node, 'src> {
             let ctx = naming_context.clone().unwrap_or_else(|| {
                 NamingContext::create(if let Some(id) = &decl.name {
                     id.element.span()
+                } else if let Some(element) = name_element {
+                    element.span()
                 } else {
                     decl.element.span()
                 })
@@ -1,5 +1,5 @@
 use crate::compiler::{CanonicalNames, Compiler, MemberKind};
-use crate::diagnostics::Error;
+use crate::diagnostics::{Error, Label};
 use crate::flat_ast::Location;
 use crate::flat_ast::{DeclBase, TypeKind, TypeShape, UnionDeclaration, UnionMember};
 use crate::name::NamingContext;
@@ -27,6 +27,7 @@ impl<'node, 'src> Compiler<'node, 'src> {
 
         let mut members = vec![];
         let mut member_names = CanonicalNames::new();
+        let mut ordinal_spans = std::collections::HashMap::new();
         for member in &decl.members {
             if !self.is_member_active(member.element.span().data.as_ptr() as usize) {
                 continue;
@@ -61,6 +62,12 @@ impl<'node, 'src> Compiler<'node, 'src> {
                 }
             };
 
+            if let Some(ord) = &member.ordinal
+                && ordinal != 0
+            {
+                ordinal_spans.entry(ordinal).or_insert(ord.element.span());
+            }
+
             if let Some(prev) = members.iter().find(|m: &&UnionMember| m.ordinal == ordinal)
                 && ordinal != 0
             {
@@ -76,11 +83,17 @@ impl<'node, 'src> Compiler<'node, 'src> {
                         "{}:{}:{}",
                         prev.location.filename, prev.location.line, prev.location.column
                     );
-                    self.reporter.fail(
+                    let labels = ordinal_spans
+                        .get(&ordinal)
+                        .map(|span| vec![Label::new(*span, "ordinal previously used here")])
+                        .unwrap_or_default();
+                    self.reporter.fail_with(
                         Error::ErrDuplicateUnionMemberOrdinal(flyweights::FlyStr::new(
                             format!("{}", &location_str).into_boxed_str(),
                         )),
                         member.ordinal.as_ref().unwrap().element.span(),
+                        labels,
+                        vec![],
                     );
                 }
             }
@@ -0,0 +1,564 @@
+//! Completion at a cursor position, for editors.
+//!
+//! The context is worked out from the tokens before the cursor rather than
+//! from the syntax tree, since the declaration being typed rarely parses.
+//! The names to offer come from the declarations of the other files, as far
+//! as the recovering parser got with them: the types of the current library
+//! and of the libraries imported by the current file, and the members of
+//! enums and bits. Attribute names and arguments come from the
+//! [`AttributeSchemaMap`].
+
+use std::collections::BTreeMap;
+
+use crate::attribute_schema::{AttributeSchemaMap, Kind};
+use crate::flat_ast::DeclarationKind;
+use crate::lexer::Lexer;
+use crate::raw_ast::{self, RawDecl};
+use crate::reporter::Reporter;
+use crate::source_file::SourceFile;
+use crate::token::{Token, TokenKind, TokenSubkind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompletionKind {
+    Keyword,
+    Type,
+    Library,
+    Member,
+    Attribute,
+    AttributeArgument,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    pub label: String,
+    /// What the label names, e.g. `struct` or the full name of a library.
+    pub detail: Option<String>,
+}
+
+const BUILTIN_TYPES: &[&str] = &[
+    "array",
+    "bool",
+    "box",
+    "client_end",
+    "float32",
+    "float64",
+    "int16",
+    "int32",
+    "int64",
+    "int8",
+    "server_end",
+    "string",
+    "uint16",
+    "uint32",
+    "uint64",
+    "uint8",
+    "vector",
+];
+
+const DECLARATION_KEYWORDS: &[&str] = &[
+    "alias",
+    "ajar",
+    "closed",
+    "const",
+    "open",
+    "protocol",
+    "resource_definition",
+    "service",
+    "type",
+    "using",
+];
+
+const LAYOUT_KEYWORDS: &[&str] = &[
+    "bits", "enum", "flexible", "resource", "strict", "struct", "table", "union",
+];
+
+/// A declaration of some library, as far as completion cares.
+struct Declaration {
+    name: String,
+    kind: DeclarationKind,
+    /// The members of an enum or bits.
+    members: Vec<String>,
+}
+
+/// What the cursor is in the middle of.
+#[derive(Debug, PartialEq)]
+enum Context<'a> {
+    Nothing,
+    /// The start of a top-level declaration.
+    Declaration {
+        library_declared: bool,
+    },
+    /// After `open`, `ajar` or `closed`.
+    Protocol,
+    /// Where a layout or a type may go, like after `type Foo =`.
+    Layout {
+        types: bool,
+    },
+    Type,
+    /// The start of a method.
+    Method,
+    /// After `using`.
+    Library,
+    /// After a dotted name, like `Color.` or `fuchsia.dep.`.
+    Dotted(Vec<&'a str>),
+    AttributeName,
+    AttributeArgument(&'a str),
+}
+
+/// The kind of block a `{` opened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Block {
+    Layout(TokenSubkind),
+    Protocol,
+    Other,
+}
+
+/// The completions at byte `offset` of `source`, by kind and then label.
+/// `files` are the parsed files of the current library and of its
+/// dependencies, which may include `source` itself.
+pub fn complete(
+    source: &SourceFile,
+    files: &[raw_ast::File<'_>],
+    offset: usize,
+) -> Vec<Completion> {
+    let reporter = Reporter::new();
+    let mut lexer = Lexer::new(source, &reporter);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.lex();
+        match token.kind {
+            TokenKind::EndOfFile => break,
+            TokenKind::StartOfFile | TokenKind::DocComment => {}
+            _ => tokens.push(token),
+        }
+    }
+
+    let start_of =
+        |token: &Token<'_>| token.span.data.as_ptr() as usize - source.data().as_ptr() as usize;
+    let end = tokens
+        .iter()
+        .position(|t| start_of(t) >= offset)
+        .unwrap_or(tokens.len());
+    let mut before = &tokens[..end];
+    let mut prefix = "";
+    if let Some(last) = before.last() {
+        let start = start_of(last);
+        if offset < start + last.span.data.len()
+            || (offset == start + last.span.data.len() && last.kind == TokenKind::Identifier)
+        {
+            if last.kind != TokenKind::Identifier {
+                return Vec::new();
+            }
+            prefix = &last.span.data[..offset - start];
+            before = &before[..before.len() - 1];
+        }
+    }
+
+    let (library, imports) = header(&tokens);
+    let mut libraries: BTreeMap<String, Vec<Declaration>> = BTreeMap::new();
+    for file in files {
+        if let Some(decl) = &file.library_decl {
+            libraries
+                .entry(decl.path.to_string())
+                .or_default()
+                .extend(file.raw_decls().filter_map(|d| declaration(&d)));
+        }
+    }
+    let no_declarations = Vec::new();
+    let own = library
+        .as_ref()
+        .and_then(|l| libraries.get(l))
+        .unwrap_or(&no_declarations);
+
+    let mut completions = Vec::new();
+    let keyword = |label: &str| Completion {
+        kind: CompletionKind::Keyword,
+        label: label.to_string(),
+        detail: None,
+    };
+    let add_types = |completions: &mut Vec<Completion>| {
+        completions.extend(BUILTIN_TYPES.iter().map(|t| Completion {
+            kind: CompletionKind::Type,
+            label: t.to_string(),
+            detail: Some("builtin".to_string()),
+        }));
+        completions.extend(
+            own.iter()
+                .filter(|d| d.kind != DeclarationKind::Const)
+                .map(type_completion),
+        );
+        completions.extend(imports.iter().map(|(name, library)| Completion {
+            kind: CompletionKind::Library,
+            label: name.clone(),
+            detail: Some(library.clone()),
+        }));
+    };
+
+    match context(before) {
+        Context::Nothing => {}
+        Context::Declaration { library_declared } => {
+            if !library_declared {
+                completions.push(keyword("library"));
+            }
+            completions.extend(DECLARATION_KEYWORDS.iter().map(|k| keyword(k)));
+        }
+        Context::Protocol => completions.push(keyword("protocol")),
+        Context::Layout { types } => {
+            completions.extend(LAYOUT_KEYWORDS.iter().map(|k| keyword(k)));
+            if types {
+                add_types(&mut completions);
+            }
+        }
+        Context::Type => add_types(&mut completions),
+        Context::Method => {
+            completions.extend(["compose", "flexible", "strict"].map(keyword));
+        }
+        Context::Library => {
+            completions.extend(
+                libraries
+                    .keys()
+                    .filter(|l| Some(*l) != library.as_ref())
+                    .map(|l| Completion {
+                        kind: CompletionKind::Library,
+                        label: l.clone(),
+                        detail: None,
+                    }),
+            );
+        }
+        Context::Dotted(path) => {
+            let path = path.join(".");
+            // A library, by its alias or full name.
+            if let Some((_, name)) = imports.iter().find(|(name, _)| *name == path) {
+                completions.extend(
+                    libraries
+                        .get(name)
+                        .into_iter()
+                        .flatten()
+                        .map(type_completion),
+                );
+            }
+            // The next component of an imported library's name.
+            for (name, library) in &imports {
+                if let Some(rest) = name.strip_prefix(&path).and_then(|r| r.strip_prefix('.')) {
+                    let next = rest.split('.').next().unwrap_or(rest);
+                    completions.push(Completion {
+                        kind: CompletionKind::Library,
+                        label: next.to_string(),
+                        detail: Some(library.clone()),
+                    });
+                }
+            }
+            // The members of an enum or bits.
+            let (decls, name) = match path.rsplit_once('.') {
+                Some((prefix, name)) => (
+                    imports
+                        .iter()
+                        .find(|(import, _)| import == prefix)
+                        .and_then(|(_, library)| libraries.get(library)),
+                    name,
+                ),
+                None => (Some(own), path.as_str()),
+            };
+            if let Some(decl) = decls.into_iter().flatten().find(|d| d.name == name) {
+                completions.extend(decl.members.iter().map(|m| Completion {
+                    kind: CompletionKind::Member,
+                    label: m.clone(),
+                    detail: Some(format!("{} member", decl.kind)),
+                }));
+            }
+        }
+        Context::AttributeName => {
+            completions.extend(
+                AttributeSchemaMap::new()
+                    .schemas
+                    .into_iter()
+                    .filter(|(_, schema)| schema.kind != Kind::Deprecated)
+                    .map(|(name, _)| Completion {
+                        kind: CompletionKind::Attribute,
+                        label: name,
+                        detail: None,
+                    }),
+            );
+        }
+        Context::AttributeArgument(attribute) => {
+            if let Some(schema) = AttributeSchemaMap::new().schemas.get(attribute) {
+                completions.extend(
+                    schema
+                        .arg_schemas
+                        .keys()
+                        .filter(|name| *name != "value")
+                        .map(|name| Completion {
+                            kind: CompletionKind::AttributeArgument,
+                            label: name.clone(),
+                            detail: Some(attribute.to_string()),
+                        }),
+                );
+            }
+        }
+    }
+    completions.retain(|c| c.label.starts_with(prefix));
+    completions.sort();
+    completions.dedup();
+    completions
+}
+
+fn type_completion(decl: &Declaration) -> Completion {
+    Completion {
+        kind: CompletionKind::Type,
+        label: decl.name.clone(),
+        detail: Some(decl.kind.to_string()),
+    }
+}
+
+fn declaration(decl: &RawDecl<'_, '_>) -> Option<Declaration> {
+    let name = decl.name()?.data().to_string();
+    let kind = decl.kind();
+    let mut members = Vec::new();
+    if matches!(kind, DeclarationKind::Enum | DeclarationKind::Bits) {
+        decl.for_each_member(|member, _, _| members.push(member.data().to_string()));
+    }
+    Some(Declaration {
+        name,
+        kind,
+        members,
+    })
+}
+
+/// The library a file declares, and the names it imports libraries by: the
+/// alias if there is one, or else the full name.
+fn header(tokens: &[Token<'_>]) -> (Option<String>, Vec<(String, String)>) {
+    let mut library = None;
+    let mut imports = Vec::new();
+    for statement in tokens.split(|t| t.kind == TokenKind::Semicolon) {
+        // Skip any attributes before the keyword.
+        let Some(start) = statement.iter().enumerate().position(|(i, t)| {
+            matches!(t.subkind, TokenSubkind::Library | TokenSubkind::Using)
+                && (i == 0
+                    || matches!(
+                        statement[i - 1].kind,
+                        TokenKind::Identifier | TokenKind::RightParen
+                    ))
+        }) else {
+            continue;
+        };
+        let statement = &statement[start..];
+        let first = &statement[0];
+        let path = |tokens: &[Token<'_>]| -> String {
+            tokens
+                .iter()
+                .take_while(|t| t.subkind != TokenSubkind::As)
+                .map(|t| t.span.data)
+                .collect()
+        };
+        match first.subkind {
+            TokenSubkind::Library => library = Some(path(&statement[1..])),
+            TokenSubkind::Using => {
+                let name = path(&statement[1..]);
+                let alias = statement
+                    .iter()
+                    .position(|t| t.subkind == TokenSubkind::As)
+                    .and_then(|i| statement.get(i + 1))
+                    .map(|t| t.span.data.to_string());
+                imports.push((alias.unwrap_or_else(|| name.clone()), name));
+            }
+            _ => {}
+        }
+    }
+    (library, imports)
+}
+
+/// Works out the context from the tokens before the cursor, by following
+/// the blocks and the statement the cursor is in.
+fn context<'a>(tokens: &[Token<'a>]) -> Context<'a> {
+    let mut blocks: Vec<(Block, Vec<&Token<'a>>)> = Vec::new();
+    let mut statement: Vec<&Token<'a>> = Vec::new();
+    let mut library_declared = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        match token.kind {
+            TokenKind::At => {
+                // Attributes are left out of the statement.
+                let Some(name) = tokens.get(i) else {
+                    return Context::AttributeName;
+                };
+                i += 1;
+                if tokens.get(i).map(|t| t.kind) == Some(TokenKind::LeftParen) {
+                    let mut depth = 0;
+                    let mut last = i;
+                    while let Some(t) = tokens.get(last) {
+                        match t.kind {
+                            TokenKind::LeftParen => depth += 1,
+                            TokenKind::RightParen => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break;
+                        }
+                        last += 1;
+                    }
+                    if last == tokens.len() {
+                        return match tokens[last - 1].kind {
+                            TokenKind::LeftParen | TokenKind::Comma => {
+                                Context::AttributeArgument(name.span.data)
+                            }
+                            _ => Context::Nothing,
+                        };
+                    }
+                    i = last + 1;
+                }
+            }
+            TokenKind::LeftCurly => {
+                let block = match statement.iter().rev().map(|t| t.subkind).find(|s| {
+                    matches!(
+                        s,
+                        TokenSubkind::Struct
+                            | TokenSubkind::Table
+                            | TokenSubkind::Union
+                            | TokenSubkind::Overlay
+                            | TokenSubkind::Enum
+                            | TokenSubkind::Bits
+                            | TokenSubkind::Protocol
+                    )
+                }) {
+                    Some(TokenSubkind::Protocol) => Block::Protocol,
+                    Some(layout) => Block::Layout(layout),
+                    None => Block::Other,
+                };
+                blocks.push((block, std::mem::take(&mut statement)));
+            }
+            TokenKind::RightCurly => {
+                if let Some((_, outer)) = blocks.pop() {
+                    statement = outer;
+                }
+                statement.push(token);
+            }
+            TokenKind::Semicolon => {
+                if blocks.is_empty()
+                    && statement.first().map(|t| t.subkind) == Some(TokenSubkind::Library)
+                {
+                    library_declared = true;
+                }
+                statement.clear();
+            }
+            _ => statement.push(token),
+        }
+    }
+
+    let kinds: Vec<TokenKind> = statement.iter().map(|t| t.kind).collect();
+    let subkinds: Vec<TokenSubkind> = statement.iter().map(|t| t.subkind).collect();
+    let last = kinds.last().copied();
+    let last_subkind = subkinds.last().copied();
+
+    if last == Some(TokenKind::Dot) {
+        let mut path = Vec::new();
+        let mut rest = &statement[..statement.len() - 1];
+        while let [init @ .., name] = rest
+            && name.kind == TokenKind::Identifier
+        {
+            path.insert(0, name.span.data);
+            match init {
+                [init @ .., dot] if dot.kind == TokenKind::Dot => rest = init,
+                _ => break,
+            }
+        }
+        return if path.is_empty() {
+            Context::Nothing
+        } else {
+            Context::Dotted(path)
+        };
+    }
+
+    // Inside the parameters of a type, like `vector<` or `array<Foo, 3>`.
+    let open_angles = kinds.iter().fold(0i32, |depth, kind| match kind {
+        TokenKind::LeftAngle => depth + 1,
+        TokenKind::RightAngle => depth - 1,
+        _ => depth,
+    });
+    if last == Some(TokenKind::LeftAngle) {
+        return Context::Type;
+    }
+    if open_angles > 0 {
+        return Context::Nothing;
+    }
+
+    let is_modifier = |s: &TokenSubkind| {
+        matches!(
+            s,
+            TokenSubkind::Strict | TokenSubkind::Flexible | TokenSubkind::Resource
+        )
+    };
+    // After a member or parameter name, or modifiers in its place.
+    let layout_or_type = |name_index: usize| {
+        if statement.len() == name_index + 1 {
+            Context::Layout { types: true }
+        } else if subkinds[name_index + 1..].iter().all(is_modifier) {
+            Context::Layout { types: false }
+        } else {
+            Context::Nothing
+        }
+    };
+
+    match blocks.last().map(|(block, _)| *block) {
+        None => match subkinds.first() {
+            None => Context::Declaration { library_declared },
+            Some(TokenSubkind::Open | TokenSubkind::Ajar | TokenSubkind::Closed)
+                if statement.len() == 1 =>
+            {
+                Context::Protocol
+            }
+            Some(TokenSubkind::Using) if statement.len() == 1 => Context::Library,
+            Some(TokenSubkind::Const) if statement.len() == 2 => Context::Type,
+            Some(TokenSubkind::Alias) if last == Some(TokenKind::Equal) => Context::Type,
+            Some(TokenSubkind::Type) => match kinds.iter().position(|k| *k == TokenKind::Equal) {
+                Some(equal) if equal + 1 == statement.len() => Context::Layout { types: true },
+                Some(equal) if subkinds[equal + 1..].iter().all(is_modifier) => {
+                    Context::Layout { types: false }
+                }
+                _ if last == Some(TokenKind::Colon) => Context::Type,
+                _ => Context::Nothing,
+            },
+            _ => Context::Nothing,
+        },
+        Some(Block::Layout(
+            TokenSubkind::Struct
+            | TokenSubkind::Table
+            | TokenSubkind::Union
+            | TokenSubkind::Overlay,
+        )) => match kinds.as_slice() {
+            [TokenKind::Identifier, ..] => layout_or_type(0),
+            [
+                TokenKind::NumericLiteral,
+                TokenKind::Colon,
+                TokenKind::Identifier,
+                ..,
+            ] => layout_or_type(2),
+            _ => Context::Nothing,
+        },
+        Some(Block::Layout(_)) => Context::Nothing,
+        Some(Block::Protocol) => {
+            if statement.is_empty() {
+                return Context::Method;
+            }
+            if subkinds == [TokenSubkind::Compose] {
+                return Context::Type;
+            }
+            if last == Some(TokenKind::LeftParen) || last_subkind == Some(TokenSubkind::Error) {
+                return Context::Layout { types: true };
+            }
+            match kinds.iter().rposition(|k| *k == TokenKind::LeftParen) {
+                Some(paren) if subkinds[paren + 1..].iter().all(is_modifier) => {
+                    Context::Layout { types: false }
+                }
+                _ => Context::Nothing,
+            }
+        }
+        Some(Block::Other) => match kinds.as_slice() {
+            // A service member, or a resource property.
+            [TokenKind::Identifier] if subkinds != [TokenSubkind::Properties] => Context::Type,
+            _ => Context::Nothing,
+        },
+    }
+}
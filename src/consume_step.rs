@@ -1,10 +1,11 @@
 use crate::compiler::{Compiler, RawDecl};
-use crate::diagnostics::Error;
+use crate::diagnostics::{Error, Label};
 use crate::name::NamingContext;
 use crate::raw_ast;
 use crate::raw_ast::AttributeList;
 use crate::source_span::SourceSpan;
 use crate::step::Step;
+use crate::versioning_types::Availability;
 
 use crate::attribute_schema;
 use crate::names::OwnedLibraryName;
@@ -28,7 +29,11 @@ impl<'node, 'src> Step<'node, 'src> for ConsumeStep<'node, 'src> {
         let mut all_library_attributes = Vec::new();
         let mut main_library_decl: Option<raw_ast::LibraryDeclaration> = None;
 
-        let mut dependent_library_names = std::collections::HashSet::new();
+        let mut dependent_library_names: std::collections::HashSet<String> = compiler
+            .precompiled_libraries
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
         for file in self.dependency_files {
             if let Some(decl) = &file.library_decl {
                 dependent_library_names.insert(decl.path.to_string());
@@ -49,8 +54,12 @@ impl<'node, 'src> Step<'node, 'src> for ConsumeStep<'node, 'src> {
                 }
             }
 
-            let mut file_imports = std::collections::HashSet::new();
-            let mut file_import_paths = std::collections::HashSet::new();
+            // Maps each imported local name and library path to the `using`
+            // that introduced it, so conflicts can point at the earlier import.
+            let mut file_imports: std::collections::HashMap<String, SourceSpan> =
+                std::collections::HashMap::new();
+            let mut file_import_paths: std::collections::HashMap<String, SourceSpan> =
+                std::collections::HashMap::new();
             for using_decl in &file.using_decls {
                 let span = unsafe {
                     std::mem::transmute::<SourceSpan, SourceSpan>(using_decl.element.span())
@@ -63,6 +72,14 @@ impl<'node, 'src> Step<'node, 'src> for ConsumeStep<'node, 'src> {
                 }
 
                 let path = using_decl.using_path.to_string();
+                compiler.record_library_reference(
+                    unsafe {
+                        std::mem::transmute::<SourceSpan, SourceSpan>(
+                            using_decl.using_path.element.span(),
+                        )
+                    },
+                    &path,
+                );
                 let local_name = using_decl
                     .maybe_alias
                     .as_ref()
@@ -70,9 +87,18 @@ impl<'node, 'src> Step<'node, 'src> for ConsumeStep<'node, 'src> {
                     .unwrap_or_else(|| path.clone());
 
                 if !dependent_library_names.contains(&path) && path != main_library_name {
-                    compiler.reporter.fail(
+                    let notes = compiler
+                        .searched_library_paths
+                        .get(&path)
+                        .into_iter()
+                        .flatten()
+                        .map(|dir| format!("searched {}", dir))
+                        .collect();
+                    compiler.reporter.fail_with(
                         Error::ErrUnknownLibrary(flyweights::FlyStr::new(path.to_string())),
                         span,
+                        vec![],
+                        notes,
                     );
                     continue;
                 }
@@ -95,33 +121,46 @@ impl<'node, 'src> Step<'node, 'src> for ConsumeStep<'node, 'src> {
                         )),
                         err_span,
                     );
-                } else if file_import_paths.contains(&path) {
-                    compiler.reporter.fail(
+                } else if let Some(prev_span) = file_import_paths.get(&path) {
+                    compiler.reporter.fail_with(
                         Error::ErrDuplicateLibraryImport(flyweights::FlyStr::new(
                             path.to_string().into_boxed_str(),
                         )),
                         span,
+                        vec![Label::new(*prev_span, "previously imported here")],
+                        vec![],
                     );
-                } else if file_imports.contains(&local_name) {
+                } else if let Some(prev_span) = file_imports.get(&local_name) {
+                    let labels = vec![Label::new(
+                        *prev_span,
+                        format!("'{}' previously imported here", local_name),
+                    )];
                     if using_decl.maybe_alias.is_some() {
-                        compiler.reporter.fail(
+                        compiler.reporter.fail_with(
                             Error::ErrConflictingLibraryImportAlias(
                                 flyweights::FlyStr::new(path.to_string()),
                                 flyweights::FlyStr::new(local_name.to_string()),
                             ),
                             span,
+                            labels,
+                            vec![],
                         );
                     } else {
-                        compiler.reporter.fail(
+                        compiler.reporter.fail_with(
                             Error::ErrConflictingLibraryImport(flyweights::FlyStr::new(
                                 path.to_string().into_boxed_str(),
                             )),
                             span,
+                            labels,
+                            vec![
+                                "use 'using ... as <alias>;' to import it under a different name"
+                                    .to_string(),
+                            ],
                         );
                     }
                 } else {
-                    file_imports.insert(local_name.clone());
-                    file_import_paths.insert(path.clone());
+                    file_imports.insert(local_name.clone(), span);
+                    file_import_paths.insert(path.clone(), span);
                     // Add to global library_imports for resolution.
                     // If multiple files import the same library with different aliases,
                     // we add them all; but our resolve_type is currently global.
@@ -158,20 +197,22 @@ impl<'node, 'src> Step<'node, 'src> for ConsumeStep<'node, 'src> {
         }
 
         // Check for collisions between local names and library imports
-        let mut canonical_names: std::collections::HashMap<String, (String, String, String)> =
-            std::collections::HashMap::new();
+        let mut canonical_names: std::collections::HashMap<
+            String,
+            (String, String, SourceSpan<'src>),
+        > = std::collections::HashMap::new();
 
         for (local_name, import) in &compiler.library_imports {
             let canon = attribute_schema::canonicalize(&local_name.to_string());
-            let span = import.element.span();
-            let site = span.position_str();
+            let span =
+                unsafe { std::mem::transmute::<SourceSpan, SourceSpan>(import.element.span()) };
             canonical_names.insert(
                 canon,
-                ((local_name).to_string(), "library import".to_string(), site),
+                ((local_name).to_string(), "library import".to_string(), span),
             );
         }
 
-        let mut errors_to_emit: Vec<(Error, SourceSpan<'src>)> = Vec::new();
+        let mut errors_to_emit: Vec<(Error, SourceSpan<'src>, Vec<Label<'src>>)> = Vec::new();
 
         let all_files = self.dependency_files.iter().chain(self.main_files.iter());
 
@@ -182,116 +223,127 @@ impl<'node, 'src> Step<'node, 'src> for ConsumeStep<'node, 'src> {
                 .map(|l| l.path.to_string())
                 .unwrap_or_else(|| main_library_name.clone());
 
-            let insert_decl =
-                |compiler: &mut Compiler<'node, 'src>,
-                 canonical_names: &mut std::collections::HashMap<
-                    String,
-                    (String, String, String),
-                >,
-                 name: String,
-                 local_decl_name: &str,
-                 decl: RawDecl<'node, 'src>,
-                 decl_kind: &'static str,
-                 is_anonymous: bool,
-                 errors_to_emit: &mut Vec<(Error, SourceSpan<'src>)>| {
-                    if !compiler.is_active(decl.attributes()) {
-                        return;
-                    }
-                    if let Some((lib, _)) = name.rsplit_once('/') {
-                        // We only check for collisions in the main library!
-                        if lib == compiler.library_name.to_string() && !is_anonymous {
-                            let canon = attribute_schema::canonicalize(local_decl_name);
-                            let span = decl.element().span();
-                            let site = span.position_str();
-
-                            if let Some((prev_raw, prev_kind, prev_site)) =
-                                canonical_names.get(&canon)
-                            {
-                                let err_span = unsafe {
-                                    std::mem::transmute::<SourceSpan<'_>, SourceSpan<'_>>(span)
-                                };
-
-                                let is_versioned = decl.attributes().is_some_and(|attrs| {
+            let insert_decl = |compiler: &mut Compiler<'node, 'src>,
+                               canonical_names: &mut std::collections::HashMap<
+                String,
+                (String, String, SourceSpan<'src>),
+            >,
+                               name: String,
+                               local_decl_name: &str,
+                               decl: RawDecl<'node, 'src>,
+                               decl_kind: &'static str,
+                               is_anonymous: bool,
+                               errors_to_emit: &mut Vec<(
+                Error,
+                SourceSpan<'src>,
+                Vec<Label<'src>>,
+            )>| {
+                if !compiler.is_decl_active(&decl) {
+                    return;
+                }
+                if let Some((lib, _)) = name.rsplit_once('/') {
+                    // We only check for collisions in the main library!
+                    if lib == compiler.library_name.to_string() && !is_anonymous {
+                        let canon = attribute_schema::canonicalize(local_decl_name);
+                        let span = decl.element().span();
+                        let err_span =
+                            unsafe { std::mem::transmute::<SourceSpan<'_>, SourceSpan<'_>>(span) };
+
+                        if let Some((prev_raw, prev_kind, prev_span)) = canonical_names.get(&canon)
+                        {
+                            let prev_site = prev_span.position_str();
+                            let labels = vec![Label::new(
+                                *prev_span,
+                                format!("previous {} '{}' declared here", prev_kind, prev_raw),
+                            )];
+
+                            let is_versioned = decl.attributes().is_some_and(|attrs| {
+                                attrs.attributes.iter().any(|a| {
+                                    a.name.data() == "available"
+                                        || a.provenance == AttributeProvenance::ModifierAvailability
+                                })
+                            });
+                            let prev_full_name = format!("{}/{}", lib, prev_raw);
+                            let prev_is_versioned = compiler
+                                .raw_decls
+                                .get::<str>(prev_full_name.as_ref())
+                                .and_then(|d| d.attributes())
+                                .is_some_and(|attrs| {
                                     attrs.attributes.iter().any(|a| {
                                         a.name.data() == "available"
                                             || a.provenance
                                                 == AttributeProvenance::ModifierAvailability
                                     })
                                 });
-                                let prev_full_name = format!("{}/{}", lib, prev_raw);
-                                let prev_is_versioned = compiler
-                                    .raw_decls
-                                    .get::<str>(prev_full_name.as_ref())
-                                    .and_then(|d| d.attributes())
-                                    .is_some_and(|attrs| {
-                                        attrs.attributes.iter().any(|a| {
-                                            a.name.data() == "available"
-                                                || a.provenance
-                                                    == AttributeProvenance::ModifierAvailability
-                                        })
-                                    });
-
-                                if is_versioned
-                                    && prev_is_versioned
-                                    && prev_kind != "library import"
-                                {
-                                    // Assume structurally sound versioning and overlap resolution happens in availability_step.
-                                } else if prev_raw == local_decl_name {
-                                    if prev_kind == "library import" {
-                                        errors_to_emit.push((
-                                            Error::ErrDeclNameConflictsWithLibraryImport(
-                                                flyweights::FlyStr::new(
-                                                    local_decl_name.to_string().into_boxed_str(),
-                                                ),
-                                            ),
-                                            err_span,
-                                        ));
-                                    } else {
-                                        errors_to_emit.push((
-                                            Error::ErrNameCollision(
-                                                flyweights::FlyStr::new(decl_kind.to_string()),
-                                                flyweights::FlyStr::new(
-                                                    local_decl_name.to_string().into_boxed_str(),
-                                                ),
-                                                flyweights::FlyStr::new(prev_kind.to_string()),
-                                                flyweights::FlyStr::new(prev_site.to_string()),
-                                            ),
-                                            err_span,
-                                        ));
-                                    }
-                                } else if prev_kind == "library import" {
+
+                            if is_versioned && prev_is_versioned && prev_kind != "library import" {
+                                // Assume structurally sound versioning and overlap resolution happens in availability_step.
+                            } else if prev_raw == local_decl_name {
+                                if prev_kind == "library import" {
                                     errors_to_emit.push((
-                                        Error::ErrDeclNameConflictsWithLibraryImportCanonical(
-                                            flyweights::FlyStr::new(local_decl_name.to_string()),
-                                            flyweights::FlyStr::new(canon),
+                                        Error::ErrDeclNameConflictsWithLibraryImport(
+                                            flyweights::FlyStr::new(
+                                                local_decl_name.to_string().into_boxed_str(),
+                                            ),
                                         ),
                                         err_span,
+                                        labels,
                                     ));
                                 } else {
                                     errors_to_emit.push((
-                                        Error::ErrNameCollisionCanonical(
+                                        Error::ErrNameCollision(
                                             flyweights::FlyStr::new(decl_kind.to_string()),
-                                            flyweights::FlyStr::new(local_decl_name.to_string()),
+                                            flyweights::FlyStr::new(
+                                                local_decl_name.to_string().into_boxed_str(),
+                                            ),
                                             flyweights::FlyStr::new(prev_kind.to_string()),
-                                            flyweights::FlyStr::new(prev_raw.to_string()),
                                             flyweights::FlyStr::new(prev_site.to_string()),
-                                            flyweights::FlyStr::new(canon),
                                         ),
                                         err_span,
+                                        labels,
                                     ));
                                 }
+                            } else if prev_kind == "library import" {
+                                errors_to_emit.push((
+                                    Error::ErrDeclNameConflictsWithLibraryImportCanonical(
+                                        flyweights::FlyStr::new(local_decl_name.to_string()),
+                                        flyweights::FlyStr::new(canon),
+                                    ),
+                                    err_span,
+                                    labels,
+                                ));
                             } else {
-                                canonical_names.insert(
-                                    canon,
-                                    (local_decl_name.to_string(), decl_kind.to_string(), site),
-                                );
+                                errors_to_emit.push((
+                                    Error::ErrNameCollisionCanonical(
+                                        flyweights::FlyStr::new(decl_kind.to_string()),
+                                        flyweights::FlyStr::new(local_decl_name.to_string()),
+                                        flyweights::FlyStr::new(prev_kind.to_string()),
+                                        flyweights::FlyStr::new(prev_raw.to_string()),
+                                        flyweights::FlyStr::new(prev_site.to_string()),
+                                        flyweights::FlyStr::new(canon),
+                                    ),
+                                    err_span,
+                                    labels,
+                                ));
                             }
+                        } else {
+                            canonical_names.insert(
+                                canon,
+                                (local_decl_name.to_string(), decl_kind.to_string(), err_span),
+                            );
                         }
                     }
-                    compiler
-                        .raw_decls
-                        .insert(OwnedQualifiedName::from(name.to_string()), decl);
-                };
+                }
+                let ptr = decl.element().span().data.as_ptr() as usize;
+                let avail = compiler
+                    .raw_decl_availability
+                    .get(&ptr)
+                    .cloned()
+                    .unwrap_or_else(Availability::unbounded);
+                let name = OwnedQualifiedName::from(name.to_string());
+                compiler.decl_availability.insert(name.clone(), avail);
+                compiler.raw_decls.insert(name, decl);
+            };
 
             for decl in &file.type_decls {
                 let local_name = decl.name.data();
@@ -485,9 +537,13 @@ impl<'node, 'src> Step<'node, 'src> for ConsumeStep<'node, 'src> {
                                 && method.modifiers[0].attributes.is_some());
                         if has_complex_modifiers {
                             let span = method.modifiers.first().unwrap().element.span();
-                            errors_to_emit.push((Error::ErrCannotChangeMethodStrictness, unsafe {
-                                std::mem::transmute::<SourceSpan<'_>, SourceSpan<'_>>(span)
-                            }));
+                            errors_to_emit.push((
+                                Error::ErrCannotChangeMethodStrictness,
+                                unsafe {
+                                    std::mem::transmute::<SourceSpan<'_>, SourceSpan<'_>>(span)
+                                },
+                                vec![],
+                            ));
                         }
                     }
 
@@ -585,8 +641,8 @@ impl<'node, 'src> Step<'node, 'src> for ConsumeStep<'node, 'src> {
             }
         }
 
-        for (err, span) in errors_to_emit {
-            compiler.reporter.fail(err, span);
+        for (err, span, labels) in errors_to_emit {
+            compiler.reporter.fail_with(err, span, labels, vec![]);
         }
     }
 }
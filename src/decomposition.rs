@@ -0,0 +1,225 @@
+//! A versioned library compiled at every version at once.
+//!
+//! The compiler works on one version at a time, but [`AvailabilityStep`]
+//! computes the availability of every element at every version. It keeps the
+//! versions at which any element changes in [`Compiler::version_points`],
+//! and, for each declaration, those at which it or anything it refers to
+//! changes in [`Compiler::decl_splits`].
+//!
+//! [`crate::api::decompose`] compiles the library once with the selected
+//! versions, which covers every declaration over the range around them in
+//! which it doesn't change. At the start of each other range, it only
+//! compiles the declarations that change there, with
+//! [`Compiler::compile_only`]. [`Decomposition`] then merges adjacent ranges
+//! over which a declaration's JSON IR is the same.
+//!
+//! Selecting several versions compiles the library at [`Version::LEGACY`],
+//! where each element is as it is at the latest of them that has it. That
+//! depends on which versions are selected, so only a library decomposed with
+//! the same ones selected can be selected at them.
+//!
+//! [`AvailabilityStep`]: crate::availability_step::AvailabilityStep
+//! [`Compiler::version_points`]: crate::compiler::Compiler::version_points
+//! [`Compiler::decl_splits`]: crate::compiler::Compiler::decl_splits
+//! [`Compiler::compile_only`]: crate::compiler::Compiler::compile_only
+
+use std::collections::BTreeSet;
+
+use crate::json_generator::{DeclarationKind, JsonRoot};
+use crate::versioning_types::{Platform, Version, VersionRange, VersionSelection};
+
+/// Where a declaration of the main library can change, across every
+/// version: see [`Compiler::decl_splits`].
+///
+/// [`Compiler::decl_splits`]: crate::compiler::Compiler::decl_splits
+#[derive(Debug, Clone, Default)]
+pub struct DeclSplit {
+    /// The versions at which it or anything it refers to changes.
+    pub points: BTreeSet<Version>,
+    /// The fully qualified names of the main library's declarations it
+    /// refers to, directly or through others.
+    pub references: BTreeSet<String>,
+}
+
+/// A declaration over a range of versions in which it doesn't change.
+#[derive(Debug, Clone)]
+pub struct DecomposedDeclaration {
+    /// The fully qualified name, like `fuchsia.example/Color`.
+    pub name: String,
+    pub kind: DeclarationKind,
+    pub range: VersionRange,
+    /// The declaration as it is in the `--json` output at every version in
+    /// `range`.
+    pub json: serde_json::Value,
+}
+
+/// The declarations of a library at every version of its platform.
+#[derive(Debug, Clone)]
+pub struct Decomposition {
+    platform: Option<Platform>,
+    /// The versions selected to compile the `LEGACY` range, if any.
+    legacy: Option<BTreeSet<Version>>,
+    declarations: Vec<DecomposedDeclaration>,
+}
+
+impl Decomposition {
+    /// Merges `pieces`, each a declaration over a range of versions, into
+    /// the ranges over which each declaration is the same. An unversioned
+    /// library has no platform and a single range; `legacy` are the
+    /// versions selected to compile the `LEGACY` range, if there is one.
+    pub(crate) fn new(
+        platform: Option<Platform>,
+        legacy: Option<BTreeSet<Version>>,
+        mut pieces: Vec<DecomposedDeclaration>,
+    ) -> Self {
+        pieces.sort_by(|a, b| (&a.name, a.range).cmp(&(&b.name, b.range)));
+        let mut declarations: Vec<DecomposedDeclaration> = Vec::new();
+        for piece in pieces {
+            if let Some(previous) = declarations.last_mut()
+                && previous.name == piece.name
+                && previous.range.upper_exclusive == piece.range.lower
+                && previous.kind == piece.kind
+                && previous.json == piece.json
+            {
+                previous.range.upper_exclusive = piece.range.upper_exclusive;
+                continue;
+            }
+            declarations.push(piece);
+        }
+        Self {
+            platform,
+            legacy,
+            declarations,
+        }
+    }
+
+    /// The platform the library is versioned by, or `None` if it is
+    /// unversioned.
+    pub fn platform(&self) -> Option<&Platform> {
+        self.platform.as_ref()
+    }
+
+    /// Every declaration at every version, by name and then by range.
+    pub fn declarations(&self) -> &[DecomposedDeclaration] {
+        &self.declarations
+    }
+
+    /// The declarations the library compiles to with `selection`, by name.
+    /// Only the versions selected for the library's own platform matter.
+    /// Returns an `Err` if several of them are selected, unless the library
+    /// was decomposed with the same ones: where each element is then depends
+    /// on which versions are selected.
+    pub fn select(
+        &self,
+        selection: &VersionSelection,
+    ) -> Result<Vec<&DecomposedDeclaration>, String> {
+        let version = match &self.platform {
+            Some(platform) => {
+                let versions = selection.lookup_all(platform);
+                if versions.len() > 1 && self.legacy.as_ref() != Some(&versions) {
+                    return Err(format!(
+                        "the library wasn't decomposed with versions {} of platform '{}' selected",
+                        versions
+                            .iter()
+                            .map(|v| v.to_string())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                        platform.name()
+                    ));
+                }
+                selection.lookup(platform)
+            }
+            None => Version::HEAD,
+        };
+        Ok(self
+            .declarations
+            .iter()
+            .filter(|d| d.range.contains(version))
+            .collect())
+    }
+}
+
+/// The ranges to compile a library in, given the versions at which its
+/// elements change: from version 1 on, split at each point. With `legacy`,
+/// the last of them ends at `LEGACY`, which is a range of its own.
+pub(crate) fn ranges(points: &BTreeSet<Version>, legacy: bool) -> Vec<VersionRange> {
+    let first = Version::from_number(1).unwrap();
+    let mut bounds: BTreeSet<Version> = points.range(first..Version::LEGACY).copied().collect();
+    bounds.extend([first, Version::POS_INF]);
+    if legacy {
+        bounds.insert(Version::LEGACY);
+    }
+    let bounds: Vec<Version> = bounds.into_iter().collect();
+    bounds
+        .windows(2)
+        .map(|pair| VersionRange::new(pair[0], pair[1]))
+        .collect()
+}
+
+/// The range of `ranges` around `range` over which nothing changes for a
+/// declaration that changes at `points`. `LEGACY` is always a range of its
+/// own.
+pub(crate) fn piece(
+    points: &BTreeSet<Version>,
+    ranges: &[VersionRange],
+    range: VersionRange,
+) -> VersionRange {
+    let boundary = |r: &VersionRange| points.contains(&r.lower) || r.lower == Version::LEGACY;
+    let Some(index) = ranges.iter().position(|r| *r == range) else {
+        return range;
+    };
+    let mut first = index;
+    while first > 0 && !boundary(&ranges[first]) {
+        first -= 1;
+    }
+    let mut last = index;
+    while last + 1 < ranges.len() && !boundary(&ranges[last + 1]) {
+        last += 1;
+    }
+    VersionRange::new(ranges[first].lower, ranges[last].upper_exclusive)
+}
+
+/// `base` with the library's platform selected at the start of `range`,
+/// which isn't the `LEGACY` range.
+pub(crate) fn selection_for(
+    base: &VersionSelection,
+    platform: &Platform,
+    range: VersionRange,
+) -> VersionSelection {
+    let mut selection = base.clone();
+    selection.remove(platform);
+    selection.insert(platform.clone(), BTreeSet::from([range.lower]));
+    selection
+}
+
+/// The declaration of `library` that `name`, declared with `json`, is part
+/// of: itself, or the one whose members declare it inline.
+pub(crate) fn owner(library: &str, name: &str, json: &serde_json::Value) -> String {
+    match json["naming_context"].as_array().and_then(|c| c.first()) {
+        Some(serde_json::Value::String(first)) => format!("{}/{}", library, first),
+        _ => name.to_string(),
+    }
+}
+
+/// The library's own declarations in `root`, with their kinds and JSON IR.
+pub(crate) fn json_declarations(
+    root: &JsonRoot,
+) -> Vec<(String, DeclarationKind, serde_json::Value)> {
+    let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(root) else {
+        return Vec::new();
+    };
+    let mut result = Vec::new();
+    for (field, value) in &fields {
+        if !field.ends_with("_declarations") || field == "external_struct_declarations" {
+            continue;
+        }
+        for json in value.as_array().into_iter().flatten() {
+            if let Some(name) = json["name"].as_str()
+                && let Some(kind) = root.declarations.get(name)
+            {
+                result.push((name.to_string(), kind.clone(), json.clone()));
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,353 @@
+//! Warns about references to elements that are deprecated at the selected
+//! version.
+//!
+//! The references are the ones the resolver recorded in
+//! [`Compiler::references`], so this runs after compilation and walks the main
+//! library again only to find which of them are made by elements in the
+//! selection that aren't deprecated themselves: a deprecated
+//! element may keep using other deprecated ones. Declarations and members of
+//! libraries compiled from source are deprecated if their inherited
+//! [`Availability`] is, narrowed to the referent's platform's selected
+//! version. Those of precompiled dependencies are deprecated if their JSON IR
+//! says so. The warning quotes the `note` of the referent's `@available`
+//! attribute, if it has one.
+
+use std::collections::HashMap;
+
+use crate::compiler::{Compiler, ReferenceKind, Referent};
+use crate::diagnostics::{Error, Label};
+use crate::names::OwnedQualifiedName;
+use crate::raw_ast::{self, AttributeList, CompoundIdentifier, Identifier, SourceElement};
+use crate::source_span::SourceSpan;
+use crate::step::Step;
+use crate::timeline::note;
+use crate::tree_visitor::{self, TreeVisitor};
+use crate::versioning_types::{Availability, Platform, Version, VersionRange};
+
+pub struct DeprecationStep<'node, 'src> {
+    pub main_files: &'node [raw_ast::File<'src>],
+}
+
+impl<'node, 'src> Step<'node, 'src> for DeprecationStep<'node, 'src> {
+    fn run(&mut self, compiler: &mut Compiler<'node, 'src>) {
+        if compiler.allow_deprecated_references {
+            return;
+        }
+        let compiler: &Compiler<'node, 'src> = compiler;
+        let referents = compiler
+            .references
+            .iter()
+            .filter(|r| r.kind != ReferenceKind::Library)
+            .filter_map(|r| match &r.referent {
+                Referent::Declaration(name) => Some((key(&r.span), name.clone())),
+                Referent::Library(_) => None,
+            })
+            .collect();
+        let mut checker = Checker {
+            compiler,
+            platform: compiler
+                .platform
+                .clone()
+                .unwrap_or_else(Platform::unversioned),
+            referents,
+        };
+        for file in self.main_files {
+            checker.visit_file(file);
+        }
+    }
+}
+
+/// Identifies a reference by where its name is.
+fn key(span: &SourceSpan<'_>) -> (usize, usize) {
+    (span.data.as_ptr() as usize, span.data.len())
+}
+
+/// Whether `availability` is deprecated at `version`.
+fn is_deprecated_at(availability: &Availability, version: Version) -> bool {
+    let mut availability = availability.clone();
+    let end = if version == Version::LEGACY {
+        Version::POS_INF
+    } else {
+        availability.removed().unwrap_or(Version::POS_INF)
+    };
+    version < end
+        && availability.narrow(VersionRange::new(version, end)).is_ok()
+        && availability.is_deprecated()
+}
+
+/// A deprecated referent.
+struct Deprecation<'src> {
+    /// Its name, if it was compiled from source.
+    name: Option<SourceSpan<'src>>,
+    note: Option<String>,
+}
+
+struct Checker<'c, 'node, 'src> {
+    compiler: &'c Compiler<'node, 'src>,
+    /// The main library's platform.
+    platform: Platform,
+    referents: HashMap<(usize, usize), OwnedQualifiedName>,
+}
+
+impl<'src> Checker<'_, '_, 'src> {
+    /// Whether an element with `availability` is in the selection and not
+    /// deprecated, so that what it refers to is checked. An element with a
+    /// `@deprecated` attribute counts as deprecated too.
+    fn is_checked(
+        &self,
+        availability: Option<&Availability>,
+        attributes: Option<&AttributeList<'_>>,
+    ) -> bool {
+        let has_deprecated_attribute = attributes.is_some_and(|list| {
+            list.attributes
+                .iter()
+                .any(|a| a.name.data() == "deprecated")
+        });
+        if has_deprecated_attribute {
+            return false;
+        }
+        match availability {
+            Some(availability) => {
+                self.compiler
+                    .version_selection
+                    .intersects(&self.platform, &availability.set())
+                    && !is_deprecated_at(
+                        availability,
+                        self.compiler.version_selection.lookup(&self.platform),
+                    )
+            }
+            None => self.compiler.is_active(attributes),
+        }
+    }
+
+    fn declaration(
+        &mut self,
+        name: &Identifier<'_>,
+        attributes: Option<&AttributeList<'_>>,
+        walk: impl FnOnce(&mut Self),
+    ) {
+        let full_name = self.compiler.library_name.with_declaration(name.data());
+        // Of the declarations that reuse a name, only the one in the
+        // selection is in `raw_decls`.
+        let is_selected = self
+            .compiler
+            .raw_decls
+            .get(&full_name)
+            .and_then(|decl| decl.name())
+            .is_some_and(|id| id.element.span().data.as_ptr() == name.element.span().data.as_ptr());
+        if is_selected
+            && self.is_checked(self.compiler.decl_availability.get(&full_name), attributes)
+        {
+            walk(self);
+        }
+    }
+
+    fn member(
+        &mut self,
+        element: &SourceElement<'_>,
+        attributes: Option<&AttributeList<'_>>,
+        walk: impl FnOnce(&mut Self),
+    ) {
+        let ptr = element.span().data.as_ptr() as usize;
+        if self.is_checked(self.compiler.member_availability.get(&ptr), attributes) {
+            walk(self);
+        }
+    }
+
+    /// The deprecation of `name`, a declaration or member, if it is
+    /// deprecated at its platform's selected version.
+    fn deprecation(&self, name: &OwnedQualifiedName) -> Option<Deprecation<'src>> {
+        let library = name.library().to_owned();
+        let decl_name = library.with_declaration(name.declaration());
+        if let Some(decl) = self.compiler.raw_decls.get(&decl_name) {
+            let platform = if library == self.compiler.library_name {
+                self.platform.clone()
+            } else {
+                Platform::parse(library.versioning_platform()).unwrap_or_else(Platform::unversioned)
+            };
+            let version = self.compiler.version_selection.lookup(&platform);
+            let (id, attributes, availability) = match name.member() {
+                None => (
+                    decl.name()?,
+                    decl.attributes(),
+                    self.compiler.decl_availability.get(&decl_name)?,
+                ),
+                Some(member) => {
+                    // Of the members that reuse a name, the one available
+                    // at `version`.
+                    let mut found = None;
+                    decl.for_each_member(|id, attributes, element| {
+                        let ptr = element.span().data.as_ptr() as usize;
+                        if id.data() == member
+                            && let Some(availability) = self.compiler.member_availability.get(&ptr)
+                            && availability.set().contains(version)
+                        {
+                            found = Some((id, attributes, availability));
+                        }
+                    });
+                    found?
+                }
+            };
+            return is_deprecated_at(availability, version).then(|| Deprecation {
+                name: Some(id.element.span()),
+                note: note(attributes),
+            });
+        }
+
+        let ir = self
+            .compiler
+            .precompiled_decls
+            .get(&decl_name)?
+            .ir
+            .as_ref()?;
+        let element = match name.member() {
+            None => ir,
+            Some(member) => ir
+                .get("members")?
+                .as_array()?
+                .iter()
+                .find(|m| m.get("name").and_then(|n| n.as_str()) == Some(member))?,
+        };
+        if element.get("deprecated").and_then(|d| d.as_bool()) != Some(true) {
+            return None;
+        }
+        Some(Deprecation {
+            name: None,
+            note: ir_note(element),
+        })
+    }
+}
+
+/// The `note` of the `@available` attribute of a declaration or member in
+/// JSON IR.
+fn ir_note(element: &serde_json::Value) -> Option<String> {
+    let available = element
+        .get("maybe_attributes")?
+        .as_array()?
+        .iter()
+        .find(|a| a.get("name").and_then(|n| n.as_str()) == Some("available"))?;
+    let note = available
+        .get("arguments")?
+        .as_array()?
+        .iter()
+        .find(|a| a.get("name").and_then(|n| n.as_str()) == Some("note"))?;
+    Some(note.get("value")?.get("value")?.as_str()?.to_string())
+}
+
+impl<'src> TreeVisitor<'src> for Checker<'_, '_, 'src> {
+    fn visit_compoundidentifier(&mut self, node: &CompoundIdentifier<'src>) {
+        let span = node.element.span();
+        let Some(name) = self.referents.get(&key(&span)) else {
+            return;
+        };
+        let Some(deprecation) = self.deprecation(name) else {
+            return;
+        };
+        let labels = deprecation
+            .name
+            .map(|span| Label::new(span, format!("'{}' is declared here", name)))
+            .into_iter()
+            .collect();
+        let notes = deprecation
+            .note
+            .map(|note| format!("'{}' is deprecated: {}", name, note))
+            .into_iter()
+            .collect();
+        self.compiler.reporter.fail_with(
+            Error::WarnReferenceToDeprecated(flyweights::FlyStr::new(name.to_string())),
+            span,
+            labels,
+            notes,
+        );
+    }
+
+    fn visit_constdeclaration(&mut self, node: &raw_ast::ConstDeclaration<'src>) {
+        self.declaration(&node.name, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_constdeclaration(v, node)
+        });
+    }
+
+    fn visit_aliasdeclaration(&mut self, node: &raw_ast::AliasDeclaration<'src>) {
+        self.declaration(&node.name, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_aliasdeclaration(v, node)
+        });
+    }
+
+    fn visit_typedeclaration(&mut self, node: &raw_ast::TypeDeclaration<'src>) {
+        self.declaration(&node.name, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_typedeclaration(v, node)
+        });
+    }
+
+    fn visit_protocoldeclaration(&mut self, node: &raw_ast::ProtocolDeclaration<'src>) {
+        self.declaration(&node.name, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_protocoldeclaration(v, node)
+        });
+    }
+
+    fn visit_servicedeclaration(&mut self, node: &raw_ast::ServiceDeclaration<'src>) {
+        self.declaration(&node.name, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_servicedeclaration(v, node)
+        });
+    }
+
+    fn visit_resourcedeclaration(&mut self, node: &raw_ast::ResourceDeclaration<'src>) {
+        self.declaration(&node.name, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_resourcedeclaration(v, node)
+        });
+    }
+
+    fn visit_structmember(&mut self, node: &raw_ast::StructMember<'src>) {
+        self.member(&node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_structmember(v, node)
+        });
+    }
+
+    fn visit_unionmember(&mut self, node: &raw_ast::UnionMember<'src>) {
+        self.member(&node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_unionmember(v, node)
+        });
+    }
+
+    fn visit_tablemember(&mut self, node: &raw_ast::TableMember<'src>) {
+        self.member(&node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_tablemember(v, node)
+        });
+    }
+
+    fn visit_enummember(&mut self, node: &raw_ast::EnumMember<'src>) {
+        self.member(&node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_enummember(v, node)
+        });
+    }
+
+    fn visit_bitsmember(&mut self, node: &raw_ast::BitsMember<'src>) {
+        self.member(&node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_bitsmember(v, node)
+        });
+    }
+
+    fn visit_protocolmethod(&mut self, node: &raw_ast::ProtocolMethod<'src>) {
+        self.member(&node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_protocolmethod(v, node)
+        });
+    }
+
+    fn visit_protocolcompose(&mut self, node: &raw_ast::ProtocolCompose<'src>) {
+        self.member(&node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_protocolcompose(v, node)
+        });
+    }
+
+    fn visit_servicemember(&mut self, node: &raw_ast::ServiceMember<'src>) {
+        self.member(&node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_servicemember(v, node)
+        });
+    }
+
+    fn visit_resourceproperty(&mut self, node: &raw_ast::ResourceProperty<'src>) {
+        self.member(&node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_resourceproperty(v, node)
+        });
+    }
+}
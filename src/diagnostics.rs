@@ -13,6 +13,28 @@ pub struct Diagnostic<'a> {
     pub def: Error,
     pub message: String,
     pub span: Option<SourceSpan<'a>>,
+    /// Secondary spans related to the primary one, such as the previous
+    /// declaration in a name collision.
+    pub labels: Vec<Label<'a>>,
+    /// Free-form notes printed after the diagnostic.
+    pub notes: Vec<String>,
+}
+
+/// A secondary span attached to a diagnostic, with a short message
+/// explaining how it relates to the primary span.
+#[derive(Debug, Clone)]
+pub struct Label<'a> {
+    pub span: SourceSpan<'a>,
+    pub message: String,
+}
+
+impl<'a> Label<'a> {
+    pub fn new(span: SourceSpan<'a>, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
 }
 
 #[allow(dead_code, non_camel_case_types)]
@@ -310,6 +332,13 @@ pub enum Error {
     ErrRequestMustBeParameterized,
     ErrDisallowedRequestType,
     ErrDisallowedResponseType,
+    WarnLintInvalidCase(FlyStr, FlyStr, FlyStr, FlyStr),
+    WarnLintLibraryPrefix(FlyStr, FlyStr),
+    WarnLintUnboundedPayload(FlyStr, FlyStr, FlyStr),
+    WarnLintMissingDocComment(FlyStr, FlyStr),
+    WarnLintPreferFlexible(FlyStr, FlyStr),
+    WarnLintDiscouragedType(FlyStr, FlyStr),
+    WarnReferenceToDeprecated(FlyStr),
 }
 
 impl Error {
@@ -562,6 +591,13 @@ impl Error {
             Error::ErrRequestMustBeParameterized => 1024,
             Error::ErrDisallowedRequestType => 1025,
             Error::ErrDisallowedResponseType => 1026,
+            Error::WarnLintInvalidCase(..) => 1027,
+            Error::WarnLintLibraryPrefix(..) => 1028,
+            Error::WarnLintUnboundedPayload(..) => 1029,
+            Error::WarnLintMissingDocComment(..) => 1030,
+            Error::WarnLintPreferFlexible(..) => 1031,
+            Error::WarnLintDiscouragedType(..) => 1032,
+            Error::WarnReferenceToDeprecated(..) => 1033,
         }
     }
 
@@ -781,9 +817,9 @@ impl Error {
             Error::ErrCannotBeRenamed(a0) => FlyStr::new(format!(r#"the @available argument 'renamed' cannot be used on a {}; it can only be used on members of a declaration"#, a0)),
             Error::ErrRenamedWithoutReplacedOrRemoved => r#"the @available argument 'renamed' cannot be used without 'replaced' or 'removed'"#.into(),
             Error::ErrRenamedToSameName(a0, a1) => FlyStr::new(format!(r#"renaming to '{}' has no effect because the element is already named '{}'; either remove the 'renamed' argument or choose a different name"#, a0, a1)),
-            Error::ErrInvalidRemovedAndRenamed(a0, a1, a2, a3) => FlyStr::new(format!(r#"{} is marked removed={}, renamed=\ but the name '{}' is already used at {}"#, a0, a1, a2, a3)),
-            Error::ErrInvalidReplacedAndRenamed(a0, a1, a2, a3) => FlyStr::new(format!(r#"{} is marked replaced={}, renamed=\ but there is no replacement '{}' marked added={}; please define it"#, a0, a1, a2, a3)),
-            Error::ErrInvalidRemovedAbi(a0, a1, a2, a3, a4, a5, a6, a7) => FlyStr::new(format!(r#"{} is marked removed={}, but its {} ({}) is reused at {}; use replaced={}, renamed=\ instead of removed={} if you intend to replace the ABI, otherwise choose a different {}"#, a0, a1, a2, a3, a4, a5, a6, a7)),
+            Error::ErrInvalidRemovedAndRenamed(a0, a1, a2, a3) => FlyStr::new(format!(r#"{0} is marked removed={1}, renamed="{2}", but the name '{2}' is already used at {3}"#, a0, a1, a2, a3)),
+            Error::ErrInvalidReplacedAndRenamed(a0, a1, a2, a3) => FlyStr::new(format!(r#"{0} is marked replaced={1}, renamed="{2}", but there is no replacement '{2}' marked added={3}; please define it"#, a0, a1, a2, a3)),
+            Error::ErrInvalidRemovedAbi(a0, a1, a2, a3, a4, a5, a6, a7) => FlyStr::new(format!(r#"{0} is marked removed={1}, but its {2} ({3}) is reused at {4}; use replaced={5}, renamed="{6}" instead of removed={1} if you intend to replace the ABI, otherwise choose a different {7}"#, a0, a1, a2, a3, a4, a5, a6, a7)),
             Error::ErrInvalidReplacedAbi(a0, a1, a2, a3, a4, a5, a6, a7, a8) => FlyStr::new(format!(r#"{} is marked replaced={}, but its {} ({}) does not match the replacement's {} ({}) at {}; use removed={} if you intend to remove the ABI, otherwise use the same {}"#, a0, a1, a2, a3, a4, a5, a6, a7, a8)),
             Error::ErrInvalidModifierAvailableArgument(a0) => FlyStr::new(format!(r#"invalid argument '{}'; only 'added' and 'removed' are allowed on modifier availabilities"#, a0)),
             Error::ErrCannotChangeMethodStrictness => r#"changing the strictness of a two-way method without error syntax is not allowed because it is ABI breaking"#.into(),
@@ -814,6 +850,13 @@ impl Error {
             Error::ErrRequestMustBeParameterized => "request type must be parameterized".into(),
             Error::ErrDisallowedRequestType => "request type must be struct, table, or union".into(),
             Error::ErrDisallowedResponseType => "response type must be struct, table, or union".into(),
+            Error::WarnLintInvalidCase(a0, a1, a2, a3) => FlyStr::new(format!(r#"{} '{}' should be {}, like '{}'"#, a0, a1, a2, a3)),
+            Error::WarnLintLibraryPrefix(a0, a1) => FlyStr::new(format!(r#"library '{}' should start with one of: {}"#, a0, a1)),
+            Error::WarnLintUnboundedPayload(a0, a1, a2) => FlyStr::new(format!(r#"unbounded {} in member '{}' of the payload of '{}'; give it a maximum size"#, a0, a1, a2)),
+            Error::WarnLintMissingDocComment(a0, a1) => FlyStr::new(format!(r#"{} '{}' should have a doc comment"#, a0, a1)),
+            Error::WarnLintPreferFlexible(a0, a1) => FlyStr::new(format!(r#"{} '{}' is strict; prefer flexible unless its members can never change"#, a0, a1)),
+            Error::WarnLintDiscouragedType(a0, a1) => FlyStr::new(format!(r#"type '{}' is discouraged; prefer {}"#, a0, a1)),
+            Error::WarnReferenceToDeprecated(a0) => FlyStr::new(format!(r#"reference to '{}', which is deprecated; either remove this reference or mark the element that makes it as deprecated"#, a0)),
         }
     }
 
@@ -1066,6 +1109,13 @@ impl Error {
             Error::ErrRequestMustBeParameterized => ErrorKind::Error,
             Error::ErrDisallowedRequestType => ErrorKind::Error,
             Error::ErrDisallowedResponseType => ErrorKind::Error,
+            Error::WarnLintInvalidCase(..) => ErrorKind::Warning,
+            Error::WarnLintLibraryPrefix(..) => ErrorKind::Warning,
+            Error::WarnLintUnboundedPayload(..) => ErrorKind::Warning,
+            Error::WarnLintMissingDocComment(..) => ErrorKind::Warning,
+            Error::WarnLintPreferFlexible(..) => ErrorKind::Warning,
+            Error::WarnLintDiscouragedType(..) => ErrorKind::Warning,
+            Error::WarnReferenceToDeprecated(..) => ErrorKind::Warning,
         }
     }
 
@@ -1318,6 +1368,13 @@ impl Error {
             Error::ErrRequestMustBeParameterized => false,
             Error::ErrDisallowedRequestType => false,
             Error::ErrDisallowedResponseType => false,
+            Error::WarnLintInvalidCase(..) => false,
+            Error::WarnLintLibraryPrefix(..) => false,
+            Error::WarnLintUnboundedPayload(..) => false,
+            Error::WarnLintMissingDocComment(..) => false,
+            Error::WarnLintPreferFlexible(..) => false,
+            Error::WarnLintDiscouragedType(..) => false,
+            Error::WarnReferenceToDeprecated(..) => false,
         }
     }
 
@@ -1660,5 +1717,12 @@ pub fn all_errors() -> Vec<Error> {
         Error::ErrRequestMustBeParameterized,
         Error::ErrDisallowedRequestType,
         Error::ErrDisallowedResponseType,
+        Error::WarnLintInvalidCase("".into(), "".into(), "".into(), "".into()),
+        Error::WarnLintLibraryPrefix("".into(), "".into()),
+        Error::WarnLintUnboundedPayload("".into(), "".into(), "".into()),
+        Error::WarnLintMissingDocComment("".into(), "".into()),
+        Error::WarnLintPreferFlexible("".into(), "".into()),
+        Error::WarnLintDiscouragedType("".into(), "".into()),
+        Error::WarnReferenceToDeprecated("".into()),
     ]
 }
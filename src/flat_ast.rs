@@ -82,7 +82,7 @@ pub struct Location {
     pub length: usize,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TypeShape {
     pub inline_size: u32,
     pub alignment: u32,
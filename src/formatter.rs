@@ -0,0 +1,469 @@
+//! Printing FIDL source in the canonical style, like `fidl-format`.
+//!
+//! The file is parsed losslessly and printed back token by token, so regular
+//! comments survive formatting. Line breaks and spacing are decided by the
+//! tokens themselves and by a [`TreeVisitor`] pass over the raw AST; from the
+//! original layout only comments and blank lines are kept, which makes
+//! formatting idempotent.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::diagnostics::ErrorKind;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::raw_ast::*;
+use crate::reporter::Reporter;
+use crate::source_file::SourceFile;
+use crate::token::{Token, TokenKind};
+use crate::tree_visitor::{self, TreeVisitor};
+
+const INDENT: &str = "    ";
+
+/// Formats `source`. Returns `None` if it has syntax errors, which are
+/// reported to `reporter`.
+pub fn format_source<'a>(source: &'a SourceFile, reporter: &'a Reporter<'a>) -> Option<String> {
+    let errors = || {
+        reporter
+            .diagnostics()
+            .iter()
+            .filter(|d| d.def.kind() == ErrorKind::Error)
+            .count()
+    };
+    let errors_before = errors();
+    let mut lexer = Lexer::new(source, reporter);
+    let mut parser = Parser::new_lossless(&mut lexer, reporter);
+    parser.consume_token(TokenKind::StartOfFile)?;
+    let file = parser.parse_file()?;
+    if errors() > errors_before {
+        return None;
+    }
+    Some(format_file(&file))
+}
+
+/// Formats a file parsed with [`Parser::new_lossless`].
+pub fn format_file(file: &File<'_>) -> String {
+    let mut marks = Marks::default();
+    marks.visit_file(file);
+
+    let tokens = &file.tokens;
+    let statements = split_statements(tokens);
+    let path_in = |range: &Range<usize>, path: &CompoundIdentifier<'_>| {
+        let key = key(&path.element.start_token);
+        tokens[range.clone()].iter().any(|t| self::key(t) == key)
+    };
+    let is_library = |range: &Range<usize>| {
+        file.library_decl
+            .as_ref()
+            .is_some_and(|decl| path_in(range, &decl.path))
+    };
+    let is_using = |range: &Range<usize>| {
+        file.using_decls
+            .iter()
+            .any(|decl| path_in(range, &decl.using_path))
+    };
+
+    // The library declaration comes first, followed by the `using`
+    // declarations, and then everything else in its original order.
+    let mut ordered: Vec<(Range<usize>, bool)> = Vec::new();
+    ordered.extend(
+        statements
+            .iter()
+            .filter(|r| is_library(r))
+            .map(|r| (r.clone(), false)),
+    );
+    ordered.extend(
+        statements
+            .iter()
+            .filter(|r| is_using(r))
+            .map(|r| (r.clone(), true)),
+    );
+    ordered.extend(
+        statements
+            .iter()
+            .filter(|r| !is_library(r) && !is_using(r))
+            .map(|r| (r.clone(), false)),
+    );
+
+    let mut printer = Printer::new(&marks);
+    let mut previous_is_using = None;
+    for (range, using) in ordered {
+        printer.blank = match previous_is_using {
+            None => Blank::Never,
+            Some(true) if using => Blank::Preserve,
+            Some(_) => Blank::Always,
+        };
+        previous_is_using = Some(using);
+        for i in range.clone() {
+            let next = tokens.get(i + 1).filter(|_| i + 1 < range.end);
+            printer.token(&tokens[i], next);
+        }
+    }
+    if let Some(end) = tokens.last().filter(|t| t.kind == TokenKind::EndOfFile) {
+        printer.comments(end.leading_trivia);
+    }
+    printer.out.push('\n');
+    printer.out
+}
+
+/// Splits the tokens between the start and end of the file into top-level
+/// declarations, each ending with its `;`.
+fn split_statements(tokens: &[Token<'_>]) -> Vec<Range<usize>> {
+    let mut statements = Vec::new();
+    let mut start = 1;
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::StartOfFile | TokenKind::EndOfFile => {}
+            TokenKind::LeftCurly | TokenKind::LeftParen => depth += 1,
+            TokenKind::RightCurly | TokenKind::RightParen => depth = depth.saturating_sub(1),
+            TokenKind::Semicolon if depth == 0 => {
+                statements.push(start..i + 1);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let end = tokens.len().saturating_sub(1);
+    if start < end {
+        statements.push(start..end);
+    }
+    statements
+}
+
+/// Identifies a token by the address of its text in the source.
+fn key(token: &Token<'_>) -> usize {
+    token.span.data.as_ptr() as usize
+}
+
+/// Tokens whose formatting depends on where they are in the raw AST.
+#[derive(Default)]
+struct Marks {
+    /// Last tokens of the attributes of declarations and members, which are
+    /// each printed on their own line.
+    attribute_ends: HashSet<usize>,
+    /// Ordinals of table and union members, which are followed by `: `.
+    ordinals: HashSet<usize>,
+    /// First tokens of enum, bits and resource subtypes, preceded by ` : `.
+    subtypes: HashSet<usize>,
+}
+
+impl Marks {
+    fn attributes(&mut self, attributes: &Option<Box<AttributeList<'_>>>) {
+        for attribute in attributes.iter().flat_map(|list| &list.attributes) {
+            if attribute.provenance == AttributeProvenance::Default {
+                self.attribute_ends
+                    .insert(key(&attribute.element.end_token));
+            }
+        }
+    }
+
+    fn ordinal(&mut self, ordinal: &Option<Literal<'_>>) {
+        if let Some(ordinal) = ordinal {
+            self.ordinals.insert(key(&ordinal.element.start_token));
+        }
+    }
+
+    fn subtype(&mut self, subtype: &Option<TypeConstructor<'_>>) {
+        if let Some(subtype) = subtype {
+            self.subtypes.insert(key(&subtype.element.start_token));
+        }
+    }
+}
+
+impl<'a> TreeVisitor<'a> for Marks {
+    fn visit_file(&mut self, node: &File<'a>) {
+        // Declarations in the old syntax are only reachable from the file.
+        for decl in &node.struct_decls {
+            self.attributes(&decl.attributes);
+        }
+        for decl in &node.enum_decls {
+            self.attributes(&decl.attributes);
+        }
+        for decl in &node.bits_decls {
+            self.attributes(&decl.attributes);
+        }
+        for decl in &node.union_decls {
+            self.attributes(&decl.attributes);
+        }
+        for decl in &node.table_decls {
+            self.attributes(&decl.attributes);
+        }
+        tree_visitor::walk_file(self, node);
+    }
+    fn visit_librarydeclaration(&mut self, node: &LibraryDeclaration<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_librarydeclaration(self, node);
+    }
+    fn visit_usingdeclaration(&mut self, node: &UsingDeclaration<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_usingdeclaration(self, node);
+    }
+    fn visit_constdeclaration(&mut self, node: &ConstDeclaration<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_constdeclaration(self, node);
+    }
+    fn visit_aliasdeclaration(&mut self, node: &AliasDeclaration<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_aliasdeclaration(self, node);
+    }
+    fn visit_typedeclaration(&mut self, node: &TypeDeclaration<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_typedeclaration(self, node);
+    }
+    fn visit_resourcedeclaration(&mut self, node: &ResourceDeclaration<'a>) {
+        self.attributes(&node.attributes);
+        self.subtype(&node.type_ctor);
+        tree_visitor::walk_resourcedeclaration(self, node);
+    }
+    fn visit_resourceproperty(&mut self, node: &ResourceProperty<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_resourceproperty(self, node);
+    }
+    fn visit_structmember(&mut self, node: &StructMember<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_structmember(self, node);
+    }
+    fn visit_enumdeclaration(&mut self, node: &EnumDeclaration<'a>) {
+        self.subtype(&node.subtype);
+        tree_visitor::walk_enumdeclaration(self, node);
+    }
+    fn visit_enummember(&mut self, node: &EnumMember<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_enummember(self, node);
+    }
+    fn visit_bitsdeclaration(&mut self, node: &BitsDeclaration<'a>) {
+        self.subtype(&node.subtype);
+        tree_visitor::walk_bitsdeclaration(self, node);
+    }
+    fn visit_bitsmember(&mut self, node: &BitsMember<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_bitsmember(self, node);
+    }
+    fn visit_unionmember(&mut self, node: &UnionMember<'a>) {
+        self.attributes(&node.attributes);
+        self.ordinal(&node.ordinal);
+        tree_visitor::walk_unionmember(self, node);
+    }
+    fn visit_tablemember(&mut self, node: &TableMember<'a>) {
+        self.attributes(&node.attributes);
+        self.ordinal(&node.ordinal);
+        tree_visitor::walk_tablemember(self, node);
+    }
+    fn visit_protocoldeclaration(&mut self, node: &ProtocolDeclaration<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_protocoldeclaration(self, node);
+    }
+    fn visit_protocolcompose(&mut self, node: &ProtocolCompose<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_protocolcompose(self, node);
+    }
+    fn visit_protocolmethod(&mut self, node: &ProtocolMethod<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_protocolmethod(self, node);
+    }
+    fn visit_servicedeclaration(&mut self, node: &ServiceDeclaration<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_servicedeclaration(self, node);
+    }
+    fn visit_servicemember(&mut self, node: &ServiceMember<'a>) {
+        self.attributes(&node.attributes);
+        tree_visitor::walk_servicemember(self, node);
+    }
+}
+
+/// Whether a blank line may separate a line break from what follows it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Blank {
+    Never,
+    /// Only if there was one in the source.
+    Preserve,
+    Always,
+}
+
+struct Printer<'m, 'a> {
+    marks: &'m Marks,
+    out: String,
+    /// How many blocks the current line is nested in.
+    indent: usize,
+    /// The open `(`, `<` and `{` around the current token.
+    brackets: Vec<TokenKind>,
+    /// The next token starts a new line.
+    line_start: bool,
+    /// The new line is a break of the canonical style, rather than one forced
+    /// by a comment; other lines get an extra level of indentation.
+    canonical: bool,
+    blank: Blank,
+    /// The last colon printed is followed by a space.
+    spaced_colon: bool,
+    prev: Option<Token<'a>>,
+}
+
+impl<'m, 'a> Printer<'m, 'a> {
+    fn new(marks: &'m Marks) -> Self {
+        Self {
+            marks,
+            out: String::new(),
+            indent: 0,
+            brackets: Vec::new(),
+            line_start: true,
+            canonical: true,
+            blank: Blank::Never,
+            spaced_colon: false,
+            prev: None,
+        }
+    }
+
+    fn token(&mut self, token: &Token<'a>, next: Option<&Token<'a>>) {
+        let closes_block = token.kind == TokenKind::RightCurly;
+        let newlines = self.comments(token.leading_trivia);
+        if closes_block {
+            self.brackets.pop();
+            self.indent = self.indent.saturating_sub(1);
+            if self.line_start
+                || self
+                    .prev
+                    .as_ref()
+                    .is_none_or(|p| p.kind != TokenKind::LeftCurly)
+            {
+                self.break_line(Blank::Never);
+            }
+        } else {
+            self.maybe_blank(newlines);
+        }
+
+        if self.line_start {
+            self.start_line();
+        } else if self.needs_space(token, next) {
+            self.out.push(' ');
+        }
+        self.out.push_str(token.span.data.trim_end());
+        self.line_start = false;
+        if token.kind == TokenKind::Colon {
+            self.spaced_colon = self
+                .prev
+                .as_ref()
+                .is_some_and(|p| self.marks.ordinals.contains(&key(p)))
+                || next.is_some_and(|n| self.marks.subtypes.contains(&key(n)));
+        }
+
+        let comment = token.trailing_trivia.trim();
+        if !comment.is_empty() {
+            self.out.push(' ');
+            self.out.push_str(comment);
+            self.line_start = true;
+            self.canonical = false;
+        }
+
+        match token.kind {
+            TokenKind::Semicolon => self.break_line(Blank::Preserve),
+            TokenKind::DocComment => self.break_line(Blank::Never),
+            TokenKind::LeftCurly => {
+                self.brackets.push(token.kind);
+                self.indent += 1;
+                let empty = next.is_some_and(|n| {
+                    n.kind == TokenKind::RightCurly && !n.leading_trivia.contains("//")
+                });
+                if !empty || self.line_start {
+                    self.break_line(Blank::Never);
+                }
+            }
+            TokenKind::LeftParen | TokenKind::LeftAngle => self.brackets.push(token.kind),
+            TokenKind::RightParen | TokenKind::RightAngle => {
+                self.brackets.pop();
+            }
+            _ => {}
+        }
+        if self.marks.attribute_ends.contains(&key(token)) {
+            self.break_line(Blank::Never);
+        }
+        self.prev = Some(token.clone());
+    }
+
+    /// Prints the comments in `trivia`, each on its own line. Returns the
+    /// number of line breaks after the last one.
+    fn comments(&mut self, trivia: &str) -> usize {
+        let mut newlines = 0;
+        for (i, line) in trivia.split('\n').enumerate() {
+            if i > 0 {
+                newlines += 1;
+            }
+            let comment = line.trim();
+            if comment.is_empty() {
+                continue;
+            }
+            if !self.line_start {
+                self.line_start = true;
+                self.canonical = false;
+            }
+            self.maybe_blank(newlines);
+            self.start_line();
+            self.out.push_str(comment);
+            self.line_start = true;
+            newlines = 0;
+        }
+        newlines
+    }
+
+    fn break_line(&mut self, blank: Blank) {
+        self.line_start = true;
+        self.canonical = true;
+        self.blank = blank;
+    }
+
+    /// Prints a blank line if one belongs at the current line break, given
+    /// the number of line breaks that preceded it in the source.
+    fn maybe_blank(&mut self, newlines: usize) {
+        if self.line_start && self.canonical && !self.out.is_empty() {
+            let blank = match self.blank {
+                Blank::Never => false,
+                Blank::Preserve => newlines >= 2,
+                Blank::Always => true,
+            };
+            if blank {
+                self.out.push('\n');
+            }
+        }
+        self.blank = Blank::Preserve;
+    }
+
+    fn start_line(&mut self) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        let depth = self.indent + usize::from(!self.canonical);
+        self.out.push_str(&INDENT.repeat(depth));
+    }
+
+    fn in_parens(&self) -> bool {
+        self.brackets.last() == Some(&TokenKind::LeftParen)
+    }
+
+    /// Whether a space separates `token` from the previous token on the line.
+    fn needs_space(&self, token: &Token<'a>, next: Option<&Token<'a>>) -> bool {
+        let Some(prev) = &self.prev else {
+            return false;
+        };
+        match token.kind {
+            TokenKind::Semicolon
+            | TokenKind::Comma
+            | TokenKind::RightParen
+            | TokenKind::LeftAngle
+            | TokenKind::RightAngle
+            | TokenKind::Dot
+            | TokenKind::Question => false,
+            TokenKind::LeftParen => prev.kind == TokenKind::Arrow,
+            TokenKind::Colon => next.is_some_and(|n| self.marks.subtypes.contains(&key(n))),
+            TokenKind::RightCurly => prev.kind != TokenKind::LeftCurly,
+            TokenKind::Equal => !self.in_parens(),
+            _ => match prev.kind {
+                TokenKind::LeftParen | TokenKind::LeftAngle | TokenKind::Dot | TokenKind::At => {
+                    false
+                }
+                TokenKind::Colon => self.spaced_colon,
+                TokenKind::Equal => !self.in_parens(),
+                _ => true,
+            },
+        }
+    }
+}
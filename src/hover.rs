@@ -0,0 +1,275 @@
+//! Summaries of declarations and members, for showing on hover in editors.
+//!
+//! [`hover`] finds what is at a source position: a reference, or the name of
+//! a declaration or member. The summary is put together from what the
+//! compiler already computed, so it has to run before the compiler is
+//! dropped: the type shape from [`Compiler::shapes`], the resourceness from
+//! the compiled declarations, and the availability from
+//! [`Compiler::decl_availability`] or [`Compiler::member_availability`].
+//! Declarations from precompiled dependencies are summarized from their
+//! JSON IR in [`Compiler::precompiled_decls`] instead.
+
+use crate::api::Location;
+use crate::compiler::Referent;
+use crate::compiler::{Compiler, MemberKind};
+use crate::flat_ast::{Attribute, Decl, DeclarationKind, TypeShape};
+use crate::raw_ast::{AttributeList, RawDecl};
+use crate::reporter::json_range;
+use crate::source_span::SourceSpan;
+use crate::versioning_types::{Availability, InitArgs, Version};
+
+#[derive(Debug, Clone)]
+pub struct Hover {
+    /// The fully qualified name, e.g. `fuchsia.example/Color.RED`.
+    pub name: String,
+    /// The kind of declaration or member, e.g. `struct` or `table field`.
+    pub kind: String,
+    /// The name or reference that was hovered over.
+    pub range: Location,
+    /// The shape of a type declaration. Members, constants, protocols and
+    /// services have none.
+    pub type_shape: Option<TypeShape>,
+    /// Whether a struct, table, union or overlay is a resource type.
+    pub resource: Option<bool>,
+    /// `None` if the library isn't versioned.
+    pub availability: Option<HoverAvailability>,
+    pub doc: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoverAvailability {
+    pub platform: String,
+    pub added: Version,
+    pub deprecated: Option<Version>,
+    /// `None` if it was never removed.
+    pub removed: Option<Version>,
+}
+
+impl HoverAvailability {
+    fn new(platform: &str, availability: &Availability) -> Option<Self> {
+        let added = availability.added()?;
+        let removed = availability.removed().filter(|&v| v != Version::POS_INF);
+        let deprecated = availability.deprecated();
+        if added == Version::NEG_INF && deprecated.is_none() && removed.is_none() {
+            return None;
+        }
+        Some(Self {
+            platform: platform.to_string(),
+            added,
+            deprecated,
+            removed,
+        })
+    }
+}
+
+impl Hover {
+    /// The summary as Markdown: the name in a code block, then the doc
+    /// comment, the type shape and the availability.
+    pub fn markdown(&self) -> String {
+        let resource = if self.resource == Some(true) {
+            "resource "
+        } else {
+            ""
+        };
+        let mut markdown = format!("```fidl\n{}{} {}\n```", resource, self.kind, self.name);
+        if let Some(doc) = &self.doc {
+            markdown.push_str("\n\n");
+            markdown.push_str(doc);
+        }
+        if let Some(shape) = &self.type_shape {
+            markdown.push_str(&format!(
+                "\n\n| Inline size | Alignment | Depth | Max handles | Max out-of-line |\n\
+                 |---|---|---|---|---|\n\
+                 | {} | {} | {} | {} | {} |",
+                shape.inline_size,
+                shape.alignment,
+                bound(shape.depth),
+                bound(shape.max_handles),
+                bound(shape.max_out_of_line),
+            ));
+        }
+        if let Some(availability) = &self.availability {
+            let mut text = format!(
+                "Added in `{}` {}",
+                availability.platform, availability.added
+            );
+            if let Some(deprecated) = availability.deprecated {
+                text.push_str(&format!(", deprecated in {}", deprecated));
+            }
+            if let Some(removed) = availability.removed {
+                text.push_str(&format!(", removed in {}", removed));
+            }
+            markdown.push_str("\n\n");
+            markdown.push_str(&text);
+            markdown.push('.');
+        }
+        markdown
+    }
+}
+
+fn bound(value: u32) -> String {
+    if value == u32::MAX {
+        "unbounded".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Decodes a `doc` attribute into the text of its `///` lines.
+pub(crate) fn doc_comment(attributes: &[Attribute]) -> Option<String> {
+    let doc = attributes.iter().find(|a| a.name == "doc")?;
+    // The value is a quoted string literal, with a line per `///` line.
+    let value = &doc.arguments.first()?.value.value;
+    let text: String = serde_json::from_str(value).ok()?;
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect();
+    Some(lines.join("\n").trim().to_string())
+}
+
+fn contains(span: &SourceSpan<'_>, filename: &str, line: usize, character: usize) -> bool {
+    let (start_line, start_char, end_line, end_char) = json_range(span);
+    span.source_file.filename() == filename
+        && (start_line, start_char) <= (line, character)
+        && (line, character) <= (end_line, end_char)
+}
+
+/// Summarizes the declaration or member at a 1-based `line` and 0-based byte
+/// `character` of `filename`, in a compiled library.
+pub fn hover<'src>(
+    compiler: &Compiler<'_, 'src>,
+    filename: &str,
+    line: usize,
+    character: usize,
+) -> Option<Hover> {
+    let at = |span: &SourceSpan<'_>| contains(span, filename, line, character);
+    let (name, span) = match compiler
+        .references
+        .iter()
+        .filter(|r| at(&r.span))
+        .min_by_key(|r| r.span.data.len())
+    {
+        Some(reference) => match &reference.referent {
+            Referent::Declaration(name) => (name.clone(), reference.span),
+            Referent::Library(_) => return None,
+        },
+        None => compiler.raw_decls.iter().find_map(|(name, decl)| {
+            if let Some(id) = decl.name()
+                && at(&id.element.span())
+            {
+                return Some((name.clone(), id.element.span()));
+            }
+            let mut found = None;
+            decl.for_each_member(|member, _, _| {
+                if found.is_none() && at(&member.element.span()) {
+                    found = Some((name.with_member(member.data()), member.element.span()));
+                }
+            });
+            found
+        })?,
+    };
+
+    let decl_name = name
+        .library()
+        .to_owned()
+        .with_declaration(name.declaration());
+    // Declarations from `--dependency-ir` have no raw declaration, and
+    // aren't among the compiled ones.
+    let precompiled = compiler.precompiled_decls.get(&decl_name);
+    let kind = match compiler.decl_kinds.get(&decl_name) {
+        Some(&kind) => kind,
+        None => precompiled?.kind,
+    };
+    let platform = name.library().versioning_platform();
+    let mut hover = Hover {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        range: Location::new(&span),
+        type_shape: None,
+        resource: None,
+        availability: None,
+        doc: None,
+    };
+    let raw_decl = compiler.raw_decls.get(&decl_name);
+
+    if let Some(member_name) = name.member() {
+        hover.kind = MemberKind::of(kind).to_string();
+        let mut member: Option<(Option<&AttributeList<'_>>, usize)> = None;
+        if let Some(decl) = raw_decl {
+            decl.for_each_member(|id, attributes, element| {
+                if id.data() == member_name {
+                    member = Some((attributes, element.span().data.as_ptr() as usize));
+                }
+            });
+        }
+        if let Some((attributes, member_ptr)) = member {
+            hover.availability = compiler
+                .member_availability
+                .get(&member_ptr)
+                .and_then(|a| HoverAvailability::new(platform, a));
+            hover.doc =
+                attributes.and_then(|a| doc_comment(&compiler.compile_attributes_from_ref(a)));
+        } else if let Some(attributes) =
+            compiler.precompiled_attributes(decl_name.as_ref(), Some(member_name))
+        {
+            hover.availability = precompiled_availability(platform, &attributes);
+            hover.doc = doc_comment(&attributes);
+        }
+        return Some(hover);
+    }
+
+    hover.type_shape = match kind {
+        DeclarationKind::Const
+        | DeclarationKind::Alias
+        | DeclarationKind::Protocol
+        | DeclarationKind::Service => None,
+        _ => compiler.shapes.get(&decl_name).cloned(),
+    };
+    if let Some(precompiled) = precompiled.filter(|_| raw_decl.is_none()) {
+        hover.resource = precompiled.resource;
+        if let Some(attributes) = compiler.precompiled_attributes(decl_name.as_ref(), None) {
+            hover.availability = precompiled_availability(platform, &attributes);
+            hover.doc = doc_comment(&attributes);
+        }
+        return Some(hover);
+    }
+    hover.resource = compiler.declarations.decls().find_map(|d| match d {
+        Decl::Struct(s) if s.base.name == decl_name => Some(s.resource),
+        Decl::Table(t) if t.base.name == decl_name => Some(t.resource),
+        Decl::Union(u) | Decl::Overlay(u) if u.base.name == decl_name => Some(u.resource),
+        _ => None,
+    });
+    hover.availability = compiler
+        .decl_availability
+        .get(&decl_name)
+        .and_then(|a| HoverAvailability::new(platform, a));
+    hover.doc = raw_decl
+        .and_then(RawDecl::attributes)
+        .and_then(|a| doc_comment(&compiler.compile_attributes_from_ref(a)));
+    Some(hover)
+}
+
+/// The availability written in the `@available` attribute of a declaration
+/// or member from JSON IR. The IR doesn't record what it inherits from the
+/// library or its parent.
+fn precompiled_availability(platform: &str, attributes: &[Attribute]) -> Option<HoverAvailability> {
+    let available = attributes.iter().find(|a| a.name == "available")?;
+    let arg = |name: &str| {
+        let arg = available.arguments.iter().find(|a| a.name == name)?;
+        let value: String = serde_json::from_str(&arg.value.value).ok()?;
+        Version::parse(&value)
+    };
+    let mut availability = Availability::new();
+    let initialized = availability.init(InitArgs {
+        added: arg("added"),
+        deprecated: arg("deprecated"),
+        removed: arg("removed").or(arg("replaced")),
+        replaced: arg("replaced").is_some(),
+    });
+    if !initialized {
+        return None;
+    }
+    availability.inherit(&Availability::unbounded());
+    HoverAvailability::new(platform, &availability)
+}
@@ -0,0 +1,614 @@
+//! Generates the index JSON output (`--json-index`), which lists every
+//! declaration, member and reference in the compiled library together with
+//! its source location. It mirrors the structure of fidlc's index JSON and is
+//! only produced when the `output_index_json` experimental flag is enabled.
+//!
+//! The declarations are the ones the compiler compiled, so elements outside
+//! the version selection are left out, and references are the ones the
+//! resolver recorded in [`Compiler::references`].
+
+use crate::compiler::{Compiler, ReferenceKind, Referent};
+use crate::raw_ast::{self, RawDecl};
+use crate::source_span::SourceSpan;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexLocation {
+    pub is_virtual: bool,
+    pub file: String,
+    pub data: String,
+    pub start_offset: usize,
+    pub end_offset: usize,
+}
+
+impl From<SourceSpan<'_>> for IndexLocation {
+    fn from(span: SourceSpan<'_>) -> Self {
+        let file = span.source_file;
+        let (start_offset, end_offset) = if file.is_virtual() {
+            (0, 0)
+        } else {
+            let start = (span.data.as_ptr() as usize).saturating_sub(file.data().as_ptr() as usize);
+            (start, start + span.data.len())
+        };
+        Self {
+            is_virtual: file.is_virtual(),
+            file: file.filename().to_string(),
+            data: span.data.to_string(),
+            start_offset,
+            end_offset,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexRoot {
+    pub name: String,
+    pub lib_declarations: Vec<IndexLocation>,
+    pub using_declarations: Vec<IndexUsingDeclaration>,
+    pub dependencies: Vec<IndexDependency>,
+    pub dependency_identifiers: Vec<IndexReference>,
+    pub consts: Vec<IndexConst>,
+    pub enums: Vec<IndexLayout>,
+    pub bits: Vec<IndexLayout>,
+    pub unions: Vec<IndexLayout>,
+    pub tables: Vec<IndexLayout>,
+    pub structs: Vec<IndexLayout>,
+    pub aliases: Vec<IndexAlias>,
+    pub protocols: Vec<IndexProtocol>,
+    pub services: Vec<IndexService>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexUsingDeclaration {
+    pub library_name: String,
+    pub referenced_at: IndexLocation,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexDependency {
+    pub library_name: String,
+}
+
+/// A use of a declaration, identified by its fully qualified name.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexReference {
+    pub identifier: String,
+    pub referenced_at: IndexLocation,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexConst {
+    pub identifier: String,
+    pub location: IndexLocation,
+    #[serde(rename = "type")]
+    pub type_: IndexType,
+    pub value: IndexConstant,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexConstant {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referenced_at: Option<IndexLocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lhs: Option<Box<IndexConstant>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rhs: Option<Box<IndexConstant>>,
+}
+
+/// A type constructor. `kind` is `identifier` for references to declarations,
+/// `anonymous` for inline layouts and `builtin` for everything else.
+#[derive(Serialize, Debug)]
+pub struct IndexType {
+    pub kind: &'static str,
+    pub identifier: String,
+    pub referenced_at: IndexLocation,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<IndexType>,
+}
+
+/// An enum, bits, struct, table or union declaration.
+#[derive(Serialize, Debug)]
+pub struct IndexLayout {
+    pub is_anonymous: bool,
+    pub identifier: String,
+    pub location: IndexLocation,
+    pub members: Vec<IndexMember>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexMember {
+    pub name: String,
+    pub location: IndexLocation,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<IndexType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<IndexConstant>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexAlias {
+    pub identifier: String,
+    pub location: IndexLocation,
+    #[serde(rename = "type")]
+    pub type_: IndexType,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexProtocol {
+    pub identifier: String,
+    pub location: IndexLocation,
+    pub composed_protocols: Vec<IndexReference>,
+    pub methods: Vec<IndexMethod>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexMethod {
+    pub identifier: String,
+    pub location: IndexLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_type: Option<IndexType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_type: Option<IndexType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<IndexType>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexService {
+    pub identifier: String,
+    pub location: IndexLocation,
+    pub members: Vec<IndexMember>,
+}
+
+/// Builds the index from the compiler state after a successful compile.
+struct IndexBuilder<'c, 'node, 'src> {
+    compiler: &'c Compiler<'node, 'src>,
+    library_name: String,
+    /// Names of anonymous layouts, keyed by the address of their raw node.
+    anonymous_names: HashMap<usize, String>,
+    /// What each name in the main library refers to, keyed by the address of
+    /// the name.
+    referents: HashMap<usize, String>,
+}
+
+fn decl_ptr(decl: &RawDecl<'_, '_>) -> usize {
+    match decl {
+        RawDecl::Struct(d) => *d as *const _ as usize,
+        RawDecl::Enum(d) => *d as *const _ as usize,
+        RawDecl::Bits(d) => *d as *const _ as usize,
+        RawDecl::Union(d) => *d as *const _ as usize,
+        RawDecl::Table(d) => *d as *const _ as usize,
+        RawDecl::Protocol(d) => *d as *const _ as usize,
+        RawDecl::Service(d) => *d as *const _ as usize,
+        RawDecl::Resource(d) => *d as *const _ as usize,
+        RawDecl::Const(d) => *d as *const _ as usize,
+        RawDecl::Alias(d) => *d as *const _ as usize,
+        RawDecl::Type(d) => *d as *const _ as usize,
+    }
+}
+
+fn layout_ptr(layout: &raw_ast::Layout<'_>) -> usize {
+    match layout {
+        raw_ast::Layout::Struct(d) => d as *const _ as usize,
+        raw_ast::Layout::Enum(d) => d as *const _ as usize,
+        raw_ast::Layout::Bits(d) => d as *const _ as usize,
+        raw_ast::Layout::Union(d) => d as *const _ as usize,
+        raw_ast::Layout::Table(d) => d as *const _ as usize,
+        raw_ast::Layout::TypeConstructor(d) => d as *const _ as usize,
+    }
+}
+
+fn layout_element<'a, 'src>(layout: &'a raw_ast::Layout<'src>) -> &'a raw_ast::SourceElement<'src> {
+    match layout {
+        raw_ast::Layout::Struct(d) => &d.element,
+        raw_ast::Layout::Enum(d) => &d.element,
+        raw_ast::Layout::Bits(d) => &d.element,
+        raw_ast::Layout::Union(d) => &d.element,
+        raw_ast::Layout::Table(d) => &d.element,
+        raw_ast::Layout::TypeConstructor(d) => &d.element,
+    }
+}
+
+impl<'c, 'node, 'src> IndexBuilder<'c, 'node, 'src> {
+    fn new(compiler: &'c Compiler<'node, 'src>) -> Self {
+        let library_name = compiler.library_name.to_string();
+        let anonymous_names = compiler
+            .raw_decls
+            .iter()
+            .filter(|(name, _)| compiler.anonymous_structs.contains(*name))
+            .map(|(name, decl)| (decl_ptr(decl), name.to_string()))
+            .collect();
+        let referents = compiler
+            .references
+            .iter()
+            .filter(|r| r.kind != ReferenceKind::Library)
+            .filter_map(|r| match &r.referent {
+                Referent::Declaration(name) => {
+                    Some((r.span.data.as_ptr() as usize, name.to_string()))
+                }
+                Referent::Library(_) => None,
+            })
+            .collect();
+        Self {
+            compiler,
+            library_name,
+            anonymous_names,
+            referents,
+        }
+    }
+
+    fn is_main_library(&self, name: &str) -> bool {
+        name.rsplit_once('/')
+            .is_some_and(|(lib, _)| lib == self.library_name)
+    }
+
+    /// The declaration or member that `id` resolved to, if any.
+    fn referent(&self, id: &raw_ast::CompoundIdentifier<'_>) -> Option<String> {
+        self.referents
+            .get(&(id.element.span().data.as_ptr() as usize))
+            .cloned()
+    }
+
+    fn is_active(&self, element: &raw_ast::SourceElement<'_>) -> bool {
+        self.compiler
+            .is_member_active(element.span().data.as_ptr() as usize)
+    }
+
+    fn location(&self, span: SourceSpan<'_>) -> IndexLocation {
+        IndexLocation::from(span)
+    }
+
+    fn type_(&self, type_ctor: &raw_ast::TypeConstructor<'_>) -> IndexType {
+        let parameters = type_ctor.parameters.iter().map(|p| self.type_(p)).collect();
+        match &type_ctor.layout {
+            raw_ast::LayoutParameter::Identifier(id) => match self.referent(id) {
+                Some(full_name) => IndexType {
+                    kind: "identifier",
+                    identifier: full_name,
+                    referenced_at: self.location(id.element.span()),
+                    parameters,
+                },
+                None => IndexType {
+                    kind: "builtin",
+                    identifier: id.to_string(),
+                    referenced_at: self.location(id.element.span()),
+                    parameters,
+                },
+            },
+            raw_ast::LayoutParameter::Inline(layout) => self.layout_type(layout),
+            raw_ast::LayoutParameter::Type(inner) => self.type_(inner),
+            raw_ast::LayoutParameter::Literal(lit) => IndexType {
+                kind: "builtin",
+                identifier: lit.literal.value.clone(),
+                referenced_at: self.location(lit.element.span()),
+                parameters,
+            },
+        }
+    }
+
+    fn layout_type(&self, layout: &raw_ast::Layout<'_>) -> IndexType {
+        if let raw_ast::Layout::TypeConstructor(type_ctor) = layout {
+            return self.type_(type_ctor);
+        }
+        IndexType {
+            kind: "anonymous",
+            identifier: self
+                .anonymous_names
+                .get(&layout_ptr(layout))
+                .cloned()
+                .unwrap_or_default(),
+            referenced_at: self.location(layout_element(layout).span()),
+            parameters: vec![],
+        }
+    }
+
+    fn constant(&self, constant: &raw_ast::Constant<'_>) -> IndexConstant {
+        let mut out = IndexConstant {
+            kind: "literal",
+            identifier: None,
+            referenced_at: None,
+            lhs: None,
+            rhs: None,
+        };
+        match constant {
+            raw_ast::Constant::Identifier(id) => {
+                out.kind = "identifier";
+                out.identifier = Some(
+                    self.referent(&id.identifier)
+                        .unwrap_or_else(|| id.identifier.to_string()),
+                );
+                out.referenced_at = Some(self.location(id.identifier.element.span()));
+            }
+            raw_ast::Constant::Literal(_) => {}
+            raw_ast::Constant::BinaryOperator(bin) => {
+                out.kind = "binary_operator";
+                out.lhs = Some(Box::new(self.constant(&bin.left)));
+                out.rhs = Some(Box::new(self.constant(&bin.right)));
+            }
+        }
+        out
+    }
+
+    fn member(
+        &self,
+        name: &raw_ast::Identifier<'_>,
+        type_ctor: Option<&raw_ast::TypeConstructor<'_>>,
+        value: Option<&raw_ast::Constant<'_>>,
+    ) -> IndexMember {
+        IndexMember {
+            name: name.data().to_string(),
+            location: self.location(name.element.span()),
+            type_: type_ctor.map(|t| self.type_(t)),
+            value: value.map(|v| self.constant(v)),
+        }
+    }
+
+    fn struct_members(&self, decl: &raw_ast::StructDeclaration<'_>) -> Vec<IndexMember> {
+        decl.members
+            .iter()
+            .filter(|m| self.is_active(&m.element))
+            .map(|m| self.member(&m.name, Some(&m.type_ctor), m.default_value.as_ref()))
+            .collect()
+    }
+
+    fn enum_members(&self, decl: &raw_ast::EnumDeclaration<'_>) -> Vec<IndexMember> {
+        decl.members
+            .iter()
+            .filter(|m| self.is_active(&m.element))
+            .map(|m| self.member(&m.name, None, Some(&m.value)))
+            .collect()
+    }
+
+    fn bits_members(&self, decl: &raw_ast::BitsDeclaration<'_>) -> Vec<IndexMember> {
+        decl.members
+            .iter()
+            .filter(|m| self.is_active(&m.element))
+            .map(|m| self.member(&m.name, None, Some(&m.value)))
+            .collect()
+    }
+
+    fn union_members(&self, decl: &raw_ast::UnionDeclaration<'_>) -> Vec<IndexMember> {
+        decl.members
+            .iter()
+            .filter(|m| self.is_active(&m.element))
+            .filter_map(|m| Some(self.member(m.name.as_ref()?, m.type_ctor.as_ref(), None)))
+            .collect()
+    }
+
+    fn table_members(&self, decl: &raw_ast::TableDeclaration<'_>) -> Vec<IndexMember> {
+        decl.members
+            .iter()
+            .filter(|m| self.is_active(&m.element))
+            .filter_map(|m| Some(self.member(m.name.as_ref()?, m.type_ctor.as_ref(), None)))
+            .collect()
+    }
+
+    fn layout_decl(
+        &self,
+        full_name: &str,
+        name: Option<&raw_ast::Identifier<'_>>,
+        element: &raw_ast::SourceElement<'_>,
+        members: Vec<IndexMember>,
+    ) -> IndexLayout {
+        IndexLayout {
+            is_anonymous: self
+                .compiler
+                .anonymous_structs
+                .contains::<str>(full_name.as_ref()),
+            identifier: full_name.to_string(),
+            location: match name {
+                Some(name) => self.location(name.element.span()),
+                None => self.location(element.span()),
+            },
+            members,
+        }
+    }
+
+    fn decl(&self, root: &mut IndexRoot, full_name: &str, decl: &RawDecl<'_, '_>) {
+        match decl {
+            RawDecl::Type(d) => {
+                let name = Some(&d.name);
+                match &d.layout {
+                    raw_ast::Layout::Struct(l) => root.structs.push(self.layout_decl(
+                        full_name,
+                        name,
+                        &l.element,
+                        self.struct_members(l),
+                    )),
+                    raw_ast::Layout::Enum(l) => root.enums.push(self.layout_decl(
+                        full_name,
+                        name,
+                        &l.element,
+                        self.enum_members(l),
+                    )),
+                    raw_ast::Layout::Bits(l) => root.bits.push(self.layout_decl(
+                        full_name,
+                        name,
+                        &l.element,
+                        self.bits_members(l),
+                    )),
+                    raw_ast::Layout::Union(l) => root.unions.push(self.layout_decl(
+                        full_name,
+                        name,
+                        &l.element,
+                        self.union_members(l),
+                    )),
+                    raw_ast::Layout::Table(l) => root.tables.push(self.layout_decl(
+                        full_name,
+                        name,
+                        &l.element,
+                        self.table_members(l),
+                    )),
+                    raw_ast::Layout::TypeConstructor(type_ctor) => root.aliases.push(IndexAlias {
+                        identifier: full_name.to_string(),
+                        location: self.location(d.name.element.span()),
+                        type_: self.type_(type_ctor),
+                    }),
+                }
+            }
+            RawDecl::Struct(d) => root.structs.push(self.layout_decl(
+                full_name,
+                d.name.as_ref(),
+                &d.element,
+                self.struct_members(d),
+            )),
+            RawDecl::Enum(d) => root.enums.push(self.layout_decl(
+                full_name,
+                d.name.as_ref(),
+                &d.element,
+                self.enum_members(d),
+            )),
+            RawDecl::Bits(d) => root.bits.push(self.layout_decl(
+                full_name,
+                d.name.as_ref(),
+                &d.element,
+                self.bits_members(d),
+            )),
+            RawDecl::Union(d) => root.unions.push(self.layout_decl(
+                full_name,
+                d.name.as_ref(),
+                &d.element,
+                self.union_members(d),
+            )),
+            RawDecl::Table(d) => root.tables.push(self.layout_decl(
+                full_name,
+                d.name.as_ref(),
+                &d.element,
+                self.table_members(d),
+            )),
+            RawDecl::Const(d) => root.consts.push(IndexConst {
+                identifier: full_name.to_string(),
+                location: self.location(d.name.element.span()),
+                type_: self.type_(&d.type_ctor),
+                value: self.constant(&d.value),
+            }),
+            RawDecl::Alias(d) => root.aliases.push(IndexAlias {
+                identifier: full_name.to_string(),
+                location: self.location(d.name.element.span()),
+                type_: self.type_(&d.type_ctor),
+            }),
+            RawDecl::Protocol(d) => root.protocols.push(IndexProtocol {
+                identifier: full_name.to_string(),
+                location: self.location(d.name.element.span()),
+                composed_protocols: d
+                    .composed_protocols
+                    .iter()
+                    .filter(|c| self.is_active(&c.element))
+                    .map(|c| IndexReference {
+                        identifier: self
+                            .referent(&c.protocol_name)
+                            .unwrap_or_else(|| c.protocol_name.to_string()),
+                        referenced_at: self.location(c.protocol_name.element.span()),
+                    })
+                    .collect(),
+                methods: d
+                    .methods
+                    .iter()
+                    .filter(|m| self.is_active(&m.element))
+                    .map(|m| IndexMethod {
+                        identifier: m.name.data().to_string(),
+                        location: self.location(m.name.element.span()),
+                        request_type: m.request_payload.as_ref().map(|l| self.layout_type(l)),
+                        response_type: m.response_payload.as_ref().map(|l| self.layout_type(l)),
+                        error_type: m.error_payload.as_ref().map(|l| self.layout_type(l)),
+                    })
+                    .collect(),
+            }),
+            RawDecl::Service(d) => root.services.push(IndexService {
+                identifier: full_name.to_string(),
+                location: self.location(d.name.element.span()),
+                members: d
+                    .members
+                    .iter()
+                    .filter(|m| self.is_active(&m.element))
+                    .map(|m| self.member(&m.name, Some(&m.type_ctor), None))
+                    .collect(),
+            }),
+            // Resources are an experimental, zx-internal construct that the
+            // index does not cover.
+            RawDecl::Resource(_) => {}
+        }
+    }
+
+    fn build(&self) -> IndexRoot {
+        let mut root = IndexRoot {
+            name: self.library_name.clone(),
+            lib_declarations: vec![],
+            using_declarations: vec![],
+            dependencies: vec![],
+            dependency_identifiers: vec![],
+            consts: vec![],
+            enums: vec![],
+            bits: vec![],
+            unions: vec![],
+            tables: vec![],
+            structs: vec![],
+            aliases: vec![],
+            protocols: vec![],
+            services: vec![],
+        };
+
+        if let Some(decl) = &self.compiler.library_decl {
+            root.lib_declarations
+                .push(self.location(decl.path.element.span()));
+        }
+
+        let mut imports: Vec<_> = self.compiler.library_imports.values().collect();
+        imports.sort_by_key(|u| u.using_path.to_string());
+        for import in imports {
+            root.using_declarations.push(IndexUsingDeclaration {
+                library_name: import.using_path.to_string(),
+                referenced_at: self.location(import.using_path.element.span()),
+            });
+            root.dependencies.push(IndexDependency {
+                library_name: import.using_path.to_string(),
+            });
+        }
+        root.dependencies
+            .dedup_by(|a, b| a.library_name == b.library_name);
+
+        let mut names: Vec<_> = self
+            .compiler
+            .declarations
+            .decls()
+            .map(|decl| &decl.name)
+            .filter(|name| self.is_main_library(name.as_ref()))
+            .collect();
+        names.sort_by_key(|name| name.to_string());
+        names.dedup();
+        for name in names {
+            if let Some(decl) = self.compiler.raw_decls.get(name) {
+                self.decl(&mut root, name.as_ref(), decl);
+            }
+        }
+
+        let dependency_identifiers = self
+            .compiler
+            .references
+            .iter()
+            .filter(|r| r.kind != ReferenceKind::Library)
+            .filter_map(|r| match &r.referent {
+                Referent::Declaration(name) if !self.is_main_library(name.as_ref()) => {
+                    Some(IndexReference {
+                        identifier: name.to_string(),
+                        referenced_at: self.location(r.span),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        root.dependency_identifiers = dependency_identifiers;
+        root
+    }
+}
+
+impl From<&Compiler<'_, '_>> for IndexRoot {
+    fn from(compiler: &Compiler<'_, '_>) -> Self {
+        IndexBuilder::new(compiler).build()
+    }
+}
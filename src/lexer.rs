@@ -14,6 +14,8 @@ pub struct Lexer<'a> {
     token_size: usize,
     leading_newlines: u16,
     start_of_file: bool,
+    /// Where the next token's leading trivia starts.
+    trivia_start: usize,
 }
 
 impl<'a> Lexer<'a> {
@@ -28,6 +30,7 @@ impl<'a> Lexer<'a> {
             token_size: 0,
             leading_newlines: 0,
             start_of_file: true,
+            trivia_start: 0,
         }
     }
 
@@ -65,12 +68,40 @@ impl<'a> Lexer<'a> {
 
     fn finish(&mut self, kind: TokenKind) -> Token<'a> {
         let (newlines, data) = self.reset(TokenKind::Identifier); // kind arg to reset is ignored in C++ too?
-        Token::new(
+        let token = Token::new(
             SourceSpan::new(data, self.source_file),
             kind,
             TokenSubkind::None,
             newlines,
-        )
+        );
+        match kind {
+            // Regular comments are skipped, so they end up in the trivia of
+            // the next token. The start of file has none of its own.
+            TokenKind::Comment | TokenKind::StartOfFile => token,
+            _ => self.with_trivia(token),
+        }
+    }
+
+    /// Attaches to `token` the trivia since the previous token, and the
+    /// trivia that follows it up to the end of its line.
+    fn with_trivia(&mut self, token: Token<'a>) -> Token<'a> {
+        let source = self.source_file.data();
+        let start = token.span.data.as_ptr() as usize - source.as_ptr() as usize;
+        let end = start + token.span.data.len();
+        let rest = &source[end..];
+        let mut len = rest.len() - rest.trim_start_matches([' ', '\t', '\r']).len();
+        let after = &rest[len..];
+        let is_doc_comment = after.starts_with("///") && !after.starts_with("////");
+        if after.starts_with("//") && !is_doc_comment {
+            len += after.find('\n').unwrap_or(after.len());
+        }
+        let leading_trivia = &source[self.trivia_start.min(start)..start];
+        self.trivia_start = end + len;
+        Token {
+            leading_trivia,
+            trailing_trivia: &rest[..len],
+            ..token
+        }
     }
 
     // Lexer implementation methods...
@@ -199,12 +230,12 @@ impl<'a> Lexer<'a> {
         }
 
         let subkind = lookup_subkind(data);
-        Token::new(
+        self.with_trivia(Token::new(
             SourceSpan::new(data, self.source_file),
             TokenKind::Identifier,
             subkind,
             newlines,
-        )
+        ))
     }
 
     fn lex_string_literal(&mut self) -> Token<'a> {
@@ -1,20 +1,36 @@
 #![allow(unused_crate_dependencies)]
+pub mod api;
 pub mod cli;
+pub mod compat;
 pub mod compiler;
+pub mod completion;
+pub mod decomposition;
 pub mod diagnostics;
 pub mod experimental_flags;
 pub mod flat_ast;
+pub mod formatter;
+pub mod hover;
+pub mod index_json_generator;
 pub mod json_generator;
 pub mod lexer;
+pub mod library_path;
+pub mod linter;
+pub mod lsp;
 pub mod name;
 pub mod names;
+pub mod outline;
 pub mod parser;
+pub mod projection;
 pub mod raw_ast;
+pub mod rename;
 pub mod reporter;
+pub mod semantic_tokens;
 pub mod source_file;
 pub mod source_span;
+pub mod timeline;
 pub mod token;
 pub mod tree_visitor;
+pub mod utils;
 pub mod versioning_types;
 
 pub mod attribute_schema;
@@ -22,8 +38,15 @@ pub mod availability_step;
 pub mod canonical_names;
 pub mod compile_step;
 pub mod consume_step;
+pub mod deprecation_step;
 pub mod replacement_step;
 pub mod resolve_step;
 pub mod step;
 #[cfg(test)]
 pub mod tests;
+
+pub use api::{
+    CompatLibrary, CompatOutput, CompileOptions, CompileOutput, DecomposeOutput, Diagnostic,
+    Location, ProjectOutput, Reference, RenameOutput, Sources, TextEdit, TimelineOutput, compat,
+    compile, decompose, format, hover, lint, outline, project, rename, semantic_tokens, timeline,
+};
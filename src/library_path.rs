@@ -0,0 +1,135 @@
+//! Locating the sources of libraries named in `using` declarations.
+//!
+//! Each `--library-path` root is searched for a library `fuchsia.foo.bar` in
+//! two conventional layouts: `<root>/fuchsia.foo.bar/` and
+//! `<root>/fuchsia/foo/bar/`. The first of these directories that exists
+//! provides the library's sources: the files listed by its [`MANIFEST`] if it
+//! has one, and otherwise every `.fidl` file directly inside it.
+
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::reporter::Reporter;
+use crate::source_file::SourceFile;
+use crate::token::TokenKind;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the per-directory manifest that lists a library's sources.
+pub const MANIFEST: &str = "fidl_library.json";
+
+/// The contents of a [`MANIFEST`] file.
+#[derive(Deserialize, Debug)]
+pub struct LibraryManifest {
+    pub name: String,
+    /// Source files, relative to the manifest's directory.
+    pub sources: Vec<String>,
+}
+
+/// The outcome of looking a library up in the search path.
+#[derive(Debug, PartialEq)]
+pub enum LibrarySearch {
+    Found(Vec<String>),
+    /// The library wasn't found; holds the directories that were searched.
+    NotFound(Vec<String>),
+}
+
+#[derive(Debug, Default)]
+pub struct LibraryPath {
+    roots: Vec<PathBuf>,
+}
+
+impl LibraryPath {
+    pub fn new(roots: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self {
+            roots: roots.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns the directories that may hold `library`'s sources, in the
+    /// order they are searched.
+    pub fn candidates(&self, library: &str) -> Vec<PathBuf> {
+        let mut candidates = Vec::new();
+        for root in &self.roots {
+            candidates.push(root.join(library));
+            let nested: PathBuf = library.split('.').collect();
+            if nested != Path::new(library) {
+                candidates.push(root.join(nested));
+            }
+        }
+        candidates
+    }
+
+    pub fn find(&self, library: &str) -> Result<LibrarySearch, String> {
+        let candidates = self.candidates(library);
+        for dir in &candidates {
+            if dir.is_dir() {
+                return library_sources(dir, library).map(LibrarySearch::Found);
+            }
+        }
+        Ok(LibrarySearch::NotFound(
+            candidates
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect(),
+        ))
+    }
+}
+
+fn library_sources(dir: &Path, library: &str) -> Result<Vec<String>, String> {
+    let manifest_path = dir.join(MANIFEST);
+    if manifest_path.is_file() {
+        let content = fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("Error reading file {}: {}", manifest_path.display(), e))?;
+        let manifest: LibraryManifest = serde_json::from_str(&content)
+            .map_err(|e| format!("Error parsing {}: {}", manifest_path.display(), e))?;
+        if manifest.name != library {
+            return Err(format!(
+                "{} describes library {}, but was found looking for library {}",
+                manifest_path.display(),
+                manifest.name,
+                library
+            ));
+        }
+        return Ok(manifest
+            .sources
+            .iter()
+            .map(|source| dir.join(source).display().to_string())
+            .collect());
+    }
+
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Error reading directory {}: {}", dir.display(), e))?;
+    let mut sources: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "fidl"))
+        .map(|path| path.display().to_string())
+        .collect();
+    sources.sort();
+    if sources.is_empty() {
+        return Err(format!(
+            "Found directory {} for library {}, but it contains no .fidl files",
+            dir.display(),
+            library
+        ));
+    }
+    Ok(sources)
+}
+
+/// Returns the library name and the `using` paths of a source file, or `None`
+/// if it doesn't parse. Diagnostics are discarded; they are reported when the
+/// file is compiled.
+pub fn library_header(source: &SourceFile) -> Option<(String, Vec<String>)> {
+    let reporter = Reporter::new();
+    let mut lexer = Lexer::new(source, &reporter);
+    let mut parser = Parser::new(&mut lexer, &reporter);
+    parser.consume_token(TokenKind::StartOfFile)?;
+    let file = parser.parse_file()?;
+    let name = file.library_decl.as_ref()?.path.to_string();
+    let usings = file
+        .using_decls
+        .iter()
+        .map(|u| u.using_path.to_string())
+        .collect();
+    Some((name, usings))
+}
@@ -0,0 +1,462 @@
+//! Style and API rubric checks, like `fidl-lint`.
+//!
+//! Most checks are [`TreeVisitor`] passes over the raw AST, so they see names
+//! and modifiers as written. Checks that need resolved types run over the
+//! compiled [`Root`]. Findings are reported as warnings to the same
+//! [`Reporter`] as compiler diagnostics, and each check has a stable id so it
+//! can be turned off on its own.
+
+use std::collections::HashSet;
+
+use flyweights::FlyStr;
+
+use crate::diagnostics::Error;
+use crate::flat_ast::{Location, Root, Type};
+use crate::raw_ast::*;
+use crate::reporter::Reporter;
+use crate::source_file::SourceFile;
+use crate::source_span::SourceSpan;
+use crate::token::TokenSubkind;
+use crate::tree_visitor::{self, TreeVisitor};
+use crate::utils::{
+    is_lower_snake_case, is_upper_camel_case, is_upper_snake_case, to_lower_snake_case,
+    to_upper_camel_case, to_upper_snake_case,
+};
+
+/// Primitive types that shouldn't appear in public APIs, with what to use
+/// instead.
+const DISCOURAGED_TYPES: &[(&str, &str)] = &[
+    ("float32", "float64"),
+    ("uchar", "uint8"),
+    ("usize64", "uint64"),
+    ("uintptr64", "uint64"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintCheck {
+    InvalidCase,
+    LibraryPrefix,
+    UnboundedPayload,
+    MissingDocComment,
+    PreferFlexible,
+    DiscouragedType,
+}
+
+impl std::str::FromStr for LintCheck {
+    type Err = String;
+
+    /// Accepts either the check's name or the `fi-NNNN` id of its warning.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LintCheck::ALL
+            .into_iter()
+            .find(|check| check.name() == s || check.error_id() == s)
+            .ok_or_else(|| format!("Unknown lint check: {}", s))
+    }
+}
+
+impl LintCheck {
+    pub const ALL: [LintCheck; 6] = [
+        LintCheck::InvalidCase,
+        LintCheck::LibraryPrefix,
+        LintCheck::UnboundedPayload,
+        LintCheck::MissingDocComment,
+        LintCheck::PreferFlexible,
+        LintCheck::DiscouragedType,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintCheck::InvalidCase => "invalid-case",
+            LintCheck::LibraryPrefix => "library-prefix",
+            LintCheck::UnboundedPayload => "unbounded-payload",
+            LintCheck::MissingDocComment => "missing-doc-comment",
+            LintCheck::PreferFlexible => "prefer-flexible",
+            LintCheck::DiscouragedType => "discouraged-type",
+        }
+    }
+
+    /// The id of the warning this check reports, e.g. `fi-1027`.
+    pub fn error_id(&self) -> &'static str {
+        match self {
+            LintCheck::InvalidCase => "fi-1027",
+            LintCheck::LibraryPrefix => "fi-1028",
+            LintCheck::UnboundedPayload => "fi-1029",
+            LintCheck::MissingDocComment => "fi-1030",
+            LintCheck::PreferFlexible => "fi-1031",
+            LintCheck::DiscouragedType => "fi-1032",
+        }
+    }
+
+    fn of(error: &Error) -> Option<LintCheck> {
+        match error {
+            Error::WarnLintInvalidCase(..) => Some(LintCheck::InvalidCase),
+            Error::WarnLintLibraryPrefix(..) => Some(LintCheck::LibraryPrefix),
+            Error::WarnLintUnboundedPayload(..) => Some(LintCheck::UnboundedPayload),
+            Error::WarnLintMissingDocComment(..) => Some(LintCheck::MissingDocComment),
+            Error::WarnLintPreferFlexible(..) => Some(LintCheck::PreferFlexible),
+            Error::WarnLintDiscouragedType(..) => Some(LintCheck::DiscouragedType),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    /// A library's first component must be one of these. Empty allows any
+    /// name.
+    pub library_prefixes: Vec<String>,
+    pub excluded: HashSet<LintCheck>,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            library_prefixes: vec!["fuchsia".into(), "fidl".into(), "test".into()],
+            excluded: HashSet::new(),
+        }
+    }
+}
+
+/// Lints the files of a library. `root` is the compiled library, if it
+/// compiled; without it the checks over the flat AST are skipped.
+/// `source_files` are used to find the spans of flat AST locations.
+pub fn lint<'a>(
+    files: &[File<'a>],
+    root: Option<&Root>,
+    source_files: &[&'a SourceFile],
+    reporter: &Reporter<'a>,
+    options: &LintOptions,
+) {
+    let mut linter = Linter {
+        reporter,
+        options,
+        context: "",
+    };
+    for file in files {
+        linter.visit_file(file);
+    }
+    if let Some(root) = root {
+        check_unbounded_payloads(root, source_files, &linter);
+    }
+}
+
+struct Linter<'r, 'a> {
+    reporter: &'r Reporter<'a>,
+    options: &'r LintOptions,
+    /// The name of the innermost named declaration or member, used to
+    /// describe anonymous layouts.
+    context: &'a str,
+}
+
+impl<'r, 'a> Linter<'r, 'a> {
+    fn report(&self, error: Error, span: SourceSpan<'a>) {
+        if LintCheck::of(&error).is_some_and(|check| !self.options.excluded.contains(&check)) {
+            self.reporter.fail(error, span);
+        }
+    }
+
+    fn check_case(&self, kind: &str, name: &Identifier<'a>, case: Case) {
+        let data = name.data();
+        let (ok, suggestion, case_name) = match case {
+            Case::UpperCamel => (
+                is_upper_camel_case(data),
+                to_upper_camel_case(data),
+                "UpperCamelCase",
+            ),
+            Case::UpperSnake => (
+                is_upper_snake_case(data),
+                to_upper_snake_case(data),
+                "UPPER_SNAKE_CASE",
+            ),
+            Case::LowerSnake => (
+                is_lower_snake_case(data),
+                to_lower_snake_case(data),
+                "lower_snake_case",
+            ),
+        };
+        if !ok {
+            self.report(
+                Error::WarnLintInvalidCase(
+                    kind.into(),
+                    data.into(),
+                    case_name.into(),
+                    suggestion.into(),
+                ),
+                name.element.span(),
+            );
+        }
+    }
+
+    fn check_doc_comment(
+        &self,
+        kind: &str,
+        name: &Identifier<'a>,
+        attributes: &Option<Box<AttributeList<'a>>>,
+    ) {
+        let documented = attributes.as_ref().is_some_and(|list| {
+            list.attributes
+                .iter()
+                .any(|a| a.provenance == AttributeProvenance::DocComment)
+        });
+        if !documented {
+            self.report(
+                Error::WarnLintMissingDocComment(kind.into(), name.data().into()),
+                name.element.span(),
+            );
+        }
+    }
+
+    fn check_strict(&self, kind: &str, name: Option<&Identifier<'a>>, modifiers: &[Modifier<'a>]) {
+        let name = name.map_or(self.context, Identifier::data);
+        for modifier in modifiers {
+            if modifier.subkind == TokenSubkind::Strict {
+                self.report(
+                    Error::WarnLintPreferFlexible(kind.into(), name.into()),
+                    modifier.element.span(),
+                );
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Case {
+    UpperCamel,
+    UpperSnake,
+    LowerSnake,
+}
+
+fn layout_kind(layout: &Layout<'_>) -> &'static str {
+    match layout {
+        Layout::Struct(_) => "struct",
+        Layout::Enum(_) => "enum",
+        Layout::Bits(_) => "bits",
+        Layout::Union(u) if u.is_overlay => "overlay",
+        Layout::Union(_) => "union",
+        Layout::Table(_) => "table",
+        Layout::TypeConstructor(_) => "type",
+    }
+}
+
+impl<'r, 'a> TreeVisitor<'a> for Linter<'r, 'a> {
+    fn visit_librarydeclaration(&mut self, node: &LibraryDeclaration<'a>) {
+        let prefixes = &self.options.library_prefixes;
+        let first = node.path.components.first().map(Identifier::data);
+        if !prefixes.is_empty() && !first.is_some_and(|first| prefixes.iter().any(|p| p == first)) {
+            self.report(
+                Error::WarnLintLibraryPrefix(
+                    FlyStr::new(node.path.to_string()),
+                    FlyStr::new(prefixes.join(", ")),
+                ),
+                node.path.element.span(),
+            );
+        }
+        tree_visitor::walk_librarydeclaration(self, node);
+    }
+
+    fn visit_constdeclaration(&mut self, node: &ConstDeclaration<'a>) {
+        self.check_case("const", &node.name, Case::UpperSnake);
+        self.check_doc_comment("const", &node.name, &node.attributes);
+        tree_visitor::walk_constdeclaration(self, node);
+    }
+
+    fn visit_aliasdeclaration(&mut self, node: &AliasDeclaration<'a>) {
+        self.check_case("alias", &node.name, Case::UpperCamel);
+        self.check_doc_comment("alias", &node.name, &node.attributes);
+        tree_visitor::walk_aliasdeclaration(self, node);
+    }
+
+    fn visit_typedeclaration(&mut self, node: &TypeDeclaration<'a>) {
+        let kind = layout_kind(&node.layout);
+        self.check_case(kind, &node.name, Case::UpperCamel);
+        self.check_doc_comment(kind, &node.name, &node.attributes);
+        self.context = node.name.data();
+        tree_visitor::walk_typedeclaration(self, node);
+    }
+
+    fn visit_protocoldeclaration(&mut self, node: &ProtocolDeclaration<'a>) {
+        self.check_case("protocol", &node.name, Case::UpperCamel);
+        self.check_doc_comment("protocol", &node.name, &node.attributes);
+        tree_visitor::walk_protocoldeclaration(self, node);
+    }
+
+    fn visit_protocolmethod(&mut self, node: &ProtocolMethod<'a>) {
+        self.check_case("method", &node.name, Case::UpperCamel);
+        self.check_doc_comment("method", &node.name, &node.attributes);
+        self.context = node.name.data();
+        tree_visitor::walk_protocolmethod(self, node);
+    }
+
+    fn visit_servicedeclaration(&mut self, node: &ServiceDeclaration<'a>) {
+        self.check_case("service", &node.name, Case::UpperCamel);
+        self.check_doc_comment("service", &node.name, &node.attributes);
+        tree_visitor::walk_servicedeclaration(self, node);
+    }
+
+    fn visit_servicemember(&mut self, node: &ServiceMember<'a>) {
+        self.check_case("service member", &node.name, Case::LowerSnake);
+        tree_visitor::walk_servicemember(self, node);
+    }
+
+    fn visit_resourcedeclaration(&mut self, node: &ResourceDeclaration<'a>) {
+        self.check_case("resource", &node.name, Case::UpperCamel);
+        self.check_doc_comment("resource", &node.name, &node.attributes);
+        tree_visitor::walk_resourcedeclaration(self, node);
+    }
+
+    fn visit_resourceproperty(&mut self, node: &ResourceProperty<'a>) {
+        self.check_case("resource property", &node.name, Case::LowerSnake);
+        tree_visitor::walk_resourceproperty(self, node);
+    }
+
+    fn visit_structmember(&mut self, node: &StructMember<'a>) {
+        self.check_case("struct member", &node.name, Case::LowerSnake);
+        self.context = node.name.data();
+        tree_visitor::walk_structmember(self, node);
+    }
+
+    fn visit_tablemember(&mut self, node: &TableMember<'a>) {
+        if let Some(name) = &node.name {
+            self.check_case("table member", name, Case::LowerSnake);
+            self.context = name.data();
+        }
+        tree_visitor::walk_tablemember(self, node);
+    }
+
+    fn visit_unionmember(&mut self, node: &UnionMember<'a>) {
+        if let Some(name) = &node.name {
+            self.check_case("union member", name, Case::LowerSnake);
+            self.context = name.data();
+        }
+        tree_visitor::walk_unionmember(self, node);
+    }
+
+    fn visit_enummember(&mut self, node: &EnumMember<'a>) {
+        self.check_case("enum member", &node.name, Case::UpperSnake);
+        tree_visitor::walk_enummember(self, node);
+    }
+
+    fn visit_bitsmember(&mut self, node: &BitsMember<'a>) {
+        self.check_case("bits member", &node.name, Case::UpperSnake);
+        tree_visitor::walk_bitsmember(self, node);
+    }
+
+    fn visit_enumdeclaration(&mut self, node: &EnumDeclaration<'a>) {
+        self.check_strict("enum", node.name.as_ref(), &node.modifiers);
+        tree_visitor::walk_enumdeclaration(self, node);
+    }
+
+    fn visit_bitsdeclaration(&mut self, node: &BitsDeclaration<'a>) {
+        self.check_strict("bits", node.name.as_ref(), &node.modifiers);
+        tree_visitor::walk_bitsdeclaration(self, node);
+    }
+
+    fn visit_uniondeclaration(&mut self, node: &UnionDeclaration<'a>) {
+        if !node.is_overlay {
+            self.check_strict("union", node.name.as_ref(), &node.modifiers);
+        }
+        tree_visitor::walk_uniondeclaration(self, node);
+    }
+
+    fn visit_typeconstructor(&mut self, node: &TypeConstructor<'a>) {
+        if let LayoutParameter::Identifier(id) = &node.layout
+            && let [component] = id.components.as_slice()
+            && let Some((name, suggestion)) = DISCOURAGED_TYPES
+                .iter()
+                .find(|(name, _)| *name == component.data())
+        {
+            self.report(
+                Error::WarnLintDiscouragedType((*name).into(), (*suggestion).into()),
+                id.element.span(),
+            );
+        }
+        tree_visitor::walk_typeconstructor(self, node);
+    }
+}
+
+/// Warns about `vector` and `string` members without a maximum size in the
+/// payloads of the library's own protocol methods, since a peer can't bound
+/// the memory a message needs.
+fn check_unbounded_payloads<'a>(
+    root: &Root,
+    source_files: &[&'a SourceFile],
+    linter: &Linter<'_, 'a>,
+) {
+    let span_of = |location: &Location| {
+        source_files
+            .iter()
+            .find(|f| f.filename() == location.filename)?
+            .span_at(location.line, location.column, location.length)
+    };
+    let mut checked = HashSet::new();
+    for protocol in &root.protocol_declarations {
+        for method in protocol.methods.iter().filter(|m| !m.is_composed) {
+            // Error and flexible methods wrap the response in a result union.
+            let response = method
+                .maybe_response_success_type
+                .as_ref()
+                .or(method.maybe_response_payload.as_ref());
+            for payload in [method.maybe_request_payload.as_ref(), response]
+                .into_iter()
+                .flatten()
+            {
+                let Some(payload_name) = payload.identifier() else {
+                    continue;
+                };
+                if !checked.insert(payload_name.clone()) {
+                    continue;
+                }
+                let members = root
+                    .struct_declarations
+                    .iter()
+                    .filter(|d| d.base.name == payload_name)
+                    .flat_map(|d| d.members.iter().map(|m| (&m.base, Some(&m.type_))))
+                    .chain(
+                        root.table_declarations
+                            .iter()
+                            .filter(|d| d.base.name == payload_name)
+                            .flat_map(|d| d.members.iter().map(|m| (&m.base, m.type_.as_ref()))),
+                    )
+                    .chain(
+                        root.union_declarations
+                            .iter()
+                            .filter(|d| d.base.name == payload_name)
+                            .flat_map(|d| d.members.iter().map(|m| (&m.base, m.type_.as_ref()))),
+                    );
+                for (base, type_) in members {
+                    let Some(kind) = type_.and_then(unbounded_kind) else {
+                        continue;
+                    };
+                    let Some(span) = span_of(&base.location) else {
+                        continue;
+                    };
+                    let member = base.name.member().unwrap_or(base.name.declaration());
+                    linter.report(
+                        Error::WarnLintUnboundedPayload(
+                            kind.into(),
+                            member.into(),
+                            FlyStr::new(format!(
+                                "{}.{}",
+                                protocol.base.name.declaration(),
+                                method.base.name.declaration()
+                            )),
+                        ),
+                        span,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Returns "vector" or "string" if `type_`, or a vector or array element of
+/// it, has no maximum size.
+fn unbounded_kind(type_: &Type) -> Option<&'static str> {
+    match type_ {
+        Type::Vector(v) if v.maybe_element_count.is_none() => Some("vector"),
+        Type::String(s) if s.maybe_element_count.is_none() => Some("string"),
+        Type::Vector(v) => unbounded_kind(&v.element_type),
+        Type::Array(a) => unbounded_kind(&a.element_type),
+        _ => None,
+    }
+}
@@ -0,0 +1,854 @@
+//! A language server for FIDL, run with `fidlc lsp`.
+//!
+//! The server speaks the Language Server Protocol over stdin and stdout. It
+//! keeps the text of every open document, recompiles all of them whenever one
+//! changes, and publishes the diagnostics. Definitions and references are
+//! answered from the compiled declarations, their [`Location`]s and the
+//! references the compiler recorded. Hovers, completions and renames come
+//! from [`api::hover`], [`api::complete`] and [`api::rename`]. Document
+//! symbols and semantic tokens only need each document parsed, and come from
+//! [`api::outline`] and [`api::semantic_tokens`].
+//!
+//! Documents are grouped into libraries by their `library` declaration. Each
+//! library is compiled with the open libraries it imports, directly or not,
+//! as dependencies. Document URIs are used as the source filenames, so the
+//! locations in diagnostics and declarations refer back to documents.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+
+use serde_json::{Value, json};
+
+use crate::api::{self, CompileOptions, CompileOutput, Sources};
+use crate::compiler::Referent;
+use crate::completion::CompletionKind;
+use crate::diagnostics::ErrorKind;
+use crate::flat_ast::{DeclBase, Location, Root};
+use crate::hover::doc_comment;
+use crate::library_path::library_header;
+use crate::names::OwnedQualifiedName;
+use crate::outline::OutlineSymbol;
+use crate::semantic_tokens::SemanticTokenKind;
+use crate::source_file::{ColumnUnit, SourceFile};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32803;
+
+/// The legend for semantic tokens, indexed by [`semantic_token_type`].
+const SEMANTIC_TOKEN_TYPES: [&str; 10] = [
+    "keyword",
+    "modifier",
+    "namespace",
+    "type",
+    "property",
+    "variable",
+    "decorator",
+    "comment",
+    "number",
+    "string",
+];
+const SEMANTIC_TOKEN_MODIFIERS: [&str; 2] = ["declaration", "documentation"];
+
+/// Reads one JSON-RPC message with its `Content-Length` header. Returns
+/// `None` at the end of the input. A body that isn't JSON is an
+/// [`io::ErrorKind::InvalidData`] error wrapping the [`serde_json::Error`];
+/// the next message can still be read after it.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            content_length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(length) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message has no Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Serves requests from `input` until the client sends `exit`. Returns an
+/// error if the input ends, or `exit` comes, without a `shutdown` request.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> Result<(), String> {
+    let mut server = Server::new();
+    loop {
+        let replies = match read_message(&mut input) {
+            Ok(Some(message)) => server.handle(&message),
+            Ok(None) => break,
+            Err(e) if e.get_ref().is_some_and(|e| e.is::<serde_json::Error>()) => {
+                vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": PARSE_ERROR, "message": e.to_string() },
+                })]
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+        for reply in replies {
+            write_message(&mut output, &reply).map_err(|e| e.to_string())?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    if server.shutdown_requested {
+        Ok(())
+    } else {
+        Err("The language server exited without a shutdown request".to_string())
+    }
+}
+
+/// A compiled declaration or member.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    /// The fully qualified name, e.g. `fuchsia.example/Color.RED`.
+    pub name: String,
+    pub kind: &'static str,
+    pub location: Location,
+    pub doc: Option<String>,
+    pub members: Vec<Symbol>,
+}
+
+impl Symbol {
+    fn new(kind: &'static str, base: &DeclBase) -> Self {
+        Self {
+            name: base.name.to_string(),
+            kind,
+            location: base.location.clone(),
+            doc: doc_comment(&base.maybe_attributes),
+            members: Vec::new(),
+        }
+    }
+
+    fn with_members<'b>(
+        mut self,
+        members: impl IntoIterator<Item = (&'static str, &'b DeclBase)>,
+    ) -> Self {
+        self.members = members
+            .into_iter()
+            .map(|(kind, base)| {
+                let mut member = Symbol::new(kind, base);
+                member.name = format!("{}.{}", self.name, base.name);
+                member
+            })
+            .collect();
+        self
+    }
+}
+
+/// Lists the declarations of a compiled library that were written in its
+/// source, leaving out the layouts the compiler names itself.
+pub fn root_symbols(root: &Root) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    for decl in &root.bits_declarations {
+        symbols.push(
+            Symbol::new("bits", &decl.base)
+                .with_members(decl.members.iter().map(|m| ("member", &m.base))),
+        );
+    }
+    for decl in &root.const_declarations {
+        symbols.push(Symbol::new("const", &decl.base));
+    }
+    for decl in &root.enum_declarations {
+        symbols.push(
+            Symbol::new("enum", &decl.base)
+                .with_members(decl.members.iter().map(|m| ("member", &m.base))),
+        );
+    }
+    for decl in &root.experimental_resource_declarations {
+        symbols.push(Symbol::new("resource", &decl.base));
+    }
+    for decl in &root.protocol_declarations {
+        symbols.push(
+            Symbol::new("protocol", &decl.base).with_members(
+                decl.methods
+                    .iter()
+                    .filter(|m| !m.is_composed)
+                    .map(|m| ("method", &m.base)),
+            ),
+        );
+    }
+    for decl in &root.service_declarations {
+        symbols.push(
+            Symbol::new("service", &decl.base)
+                .with_members(decl.members.iter().map(|m| ("member", &m.base))),
+        );
+    }
+    for decl in &root.struct_declarations {
+        if decl.naming_context.len() == 1 {
+            symbols.push(
+                Symbol::new("struct", &decl.base)
+                    .with_members(decl.members.iter().map(|m| ("member", &m.base))),
+            );
+        }
+    }
+    for decl in &root.table_declarations {
+        if decl.naming_context.len() == 1 {
+            symbols.push(
+                Symbol::new("table", &decl.base)
+                    .with_members(decl.members.iter().map(|m| ("member", &m.base))),
+            );
+        }
+    }
+    let overlays = root.overlay_declarations.iter().flatten();
+    for (kind, decl) in root
+        .union_declarations
+        .iter()
+        .map(|d| ("union", d))
+        .chain(overlays.map(|d| ("overlay", d)))
+    {
+        if decl.naming_context.len() == 1 {
+            symbols.push(
+                Symbol::new(kind, &decl.base)
+                    .with_members(decl.members.iter().map(|m| ("member", &m.base))),
+            );
+        }
+    }
+    for decl in &root.alias_declarations {
+        symbols.push(Symbol::new("alias", &decl.base));
+    }
+    for decl in &root.new_type_declarations {
+        symbols.push(Symbol::new("type", &decl.base));
+    }
+    symbols.sort_by_key(|s| {
+        (
+            s.location.filename.clone(),
+            s.location.line,
+            s.location.column,
+        )
+    });
+    symbols
+}
+
+struct Document {
+    source: SourceFile,
+    /// The library the document declares, if its header parses.
+    library: Option<String>,
+    /// The libraries the document imports with `using`.
+    imports: Vec<String>,
+}
+
+#[derive(Default)]
+struct Analysis {
+    /// Diagnostics for each document, by URI.
+    diagnostics: BTreeMap<String, Vec<api::Diagnostic>>,
+    /// Declarations of every library that compiled.
+    symbols: Vec<Symbol>,
+    /// The sources and output of compiling each library.
+    outputs: Vec<(Sources, CompileOutput)>,
+}
+
+pub struct Server {
+    documents: BTreeMap<String, Document>,
+    analysis: Analysis,
+    shutdown_requested: bool,
+    exited: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self {
+            documents: BTreeMap::new(),
+            analysis: Analysis::default(),
+            shutdown_requested: false,
+            exited: false,
+        }
+    }
+
+    /// Handles one message from the client and returns the messages to send
+    /// back: the response to a request, and any diagnostics to publish.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let id = message.get("id");
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                    "referencesProvider": true,
+                    "renameProvider": true,
+                    "semanticTokensProvider": {
+                        "legend": {
+                            "tokenTypes": SEMANTIC_TOKEN_TYPES,
+                            "tokenModifiers": SEMANTIC_TOKEN_MODIFIERS,
+                        },
+                        "full": true,
+                    },
+                    "completionProvider": { "triggerCharacters": [".", "@"] },
+                },
+                "serverInfo": { "name": "fidlc" },
+            })),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            }
+            "exit" => {
+                self.exited = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                if let (Some(uri), Some(text)) =
+                    (document["uri"].as_str(), document["text"].as_str())
+                {
+                    self.open(uri, text);
+                }
+                return self.analyze();
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                // The server asks for full document sync, so the last change
+                // holds the whole text.
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let Some(text) = text
+                    && self.documents.contains_key(uri)
+                {
+                    self.open(uri, text);
+                }
+                return self.analyze();
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                let mut messages = self.analyze();
+                messages.push(publish_diagnostics(uri, Vec::new()));
+                return messages;
+            }
+            "textDocument/definition" => self
+                .symbol_at(params)
+                .map(|symbol| symbol.map_or(Value::Null, |s| self.lsp_location(&s.location))),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/rename" => self.rename(params),
+            "textDocument/documentSymbol" => Ok(self.document_symbols(params)),
+            "textDocument/semanticTokens/full" => Ok(self.semantic_tokens(params)),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        };
+
+        // Notifications have no id and get no response.
+        let Some(id) = id else {
+            return Vec::new();
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        vec![response]
+    }
+
+    fn open(&mut self, uri: &str, text: &str) {
+        let source = SourceFile::new(uri.to_string(), text.to_string());
+        let (library, imports) = match library_header(&source) {
+            Some((name, imports)) => (Some(name), imports),
+            None => (None, Vec::new()),
+        };
+        self.documents.insert(
+            uri.to_string(),
+            Document {
+                source,
+                library,
+                imports,
+            },
+        );
+    }
+
+    /// Recompiles every open library and returns the diagnostics to publish
+    /// for each open document.
+    fn analyze(&mut self) -> Vec<Value> {
+        let mut libraries: BTreeMap<Option<&str>, Vec<&str>> = BTreeMap::new();
+        for (uri, document) in &self.documents {
+            libraries
+                .entry(document.library.as_deref())
+                .or_default()
+                .push(uri);
+        }
+
+        let mut analysis = Analysis::default();
+        for (library, uris) in &libraries {
+            let dependencies = match library {
+                Some(library) => self.dependencies(library),
+                None => BTreeSet::new(),
+            };
+            let mut sources = Sources::new();
+            for (uri, document) in &self.documents {
+                if uris.contains(&uri.as_str()) {
+                    sources.add_file(uri.clone(), document.source.data());
+                } else if document
+                    .library
+                    .as_ref()
+                    .is_some_and(|l| dependencies.contains(l.as_str()))
+                {
+                    sources.add_dependency_file(uri.clone(), document.source.data());
+                }
+            }
+            let Ok(mut output) = api::compile(&sources, &CompileOptions::default()) else {
+                continue;
+            };
+            for diagnostic in std::mem::take(&mut output.diagnostics) {
+                let uri = diagnostic.location.as_ref().map(|l| l.filename.as_str());
+                if let Some(uri) = uri.filter(|uri| uris.contains(uri)) {
+                    analysis
+                        .diagnostics
+                        .entry(uri.to_string())
+                        .or_default()
+                        .push(diagnostic);
+                }
+            }
+            if let Some(root) = &output.root {
+                analysis.symbols.extend(root_symbols(root));
+            }
+            analysis.outputs.push((sources, output));
+        }
+        self.analysis = analysis;
+
+        self.documents
+            .keys()
+            .map(|uri| {
+                let diagnostics = self
+                    .analysis
+                    .diagnostics
+                    .get(uri)
+                    .into_iter()
+                    .flatten()
+                    .map(|d| self.lsp_diagnostic(uri, d))
+                    .collect();
+                publish_diagnostics(uri, diagnostics)
+            })
+            .collect()
+    }
+
+    /// The open libraries that `library` imports, directly or through one
+    /// another, not including `library` itself.
+    fn dependencies(&self, library: &str) -> BTreeSet<&str> {
+        let mut dependencies = BTreeSet::new();
+        let mut pending = vec![library];
+        while let Some(current) = pending.pop() {
+            let imports = self
+                .documents
+                .values()
+                .filter(|d| d.library.as_deref() == Some(current))
+                .flat_map(|d| &d.imports);
+            for import in imports {
+                if import != library && dependencies.insert(import.as_str()) {
+                    pending.push(import);
+                }
+            }
+        }
+        dependencies
+    }
+
+    /// Converts the position of a `TextDocumentPositionParams` to a URI,
+    /// a 1-based line and a 0-based byte offset.
+    fn position<'p>(&self, params: &'p Value) -> Result<(&'p str, usize, usize), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let (Some(line), Some(character)) = (
+            params["position"]["line"].as_u64(),
+            params["position"]["character"].as_u64(),
+        ) else {
+            return Err((INVALID_PARAMS, "Missing position".to_string()));
+        };
+        let (line, character) = (line as usize + 1, character as usize);
+        let byte = self
+            .documents
+            .get(uri)
+            .and_then(|d| {
+                let offset = d.source.offset_at(line, character + 1, ColumnUnit::Utf16)?;
+                d.source.position_at(offset, ColumnUnit::Byte)
+            })
+            .map_or(character, |position| position.column - 1);
+        Ok((uri, line, byte))
+    }
+
+    /// Finds what the cursor is on: a reference, or the name of a
+    /// declaration or member.
+    fn referent_at(&self, params: &Value) -> Result<Option<Referent>, (i64, String)> {
+        let (uri, line, byte) = self.position(params)?;
+        if let Some(reference) = self
+            .analysis
+            .outputs
+            .iter()
+            .find_map(|(_, output)| output.reference_at(uri, line, byte))
+        {
+            return Ok(Some(reference.referent.clone()));
+        }
+        let symbol = self
+            .analysis
+            .symbols
+            .iter()
+            .flat_map(|s| std::iter::once(s).chain(&s.members))
+            .find(|s| {
+                let l = &s.location;
+                l.filename == uri
+                    && l.line == line
+                    && (l.column - 1..=l.column - 1 + l.length).contains(&byte)
+            });
+        Ok(symbol.map(|s| Referent::Declaration(OwnedQualifiedName::parse(&s.name))))
+    }
+
+    fn symbol(&self, referent: &Referent) -> Option<&Symbol> {
+        let Referent::Declaration(name) = referent else {
+            return None;
+        };
+        let name = name.to_string();
+        self.analysis
+            .symbols
+            .iter()
+            .flat_map(|s| std::iter::once(s).chain(&s.members))
+            .find(|s| s.name == name)
+    }
+
+    fn symbol_at(&self, params: &Value) -> Result<Option<&Symbol>, (i64, String)> {
+        Ok(self
+            .referent_at(params)?
+            .and_then(|referent| self.symbol(&referent)))
+    }
+
+    fn references(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let Some(referent) = self.referent_at(params)? else {
+            return Ok(Value::Null);
+        };
+        let mut locations = Vec::new();
+        if params["context"]["includeDeclaration"].as_bool() == Some(true)
+            && let Some(symbol) = self.symbol(&referent)
+        {
+            locations.push(self.lsp_location(&symbol.location));
+        }
+        for (_, output) in &self.analysis.outputs {
+            for reference in output.references_to(&referent) {
+                let location = &reference.location;
+                locations.push(json!({
+                    "uri": location.filename,
+                    "range": self.api_range(location),
+                }));
+            }
+        }
+        Ok(Value::Array(locations))
+    }
+
+    /// Renames what is under the cursor in the library that declares it and
+    /// in every other open library. A new name that doesn't work fails the
+    /// request with the reason.
+    fn rename(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let Some(new_name) = params["newName"].as_str() else {
+            return Err((INVALID_PARAMS, "Missing newName".to_string()));
+        };
+        let Some(referent @ Referent::Declaration(_)) = self.referent_at(params)? else {
+            return Ok(Value::Null);
+        };
+        let Some((sources, _)) = self
+            .symbol(&referent)
+            .and_then(|symbol| self.library_of(&symbol.location.filename))
+        else {
+            return Ok(Value::Null);
+        };
+        let dependents: Vec<Sources> = self
+            .analysis
+            .outputs
+            .iter()
+            .map(|(dependent, _)| dependent)
+            .filter(|dependent| !std::ptr::eq(*dependent, sources))
+            .cloned()
+            .collect();
+        let output = api::rename(
+            sources,
+            &dependents,
+            &CompileOptions::default(),
+            &referent.to_string(),
+            new_name,
+        )
+        .map_err(|e| (REQUEST_FAILED, e))?;
+        if let Some(diagnostic) = output.diagnostics.first() {
+            return Err((REQUEST_FAILED, diagnostic.message.clone()));
+        }
+        let edits = output.edits;
+        let mut changes: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+        for edit in &edits {
+            changes
+                .entry(&edit.location.filename)
+                .or_default()
+                .push(json!({ "range": self.api_range(&edit.location), "newText": edit.new_text }));
+        }
+        Ok(json!({ "changes": changes }))
+    }
+
+    /// Recompiles the library of the document to summarize what is under
+    /// the cursor, since the compiler's state isn't kept between requests.
+    fn hover(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, line, byte) = self.position(params)?;
+        let Some((sources, _)) = self.library_of(uri) else {
+            return Ok(Value::Null);
+        };
+        let hover = api::hover(sources, &CompileOptions::default(), uri, line, byte)
+            .map_err(|e| (INVALID_PARAMS, e))?;
+        Ok(hover.map_or(Value::Null, |hover| {
+            json!({
+                "contents": { "kind": "markdown", "value": hover.markdown() },
+                "range": self.api_range(&hover.range),
+            })
+        }))
+    }
+
+    fn lsp_diagnostic(&self, uri: &str, diagnostic: &api::Diagnostic) -> Value {
+        let severity = match diagnostic.kind() {
+            ErrorKind::Error => 1,
+            ErrorKind::Warning | ErrorKind::Retired => 2,
+        };
+        let range = diagnostic.location.as_ref().map_or(Value::Null, |l| {
+            json!({
+                "start": self.lsp_position(uri, l.start_line, l.start_char),
+                "end": self.lsp_position(uri, l.end_line, l.end_char),
+            })
+        });
+        json!({
+            "range": range,
+            "severity": severity,
+            "code": diagnostic.id(),
+            "source": "fidlc",
+            "message": diagnostic.message,
+        })
+    }
+
+    /// Converts a 1-based line and 0-based byte offset to an LSP position,
+    /// whose character counts UTF-16 code units.
+    fn lsp_position(&self, uri: &str, line: usize, byte: usize) -> Value {
+        let (line, character) = self.utf16_position(uri, line, byte);
+        json!({ "line": line, "character": character })
+    }
+
+    /// Converts a 1-based line and 0-based byte offset to a 0-based line and
+    /// UTF-16 offset.
+    fn utf16_position(&self, uri: &str, line: usize, byte: usize) -> (usize, usize) {
+        let character = self
+            .documents
+            .get(uri)
+            .and_then(|d| {
+                let offset = d.source.offset_at(line, byte + 1, ColumnUnit::Byte)?;
+                d.source.position_at(offset, ColumnUnit::Utf16)
+            })
+            .map_or(byte, |position| position.column - 1);
+        (line.saturating_sub(1), character)
+    }
+
+    fn lsp_range(&self, location: &Location) -> Value {
+        let start = location.column.saturating_sub(1);
+        json!({
+            "start": self.lsp_position(&location.filename, location.line, start),
+            "end": self.lsp_position(&location.filename, location.line, start + location.length),
+        })
+    }
+
+    fn completion(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, line, byte) = self.position(params)?;
+        let Some((sources, _)) = self.library_of(uri) else {
+            return Ok(Value::Array(Vec::new()));
+        };
+        let items = api::complete(sources, uri, line, byte)
+            .into_iter()
+            .map(|completion| {
+                json!({
+                    "label": completion.label,
+                    "kind": completion_kind(completion.kind),
+                    "detail": completion.detail,
+                })
+            })
+            .collect();
+        Ok(Value::Array(items))
+    }
+
+    /// The sources and output of the library `uri` belongs to.
+    fn library_of(&self, uri: &str) -> Option<&(Sources, CompileOutput)> {
+        self.analysis
+            .outputs
+            .iter()
+            .find(|(sources, _)| sources.library.iter().any(|(filename, _)| filename == uri))
+    }
+
+    fn api_range(&self, location: &api::Location) -> Value {
+        json!({
+            "start": self.lsp_position(&location.filename, location.start_line, location.start_char),
+            "end": self.lsp_position(&location.filename, location.end_line, location.end_char),
+        })
+    }
+
+    fn lsp_location(&self, location: &Location) -> Value {
+        json!({ "uri": location.filename, "range": self.lsp_range(location) })
+    }
+
+    /// The declarations of a document and their members, from its outline,
+    /// so that a document with errors still has them.
+    fn document_symbols(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let outline = self
+            .documents
+            .get(uri)
+            .and_then(|document| api::outline(uri, document.source.data()));
+        Value::Array(outline.map_or_else(Vec::new, |library| {
+            library
+                .children
+                .iter()
+                .map(|s| self.document_symbol(s))
+                .collect()
+        }))
+    }
+
+    fn document_symbol(&self, symbol: &OutlineSymbol) -> Value {
+        json!({
+            "name": symbol.name,
+            "detail": symbol.kind,
+            "kind": symbol_kind(&symbol.kind),
+            "range": self.api_range(&symbol.range),
+            "selectionRange": self.api_range(&symbol.selection_range),
+            "children": symbol
+                .children
+                .iter()
+                .map(|s| self.document_symbol(s))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Encodes the semantic tokens of a document as LSP expects: five
+    /// numbers per token, with each position relative to the previous token.
+    /// LSP tokens can't span lines, so a token that does is split per line.
+    fn semantic_tokens(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some(document) = self.documents.get(uri) else {
+            return Value::Null;
+        };
+        let lines: Vec<&str> = document.source.data().lines().collect();
+        let mut data = Vec::new();
+        let (mut previous_line, mut previous_start) = (0, 0);
+        for token in api::semantic_tokens(uri, document.source.data()) {
+            let Some((token_type, modifiers)) = semantic_token_type(token.kind) else {
+                continue;
+            };
+            let location = &token.location;
+            for line_number in location.start_line..=location.end_line {
+                let start_byte = if line_number == location.start_line {
+                    location.start_char
+                } else {
+                    0
+                };
+                let end_byte = if line_number == location.end_line {
+                    location.end_char
+                } else {
+                    lines.get(line_number - 1).map_or(0, |l| l.len())
+                };
+                let (line, start) = self.utf16_position(uri, line_number, start_byte);
+                let (_, end) = self.utf16_position(uri, line_number, end_byte);
+                if end <= start {
+                    continue;
+                }
+                let delta_start = if line == previous_line {
+                    start - previous_start
+                } else {
+                    start
+                };
+                data.extend([
+                    line - previous_line,
+                    delta_start,
+                    end - start,
+                    token_type,
+                    modifiers,
+                ]);
+                (previous_line, previous_start) = (line, start);
+            }
+        }
+        json!({ "data": data })
+    }
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+/// The LSP `CompletionItemKind` for a kind of completion.
+fn completion_kind(kind: CompletionKind) -> u32 {
+    match kind {
+        CompletionKind::Keyword | CompletionKind::Attribute => 14,
+        CompletionKind::Type => 7,
+        CompletionKind::Library => 9,
+        CompletionKind::Member => 20,
+        CompletionKind::AttributeArgument => 10,
+    }
+}
+
+/// The index of the LSP token type in [`SEMANTIC_TOKEN_TYPES`] and the bits
+/// of the [`SEMANTIC_TOKEN_MODIFIERS`] for a kind of token. Punctuation and
+/// unclassified identifiers are left to the editor's syntax highlighting.
+fn semantic_token_type(kind: SemanticTokenKind) -> Option<(usize, usize)> {
+    const DECLARATION: usize = 1;
+    const DOCUMENTATION: usize = 2;
+    Some(match kind {
+        SemanticTokenKind::Keyword => (0, 0),
+        SemanticTokenKind::Modifier => (1, 0),
+        SemanticTokenKind::Library => (2, 0),
+        SemanticTokenKind::DeclarationName => (3, DECLARATION),
+        SemanticTokenKind::TypeReference => (3, 0),
+        SemanticTokenKind::MemberName => (4, DECLARATION),
+        SemanticTokenKind::ConstantReference => (5, 0),
+        SemanticTokenKind::Attribute => (6, 0),
+        SemanticTokenKind::DocComment => (7, DOCUMENTATION),
+        SemanticTokenKind::NumericLiteral => (8, 0),
+        SemanticTokenKind::StringLiteral => (9, 0),
+        SemanticTokenKind::Punctuation | SemanticTokenKind::Identifier => return None,
+    })
+}
+
+/// The LSP `SymbolKind` for a kind of declaration or member.
+fn symbol_kind(kind: &str) -> u32 {
+    match kind {
+        "protocol" => 11,
+        "method" => 6,
+        "const" => 14,
+        "enum" | "bits" => 10,
+        "member" => 8,
+        "service" | "resource" => 5,
+        _ => 23,
+    }
+}
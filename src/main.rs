@@ -31,6 +31,14 @@ fn main() {
     let matches = cmd.get_matches_from(expanded_args);
     let cli = fidlcrs::cli::Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
+    if let Some(command) = &cli.command {
+        if let Err(e) = fidlcrs::cli::run_command(command) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
     let mut source_managers: Vec<Vec<String>> = Vec::new();
 
     if let Some(files_vals) = matches.get_many::<String>("files") {
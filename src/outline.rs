@@ -0,0 +1,66 @@
+//! The outline of a source file: its library, the declarations in it, and
+//! their members and methods.
+//!
+//! [`outline`] works from the raw AST alone, so it has an outline for a file
+//! that doesn't compile, and for every declaration the parser recovered in a
+//! file with syntax errors.
+
+use crate::api::Location;
+use crate::flat_ast::DeclarationKind;
+use crate::raw_ast::{self, RawDecl};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineSymbol {
+    /// The library name, or the unqualified name of a declaration or member.
+    pub name: String,
+    /// `library`, the kind of declaration like `struct`, or `member` or
+    /// `method`.
+    pub kind: String,
+    /// The whole library, declaration or member, including its attributes.
+    pub range: Location,
+    /// Just the name.
+    pub selection_range: Location,
+    pub children: Vec<OutlineSymbol>,
+}
+
+/// The outline of `file`, with its library at the root and the declarations
+/// in source order. Returns `None` if the file has no library declaration.
+pub fn outline(file: &raw_ast::File<'_>) -> Option<OutlineSymbol> {
+    let library = file.library_decl.as_ref()?;
+    let mut decls: Vec<RawDecl<'_, '_>> = file.raw_decls().filter(|d| d.name().is_some()).collect();
+    decls.sort_by_key(|d| d.element().span().data.as_ptr() as usize);
+    Some(OutlineSymbol {
+        name: library.path.to_string(),
+        kind: "library".to_string(),
+        range: Location::new(&file.element.span()),
+        selection_range: Location::new(&library.path.element.span()),
+        children: decls.iter().map(declaration).collect(),
+    })
+}
+
+fn declaration(decl: &RawDecl<'_, '_>) -> OutlineSymbol {
+    let kind = decl.kind();
+    let member_kind = if kind == DeclarationKind::Protocol {
+        "method"
+    } else {
+        "member"
+    };
+    let mut children = Vec::new();
+    decl.for_each_member(|name, _, element| {
+        children.push(OutlineSymbol {
+            name: name.data().to_string(),
+            kind: member_kind.to_string(),
+            range: Location::new(&element.span()),
+            selection_range: Location::new(&name.element.span()),
+            children: Vec::new(),
+        });
+    });
+    let name = decl.name().expect("anonymous layouts are filtered out");
+    OutlineSymbol {
+        name: name.data().to_string(),
+        kind: kind.to_string(),
+        range: Location::new(&decl.element().span()),
+        selection_range: Location::new(&name.element.span()),
+        children,
+    }
+}
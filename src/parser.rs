@@ -10,6 +10,8 @@ pub struct Parser<'a, 'b> {
     reporter: &'a Reporter<'a>,
     last_token: Token<'a>,
     previous_token: Option<Token<'a>>,
+    /// Every token lexed so far, when parsing losslessly.
+    tokens: Option<Vec<Token<'a>>>,
 }
 
 impl<'a, 'b> Parser<'a, 'b> {
@@ -20,13 +22,30 @@ impl<'a, 'b> Parser<'a, 'b> {
             reporter,
             last_token,
             previous_token: None,
+            tokens: None,
         }
     }
 
+    /// Like [`Parser::new`], but the parsed [`File`] keeps every token with
+    /// its trivia, so that [`File::to_source`] reproduces the source exactly.
+    pub fn new_lossless(lexer: &'b mut Lexer<'a>, reporter: &'a Reporter<'a>) -> Self {
+        let mut parser = Self::new(lexer, reporter);
+        parser.tokens = Some(vec![parser.last_token.clone()]);
+        parser
+    }
+
+    fn lex(&mut self) -> Token<'a> {
+        let token = self.lexer.lex();
+        if let Some(tokens) = &mut self.tokens {
+            tokens.push(token.clone());
+        }
+        token
+    }
+
     pub fn parse_file(&mut self) -> Option<File<'a>> {
         let start_pos = self.last_token.clone();
         if self.last_token.kind == TokenKind::StartOfFile {
-            self.last_token = self.lexer.lex();
+            self.last_token = self.lex();
         }
 
         let mut attributes = self.maybe_parse_attribute_list();
@@ -113,50 +132,50 @@ impl<'a, 'b> Parser<'a, 'b> {
                 let _ = library_decl.is_some();
                 library_decl = self.parse_library_declaration(attributes.take());
                 if library_decl.is_none() {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Using {
                 if let Some(decl) = self.parse_using_declaration(attributes.take()) {
                     using_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Const {
                 if let Some(decl) = self.parse_const_declaration(attributes.take()) {
                     const_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Alias {
                 if let Some(decl) = self.parse_alias_declaration(attributes.take()) {
                     alias_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Type {
                 if let Some(decl) = self.parse_type_declaration(attributes.take()) {
                     type_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Protocol {
                 if let Some(decl) = self.parse_protocol_declaration(attributes.take(), mods) {
                     protocol_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Service {
                 if let Some(decl) = self.parse_service_declaration(attributes.take()) {
                     service_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Struct {
                 if let Some(decl) = self.parse_struct_declaration(attributes.take(), mods) {
                     self.consume_token(TokenKind::Semicolon);
                     struct_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Union
                 || self.last_token.subkind == TokenSubkind::Overlay
@@ -165,41 +184,41 @@ impl<'a, 'b> Parser<'a, 'b> {
                     self.consume_token(TokenKind::Semicolon);
                     union_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Table {
                 if let Some(decl) = self.parse_table_declaration(attributes.take(), mods) {
                     self.consume_token(TokenKind::Semicolon);
                     table_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Enum {
                 if let Some(decl) = self.parse_enum_declaration(attributes.take(), mods) {
                     self.consume_token(TokenKind::Semicolon);
                     enum_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::Bits {
                 if let Some(decl) = self.parse_bits_declaration(attributes.take(), mods) {
                     self.consume_token(TokenKind::Semicolon);
                     bits_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else if self.last_token.subkind == TokenSubkind::ResourceDefinition {
                 if let Some(decl) = self.parse_resource_declaration(attributes.take()) {
                     resource_decls.push(decl);
                 } else {
-                    self.last_token = self.lexer.lex();
+                    self.last_token = self.lex();
                 }
             } else {
                 self.reporter.fail(
                     Error::ErrExpectedDeclaration(self.last_token.span.data.into()),
                     self.last_token.span,
                 );
-                self.last_token = self.lexer.lex();
+                self.last_token = self.lex();
             }
         }
 
@@ -219,7 +238,7 @@ impl<'a, 'b> Parser<'a, 'b> {
             protocol_decls,
             service_decls,
             resource_decls,
-            tokens: vec![], // Assuming token list is not needed internally
+            tokens: self.tokens.take().unwrap_or_default(),
         })
     }
 
@@ -615,7 +634,7 @@ impl<'a, 'b> Parser<'a, 'b> {
             );
             self.reporter
                 .fail(Error::ErrMissingOrdinalBeforeMember, self.last_token.span);
-            self.last_token = self.lexer.lex(); // Consume equal
+            self.last_token = self.lex(); // Consume equal
             self.parse_literal(); // Attempt to consume the default value
         }
 
@@ -812,6 +831,8 @@ impl<'a, 'b> Parser<'a, 'b> {
             self.consume_token(TokenKind::RightParen)?;
             Some(constant)
         } else {
+            self.reporter
+                .fail(Error::ErrUnexpectedToken, self.last_token.span);
             None
         }
     }
@@ -1559,7 +1580,7 @@ impl<'a, 'b> Parser<'a, 'b> {
         if self.last_token.subkind == subkind {
             let token = self.last_token.clone();
             self.previous_token = Some(token.clone());
-            self.last_token = self.lexer.lex();
+            self.last_token = self.lex();
             Some(token)
         } else {
             let expected = format!("{:?}", subkind);
@@ -1597,7 +1618,7 @@ impl<'a, 'b> Parser<'a, 'b> {
         if self.last_token.kind == kind {
             let token = self.last_token.clone();
             self.previous_token = Some(token.clone());
-            self.last_token = self.lexer.lex();
+            self.last_token = self.lex();
             Some(token)
         } else {
             let expected = format!("{:?}", kind);
@@ -1704,14 +1725,18 @@ impl<'a, 'b> Parser<'a, 'b> {
         while self.last_token.kind != TokenKind::RightCurly
             && self.last_token.kind != TokenKind::EndOfFile
         {
-            let prop_start = self.last_token.clone();
+            let prop_attrs = self.maybe_parse_attribute_list();
+            let prop_start = prop_attrs
+                .as_ref()
+                .map(|a| a.element.start_token.clone())
+                .unwrap_or_else(|| self.last_token.clone());
             let prop_name = self.parse_identifier()?;
             let prop_type = self.parse_type_constructor()?;
             self.consume_token(TokenKind::Semicolon)?;
             let prop_end = self.previous_token.as_ref().unwrap().clone();
             properties.push(ResourceProperty {
                 element: SourceElement::new(prop_start, prop_end),
-                attributes: None,
+                attributes: prop_attrs.map(Box::new),
                 type_ctor: prop_type,
                 name: prop_name,
             });
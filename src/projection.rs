@@ -0,0 +1,317 @@
+//! A library's source as it is at one version.
+//!
+//! [`project`] rewrites a file of the main library to keep only the
+//! declarations, members and modifiers in the compiler's version selection,
+//! and drops the `@available` attributes of the rest. A member that is
+//! `renamed` at or before the selected version is written under its new
+//! name. Whether an element is in the selection comes from the availability
+//! the compiler inherited for it, so the compiler must have run over the
+//! same files.
+
+use crate::compiler::Compiler;
+use crate::formatter::format_source;
+use crate::raw_ast::{self, AttributeList, AttributeProvenance, Identifier, SourceElement};
+use crate::reporter::Reporter;
+use crate::source_file::SourceFile;
+use crate::source_span::SourceSpan;
+use crate::tree_visitor::{self, TreeVisitor};
+use crate::versioning_types::{Availability, Platform, Version};
+
+/// `file` at the compiler's selected version, in the canonical style. It is
+/// left unformatted if the result doesn't parse.
+pub fn project(
+    source: &SourceFile,
+    file: &raw_ast::File<'_>,
+    compiler: &Compiler<'_, '_>,
+) -> String {
+    let platform = compiler
+        .platform
+        .clone()
+        .unwrap_or_else(Platform::unversioned);
+    let mut projector = Projector {
+        compiler,
+        version: compiler.version_selection.lookup(&platform),
+        platform,
+        source,
+        edits: Vec::new(),
+    };
+    projector.visit_file(file);
+
+    // Edits are made from the end so that the earlier offsets still hold.
+    // Nothing is visited inside a removed element, so edits never overlap.
+    let mut text = source.data().to_string();
+    projector.edits.sort_by_key(|(start, _, _)| *start);
+    for (start, end, replacement) in projector.edits.into_iter().rev() {
+        text.replace_range(start..end, &replacement);
+    }
+    let projected = SourceFile::new(source.filename().to_string(), text);
+    let reporter = Reporter::new();
+    format_source(&projected, &reporter).unwrap_or_else(|| projected.data().to_string())
+}
+
+struct Projector<'c, 'node, 'src> {
+    compiler: &'c Compiler<'node, 'src>,
+    platform: Platform,
+    version: Version,
+    source: &'c SourceFile,
+    /// Byte ranges of the source to replace, and what with.
+    edits: Vec<(usize, usize, String)>,
+}
+
+impl Projector<'_, '_, '_> {
+    fn offset(&self, span: &SourceSpan<'_>) -> usize {
+        span.data.as_ptr() as usize - self.source.data().as_ptr() as usize
+    }
+
+    /// Removes an element, along with the `;` that ends it. If that leaves
+    /// its lines empty, they are removed too.
+    fn remove(&mut self, element: &SourceElement<'_>) {
+        let data = self.source.data();
+        let span = element.span();
+        let mut start = self.offset(&span);
+        let mut end = start + span.data.len();
+        let rest = &data[end..];
+        let trimmed = rest.trim_start();
+        if trimmed.starts_with(';') {
+            end += rest.len() - trimmed.len() + 1;
+        }
+        let before = data[..start].trim_end_matches([' ', '\t']);
+        let after = data[end..].trim_start_matches([' ', '\t']);
+        if (before.is_empty() || before.ends_with('\n')) && after.starts_with('\n') {
+            start = before.len();
+            end = data.len() - after.len() + 1;
+        }
+        self.edits.push((start, end, String::new()));
+    }
+
+    fn replace(&mut self, span: &SourceSpan<'_>, text: String) {
+        let start = self.offset(span);
+        self.edits.push((start, start + span.data.len(), text));
+    }
+
+    fn is_selected(
+        &self,
+        availability: Option<&Availability>,
+        attributes: Option<&AttributeList<'_>>,
+    ) -> bool {
+        match availability {
+            Some(availability) => self
+                .compiler
+                .version_selection
+                .intersects(&self.platform, &availability.set()),
+            None => self.compiler.is_active(attributes),
+        }
+    }
+
+    /// Keeps a declaration if it is in the selection, walking into it with
+    /// `walk`, or removes it.
+    fn declaration(
+        &mut self,
+        name: &Identifier<'_>,
+        element: &SourceElement<'_>,
+        attributes: Option<&AttributeList<'_>>,
+        walk: impl FnOnce(&mut Self),
+    ) {
+        let full_name = format!("{}/{}", self.compiler.library_name, name.data());
+        // Of the declarations that reuse a name, the compiler only keeps the
+        // one that can be in the selection.
+        let is_kept = |id: &Identifier<'_>| {
+            id.element.span().data.as_ptr() == name.element.span().data.as_ptr()
+        };
+        let selected = self
+            .compiler
+            .raw_decls
+            .get(full_name.as_str())
+            .and_then(|decl| decl.name())
+            .is_some_and(is_kept)
+            && self.is_selected(
+                self.compiler.decl_availability.get(full_name.as_str()),
+                attributes,
+            );
+        if selected {
+            walk(self);
+        } else {
+            self.remove(element);
+        }
+    }
+
+    /// Keeps a member if it is in the selection, walking into it with `walk`
+    /// and renaming it if it was renamed by this version, or removes it.
+    fn member(
+        &mut self,
+        name: Option<&Identifier<'_>>,
+        element: &SourceElement<'_>,
+        attributes: Option<&AttributeList<'_>>,
+        walk: impl FnOnce(&mut Self),
+    ) {
+        let ptr = element.span().data.as_ptr() as usize;
+        if !self.is_selected(self.compiler.member_availability.get(&ptr), attributes) {
+            self.remove(element);
+            return;
+        }
+        if let Some(name) = name
+            && let Some(renamed) = self.renamed(attributes)
+        {
+            self.replace(&name.element.span(), renamed);
+        }
+        walk(self);
+    }
+
+    /// The new name of an element `renamed` at or before the selected
+    /// version.
+    fn renamed(&self, attributes: Option<&AttributeList<'_>>) -> Option<String> {
+        let available = attributes?
+            .attributes
+            .iter()
+            .find(|a| a.name.data() == "available")?;
+        let mut renamed = None;
+        let mut ending = None;
+        for arg in &available.args {
+            let raw_ast::Constant::Literal(lit) = &arg.value else {
+                continue;
+            };
+            let value = lit.literal.value.trim_matches('"');
+            match arg.name.as_ref().map(|n| n.data()) {
+                Some("renamed") => renamed = Some(value.to_string()),
+                Some("removed" | "replaced") => ending = Version::parse(value),
+                _ => {}
+            }
+        }
+        renamed.filter(|_| ending.is_some_and(|v| v <= self.version))
+    }
+}
+
+impl<'a> TreeVisitor<'a> for Projector<'_, '_, '_> {
+    fn visit_attribute(&mut self, node: &raw_ast::Attribute<'a>) {
+        if node.provenance == AttributeProvenance::Default && node.name.data() == "available" {
+            self.remove(&node.element);
+        }
+    }
+
+    fn visit_modifier(&mut self, node: &raw_ast::Modifier<'a>) {
+        let keyword = node.element.start_token.span;
+        if !self.compiler.is_active(node.attributes.as_ref()) {
+            self.remove(&node.element);
+        } else if node.attributes.is_some() {
+            // Drops the `(added=N)` after the keyword.
+            let start = self.offset(&keyword) + keyword.data.len();
+            let span = node.element.span();
+            let end = self.offset(&span) + span.data.len();
+            self.edits.push((start, end, String::new()));
+        }
+    }
+
+    fn visit_constdeclaration(&mut self, node: &raw_ast::ConstDeclaration<'a>) {
+        self.declaration(&node.name, &node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_constdeclaration(v, node)
+        });
+    }
+
+    fn visit_aliasdeclaration(&mut self, node: &raw_ast::AliasDeclaration<'a>) {
+        self.declaration(&node.name, &node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_aliasdeclaration(v, node)
+        });
+    }
+
+    fn visit_typedeclaration(&mut self, node: &raw_ast::TypeDeclaration<'a>) {
+        self.declaration(&node.name, &node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_typedeclaration(v, node)
+        });
+    }
+
+    fn visit_protocoldeclaration(&mut self, node: &raw_ast::ProtocolDeclaration<'a>) {
+        self.declaration(&node.name, &node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_protocoldeclaration(v, node)
+        });
+    }
+
+    fn visit_servicedeclaration(&mut self, node: &raw_ast::ServiceDeclaration<'a>) {
+        self.declaration(&node.name, &node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_servicedeclaration(v, node)
+        });
+    }
+
+    fn visit_resourcedeclaration(&mut self, node: &raw_ast::ResourceDeclaration<'a>) {
+        self.declaration(&node.name, &node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_resourcedeclaration(v, node)
+        });
+    }
+
+    fn visit_structmember(&mut self, node: &raw_ast::StructMember<'a>) {
+        self.member(
+            Some(&node.name),
+            &node.element,
+            node.attributes.as_deref(),
+            |v| tree_visitor::walk_structmember(v, node),
+        );
+    }
+
+    fn visit_enummember(&mut self, node: &raw_ast::EnumMember<'a>) {
+        self.member(
+            Some(&node.name),
+            &node.element,
+            node.attributes.as_deref(),
+            |v| tree_visitor::walk_enummember(v, node),
+        );
+    }
+
+    fn visit_bitsmember(&mut self, node: &raw_ast::BitsMember<'a>) {
+        self.member(
+            Some(&node.name),
+            &node.element,
+            node.attributes.as_deref(),
+            |v| tree_visitor::walk_bitsmember(v, node),
+        );
+    }
+
+    fn visit_unionmember(&mut self, node: &raw_ast::UnionMember<'a>) {
+        self.member(
+            node.name.as_ref(),
+            &node.element,
+            node.attributes.as_deref(),
+            |v| tree_visitor::walk_unionmember(v, node),
+        );
+    }
+
+    fn visit_tablemember(&mut self, node: &raw_ast::TableMember<'a>) {
+        self.member(
+            node.name.as_ref(),
+            &node.element,
+            node.attributes.as_deref(),
+            |v| tree_visitor::walk_tablemember(v, node),
+        );
+    }
+
+    fn visit_protocolmethod(&mut self, node: &raw_ast::ProtocolMethod<'a>) {
+        self.member(
+            Some(&node.name),
+            &node.element,
+            node.attributes.as_deref(),
+            |v| tree_visitor::walk_protocolmethod(v, node),
+        );
+    }
+
+    fn visit_protocolcompose(&mut self, node: &raw_ast::ProtocolCompose<'a>) {
+        self.member(None, &node.element, node.attributes.as_deref(), |v| {
+            tree_visitor::walk_protocolcompose(v, node)
+        });
+    }
+
+    fn visit_servicemember(&mut self, node: &raw_ast::ServiceMember<'a>) {
+        self.member(
+            Some(&node.name),
+            &node.element,
+            node.attributes.as_deref(),
+            |v| tree_visitor::walk_servicemember(v, node),
+        );
+    }
+
+    fn visit_resourceproperty(&mut self, node: &raw_ast::ResourceProperty<'a>) {
+        self.member(
+            Some(&node.name),
+            &node.element,
+            node.attributes.as_deref(),
+            |v| tree_visitor::walk_resourceproperty(v, node),
+        );
+    }
+}
@@ -212,6 +212,30 @@ pub struct File<'a> {
     pub tokens: Vec<Token<'a>>,
 }
 
+impl<'a> File<'a> {
+    /// Prints the file back out from its tokens. For a file parsed with
+    /// [`crate::parser::Parser::new_lossless`] this is the original source.
+    pub fn to_source(&self) -> String {
+        self.tokens.iter().map(Token::full_text).collect()
+    }
+
+    /// Every declaration in the file, in no particular order.
+    pub fn raw_decls(&self) -> impl Iterator<Item = RawDecl<'_, 'a>> {
+        let decls = self.type_decls.iter().map(RawDecl::Type);
+        decls
+            .chain(self.alias_decls.iter().map(RawDecl::Alias))
+            .chain(self.const_decls.iter().map(RawDecl::Const))
+            .chain(self.struct_decls.iter().map(RawDecl::Struct))
+            .chain(self.enum_decls.iter().map(RawDecl::Enum))
+            .chain(self.bits_decls.iter().map(RawDecl::Bits))
+            .chain(self.union_decls.iter().map(RawDecl::Union))
+            .chain(self.table_decls.iter().map(RawDecl::Table))
+            .chain(self.protocol_decls.iter().map(RawDecl::Protocol))
+            .chain(self.service_decls.iter().map(RawDecl::Service))
+            .chain(self.resource_decls.iter().map(RawDecl::Resource))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UsingDeclaration<'a> {
     pub element: SourceElement<'a>,
@@ -496,6 +520,102 @@ impl<'node, 'src> RawDecl<'node, 'src> {
         }
     }
 
+    /// The declaration's name, or `None` for an anonymous layout.
+    pub fn name(&self) -> Option<&'node Identifier<'src>> {
+        match self {
+            RawDecl::Struct(d) => d.name.as_ref(),
+            RawDecl::Enum(d) => d.name.as_ref(),
+            RawDecl::Bits(d) => d.name.as_ref(),
+            RawDecl::Union(d) => d.name.as_ref(),
+            RawDecl::Table(d) => d.name.as_ref(),
+            RawDecl::Protocol(d) => Some(&d.name),
+            RawDecl::Service(d) => Some(&d.name),
+            RawDecl::Resource(d) => Some(&d.name),
+            RawDecl::Const(d) => Some(&d.name),
+            RawDecl::Alias(d) => Some(&d.name),
+            RawDecl::Type(d) => Some(&d.name),
+        }
+    }
+
+    /// Calls `f` with the name, attributes and element of each named member,
+    /// method or resource property. Reserved members have no name and are
+    /// skipped.
+    pub fn for_each_member<F>(&self, mut f: F)
+    where
+        F: FnMut(
+            &'node Identifier<'src>,
+            Option<&'node AttributeList<'src>>,
+            &'node SourceElement<'src>,
+        ),
+    {
+        self.visit_members(&mut f);
+    }
+
+    fn visit_members(
+        &self,
+        f: &mut dyn FnMut(
+            &'node Identifier<'src>,
+            Option<&'node AttributeList<'src>>,
+            &'node SourceElement<'src>,
+        ),
+    ) {
+        match self {
+            RawDecl::Struct(d) => {
+                for m in &d.members {
+                    f(&m.name, m.attributes.as_deref(), &m.element);
+                }
+            }
+            RawDecl::Enum(d) => {
+                for m in &d.members {
+                    f(&m.name, m.attributes.as_deref(), &m.element);
+                }
+            }
+            RawDecl::Bits(d) => {
+                for m in &d.members {
+                    f(&m.name, m.attributes.as_deref(), &m.element);
+                }
+            }
+            RawDecl::Union(d) => {
+                for m in &d.members {
+                    if let Some(name) = &m.name {
+                        f(name, m.attributes.as_deref(), &m.element);
+                    }
+                }
+            }
+            RawDecl::Table(d) => {
+                for m in &d.members {
+                    if let Some(name) = &m.name {
+                        f(name, m.attributes.as_deref(), &m.element);
+                    }
+                }
+            }
+            RawDecl::Protocol(d) => {
+                for m in &d.methods {
+                    f(&m.name, m.attributes.as_deref(), &m.element);
+                }
+            }
+            RawDecl::Service(d) => {
+                for m in &d.members {
+                    f(&m.name, m.attributes.as_deref(), &m.element);
+                }
+            }
+            RawDecl::Resource(d) => {
+                for p in &d.properties {
+                    f(&p.name, p.attributes.as_deref(), &p.element);
+                }
+            }
+            RawDecl::Type(d) => match &d.layout {
+                Layout::Struct(l) => RawDecl::Struct(l).visit_members(f),
+                Layout::Enum(l) => RawDecl::Enum(l).visit_members(f),
+                Layout::Bits(l) => RawDecl::Bits(l).visit_members(f),
+                Layout::Union(l) => RawDecl::Union(l).visit_members(f),
+                Layout::Table(l) => RawDecl::Table(l).visit_members(f),
+                Layout::TypeConstructor(_) => {}
+            },
+            RawDecl::Const(_) | RawDecl::Alias(_) => {}
+        }
+    }
+
     pub fn for_each_modifier_list<F: FnMut(&'node [Modifier<'src>])>(&self, mut f: F) {
         match self {
             RawDecl::Struct(d) => f(&d.modifiers),
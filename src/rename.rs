@@ -0,0 +1,225 @@
+//! Renaming a declaration or member along with every reference to it.
+//!
+//! [`rename`] finds the name where it is declared in the raw AST, and the
+//! references to it that the compiler recorded in [`Compiler::references`]:
+//! type constructors, constants, `compose` clauses and member references like
+//! `Color.RED`. Renaming `Color` edits the `Color` part of `Color.RED`, and
+//! renaming `Color.RED` edits the `RED` part.
+//!
+//! References are only recorded for the library being compiled, so
+//! [`api::rename`] compiles each library that depends on the renamed one as
+//! well, and merges their edits.
+//!
+//! [`api::rename`]: crate::api::rename
+//!
+//! Before any edits are returned, the new name is checked against the names
+//! next to it the way the compiler checks them, so that the result compiles.
+//! A name that collides with another one, or has the same canonical form as
+//! another one, is reported as `ErrNameCollision` or
+//! `ErrNameCollisionCanonical`.
+
+use crate::canonical_names::canonicalize;
+use crate::compiler::Referent;
+use crate::compiler::{Compiler, MemberKind};
+use crate::diagnostics::{Error, Label};
+use crate::names::OwnedQualifiedName;
+use crate::raw_ast::Identifier;
+use crate::source_span::SourceSpan;
+
+/// Finds the spans to replace with `new_name` to rename `name`, a declaration
+/// like `fuchsia.example/Color` or a member like `fuchsia.example/Color.RED`.
+///
+/// Returns `None` if `name` isn't declared in the sources being compiled. If
+/// `new_name` isn't a valid identifier or would collide with another name,
+/// the problem is reported to the compiler's reporter and no spans are
+/// returned.
+pub fn rename<'src>(
+    compiler: &Compiler<'_, 'src>,
+    name: &OwnedQualifiedName,
+    new_name: &str,
+) -> Option<Vec<SourceSpan<'src>>> {
+    let decl_name = name
+        .library()
+        .to_owned()
+        .with_declaration(name.declaration());
+    let decl = compiler.raw_decls.get(&decl_name)?;
+    let kind = *compiler.decl_kinds.get(&decl_name)?;
+
+    // The declared name, and the names it must not collide with.
+    let (target, kind, siblings) = match name.member() {
+        None => {
+            let target = decl.name()?;
+            let mut siblings = Vec::new();
+            for (other_name, other) in &compiler.raw_decls {
+                if other_name.library() == name.library()
+                    && other_name.declaration() != name.declaration()
+                    && let Some(id) = other.name()
+                    && let Some(other_kind) = compiler.decl_kinds.get(other_name)
+                {
+                    siblings.push((other_kind.to_string(), id));
+                }
+            }
+            (target, kind.to_string(), siblings)
+        }
+        Some(member) => {
+            let member_kind = MemberKind::of(kind).to_string();
+            let mut target = None;
+            let mut siblings = Vec::new();
+            decl.for_each_member(|id, _, _| {
+                if id.data() == member {
+                    target = Some(id);
+                } else {
+                    siblings.push((member_kind.clone(), id));
+                }
+            });
+            (target?, member_kind, siblings)
+        }
+    };
+    let target_span = target.element.span();
+
+    if !is_identifier(new_name) {
+        compiler.reporter.fail(
+            Error::ErrInvalidIdentifier(flyweights::FlyStr::new(new_name)),
+            target_span,
+        );
+        return Some(Vec::new());
+    }
+    let mut collides = check_collisions(compiler, target_span, &kind, new_name, siblings);
+    if name.member().is_none() && name.library() == compiler.library_name.as_borrowed() {
+        collides |= check_library_imports(compiler, target_span, new_name);
+    }
+    if collides {
+        return Some(Vec::new());
+    }
+
+    let mut spans = vec![target_span];
+    for reference in &compiler.references {
+        let Referent::Declaration(referent) = &reference.referent else {
+            continue;
+        };
+        let component = if referent == name {
+            0
+        } else if name.member().is_none()
+            && referent.member().is_some()
+            && referent.library() == name.library()
+            && referent.declaration() == name.declaration()
+        {
+            1
+        } else {
+            continue;
+        };
+        if let Some(span) = component_span(reference.span, component) {
+            spans.push(span);
+        }
+    }
+    spans.sort_by_key(|span| (span.source_file.filename(), span.data.as_ptr() as usize));
+    spans.dedup_by_key(|span| span.data.as_ptr() as usize);
+    Some(spans)
+}
+
+/// Whether `name` is a valid FIDL identifier: letters, digits and
+/// underscores, starting with a letter and not ending with an underscore.
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && !name.ends_with('_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The span of a component of a dotted name, counting from the end: `RED` is
+/// component 0 of `Color.RED`, and `Color` is component 1.
+fn component_span(span: SourceSpan<'_>, component: usize) -> Option<SourceSpan<'_>> {
+    let data = span.data.rsplit('.').nth(component)?.trim();
+    Some(SourceSpan::new(data, span.source_file))
+}
+
+/// Reports each sibling whose name collides with `new_name`. Returns whether
+/// there were any.
+fn check_collisions<'src>(
+    compiler: &Compiler<'_, 'src>,
+    target: SourceSpan<'src>,
+    kind: &str,
+    new_name: &str,
+    mut siblings: Vec<(String, &Identifier<'src>)>,
+) -> bool {
+    let canon = canonicalize(new_name);
+    siblings.sort_by_key(|(_, id)| {
+        let span = id.element.span();
+        (span.source_file.filename(), span.data.as_ptr() as usize)
+    });
+    let mut collides = false;
+    for (prev_kind, id) in siblings {
+        let prev_raw = id.data();
+        if canonicalize(prev_raw) != canon {
+            continue;
+        }
+        collides = true;
+        let prev_span = id.element.span();
+        let prev_site = prev_span.position_str();
+        let labels = vec![Label::new(
+            prev_span,
+            format!("previous {} '{}' declared here", prev_kind, prev_raw),
+        )];
+        if prev_raw == new_name {
+            compiler.reporter.fail_with(
+                Error::ErrNameCollision(
+                    flyweights::FlyStr::new(kind),
+                    flyweights::FlyStr::new(new_name),
+                    flyweights::FlyStr::new(prev_kind),
+                    flyweights::FlyStr::new(prev_site),
+                ),
+                target,
+                labels,
+                vec![],
+            );
+        } else {
+            compiler.reporter.fail_with(
+                Error::ErrNameCollisionCanonical(
+                    flyweights::FlyStr::new(kind),
+                    flyweights::FlyStr::new(new_name),
+                    flyweights::FlyStr::new(prev_kind),
+                    flyweights::FlyStr::new(prev_raw),
+                    flyweights::FlyStr::new(prev_site),
+                    flyweights::FlyStr::new(canon.as_str()),
+                ),
+                target,
+                labels,
+                vec![format!(
+                    "'{}' and '{}' both have the canonical form '{}'",
+                    new_name, prev_raw, canon
+                )],
+            );
+        }
+    }
+    collides
+}
+
+/// Reports a collision between a renamed declaration of the main library and
+/// a library it imports. Returns whether there was one.
+fn check_library_imports<'src>(
+    compiler: &Compiler<'_, 'src>,
+    target: SourceSpan<'src>,
+    new_name: &str,
+) -> bool {
+    let canon = canonicalize(new_name);
+    let Some((import_name, import)) = compiler
+        .library_imports
+        .iter()
+        .find(|(import_name, _)| canonicalize(&import_name.to_string()) == canon)
+    else {
+        return false;
+    };
+    let labels = vec![Label::new(
+        import.element.span(),
+        format!("library import '{}' declared here", import_name),
+    )];
+    let error = if import_name.to_string() == new_name {
+        Error::ErrDeclNameConflictsWithLibraryImport(flyweights::FlyStr::new(new_name))
+    } else {
+        Error::ErrDeclNameConflictsWithLibraryImportCanonical(
+            flyweights::FlyStr::new(new_name),
+            flyweights::FlyStr::new(canon.as_str()),
+        )
+    };
+    compiler.reporter.fail_with(error, target, labels, vec![]);
+    true
+}
@@ -1,12 +1,1127 @@
-use crate::compiler::Compiler;
+//! Validates the `removed` and `replaced` arguments of `@available`.
+//!
+//! An element marked `replaced=N` must have a replacement: an element of the
+//! same name, in the same scope, marked `added=N`. An element marked
+//! `removed=N` must not have one. When the element is also `renamed`, it lives
+//! on under the new name from version N, so the check looks for that name
+//! instead. Only endings written on the element itself are checked; an
+//! availability inherited from a parent is never validated here. Each
+//! element's availability is the one [`AvailabilityStep`] computed for every
+//! version, so the step runs once the library is compiled.
+//!
+//! Members also have an ABI: the value of a bits or enum member, the ordinal
+//! of a table or union member, the selector of a method and the offset of a
+//! struct member. A replacement must keep the ABI of the member it replaces,
+//! and a removed member's ABI must not be reused by a member added in its
+//! place. Struct member offsets come from the shapes of the compiled types.
+//! The methods a protocol gets through `compose`, from this library or a
+//! dependency, are part of its scope, and end where the `compose` does.
+//!
+//! [`AvailabilityStep`]: crate::availability_step::AvailabilityStep
+
+use crate::compiler::{Compiler, to_camel_case};
+use crate::diagnostics::Error;
+use crate::flat_ast::{self, DeclarationKind, PrimitiveSubtype, Type};
+use crate::names::OwnedLibraryName;
+use crate::raw_ast::{self, AttributeList, Layout, LayoutParameter, RawDecl, TypeConstructor};
+use crate::source_span::SourceSpan;
 use crate::step::Step;
+use crate::versioning_types::{Availability, Ending, Platform, Version};
+use flyweights::FlyStr;
+use std::collections::HashSet;
+
+pub struct ReplacementStep<'node, 'src> {
+    pub main_files: &'node [raw_ast::File<'src>],
+}
+
+/// The new name an element's `@available` attribute gives it, if any.
+fn renamed(attributes: Option<&AttributeList<'_>>) -> Option<String> {
+    attributes?
+        .attributes
+        .iter()
+        .filter(|a| a.name.data() == "available")
+        .flat_map(|a| &a.args)
+        .find(|arg| arg.name.as_ref().is_some_and(|n| n.data() == "renamed"))
+        .and_then(|arg| match &arg.value {
+            raw_ast::Constant::Literal(lit) => {
+                Some(lit.literal.value.trim_matches('"').to_string())
+            }
+            _ => None,
+        })
+}
+
+/// The versions `[added, removed)` in which an element exists.
+#[derive(Clone, Copy)]
+struct Lifetime {
+    added: Version,
+    removed: Version,
+}
+
+impl Lifetime {
+    const ALWAYS: Lifetime = Lifetime {
+        added: Version::NEG_INF,
+        removed: Version::POS_INF,
+    };
+
+    fn new(availability: &Availability) -> Self {
+        Lifetime {
+            added: availability.added().unwrap_or(Version::NEG_INF),
+            removed: availability.removed().unwrap_or(Version::POS_INF),
+        }
+    }
+
+    fn intersect(self, other: Lifetime) -> Lifetime {
+        Lifetime {
+            added: self.added.max(other.added),
+            removed: self.removed.min(other.removed),
+        }
+    }
+}
+
+/// A declaration or member, as far as replacement is concerned.
+struct Element<'src> {
+    kind: &'static str,
+    name: String,
+    span: SourceSpan<'src>,
+    lifetime: Lifetime,
+    /// The `removed` or `replaced` version written on the element, and
+    /// whether it was `replaced`.
+    ending: Option<(Version, bool)>,
+    renamed: Option<String>,
+    /// The value, ordinal or selector of a member whose ABI doesn't depend
+    /// on the other members.
+    abi: Option<String>,
+    /// For a method a protocol gets through `compose`, the `compose` in that
+    /// protocol, and whether it's what ends the method.
+    composed: Option<(SourceSpan<'src>, bool)>,
+}
+
+impl<'src> Element<'src> {
+    /// An element with `availability`, which is inherited from its parent
+    /// unless `attributes` say otherwise. An availability that conflicts with
+    /// the parent's is reported by the AvailabilityStep, which then keeps the
+    /// parent's.
+    fn new(
+        kind: &'static str,
+        name: impl Into<String>,
+        span: SourceSpan<'src>,
+        availability: &Availability,
+        attributes: Option<&AttributeList<'src>>,
+    ) -> Self {
+        let ending = match availability.ending() {
+            Ending::Removed => availability.removed().map(|version| (version, false)),
+            Ending::Replaced => availability.removed().map(|version| (version, true)),
+            _ => None,
+        };
+        Self {
+            kind,
+            name: name.into(),
+            span,
+            lifetime: Lifetime::new(availability),
+            ending,
+            renamed: renamed(attributes),
+            abi: None,
+            composed: None,
+        }
+    }
+
+    fn with_abi(mut self, abi: String) -> Self {
+        self.abi = Some(abi);
+        self
+    }
+
+    /// An element whose availability is entirely inherited, such as an
+    /// anonymous layout. It can be a replacement, but is never checked.
+    fn inherited(
+        kind: &'static str,
+        name: impl Into<String>,
+        span: SourceSpan<'src>,
+        lifetime: Lifetime,
+    ) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+            span,
+            lifetime,
+            ending: None,
+            renamed: None,
+            abi: None,
+            composed: None,
+        }
+    }
+
+    fn describe(&self) -> FlyStr {
+        FlyStr::new(format!("{} '{}'", self.kind, self.name))
+    }
+
+    /// Where a problem with the element's ending is reported: on the
+    /// `compose` that ends it, if that's what does.
+    fn reported_at(&self) -> SourceSpan<'src> {
+        match self.composed {
+            Some((compose, true)) => compose,
+            _ => self.span,
+        }
+    }
+
+    /// Whether `other` can be this element's replacement. Methods that come
+    /// through the same `compose` replace each other in the protocol that
+    /// declares them, and are checked there.
+    fn can_be_replaced_by(&self, other: &Element<'src>) -> bool {
+        match (self.composed, other.composed) {
+            (Some((a, _)), Some((b, _))) => a.data.as_ptr() != b.data.as_ptr(),
+            _ => true,
+        }
+    }
+}
+
+/// What identifies the members of a scope on the wire, besides their names.
+enum Abi {
+    /// Nothing, as for declarations.
+    None,
+    /// The `value`, `ordinal` or `selector` in each element's `abi`.
+    Fixed(&'static str),
+    /// The offset of each struct member, given the inline size and alignment
+    /// of each one's type, if they're all known.
+    Offset(Option<Vec<(u32, u32)>>),
+}
+
+/// Elements that can replace each other.
+struct Scope<'src> {
+    elements: Vec<Element<'src>>,
+    abi: Abi,
+}
+
+impl<'src> Scope<'src> {
+    fn new(elements: Vec<Element<'src>>) -> Self {
+        Self {
+            elements,
+            abi: Abi::None,
+        }
+    }
+
+    /// The kind and value of the ABI of the element at `index`, as it is just
+    /// before `version` if `before`, or at `version` otherwise.
+    fn abi(&self, index: usize, version: Version, before: bool) -> Option<(&'static str, String)> {
+        match &self.abi {
+            Abi::None => None,
+            Abi::Fixed(kind) => Some((kind, self.elements[index].abi.clone()?)),
+            Abi::Offset(shapes) => {
+                let mut offset: u32 = 0;
+                for (i, (element, (size, alignment))) in
+                    self.elements.iter().zip(shapes.as_ref()?).enumerate()
+                {
+                    let lifetime = element.lifetime;
+                    let exists = if before {
+                        lifetime.added < version && version <= lifetime.removed
+                    } else {
+                        lifetime.added <= version && version < lifetime.removed
+                    };
+                    if !exists {
+                        continue;
+                    }
+                    offset = offset.next_multiple_of(*alignment);
+                    if i == index {
+                        return Some(("offset", offset.to_string()));
+                    }
+                    offset = offset.saturating_add(*size);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Checks each element of `scope` that is marked removed or replaced against
+/// the other elements of the same scope.
+fn check_scope<'src>(compiler: &Compiler<'_, 'src>, scope: &Scope<'src>) {
+    for (index, element) in scope.elements.iter().enumerate() {
+        let Some((version, replaced)) = element.ending else {
+            continue;
+        };
+        // A composed method that ends by itself is checked against the
+        // protocol's own elements only for reuse.
+        if replaced && element.composed.is_some_and(|(_, by_compose)| !by_compose) {
+            continue;
+        }
+        let v = FlyStr::new(version.to_string());
+        let added_at = |name: &str| {
+            scope.elements.iter().position(|other| {
+                other.name == name
+                    && other.lifetime.added == version
+                    && element.can_be_replaced_by(other)
+            })
+        };
+        let span = element.reported_at();
+        match (&element.renamed, replaced) {
+            (None, true) => match added_at(&element.name) {
+                None => compiler.reporter.fail(
+                    Error::ErrInvalidReplaced(
+                        element.describe(),
+                        v.clone(),
+                        v.clone(),
+                        v.clone(),
+                        v,
+                    ),
+                    span,
+                ),
+                Some(replacement) => check_replaced_abi(compiler, scope, index, replacement),
+            },
+            (None, false) => {
+                if let Some(replacement) = added_at(&element.name) {
+                    compiler.reporter.fail(
+                        Error::ErrInvalidRemoved(
+                            element.describe(),
+                            v.clone(),
+                            v.clone(),
+                            FlyStr::new(scope.elements[replacement].span.position_str()),
+                            v.clone(),
+                            v,
+                        ),
+                        span,
+                    );
+                } else {
+                    check_removed_abi(compiler, scope, index);
+                }
+            }
+            (Some(new_name), true) => match added_at(new_name) {
+                None => compiler.reporter.fail(
+                    Error::ErrInvalidReplacedAndRenamed(
+                        element.describe(),
+                        v.clone(),
+                        FlyStr::new(new_name),
+                        v,
+                    ),
+                    span,
+                ),
+                Some(replacement) => check_replaced_abi(compiler, scope, index, replacement),
+            },
+            (Some(new_name), false) => {
+                // From `version` on, the element is known by its new name.
+                if let Some(other) = scope
+                    .elements
+                    .iter()
+                    .find(|other| other.name == *new_name && other.lifetime.removed > version)
+                {
+                    compiler.reporter.fail(
+                        Error::ErrInvalidRemovedAndRenamed(
+                            element.describe(),
+                            v,
+                            FlyStr::new(new_name),
+                            FlyStr::new(other.span.position_str()),
+                        ),
+                        span,
+                    );
+                } else {
+                    check_removed_abi(compiler, scope, index);
+                }
+            }
+        }
+    }
+}
+
+/// Checks that no element added where the removed element at `index` ends
+/// reuses its ABI.
+fn check_removed_abi<'src>(compiler: &Compiler<'_, 'src>, scope: &Scope<'src>, index: usize) {
+    let element = &scope.elements[index];
+    let Some((version, _)) = element.ending else {
+        return;
+    };
+    let Some((kind, abi)) = scope.abi(index, version, true) else {
+        return;
+    };
+    let reused = scope.elements.iter().enumerate().find(|(i, other)| {
+        *i != index
+            && other.lifetime.added == version
+            && element.can_be_replaced_by(other)
+            && scope
+                .abi(*i, version, false)
+                .is_some_and(|(_, other_abi)| other_abi == abi)
+    });
+    if let Some((_, other)) = reused {
+        let v = FlyStr::new(version.to_string());
+        compiler.reporter.fail(
+            Error::ErrInvalidRemovedAbi(
+                element.describe(),
+                v.clone(),
+                kind.into(),
+                FlyStr::new(abi),
+                FlyStr::new(other.span.position_str()),
+                v,
+                FlyStr::new(other.name.as_str()),
+                kind.into(),
+            ),
+            element.reported_at(),
+        );
+    }
+}
+
+/// Checks that the replacement at `replacement` keeps the ABI of the
+/// replaced element at `index`.
+fn check_replaced_abi<'src>(
+    compiler: &Compiler<'_, 'src>,
+    scope: &Scope<'src>,
+    index: usize,
+    replacement: usize,
+) {
+    let element = &scope.elements[index];
+    let Some((version, _)) = element.ending else {
+        return;
+    };
+    let (Some((kind, abi)), Some((_, new_abi))) = (
+        scope.abi(index, version, true),
+        scope.abi(replacement, version, false),
+    ) else {
+        return;
+    };
+    if abi != new_abi {
+        let v = FlyStr::new(version.to_string());
+        compiler.reporter.fail(
+            Error::ErrInvalidReplacedAbi(
+                element.describe(),
+                v.clone(),
+                kind.into(),
+                FlyStr::new(abi),
+                kind.into(),
+                FlyStr::new(new_abi),
+                FlyStr::new(scope.elements[replacement].span.position_str()),
+                v,
+                kind.into(),
+            ),
+            element.reported_at(),
+        );
+    }
+}
+
+/// Collects the library's declarations, including the anonymous layouts
+/// declared inline in members, and checks the members of each one.
+struct Collector<'a, 'node, 'src> {
+    compiler: &'a Compiler<'node, 'src>,
+    library_name: &'a str,
+    decls: Vec<Element<'src>>,
+}
+
+impl<'a, 'node, 'src> Collector<'a, 'node, 'src> {
+    fn add_decl(
+        &mut self,
+        kind: &'static str,
+        decl: RawDecl<'node, 'src>,
+        name: &raw_ast::Identifier<'src>,
+        attributes: Option<&AttributeList<'src>>,
+    ) -> Lifetime {
+        let availability = self
+            .compiler
+            .raw_decl_availability
+            .get(&(decl.element().span().data.as_ptr() as usize))
+            .cloned()
+            .unwrap_or_else(Availability::unbounded);
+        let decl = Element::new(
+            kind,
+            name.data(),
+            name.element.span(),
+            &availability,
+            attributes,
+        );
+        let lifetime = decl.lifetime;
+        self.decls.push(decl);
+        lifetime
+    }
+
+    /// Checks the members of `layout`, which is named `name` if it's a
+    /// struct.
+    fn layout(&mut self, layout: &'node Layout<'src>, name: &str) {
+        let mut members = Vec::new();
+        let abi = match layout {
+            Layout::Struct(l) => {
+                let compiled = self
+                    .compiler
+                    .declarations
+                    .structs()
+                    .find(|s| s.name == format!("{}/{}", self.library_name, name));
+                let mut shapes = Some(Vec::new());
+                for m in &l.members {
+                    let member =
+                        self.member("struct member", m.element.span(), &m.name, &m.attributes);
+                    self.anonymous_layouts(&m.type_ctor, &m.name, member.lifetime);
+                    // A member of the selected version has been compiled;
+                    // the others' types are resolved here.
+                    let compiled_member = compiled
+                        .filter(|_| {
+                            self.compiler
+                                .is_member_active(m.element.span().data.as_ptr() as usize)
+                        })
+                        .and_then(|s| s.members.iter().find(|c| c.name == m.name.data()));
+                    let shape = match compiled_member {
+                        Some(c) => {
+                            Some((c.type_.type_shape.inline_size, c.type_.type_shape.alignment))
+                        }
+                        None => self
+                            .member_type(&m.type_ctor, 0)
+                            .map(|t| (t.type_shape.inline_size, t.type_shape.alignment)),
+                    };
+                    members.push(member);
+                    shapes = shapes.and_then(|mut shapes| {
+                        shapes.push(shape?);
+                        Some(shapes)
+                    });
+                }
+                Abi::Offset(shapes)
+            }
+            Layout::Table(l) => {
+                for m in &l.members {
+                    if let Some(name) = &m.name {
+                        let member =
+                            self.member("table member", m.element.span(), name, &m.attributes);
+                        if let Some(type_ctor) = &m.type_ctor {
+                            self.anonymous_layouts(type_ctor, name, member.lifetime);
+                        }
+                        members.push(with_ordinal(member, m.ordinal.as_ref()));
+                    }
+                }
+                Abi::Fixed("ordinal")
+            }
+            Layout::Union(l) => {
+                let kind = if l.is_overlay {
+                    "overlay member"
+                } else {
+                    "union member"
+                };
+                for m in &l.members {
+                    if let Some(name) = &m.name {
+                        let member = self.member(kind, m.element.span(), name, &m.attributes);
+                        if let Some(type_ctor) = &m.type_ctor {
+                            self.anonymous_layouts(type_ctor, name, member.lifetime);
+                        }
+                        members.push(with_ordinal(member, m.ordinal.as_ref()));
+                    }
+                }
+                Abi::Fixed("ordinal")
+            }
+            Layout::Enum(l) => {
+                for m in &l.members {
+                    let member =
+                        self.member("enum member", m.element.span(), &m.name, &m.attributes);
+                    members.push(member.with_abi(constant_value(&m.value)));
+                }
+                Abi::Fixed("value")
+            }
+            Layout::Bits(l) => {
+                for m in &l.members {
+                    let member =
+                        self.member("bits member", m.element.span(), &m.name, &m.attributes);
+                    members.push(member.with_abi(constant_value(&m.value)));
+                }
+                Abi::Fixed("value")
+            }
+            Layout::TypeConstructor(_) => Abi::None,
+        };
+        check_scope(
+            self.compiler,
+            &Scope {
+                elements: members,
+                abi,
+            },
+        );
+    }
+
+    /// A member, which starts at `element`, with the availability computed
+    /// for it.
+    fn member(
+        &self,
+        kind: &'static str,
+        element: SourceSpan<'src>,
+        name: &raw_ast::Identifier<'src>,
+        attributes: &Option<Box<AttributeList<'src>>>,
+    ) -> Element<'src> {
+        let availability = self
+            .compiler
+            .member_availability
+            .get(&(element.data.as_ptr() as usize))
+            .cloned()
+            .unwrap_or_else(Availability::unbounded);
+        Element::new(
+            kind,
+            name.data(),
+            name.element.span(),
+            &availability,
+            attributes.as_deref(),
+        )
+    }
+
+    /// Records the anonymous layouts in a member's type, which are named
+    /// after the member, and checks their members in turn.
+    fn anonymous_layouts(
+        &mut self,
+        type_ctor: &'node TypeConstructor<'src>,
+        member_name: &raw_ast::Identifier<'src>,
+        lifetime: Lifetime,
+    ) {
+        match &type_ctor.layout {
+            LayoutParameter::Inline(layout) => {
+                if let Layout::TypeConstructor(_) = layout.as_ref() {
+                    return;
+                }
+                let kind = layout_kind(layout);
+                let name =
+                    generated_name(layout).unwrap_or_else(|| to_camel_case(member_name.data()));
+                self.decls.push(Element::inherited(
+                    kind,
+                    name.clone(),
+                    type_ctor.element.span(),
+                    lifetime,
+                ));
+                self.layout(layout, &name);
+            }
+            LayoutParameter::Type(inner) => self.anonymous_layouts(inner, member_name, lifetime),
+            _ => {}
+        }
+        for parameter in &type_ctor.parameters {
+            self.anonymous_layouts(parameter, member_name, lifetime);
+        }
+    }
+
+    /// The type of a struct member that isn't in the selected version, as
+    /// far as its shape goes: builtin types are built as the compiler builds
+    /// them, and declarations have the shapes they were compiled with.
+    /// Returns `None` for a type whose shape isn't known, like a declaration
+    /// that isn't in the selected version.
+    fn member_type(&self, type_ctor: &TypeConstructor<'src>, depth: usize) -> Option<Type> {
+        let LayoutParameter::Identifier(id) = &type_ctor.layout else {
+            return None;
+        };
+        if depth > 64 {
+            return None;
+        }
+        let nullable = type_ctor.nullable;
+        let name = id.to_string();
+        if let Ok(subtype) = name.parse::<PrimitiveSubtype>() {
+            return Some(Type::primitive(subtype));
+        }
+        let ty = match name.as_str() {
+            "string" => Type::string(None, nullable, None),
+            "vector" | "bytes" => Type::vector(Box::new(Type::unknown()), None, nullable, None),
+            "box" => Type::experimental_pointer(None, false),
+            "client_end" | "server_end" => Type::endpoint(None, None, nullable, None),
+            "array" => {
+                let element = self.member_type(type_ctor.parameters.first()?, depth + 1)?;
+                let count = match &type_ctor.parameters.get(1)?.layout {
+                    LayoutParameter::Literal(lit) => lit.literal.value.parse().ok()?,
+                    _ => return None,
+                };
+                Type::array(Box::new(element), count, None)
+            }
+            _ => {
+                let qualified = match name.rsplit_once('.') {
+                    Some((library, decl)) => {
+                        let library = self
+                            .compiler
+                            .library_imports
+                            .get(library)
+                            .map_or(library.to_string(), |import| import.using_path.to_string());
+                        format!("{}/{}", library, decl)
+                    }
+                    None => format!("{}/{}", self.library_name, name),
+                };
+                if let Some(RawDecl::Alias(alias)) = self.compiler.raw_decls.get::<str>(&qualified)
+                {
+                    return self.member_type(&alias.type_ctor, depth + 1);
+                }
+                match self.compiler.decl_kinds.get::<str>(&qualified)? {
+                    DeclarationKind::ExperimentalResource => {
+                        Type::handle(None, None, None, nullable, None)
+                    }
+                    DeclarationKind::Struct if nullable => Type::experimental_pointer(None, true),
+                    _ => {
+                        let shape = self.compiler.shapes.get::<str>(&qualified)?.clone();
+                        Type::identifier_type(Some(qualified), nullable, shape, false)
+                    }
+                }
+            }
+        };
+        Some(ty)
+    }
+}
+
+fn with_ordinal<'src>(
+    member: Element<'src>,
+    ordinal: Option<&raw_ast::Literal<'_>>,
+) -> Element<'src> {
+    match ordinal {
+        Some(ordinal) => member.with_abi(number(&ordinal.value).unwrap_or(ordinal.value.clone())),
+        None => member,
+    }
+}
+
+/// The value of a bits or enum member, as written, with numbers normalized.
+fn constant_value(constant: &raw_ast::Constant<'_>) -> String {
+    let text = constant.element().span().data;
+    number(text).unwrap_or_else(|| text.to_string())
+}
+
+/// Parses a decimal, hexadecimal or binary integer literal, possibly
+/// negative.
+fn number(text: &str) -> Option<String> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b").or(digits.strip_prefix("0B")) {
+        u64::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse().ok()?
+    };
+    Some(if negative {
+        format!("-{}", value)
+    } else {
+        value.to_string()
+    })
+}
+
+/// The selector of a method: its `@selector`, qualified with the library and
+/// protocol unless it already is, or its name.
+fn selector(library_name: &str, protocol: &str, method: &raw_ast::ProtocolMethod<'_>) -> String {
+    let name = method
+        .attributes
+        .as_ref()
+        .and_then(|list| list.attributes.iter().find(|a| a.name.data() == "selector"))
+        .and_then(|attr| match &attr.args.first()?.value {
+            raw_ast::Constant::Literal(lit) => {
+                Some(lit.literal.value.trim_matches('"').to_string())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| method.name.data().to_string());
+    if name.contains('/') {
+        name
+    } else {
+        format!("{}/{}.{}", library_name, protocol, name)
+    }
+}
+
+fn layout_kind(layout: &Layout<'_>) -> &'static str {
+    match layout {
+        Layout::Struct(_) => "struct",
+        Layout::Table(_) => "table",
+        Layout::Union(l) if l.is_overlay => "overlay",
+        Layout::Union(_) => "union",
+        Layout::Enum(_) => "enum",
+        Layout::Bits(_) => "bits",
+        Layout::TypeConstructor(_) => "new type",
+    }
+}
+
+/// Returns the name given to an anonymous layout with `@generated_name`.
+fn generated_name(layout: &Layout<'_>) -> Option<String> {
+    let attributes = match layout {
+        Layout::Struct(l) => &l.attributes,
+        Layout::Table(l) => &l.attributes,
+        Layout::Union(l) => &l.attributes,
+        Layout::Enum(l) => &l.attributes,
+        Layout::Bits(l) => &l.attributes,
+        Layout::TypeConstructor(_) => return None,
+    };
+    let attr = attributes
+        .as_ref()?
+        .attributes
+        .iter()
+        .find(|a| a.name.data() == "generated_name")?;
+    match &attr.args.first()?.value {
+        raw_ast::Constant::Literal(lit) => Some(lit.literal.value.trim_matches('"').to_string()),
+        _ => None,
+    }
+}
+
+/// The `compose` a method comes through, in the protocol whose scope it's
+/// added to, and the earliest ending written on a `compose` on the way.
+#[derive(Clone, Copy)]
+struct Through<'src> {
+    compose: SourceSpan<'src>,
+    lifetime: Lifetime,
+    ending: Option<(Version, bool)>,
+}
+
+/// A protocol that is composed: one of this library's, over the versions in
+/// which it exists, or one of a dependency's, which is the same in every
+/// version of this library.
+enum Composed<'node, 'src> {
+    Source {
+        library: String,
+        decl: &'node raw_ast::ProtocolDeclaration<'src>,
+        lifetime: Lifetime,
+    },
+    Ir(flat_ast::ProtocolDeclaration),
+}
+
+/// Finds the protocols composed in protocols of this library and its
+/// dependencies, like the compiler does.
+struct ComposeResolver<'a, 'node, 'src> {
+    compiler: &'a Compiler<'node, 'src>,
+    library_name: &'a str,
+    /// The library's protocols, in every version.
+    protocols: &'a [(&'node raw_ast::ProtocolDeclaration<'src>, Lifetime)],
+}
+
+impl<'node, 'src> ComposeResolver<'_, 'node, 'src> {
+    /// The protocols `compose` in a protocol of `library` can refer to: in
+    /// this library, every one of that name.
+    fn resolve(
+        &self,
+        library: &str,
+        compose: &raw_ast::ProtocolCompose<'src>,
+    ) -> Vec<Composed<'node, 'src>> {
+        let name = compose.protocol_name.to_string();
+        let qualified = match name.rsplit_once('.') {
+            Some((prefix, decl)) => {
+                let prefix = match self.compiler.library_imports.get(prefix) {
+                    Some(import) if library == self.library_name => import.using_path.to_string(),
+                    _ => prefix.to_string(),
+                };
+                format!("{}/{}", prefix, decl)
+            }
+            None => format!("{}/{}", library, name),
+        };
+        let (target_library, decl_name) = qualified.split_once('/').unwrap();
+        if target_library == self.library_name {
+            return self
+                .protocols
+                .iter()
+                .filter(|(decl, _)| decl.name.data() == decl_name)
+                .map(|&(decl, lifetime)| Composed::Source {
+                    library: target_library.to_string(),
+                    decl,
+                    lifetime,
+                })
+                .collect();
+        }
+        if let Some(RawDecl::Protocol(decl)) = self.compiler.raw_decls.get::<str>(&qualified) {
+            return vec![Composed::Source {
+                library: target_library.to_string(),
+                decl,
+                lifetime: Lifetime::ALWAYS,
+            }];
+        }
+        self.compiler
+            .precompiled_protocol(&qualified)
+            .map(Composed::Ir)
+            .into_iter()
+            .collect()
+    }
+
+    /// Whether a method of a dependency's protocol is in the version of the
+    /// dependency that's selected.
+    fn dependency_method_active(
+        &self,
+        library: &str,
+        method: &raw_ast::ProtocolMethod<'_>,
+    ) -> bool {
+        let platform =
+            Platform::parse(OwnedLibraryName::new(library.to_string()).versioning_platform())
+                .unwrap_or_else(Platform::unversioned);
+        self.compiler
+            .member_availability
+            .get(&(method.element.span().data.as_ptr() as usize))
+            .is_none_or(|avail| {
+                avail
+                    .set()
+                    .contains(self.compiler.version_selection.lookup(&platform))
+            })
+    }
+}
+
+/// Adds the methods that `protocol`, of `library`, gains through `compose`
+/// to `scope`. They can replace the protocol's own methods and be replaced by
+/// them. They end with the `compose` they come through, or else where the
+/// protocol that declares them ends them.
+fn composed_methods<'node, 'src>(
+    compiler: &Compiler<'node, 'src>,
+    resolver: &ComposeResolver<'_, 'node, 'src>,
+    library: &str,
+    protocol: &'node raw_ast::ProtocolDeclaration<'src>,
+    through: Option<Through<'src>>,
+    visited: &mut HashSet<String>,
+    scope: &mut Vec<Element<'src>>,
+) {
+    let key = format!("{}/{:p}", library, protocol);
+    if !visited.insert(key.clone()) {
+        return;
+    }
+    let is_main = library == resolver.library_name;
+    for compose in &protocol.composed_protocols {
+        // A dependency's protocol composes the same protocols in every
+        // version of this library.
+        let compose_availability = match compiler
+            .member_availability
+            .get(&(compose.element.span().data.as_ptr() as usize))
+        {
+            Some(availability) if is_main => availability.clone(),
+            _ => Availability::unbounded(),
+        };
+        let compose_element = Element::new(
+            "protocol composition",
+            "",
+            compose.element.span(),
+            &compose_availability,
+            compose.attributes.as_deref(),
+        );
+        for composed in resolver.resolve(library, compose) {
+            let composed_lifetime = match &composed {
+                Composed::Source { lifetime, .. } => *lifetime,
+                Composed::Ir(_) => Lifetime::ALWAYS,
+            };
+            let path_lifetime = compose_element.lifetime.intersect(composed_lifetime);
+            if path_lifetime.added >= path_lifetime.removed {
+                continue;
+            }
+            let path = match through {
+                None => Through {
+                    compose: compose.element.span(),
+                    lifetime: path_lifetime,
+                    ending: compose_element.ending,
+                },
+                Some(through) => Through {
+                    compose: through.compose,
+                    lifetime: through.lifetime.intersect(path_lifetime),
+                    ending: match (through.ending, compose_element.ending) {
+                        (Some(a), Some(b)) => Some(if a.0 <= b.0 { a } else { b }),
+                        (a, b) => a.or(b),
+                    },
+                },
+            };
+            if path.lifetime.added >= path.lifetime.removed {
+                continue;
+            }
+            match composed {
+                Composed::Source {
+                    library: composed_library,
+                    decl,
+                    ..
+                } => {
+                    let composed_is_main = composed_library == resolver.library_name;
+                    for method in &decl.methods {
+                        let availability = if composed_is_main {
+                            compiler
+                                .member_availability
+                                .get(&(method.element.span().data.as_ptr() as usize))
+                                .cloned()
+                                .unwrap_or_else(Availability::unbounded)
+                        } else if resolver.dependency_method_active(&composed_library, method) {
+                            Availability::unbounded()
+                        } else {
+                            continue;
+                        };
+                        let element = Element::new(
+                            "protocol method",
+                            method.name.data(),
+                            method.name.element.span(),
+                            &availability,
+                            method.attributes.as_deref(),
+                        )
+                        .with_abi(selector(
+                            &composed_library,
+                            decl.name.data(),
+                            method,
+                        ));
+                        scope.push(through_compose(element, path));
+                    }
+                    composed_methods(
+                        compiler,
+                        resolver,
+                        &composed_library,
+                        decl,
+                        Some(path),
+                        visited,
+                        scope,
+                    );
+                }
+                Composed::Ir(decl) => ir_methods(compiler, &decl, path, visited, scope),
+            }
+        }
+    }
+    visited.remove(&key);
+}
+
+/// Adds the methods of `protocol`, from a dependency's IR, and those it
+/// composes, to `scope`. They are all in every version of this library, and
+/// have no source of their own: they're located at the `compose`.
+fn ir_methods<'src>(
+    compiler: &Compiler<'_, 'src>,
+    protocol: &flat_ast::ProtocolDeclaration,
+    path: Through<'src>,
+    visited: &mut HashSet<String>,
+    scope: &mut Vec<Element<'src>>,
+) {
+    let name = protocol.name.to_string();
+    if !visited.insert(name.clone()) {
+        return;
+    }
+    let (library, protocol_name) = name.split_once('/').unwrap_or(("", &name));
+    for method in protocol.methods.iter().filter(|m| !m.is_composed) {
+        let method_name = method.name.to_string();
+        let selector = method
+            .maybe_attributes
+            .iter()
+            .find(|a| a.name == "selector")
+            .and_then(|a| a.arguments.first()?.value.literal.as_ref())
+            .map(|lit| lit.value.trim_matches('"').to_string())
+            .unwrap_or_else(|| method_name.clone());
+        let selector = if selector.contains('/') {
+            selector
+        } else {
+            format!("{}/{}.{}", library, protocol_name, selector)
+        };
+        let element = Element::inherited(
+            "protocol method",
+            method_name,
+            path.compose,
+            Lifetime::ALWAYS,
+        )
+        .with_abi(selector);
+        scope.push(through_compose(element, path));
+    }
+    for compose in &protocol.composed_protocols {
+        if let Some(composed) = compiler.precompiled_protocol(compose.name.as_ref()) {
+            ir_methods(compiler, &composed, path, visited, scope);
+        }
+    }
+    visited.remove(&name);
+}
+
+/// `element`, a method of a composed protocol, as it is in the protocol that
+/// composes it through `path`.
+fn through_compose<'src>(mut element: Element<'src>, path: Through<'src>) -> Element<'src> {
+    element.lifetime = path.lifetime.intersect(element.lifetime);
+    element.renamed = None;
+    let by_compose = match (path.ending, element.ending) {
+        (Some((version, _)), Some((own, _))) => version <= own,
+        (ending, _) => ending.is_some(),
+    };
+    if by_compose {
+        element.ending = path.ending;
+    } else if element
+        .ending
+        .is_some_and(|(version, _)| version >= path.lifetime.removed)
+    {
+        element.ending = None;
+    }
+    element.composed = Some((path.compose, by_compose));
+    element
+}
+
+impl<'node, 'src> Step<'node, 'src> for ReplacementStep<'node, 'src> {
+    fn run(&mut self, compiler: &mut Compiler<'node, 'src>) {
+        // Unversioned libraries can't remove or replace anything.
+        if compiler.platform.is_none() {
+            return;
+        }
+        let Some(library_decl) = &compiler.library_decl else {
+            return;
+        };
+        let library_name = library_decl.path.to_string();
+
+        let mut collector = Collector {
+            compiler,
+            library_name: &library_name,
+            decls: Vec::new(),
+        };
+        let mut protocols = Vec::new();
+        for file in self.main_files {
+            for d in &file.const_decls {
+                collector.add_decl("const", RawDecl::Const(d), &d.name, d.attributes.as_deref());
+            }
+            for d in &file.alias_decls {
+                collector.add_decl("alias", RawDecl::Alias(d), &d.name, d.attributes.as_deref());
+            }
+            for d in &file.type_decls {
+                collector.add_decl(
+                    layout_kind(&d.layout),
+                    RawDecl::Type(d),
+                    &d.name,
+                    d.attributes.as_deref(),
+                );
+                collector.layout(&d.layout, d.name.data());
+            }
+            for d in &file.protocol_decls {
+                let lifetime = collector.add_decl(
+                    "protocol",
+                    RawDecl::Protocol(d),
+                    &d.name,
+                    d.attributes.as_deref(),
+                );
+                protocols.push((d, lifetime));
+            }
+            for d in &file.service_decls {
+                collector.add_decl(
+                    "service",
+                    RawDecl::Service(d),
+                    &d.name,
+                    d.attributes.as_deref(),
+                );
+                let members: Vec<_> = d
+                    .members
+                    .iter()
+                    .map(|m| {
+                        collector.member("service member", m.element.span(), &m.name, &m.attributes)
+                    })
+                    .collect();
+                check_scope(compiler, &Scope::new(members));
+            }
+            for d in &file.resource_decls {
+                collector.add_decl(
+                    "resource definition",
+                    RawDecl::Resource(d),
+                    &d.name,
+                    d.attributes.as_deref(),
+                );
+                let properties: Vec<_> = d
+                    .properties
+                    .iter()
+                    .map(|p| {
+                        collector.member(
+                            "resource property",
+                            p.element.span(),
+                            &p.name,
+                            &p.attributes,
+                        )
+                    })
+                    .collect();
+                check_scope(compiler, &Scope::new(properties));
+            }
+        }
 
-pub struct ReplacementStep;
+        let resolver = ComposeResolver {
+            compiler,
+            library_name: &library_name,
+            protocols: &protocols,
+        };
+        for (protocol, _) in &protocols {
+            let mut methods: Vec<_> = protocol
+                .methods
+                .iter()
+                .map(|m| {
+                    collector
+                        .member("protocol method", m.element.span(), &m.name, &m.attributes)
+                        .with_abi(selector(&library_name, protocol.name.data(), m))
+                })
+                .collect();
+            composed_methods(
+                compiler,
+                &resolver,
+                &library_name,
+                protocol,
+                None,
+                &mut HashSet::new(),
+                &mut methods,
+            );
+            check_scope(
+                compiler,
+                &Scope {
+                    elements: methods,
+                    abi: Abi::Fixed("selector"),
+                },
+            );
+        }
 
-impl<'node, 'src> Step<'node, 'src> for ReplacementStep {
-    fn run(&mut self, _compiler: &mut Compiler<'node, 'src>) {
-        // Just dummy logic for now to bootstrap it.
-        // It will examine `@available(removed=X, replaced=Y)`
-        // and check correctness based on Availability from AvailabilityStep.
+        check_scope(compiler, &Scope::new(collector.decls));
     }
 }
@@ -1,13 +1,133 @@
 use crate::diagnostics::ErrorKind;
-use crate::diagnostics::{Diagnostic, Error};
+use crate::diagnostics::{Diagnostic, Error, Label};
 use crate::source_span::SourceSpan;
+use serde::Serialize;
 use std::cell::RefCell;
+use std::io::IsTerminal;
+
+const COLOR_RESET: &str = "\x1b[0m";
+const COLOR_BOLD: &str = "\x1b[1m";
+const COLOR_RED: &str = "\x1b[1;31m";
+const COLOR_MAGENTA: &str = "\x1b[1;95m";
+const COLOR_GREEN: &str = "\x1b[1;32m";
 
 pub struct Reporter<'a> {
     diagnostics: RefCell<Vec<Diagnostic<'a>>>,
     pub warnings_as_errors: bool,
 }
 
+/// A single diagnostic in the format produced by `fidlc --format=json`.
+#[derive(Serialize, Debug)]
+pub struct JsonDiagnostic {
+    pub category: String,
+    pub error_id: String,
+    pub message: String,
+    pub path: String,
+    pub start_line: usize,
+    pub start_char: usize,
+    pub end_line: usize,
+    pub end_char: usize,
+    /// The byte range of the span in its file. Not set for spans in the
+    /// generated file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_offset: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<JsonLabel>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+/// A secondary span of a [`JsonDiagnostic`].
+#[derive(Serialize, Debug)]
+pub struct JsonLabel {
+    pub message: String,
+    pub path: String,
+    pub start_line: usize,
+    pub start_char: usize,
+    pub end_line: usize,
+    pub end_char: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_offset: Option<usize>,
+}
+
+/// Returns `(start_line, start_char, end_line, end_char)` for a span, with
+/// 1-based lines and 0-based characters, matching fidlc.
+pub(crate) fn json_range(span: &SourceSpan<'_>) -> (usize, usize, usize, usize) {
+    let pos = span.position();
+    let start_char = pos.column - 1;
+    match span.data.rfind('\n') {
+        Some(last_newline) => (
+            pos.line,
+            start_char,
+            pos.line + span.data.matches('\n').count(),
+            span.data.len() - last_newline - 1,
+        ),
+        None => (pos.line, start_char, pos.line, start_char + span.data.len()),
+    }
+}
+
+/// Returns the `(start_offset, end_offset)` byte range of a span in its
+/// file, if it has one.
+fn json_offsets(span: &SourceSpan<'_>) -> (Option<usize>, Option<usize>) {
+    let start = span.offset();
+    (start, start.map(|start| start + span.data.len()))
+}
+
+impl JsonLabel {
+    pub fn new(label: &Label<'_>) -> Self {
+        let (start_line, start_char, end_line, end_char) = json_range(&label.span);
+        let (start_offset, end_offset) = json_offsets(&label.span);
+        Self {
+            message: label.message.clone(),
+            path: label.span.source_file.filename().to_string(),
+            start_line,
+            start_char,
+            end_line,
+            end_char,
+            start_offset,
+            end_offset,
+        }
+    }
+}
+
+impl JsonDiagnostic {
+    pub fn new(diag: &Diagnostic<'_>) -> Self {
+        let kind = match diag.def.kind() {
+            ErrorKind::Warning => "warning",
+            _ => "error",
+        };
+        let mut json = Self {
+            category: format!("fidlc/{}", kind),
+            error_id: diag.def.format_id().to_string(),
+            message: diag.message.clone(),
+            path: String::new(),
+            start_line: 0,
+            start_char: 0,
+            end_line: 0,
+            end_char: 0,
+            start_offset: None,
+            end_offset: None,
+            labels: diag.labels.iter().map(JsonLabel::new).collect(),
+            notes: diag.notes.clone(),
+        };
+        if let Some(span) = &diag.span {
+            json.path = span.source_file.filename().to_string();
+            (
+                json.start_line,
+                json.start_char,
+                json.end_line,
+                json.end_char,
+            ) = json_range(span);
+            (json.start_offset, json.end_offset) = json_offsets(span);
+        }
+        json
+    }
+}
+
 impl<'a> Default for Reporter<'a> {
     fn default() -> Self {
         Self::new()
@@ -23,15 +143,35 @@ impl<'a> Reporter<'a> {
     }
 
     pub fn fail(&self, def: Error, span: SourceSpan<'a>) {
+        self.fail_with(def, span, Vec::new(), Vec::new());
+    }
+
+    /// Like [`Reporter::fail`], but attaches secondary labelled spans and
+    /// notes to the diagnostic.
+    pub fn fail_with(
+        &self,
+        def: Error,
+        span: SourceSpan<'a>,
+        labels: Vec<Label<'a>>,
+        notes: Vec<String>,
+    ) {
         let msg = def.msg();
 
         self.diagnostics.borrow_mut().push(Diagnostic {
             def,
             message: msg.to_string(),
             span: Some(span),
+            labels,
+            notes,
         });
     }
 
+    /// Adds a diagnostic that was made elsewhere, e.g. by
+    /// [`crate::api::Diagnostic::to_reported`].
+    pub fn report(&self, diagnostic: Diagnostic<'a>) {
+        self.diagnostics.borrow_mut().push(diagnostic);
+    }
+
     // Helper to accept varargs?
     // In Rust, macros.
 
@@ -39,17 +179,132 @@ impl<'a> Reporter<'a> {
         self.diagnostics.borrow()
     }
 
+    /// Prints all diagnostics to stderr, using ANSI colour if stderr is a
+    /// terminal and `NO_COLOR` is not set.
     pub fn print_reports(&self) {
+        let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        eprint!("{}", self.reports_text(color));
+    }
+
+    pub fn reports_text(&self, color: bool) -> String {
+        let mut out = String::new();
         for diag in self.diagnostics.borrow().iter() {
-            let prefix = match diag.def.kind() {
-                ErrorKind::Warning => "warning",
-                _ => "error",
-            };
-            if let Some(span) = &diag.span {
-                println!("{}: {}: {}", span.position_str(), prefix, diag.message);
-            } else {
-                println!("{}: {}", prefix, diag.message);
-            }
+            out.push_str(&format_diagnostic(diag, color));
+            out.push('\n');
         }
+        out
+    }
+
+    pub fn reports_json(&self) -> String {
+        let diagnostics: Vec<JsonDiagnostic> = self
+            .diagnostics
+            .borrow()
+            .iter()
+            .map(JsonDiagnostic::new)
+            .collect();
+        serde_json::to_string_pretty(&diagnostics).unwrap()
+    }
+
+    /// Prints all diagnostics to stderr as a JSON array, like
+    /// [`Self::print_reports`], or nothing if there are none.
+    pub fn print_reports_json(&self) {
+        if !self.diagnostics.borrow().is_empty() {
+            eprintln!("{}", self.reports_json());
+        }
+    }
+}
+
+/// Formats a diagnostic as `file:line:col: error[fi-NNNN]: message`, followed
+/// by the offending source line and a `^~~~` squiggle under the span. Each
+/// label is rendered the same way as `file:line:col: note: message`, and notes
+/// follow as `  = note: ...` lines.
+pub fn format_diagnostic(diag: &Diagnostic<'_>, color: bool) -> String {
+    let (qualifier, qualifier_color) = match diag.def.kind() {
+        ErrorKind::Warning => ("warning", COLOR_MAGENTA),
+        _ => ("error", COLOR_RED),
+    };
+    let mut out = String::new();
+    if let Some(span) = &diag.span {
+        if color {
+            out.push_str(COLOR_BOLD);
+        }
+        out.push_str(&span.position_str());
+        out.push_str(": ");
+    }
+    if color {
+        out.push_str(qualifier_color);
+    }
+    out.push_str(&format!("{}[{}]", qualifier, diag.def.format_id()));
+    if color {
+        out.push_str(COLOR_RESET);
+        out.push_str(COLOR_BOLD);
+    }
+    out.push_str(": ");
+    out.push_str(&diag.message);
+    if color {
+        out.push_str(COLOR_RESET);
+    }
+    if let Some(span) = &diag.span {
+        push_snippet(&mut out, span, color);
+    }
+    for label in &diag.labels {
+        out.push('\n');
+        if color {
+            out.push_str(COLOR_BOLD);
+        }
+        out.push_str(&label.span.position_str());
+        out.push_str(": ");
+        if color {
+            out.push_str(COLOR_GREEN);
+        }
+        out.push_str("note");
+        if color {
+            out.push_str(COLOR_RESET);
+        }
+        out.push_str(": ");
+        out.push_str(&label.message);
+        push_snippet(&mut out, &label.span, color);
+    }
+    for note in &diag.notes {
+        out.push_str("\n  = note: ");
+        out.push_str(note);
+    }
+    out
+}
+
+/// Appends the source line containing `span` and a squiggle under it.
+fn push_snippet(out: &mut String, span: &SourceSpan<'_>, color: bool) {
+    if let Some((line, _)) = span.source_file.line_containing(span.data) {
+        out.push('\n');
+        out.push_str(line);
+        out.push('\n');
+        if color {
+            out.push_str(COLOR_GREEN);
+        }
+        out.push_str(&squiggle(line, span));
+        if color {
+            out.push_str(COLOR_RESET);
+        }
+    }
+}
+
+/// Builds the `^~~~` underline for `span` within `line`. Tabs before the span
+/// are preserved so the caret lines up regardless of tab width. Spans covering
+/// several lines are underlined up to the end of the first line.
+fn squiggle(line: &str, span: &SourceSpan<'_>) -> String {
+    let line_start = line.as_ptr() as usize;
+    let offset = (span.data.as_ptr() as usize)
+        .saturating_sub(line_start)
+        .min(line.len());
+    let mut out: String = line[..offset]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let underlined = span.data.split('\n').next().unwrap_or("");
+    let underlined = &underlined[..underlined.len().min(line.len() - offset)];
+    out.push('^');
+    for _ in 1..underlined.chars().count() {
+        out.push('~');
     }
+    out
 }
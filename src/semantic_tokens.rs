@@ -0,0 +1,266 @@
+//! Classes of tokens for semantic highlighting.
+//!
+//! [`semantic_tokens`] lexes a source file and classes each token by its
+//! [`TokenKind`] and [`TokenSubkind`]. Since FIDL keywords are only keywords
+//! where the grammar expects them, identifiers are then reclassed by how the
+//! parser used them, if the file parsed: a `struct` used as a member name is
+//! a member name, and a `vector` in type position is a type reference.
+
+use std::collections::HashMap;
+
+use crate::api::Location;
+use crate::lexer::Lexer;
+use crate::raw_ast::{self, CompoundIdentifier, Identifier, LayoutParameter};
+use crate::reporter::Reporter;
+use crate::source_file::SourceFile;
+use crate::token::{TokenKind, TokenSubkind};
+use crate::tree_visitor::{self, TreeVisitor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemanticTokenKind {
+    /// A keyword like `library`, `type`, `struct`, `compose` or `true`.
+    Keyword,
+    /// A modifier like `strict`, `flexible`, `resource` or `open`.
+    Modifier,
+    /// A library name, or the library part of a qualified name.
+    Library,
+    /// The name of a declaration where it is declared.
+    DeclarationName,
+    /// The name of a member, method or resource property where it is
+    /// declared.
+    MemberName,
+    /// A name in type position, including builtins like `uint32`.
+    TypeReference,
+    /// A name in constant position, like `MAX` or `Color.RED`.
+    ConstantReference,
+    /// The `@` and name of an attribute, and the names of its arguments.
+    Attribute,
+    DocComment,
+    NumericLiteral,
+    StringLiteral,
+    Punctuation,
+    /// An identifier the parser didn't use, in a file that didn't parse.
+    Identifier,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub location: Location,
+    pub kind: SemanticTokenKind,
+}
+
+/// Classes every token of `source`, in order. `file` is `source` parsed, if
+/// it parsed; without it identifiers are only classed by their subkind.
+pub fn semantic_tokens(
+    source: &SourceFile,
+    file: Option<&raw_ast::File<'_>>,
+) -> Vec<SemanticToken> {
+    let mut classifier = Classifier {
+        classes: HashMap::new(),
+    };
+    if let Some(file) = file {
+        classifier.visit_file(file);
+    }
+
+    let reporter = Reporter::new();
+    let mut lexer = Lexer::new(source, &reporter);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.lex();
+        let kind = match token.kind {
+            TokenKind::EndOfFile => break,
+            TokenKind::StartOfFile | TokenKind::Comment => continue,
+            TokenKind::DocComment => SemanticTokenKind::DocComment,
+            TokenKind::NumericLiteral => SemanticTokenKind::NumericLiteral,
+            TokenKind::StringLiteral => SemanticTokenKind::StringLiteral,
+            TokenKind::At => SemanticTokenKind::Attribute,
+            TokenKind::Identifier => match classifier.classes.get(&key(token.span.data)) {
+                Some(&kind) => kind,
+                None => match token.subkind {
+                    TokenSubkind::None => SemanticTokenKind::Identifier,
+                    TokenSubkind::Strict
+                    | TokenSubkind::Flexible
+                    | TokenSubkind::Resource
+                    | TokenSubkind::Open
+                    | TokenSubkind::Ajar
+                    | TokenSubkind::Closed => SemanticTokenKind::Modifier,
+                    _ => SemanticTokenKind::Keyword,
+                },
+            },
+            _ => SemanticTokenKind::Punctuation,
+        };
+        tokens.push(SemanticToken {
+            location: Location::new(&token.span),
+            kind,
+        });
+    }
+    tokens
+}
+
+/// Identifies a token by where its text is in the source, which is the same
+/// for every lexer over it.
+fn key(data: &str) -> usize {
+    data.as_ptr() as usize
+}
+
+struct Classifier {
+    classes: HashMap<usize, SemanticTokenKind>,
+}
+
+impl Classifier {
+    fn mark(&mut self, id: &Identifier<'_>, kind: SemanticTokenKind) {
+        self.classes.insert(key(id.element.span().data), kind);
+    }
+
+    /// Marks a name like `dep.Thing`: the library part, then the rest.
+    fn mark_qualified(&mut self, id: &CompoundIdentifier<'_>, kind: SemanticTokenKind) {
+        if let Some((last, prefix)) = id.components.split_last() {
+            for component in prefix {
+                self.mark(component, SemanticTokenKind::Library);
+            }
+            self.mark(last, kind);
+        }
+    }
+
+    fn mark_all(&mut self, id: &CompoundIdentifier<'_>, kind: SemanticTokenKind) {
+        for component in &id.components {
+            self.mark(component, kind);
+        }
+    }
+}
+
+impl<'a> TreeVisitor<'a> for Classifier {
+    fn visit_librarydeclaration(&mut self, node: &raw_ast::LibraryDeclaration<'a>) {
+        self.mark_all(&node.path, SemanticTokenKind::Library);
+        tree_visitor::walk_librarydeclaration(self, node);
+    }
+
+    fn visit_usingdeclaration(&mut self, node: &raw_ast::UsingDeclaration<'a>) {
+        self.mark_all(&node.using_path, SemanticTokenKind::Library);
+        if let Some(alias) = &node.maybe_alias {
+            self.mark(alias, SemanticTokenKind::Library);
+        }
+        tree_visitor::walk_usingdeclaration(self, node);
+    }
+
+    fn visit_attribute(&mut self, node: &raw_ast::Attribute<'a>) {
+        // Doc comments and modifier availabilities are attributes without
+        // an attribute name of their own in the source.
+        if node.provenance == raw_ast::AttributeProvenance::Default {
+            self.mark(&node.name, SemanticTokenKind::Attribute);
+        }
+        tree_visitor::walk_attribute(self, node);
+    }
+
+    fn visit_attributearg(&mut self, node: &raw_ast::AttributeArg<'a>) {
+        if let Some(name) = &node.name {
+            self.mark(name, SemanticTokenKind::Attribute);
+        }
+        tree_visitor::walk_attributearg(self, node);
+    }
+
+    fn visit_modifier(&mut self, node: &raw_ast::Modifier<'a>) {
+        self.classes
+            .insert(key(node.element.span().data), SemanticTokenKind::Modifier);
+        tree_visitor::walk_modifier(self, node);
+    }
+
+    fn visit_identifierconstant(&mut self, node: &raw_ast::IdentifierConstant<'a>) {
+        self.mark_all(&node.identifier, SemanticTokenKind::ConstantReference);
+    }
+
+    fn visit_typeconstructor(&mut self, node: &raw_ast::TypeConstructor<'a>) {
+        if let LayoutParameter::Identifier(id) = &node.layout {
+            self.mark_qualified(id, SemanticTokenKind::TypeReference);
+        }
+        tree_visitor::walk_typeconstructor(self, node);
+    }
+
+    fn visit_layoutparameter(&mut self, node: &LayoutParameter<'a>) {
+        match node {
+            LayoutParameter::Identifier(id) => {
+                self.mark_qualified(id, SemanticTokenKind::TypeReference)
+            }
+            _ => tree_visitor::walk_layoutparameter(self, node),
+        }
+    }
+
+    fn visit_protocolcompose(&mut self, node: &raw_ast::ProtocolCompose<'a>) {
+        self.mark_qualified(&node.protocol_name, SemanticTokenKind::TypeReference);
+        tree_visitor::walk_protocolcompose(self, node);
+    }
+
+    fn visit_constdeclaration(&mut self, node: &raw_ast::ConstDeclaration<'a>) {
+        self.mark(&node.name, SemanticTokenKind::DeclarationName);
+        tree_visitor::walk_constdeclaration(self, node);
+    }
+
+    fn visit_aliasdeclaration(&mut self, node: &raw_ast::AliasDeclaration<'a>) {
+        self.mark(&node.name, SemanticTokenKind::DeclarationName);
+        tree_visitor::walk_aliasdeclaration(self, node);
+    }
+
+    fn visit_typedeclaration(&mut self, node: &raw_ast::TypeDeclaration<'a>) {
+        self.mark(&node.name, SemanticTokenKind::DeclarationName);
+        tree_visitor::walk_typedeclaration(self, node);
+    }
+
+    fn visit_protocoldeclaration(&mut self, node: &raw_ast::ProtocolDeclaration<'a>) {
+        self.mark(&node.name, SemanticTokenKind::DeclarationName);
+        tree_visitor::walk_protocoldeclaration(self, node);
+    }
+
+    fn visit_servicedeclaration(&mut self, node: &raw_ast::ServiceDeclaration<'a>) {
+        self.mark(&node.name, SemanticTokenKind::DeclarationName);
+        tree_visitor::walk_servicedeclaration(self, node);
+    }
+
+    fn visit_resourcedeclaration(&mut self, node: &raw_ast::ResourceDeclaration<'a>) {
+        self.mark(&node.name, SemanticTokenKind::DeclarationName);
+        tree_visitor::walk_resourcedeclaration(self, node);
+    }
+
+    fn visit_structmember(&mut self, node: &raw_ast::StructMember<'a>) {
+        self.mark(&node.name, SemanticTokenKind::MemberName);
+        tree_visitor::walk_structmember(self, node);
+    }
+
+    fn visit_enummember(&mut self, node: &raw_ast::EnumMember<'a>) {
+        self.mark(&node.name, SemanticTokenKind::MemberName);
+        tree_visitor::walk_enummember(self, node);
+    }
+
+    fn visit_bitsmember(&mut self, node: &raw_ast::BitsMember<'a>) {
+        self.mark(&node.name, SemanticTokenKind::MemberName);
+        tree_visitor::walk_bitsmember(self, node);
+    }
+
+    fn visit_unionmember(&mut self, node: &raw_ast::UnionMember<'a>) {
+        if let Some(name) = &node.name {
+            self.mark(name, SemanticTokenKind::MemberName);
+        }
+        tree_visitor::walk_unionmember(self, node);
+    }
+
+    fn visit_tablemember(&mut self, node: &raw_ast::TableMember<'a>) {
+        if let Some(name) = &node.name {
+            self.mark(name, SemanticTokenKind::MemberName);
+        }
+        tree_visitor::walk_tablemember(self, node);
+    }
+
+    fn visit_protocolmethod(&mut self, node: &raw_ast::ProtocolMethod<'a>) {
+        self.mark(&node.name, SemanticTokenKind::MemberName);
+        tree_visitor::walk_protocolmethod(self, node);
+    }
+
+    fn visit_servicemember(&mut self, node: &raw_ast::ServiceMember<'a>) {
+        self.mark(&node.name, SemanticTokenKind::MemberName);
+        tree_visitor::walk_servicemember(self, node);
+    }
+
+    fn visit_resourceproperty(&mut self, node: &raw_ast::ResourceProperty<'a>) {
+        self.mark(&node.name, SemanticTokenKind::MemberName);
+        tree_visitor::walk_resourceproperty(self, node);
+    }
+}
@@ -17,44 +17,51 @@ pub struct Position {
     pub column: usize,
 }
 
+/// What the columns of a [`Position`] count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnUnit {
+    /// UTF-8 bytes, as in diagnostics and the JSON IR.
+    Byte,
+    /// Unicode scalar values.
+    Char,
+    /// UTF-16 code units, as in the Language Server Protocol.
+    Utf16,
+}
+
+impl ColumnUnit {
+    fn width(self, c: char) -> usize {
+        match self {
+            Self::Byte => c.len_utf8(),
+            Self::Char => 1,
+            Self::Utf16 => c.len_utf16(),
+        }
+    }
+
+    fn count(self, text: &str) -> usize {
+        match self {
+            Self::Byte => text.len(),
+            Self::Char => text.chars().count(),
+            Self::Utf16 => text.encode_utf16().count(),
+        }
+    }
+}
+
 impl SourceFile {
     pub fn new(filename: String, data: String) -> Self {
+        // Like fidlc, a NUL byte ends a line as well as a newline does. A
+        // final line without a newline is only recorded if it isn't empty.
         let mut lines = Vec::new();
         let mut start = 0;
-        for (i, c) in data.char_indices() {
-            if c == '\n' || c == '\0' {
+        for (i, b) in data.bytes().enumerate() {
+            if b == b'\n' || b == 0 {
                 lines.push(start..i);
                 start = i + 1;
             }
         }
-        // Include the last line if the file does not end in a newline
-        // Note: The C++ implementation includes {start, size}
-        // If data ends with \n, start will be len.
-        // If data is "abc", start is 0, loop doesn't hit \n.
-        // We need to handle the loop correctly.
-        // Re-implementing logic:
-
-        lines.clear();
-        start = 0;
-        let mut len = 0;
-        for (i, b) in data.as_bytes().iter().enumerate() {
-            if *b == b'\n' || *b == 0 {
-                lines.push(start..(start + len));
-                start = i + 1;
-                len = 0;
-            } else {
-                len += 1;
-            }
+        if start < data.len() {
+            lines.push(start..data.len());
         }
 
-        if len > 0 {
-            lines.push(start..(start + len));
-        }
-
-        // Use simpler logic: split by \n, but keep track of indices.
-        // Actually the C++ logic handles \0 as newline too?
-        // "if (*it == '\n' || *it == '\0')"
-
         Self {
             filename,
             data,
@@ -101,6 +108,14 @@ impl SourceFile {
         SourceSpan::new(data, self)
     }
 
+    /// The span starting at a 1-based `line` and byte `column`, as recorded
+    /// in a [`crate::flat_ast::Location`].
+    pub fn span_at(&self, line: usize, column: usize, length: usize) -> Option<SourceSpan<'_>> {
+        let start = self.lines.get(line.checked_sub(1)?)?.start + column.checked_sub(1)?;
+        let data = self.data.get(start..start + length)?;
+        Some(SourceSpan::new(data, self))
+    }
+
     pub fn line_containing(&self, view: &str) -> Option<(&str, Position)> {
         if self.is_virtual {
             return self.line_containing_virtual(view);
@@ -113,83 +128,85 @@ impl SourceFile {
         let view_end = view_start + view.len();
 
         if view_end > self_end || view_start < self_start {
-            // Handle EOF case (view is empty and at the end)
-            if view.is_empty() && view_start == self_end {
-                if self.data.is_empty() {
-                    return Some(("", Position { line: 1, column: 1 }));
-                }
-                let last_line = self.lines.last()?;
-                let line_str = &self.data[last_line.clone()];
-                // Position is line number (1-based), column (1-based)
-                return Some((
-                    line_str,
-                    Position {
-                        line: self.lines.len(),
-                        column: last_line.len() + 1,
-                    },
-                ));
-            }
             return None;
         }
 
-        // Find the line containing the view
-        // Is upper_bound of lines.
-        // lines are sorted by start index.
-        // We want the last line that starts <= view_start.
-
-        // Relative offset
         let offset = view_start - self_start;
-
-        // Binary search
-        let _line_idx = match self.lines.binary_search_by(|range| {
-            if range.start > offset {
-                std::cmp::Ordering::Greater
-            } else if range.end < offset && range.end <= offset {
-                // Wait, line range is start..end (exclusive of newline)
-                // If offset is within range, it's Equal.
-                // If offset is at newline, it might be tricky.
-                // C++ uses std::upper_bound looking for first line starting *after* the token?
-                // No, "looking for the first line ... to start at or before".
-                // Abstractly: find max i such that lines[i].start <= offset.
-                std::cmp::Ordering::Less
-            } else {
-                std::cmp::Ordering::Equal
-            }
-        }) {
-            Ok(idx) => idx,
-            Err(idx) => idx - 1, // idx is where it would be inserted (first greater), so idx-1 is last smaller or equal
+        let Some(line_idx) = self.line_index(offset) else {
+            // Only an empty file has no lines.
+            return Some(("", Position { line: 1, column: 1 }));
         };
-
-        // Need to be careful with Err(0)
-
-        // Let's use simple search for now or fix binary search logic.
-        // We want index i such that lines[i].start <= offset < lines[i+1].start
-
-        let idx = self.lines.partition_point(|range| range.start <= offset);
-        // partition_point returns the first index where predicate is false.
-        // So lines[idx] has start > offset.
-        // So lines[idx-1] has start <= offset.
-
-        if idx == 0 {
-            // Should not happen if data contains the view and lines are populated correctly.
-            // Unless partial view implies something else.
-            return None;
-        }
-        let line_idx = idx - 1;
         let line_range = &self.lines[line_idx];
-
-        let line_str = &self.data[line_range.clone()];
-        let column = offset - line_range.start + 1;
-
         Some((
-            line_str,
+            &self.data[line_range.clone()],
             Position {
                 line: line_idx + 1,
-                column,
+                column: offset - line_range.start + 1,
             },
         ))
     }
 
+    /// The 0-based index of the recorded line that `offset` is on, or just
+    /// past the end of. `None` if there are no lines.
+    fn line_index(&self, offset: usize) -> Option<usize> {
+        // The last line starting at or before the offset.
+        self.lines
+            .partition_point(|range| range.start <= offset)
+            .checked_sub(1)
+    }
+
+    /// The byte range of a 0-based line, not including its newline. After a
+    /// final newline there is one more, empty line.
+    fn line_range(&self, index: usize) -> Option<Range<usize>> {
+        if let Some(range) = self.lines.get(index) {
+            return Some(range.clone());
+        }
+        let start = self.lines.last().map_or(0, |range| range.end + 1);
+        (index == self.lines.len() && start <= self.data.len()).then_some(start..self.data.len())
+    }
+
+    /// The 1-based line and 1-based column of a byte `offset`, with the
+    /// column counted in `unit`s. The end of the file has a position, but an
+    /// offset inside a multibyte character has none.
+    pub fn position_at(&self, offset: usize, unit: ColumnUnit) -> Option<Position> {
+        if self.is_virtual || !self.data.is_char_boundary(offset) {
+            return None;
+        }
+        let index = match self.line_index(offset) {
+            // A line's range leaves out its newline, so an offset after
+            // the last recorded line is on the empty line after it.
+            Some(index) if offset <= self.lines[index].end => index,
+            Some(index) => index + 1,
+            None => 0,
+        };
+        let range = self.line_range(index)?;
+        let before = &self.data[range.start..offset];
+        Some(Position {
+            line: index + 1,
+            column: unit.count(before) + 1,
+        })
+    }
+
+    /// The byte offset of a 1-based `line` and 1-based `column` counted in
+    /// `unit`s. As in the Language Server Protocol, a column past the end of
+    /// the line means the end of the line, and a column inside a character
+    /// means the start of that character.
+    pub fn offset_at(&self, line: usize, column: usize, unit: ColumnUnit) -> Option<usize> {
+        if self.is_virtual {
+            return None;
+        }
+        let range = self.line_range(line.checked_sub(1)?)?;
+        let mut remaining = column.checked_sub(1)?;
+        for (i, c) in self.data[range.clone()].char_indices() {
+            let width = unit.width(c);
+            if remaining < width {
+                return Some(range.start + i);
+            }
+            remaining -= width;
+        }
+        Some(range.end)
+    }
+
     fn line_containing_virtual(&self, view: &str) -> Option<(&str, Position)> {
         let view_ptr = view.as_ptr() as usize;
         let virtual_lines = self.virtual_lines.borrow();
@@ -18,6 +18,15 @@ impl<'a> SourceSpan<'a> {
             .1
     }
 
+    /// The byte offset of the start of the span in its file, or `None` in a
+    /// virtual file, whose lines aren't stored together.
+    pub fn offset(&self) -> Option<usize> {
+        if self.source_file.is_virtual() {
+            return None;
+        }
+        Some(self.data.as_ptr() as usize - self.source_file.data().as_ptr() as usize)
+    }
+
     pub fn position_str(&self) -> String {
         let pos = self.position();
         format!(
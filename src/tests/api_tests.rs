@@ -0,0 +1,214 @@
+use crate::api::{CompileOptions, Sources, compile, decompose};
+use crate::diagnostics::{Error, ErrorKind};
+use crate::versioning_types::{Platform, Version, VersionRange, VersionSelection};
+
+#[test]
+fn good_compiles_in_memory_sources() {
+    let mut sources = Sources::new();
+    sources.add_dependency_file(
+        "dep.fidl",
+        "library dep;\ntype Point = struct { x int32; };\n",
+    );
+    sources.add_file(
+        "example.fidl",
+        "library example;\nusing dep;\ntype S = struct { p dep.Point; };\n",
+    );
+    let output = compile(&sources, &CompileOptions::default()).unwrap();
+
+    assert!(output.diagnostics.is_empty());
+    let json = output.json().expect("compilation succeeded");
+    assert_eq!(json.name, "example");
+    assert_eq!(json.struct_declarations.len(), 1);
+}
+
+#[test]
+fn bad_returns_diagnostics_with_locations() {
+    let mut sources = Sources::new();
+    sources.add_file(
+        "example.fidl",
+        "library example;\n\ntype S = struct {};\ntype S = struct {};\n",
+    );
+    let output = compile(&sources, &CompileOptions::default()).unwrap();
+
+    assert!(!output.succeeded());
+    assert_eq!(output.diagnostics.len(), 1);
+    let diag = &output.diagnostics[0];
+    assert!(matches!(diag.error, Error::ErrNameCollision(..)));
+    assert_eq!(diag.id(), "fi-0034");
+    assert_eq!(diag.kind(), ErrorKind::Error);
+    let location = diag.location.as_ref().unwrap();
+    assert_eq!(location.filename, "example.fidl");
+    assert_eq!((location.start_line, location.start_char), (4, 0));
+    assert_eq!((location.end_line, location.end_char), (4, 19));
+    assert_eq!(diag.labels.len(), 1);
+    assert_eq!(diag.labels[0].1.start_line, 3);
+}
+
+#[test]
+fn bad_parse_error() {
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", "library example;\ntype S = struct {\n");
+    let output = compile(&sources, &CompileOptions::default()).unwrap();
+
+    assert!(!output.succeeded());
+    assert!(!output.diagnostics.is_empty());
+    assert_eq!(
+        output.diagnostics[0].location.as_ref().unwrap().filename,
+        "example.fidl"
+    );
+}
+
+#[test]
+fn bad_warnings_as_errors() {
+    let mut sources = Sources::new();
+    sources.add_file(
+        "example.fidl",
+        "library example;\n@availabe\ntype S = struct {};\n",
+    );
+
+    let output = compile(&sources, &CompileOptions::default()).unwrap();
+    assert!(output.succeeded());
+    assert_eq!(output.diagnostics.len(), 1);
+    assert_eq!(output.diagnostics[0].kind(), ErrorKind::Warning);
+
+    let options = CompileOptions {
+        werror: true,
+        ..Default::default()
+    };
+    let output = compile(&sources, &options).unwrap();
+    assert!(!output.succeeded());
+    assert_eq!(output.diagnostics.len(), 1);
+}
+
+#[test]
+fn bad_reports_errors_from_every_file_after_parse_failure() {
+    let mut sources = Sources::new();
+    sources.add_file(
+        "a.fidl",
+        "library example;\ntype S = struct {\n    a int32\n};\ntype T = struct { x Missing; };\n",
+    );
+    sources.add_file(
+        "b.fidl",
+        "library example;\nconst C uint32 = ;\ntype U = struct { y AlsoMissing; };\n",
+    );
+    let output = compile(&sources, &CompileOptions::default()).unwrap();
+
+    assert!(!output.succeeded());
+    let errors: Vec<(String, &str)> = output
+        .diagnostics
+        .iter()
+        .map(|d| (d.id(), d.location.as_ref().unwrap().filename.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            ("fi-0008".to_string(), "a.fidl"),
+            ("fi-0007".to_string(), "b.fidl"),
+            ("fi-0052".to_string(), "a.fidl"),
+            ("fi-0052".to_string(), "b.fidl"),
+        ]
+    );
+}
+
+#[test]
+fn good_decompose_splits_by_member_and_reference() {
+    let mut sources = Sources::new();
+    sources.add_file(
+        "example.fidl",
+        r#"@available(added=1)
+library example;
+
+type Inner = struct {
+    @available(added=3)
+    x uint32;
+};
+
+@available(added=2)
+type Outer = struct {
+    inner Inner;
+};
+
+const C uint32 = 1;
+"#,
+    );
+    let output = decompose(&sources, &CompileOptions::default()).unwrap();
+    assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+    let decomposition = output.decomposition.unwrap();
+
+    let v = |n| Version::from_number(n).unwrap();
+    let ranges: Vec<(&str, VersionRange)> = decomposition
+        .declarations()
+        .iter()
+        .map(|d| (d.name.as_str(), d.range))
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            ("example/C", VersionRange::new(v(1), Version::POS_INF)),
+            ("example/Inner", VersionRange::new(v(1), v(3))),
+            ("example/Inner", VersionRange::new(v(3), Version::POS_INF)),
+            // `Outer` only changes because `Inner` does.
+            ("example/Outer", VersionRange::new(v(2), v(3))),
+            ("example/Outer", VersionRange::new(v(3), Version::POS_INF)),
+        ]
+    );
+
+    let mut selection = VersionSelection::new();
+    selection.insert(Platform::parse("example").unwrap(), [v(2)].into());
+    let selected: Vec<&str> = decomposition
+        .select(&selection)
+        .unwrap()
+        .iter()
+        .map(|d| d.name.as_str())
+        .collect();
+    assert_eq!(
+        selected,
+        vec!["example/C", "example/Inner", "example/Outer"]
+    );
+    assert_eq!(
+        decomposition.select(&selection).unwrap()[1].json["type_shape_v2"]["inline_size"],
+        1
+    );
+
+    // Which of several versions an element is at depends on the selection.
+    let mut selection = VersionSelection::new();
+    selection.insert(
+        Platform::parse("example").unwrap(),
+        [v(2), Version::HEAD].into(),
+    );
+    assert!(decomposition.select(&selection).is_err());
+}
+
+#[test]
+fn good_decompose_unversioned() {
+    let mut sources = Sources::new();
+    sources.add_file(
+        "example.fidl",
+        "library example;
+type S = struct {};
+",
+    );
+    let output = decompose(&sources, &CompileOptions::default()).unwrap();
+    let decomposition = output.decomposition.unwrap();
+
+    assert_eq!(decomposition.platform(), None);
+    assert_eq!(decomposition.declarations().len(), 1);
+    assert_eq!(
+        decomposition.declarations()[0].range,
+        VersionRange::new(Version::NEG_INF, Version::POS_INF)
+    );
+}
+
+#[test]
+fn bad_decompose_reports_errors_once() {
+    let mut sources = Sources::new();
+    sources.add_file(
+        "example.fidl",
+        "@available(added=1)\nlibrary example;\n@available(added=2)\ntype S = struct { x Missing; };\n",
+    );
+    let output = decompose(&sources, &CompileOptions::default()).unwrap();
+
+    assert!(output.decomposition.is_none());
+    assert_eq!(output.diagnostics.len(), 1, "{:?}", output.diagnostics);
+    assert_eq!(output.diagnostics[0].id(), "fi-0052");
+}
@@ -792,3 +792,25 @@ fn bad_inconsistent_enum_member_spelling() {
 fn bad_inconsistent_bits_member_spelling() {
     // TODO: port manually
 }
+
+#[test]
+fn bad_collision_labels_previous_declaration() {
+    let mut library = TestLibrary::new();
+    library.add_source_file(
+        "example.fidl",
+        r#"library example;
+
+type Foo = struct {};
+const FOO uint32 = 1;
+"#,
+    );
+    assert!(library.compile().is_err());
+
+    let diagnostics = library.reporter().diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    let labels = &diagnostics[0].labels;
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels[0].span.position_str(), "example.fidl:3:1");
+    assert_eq!(labels[0].message, "previous type 'Foo' declared here");
+    assert_eq!(diagnostics[0].notes.len(), 0);
+}
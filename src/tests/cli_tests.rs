@@ -68,3 +68,317 @@ fn test_zx_library_is_ignored() {
     // zx shouldn't trigger unused library error
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_json_index_requires_experimental_flag() {
+    let dir = tempdir().unwrap();
+    let main_path = dir.path().join("main.fidl");
+    fs::write(&main_path, "library main;").unwrap();
+
+    let cli = Cli {
+        json_index: Some(dir.path().join("index.json").to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    let source_managers = vec![vec![main_path.to_str().unwrap().to_string()]];
+
+    let err = run(&cli, &source_managers).unwrap_err();
+    assert!(err.contains("output_index_json"));
+}
+
+#[test]
+fn test_json_index_output() {
+    let dir = tempdir().unwrap();
+    let main_path = dir.path().join("main.fidl");
+    let dep_path = dir.path().join("dep.fidl");
+    let index_path = dir.path().join("index.json");
+    fs::write(
+        &main_path,
+        r#"library main;
+using dep as d;
+const MAX uint32 = d.LIMIT;
+type Foo = struct {
+    x d.Type;
+    inner struct { y vector<Foo>:MAX; };
+};
+protocol P {
+    Get() -> (struct { foo Foo; });
+};
+"#,
+    )
+    .unwrap();
+    fs::write(
+        &dep_path,
+        "library dep; const LIMIT uint32 = 4; type Type = struct {};",
+    )
+    .unwrap();
+
+    let cli = Cli {
+        json_index: Some(index_path.to_string_lossy().to_string()),
+        experimental: vec!["output_index_json".to_string()],
+        ..Default::default()
+    };
+    let source_managers = vec![
+        vec![dep_path.to_str().unwrap().to_string()],
+        vec![main_path.to_str().unwrap().to_string()],
+    ];
+    run(&cli, &source_managers).unwrap();
+
+    let index: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&index_path).unwrap()).unwrap();
+    assert_eq!(index["name"], "main");
+    assert_eq!(index["lib_declarations"][0]["data"], "main");
+    assert_eq!(index["using_declarations"][0]["library_name"], "dep");
+    assert_eq!(index["dependencies"][0]["library_name"], "dep");
+
+    let dep_ids = index["dependency_identifiers"].as_array().unwrap();
+    assert_eq!(dep_ids.len(), 2);
+    assert_eq!(dep_ids[0]["identifier"], "dep/LIMIT");
+    assert_eq!(dep_ids[0]["referenced_at"]["data"], "d.LIMIT");
+    assert_eq!(dep_ids[1]["identifier"], "dep/Type");
+    assert_eq!(dep_ids[1]["referenced_at"]["data"], "d.Type");
+    let source = fs::read_to_string(&main_path).unwrap();
+    let start = dep_ids[1]["referenced_at"]["start_offset"]
+        .as_u64()
+        .unwrap() as usize;
+    let end = dep_ids[1]["referenced_at"]["end_offset"].as_u64().unwrap() as usize;
+    assert_eq!(&source[start..end], "d.Type");
+
+    assert_eq!(index["consts"][0]["identifier"], "main/MAX");
+    assert_eq!(index["consts"][0]["value"]["identifier"], "dep/LIMIT");
+
+    let structs = index["structs"].as_array().unwrap();
+    let foo = structs
+        .iter()
+        .find(|s| s["identifier"] == "main/Foo")
+        .unwrap();
+    assert_eq!(foo["is_anonymous"], false);
+    assert_eq!(foo["location"]["data"], "Foo");
+    assert_eq!(foo["members"][0]["name"], "x");
+    assert_eq!(foo["members"][0]["type"]["kind"], "identifier");
+    assert_eq!(foo["members"][0]["type"]["identifier"], "dep/Type");
+    assert_eq!(foo["members"][1]["type"]["kind"], "anonymous");
+    let inner_name = foo["members"][1]["type"]["identifier"].as_str().unwrap();
+    let inner = structs
+        .iter()
+        .find(|s| s["identifier"] == inner_name)
+        .unwrap();
+    assert_eq!(inner["is_anonymous"], true);
+    assert_eq!(inner["members"][0]["type"]["identifier"], "vector");
+    assert_eq!(
+        inner["members"][0]["type"]["parameters"][0]["identifier"],
+        "main/Foo"
+    );
+
+    let method = &index["protocols"][0]["methods"][0];
+    assert_eq!(method["identifier"], "Get");
+    assert_eq!(method["response_type"]["kind"], "anonymous");
+    assert!(method.get("request_type").is_none());
+}
+
+#[test]
+fn test_json_index_only_has_selected_elements() {
+    let dir = tempdir().unwrap();
+    let main_path = dir.path().join("main.fidl");
+    let index_path = dir.path().join("index.json");
+    fs::write(
+        &main_path,
+        r#"@available(added=1)
+library main;
+type E = strict enum {
+    A = 1;
+    @available(removed=2)
+    B = 2;
+};
+@available(removed=2)
+type Old = struct {};
+closed protocol P {
+    @available(added=2)
+    strict Added();
+    strict Kept();
+};
+"#,
+    )
+    .unwrap();
+
+    let cli = Cli {
+        json_index: Some(index_path.to_string_lossy().to_string()),
+        experimental: vec!["output_index_json".to_string()],
+        available: vec!["main:2".to_string()],
+        ..Default::default()
+    };
+    run(&cli, &[vec![main_path.to_str().unwrap().to_string()]]).unwrap();
+
+    let index: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&index_path).unwrap()).unwrap();
+    let members = index["enums"][0]["members"].as_array().unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0]["name"], "A");
+    assert_eq!(index["structs"].as_array().unwrap().len(), 0);
+    let methods = index["protocols"][0]["methods"].as_array().unwrap();
+    assert_eq!(methods.len(), 2);
+    assert_eq!(methods[0]["identifier"], "Added");
+}
+
+#[test]
+fn test_dependency_ir() {
+    let dir = tempdir().unwrap();
+    let main_path = dir.path().join("main.fidl");
+    let dep_path = dir.path().join("dep.fidl");
+    let dep_json_path = dir.path().join("dep.json");
+    let main_json_path = dir.path().join("main.json");
+    fs::write(
+        &main_path,
+        "library main; using dep; type Foo = struct { t dep.Type; };",
+    )
+    .unwrap();
+    fs::write(&dep_path, "library dep; type Type = struct { x uint64; };").unwrap();
+
+    let cli = Cli {
+        json: Some(dep_json_path.to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    run(&cli, &[vec![dep_path.to_str().unwrap().to_string()]]).unwrap();
+
+    let cli = Cli {
+        json: Some(main_json_path.to_string_lossy().to_string()),
+        dependency_ir: vec![dep_json_path.to_string_lossy().to_string()],
+        ..Default::default()
+    };
+    run(&cli, &[vec![main_path.to_str().unwrap().to_string()]]).unwrap();
+
+    let ir: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&main_json_path).unwrap()).unwrap();
+    assert_eq!(ir["library_dependencies"][0]["name"], "dep");
+    assert_eq!(
+        ir["struct_declarations"][0]["type_shape_v2"]["inline_size"],
+        8
+    );
+}
+
+#[test]
+fn test_out_dir_compiles_libraries_in_dependency_order() {
+    let dir = tempdir().unwrap();
+    let a_path = dir.path().join("a.fidl");
+    let b_path = dir.path().join("b.fidl");
+    let c_path = dir.path().join("c.fidl");
+    let out_dir = dir.path().join("out");
+    fs::write(
+        &a_path,
+        "library a; type A = struct { x uint32; }; protocol PA { M(A); };",
+    )
+    .unwrap();
+    fs::write(
+        &b_path,
+        "library b; using a; type B = struct { a a.A; }; protocol PB { compose a.PA; };",
+    )
+    .unwrap();
+    fs::write(
+        &c_path,
+        "library c; using a; using b; type C = struct { a a.A; b b.B; };",
+    )
+    .unwrap();
+
+    let cli = Cli {
+        out_dir: Some(out_dir.to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    let source_managers = vec![
+        vec![c_path.to_str().unwrap().to_string()],
+        vec![b_path.to_str().unwrap().to_string()],
+        vec![a_path.to_str().unwrap().to_string()],
+    ];
+    run(&cli, &source_managers).unwrap();
+
+    let read_ir = |name: &str| -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(out_dir.join(name)).unwrap()).unwrap()
+    };
+    assert_eq!(read_ir("a.fidl.json")["name"], "a");
+    let b = read_ir("b.fidl.json");
+    assert_eq!(b["protocol_declarations"][0]["methods"][0]["name"], "M");
+    assert_eq!(
+        b["protocol_declarations"][0]["methods"][0]["is_composed"],
+        true
+    );
+    let c = read_ir("c.fidl.json");
+    assert_eq!(c["library_dependencies"][0]["name"], "a");
+    assert_eq!(c["library_dependencies"][1]["name"], "b");
+    assert_eq!(
+        c["struct_declarations"][0]["type_shape_v2"]["inline_size"],
+        8
+    );
+}
+
+#[test]
+fn test_out_dir_reports_library_cycle() {
+    let dir = tempdir().unwrap();
+    let a_path = dir.path().join("a.fidl");
+    let b_path = dir.path().join("b.fidl");
+    fs::write(&a_path, "library a; using b; alias A = b.B;").unwrap();
+    fs::write(&b_path, "library b; using a; alias B = a.A;").unwrap();
+
+    let cli = Cli {
+        out_dir: Some(dir.path().join("out").to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    let source_managers = vec![
+        vec![a_path.to_str().unwrap().to_string()],
+        vec![b_path.to_str().unwrap().to_string()],
+    ];
+    let err = run(&cli, &source_managers).unwrap_err();
+    assert_eq!(err, "There is an includes-cycle in libraries: a -> b -> a");
+}
+
+#[test]
+fn test_out_dir_rejects_json() {
+    let dir = tempdir().unwrap();
+    let a_path = dir.path().join("a.fidl");
+    fs::write(&a_path, "library a;").unwrap();
+
+    let cli = Cli {
+        out_dir: Some(dir.path().join("out").to_string_lossy().to_string()),
+        json: Some(dir.path().join("a.json").to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    let err = run(&cli, &[vec![a_path.to_str().unwrap().to_string()]]).unwrap_err();
+    assert_eq!(err, "--json cannot be combined with --out-dir");
+}
+
+#[test]
+fn test_library_path_resolves_imports() {
+    let dir = tempdir().unwrap();
+    let main_path = dir.path().join("main.fidl");
+    let json_path = dir.path().join("main.json");
+    let lib_dir = dir.path().join("lib");
+    fs::create_dir_all(lib_dir.join("dep")).unwrap();
+    fs::create_dir_all(lib_dir.join("fuchsia/leaf")).unwrap();
+    fs::write(
+        &main_path,
+        "library main; using dep; type Foo = struct { d dep.Type; };",
+    )
+    .unwrap();
+    fs::write(
+        lib_dir.join("dep/dep.fidl"),
+        "library dep; using fuchsia.leaf; type Type = struct { l fuchsia.leaf.Leaf; };",
+    )
+    .unwrap();
+    fs::write(
+        lib_dir.join("fuchsia/leaf/leaf.fidl"),
+        "library fuchsia.leaf; type Leaf = struct { x uint16; };",
+    )
+    .unwrap();
+
+    let cli = Cli {
+        json: Some(json_path.to_string_lossy().to_string()),
+        library_path: vec![lib_dir.to_string_lossy().to_string()],
+        ..Default::default()
+    };
+    run(&cli, &[vec![main_path.to_str().unwrap().to_string()]]).unwrap();
+
+    let ir: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+    assert_eq!(ir["library_dependencies"][0]["name"], "dep");
+    assert_eq!(
+        ir["struct_declarations"][0]["type_shape_v2"]["inline_size"],
+        2
+    );
+}
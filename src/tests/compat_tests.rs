@@ -0,0 +1,364 @@
+use std::collections::BTreeSet;
+use std::fs;
+
+use tempfile::tempdir;
+
+use crate::api::{self, CompatLibrary, CompileOptions, Sources};
+use crate::cli::{Command, CompatArgs, run_command};
+use crate::compat::{Compatibility, check};
+use crate::flat_ast::Root;
+use crate::json_generator::JsonRoot;
+use crate::versioning_types::{Platform, Version, VersionSelection};
+
+fn at(version: u32) -> CompileOptions {
+    let mut options = CompileOptions::default();
+    let mut selection = VersionSelection::new();
+    selection.insert(
+        Platform::parse("example").unwrap(),
+        BTreeSet::from([Version::from_number(version).unwrap()]),
+    );
+    options.version_selection = selection;
+    options
+}
+
+/// The changes from version 1 to version 2 of `source`, as
+/// `(compatibility, name)`.
+fn changes(source: &str) -> Vec<(Compatibility, String)> {
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", source);
+    let output = api::compat(
+        &sources,
+        CompatLibrary::Compiled(&at(1)),
+        CompatLibrary::Compiled(&at(2)),
+    )
+    .unwrap();
+    assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+    output
+        .changes
+        .unwrap()
+        .into_iter()
+        .map(|c| (c.compatibility, c.name))
+        .collect()
+}
+
+#[test]
+fn good_no_changes() {
+    assert_eq!(
+        changes(
+            r#"@available(added=1)
+library example;
+
+type S = struct {
+    a uint32;
+};
+
+closed protocol P {
+    strict M(struct { s S; }) -> ();
+};
+"#
+        ),
+        vec![]
+    );
+}
+
+#[test]
+fn good_struct_layout() {
+    assert_eq!(
+        changes(
+            r#"@available(added=1)
+library example;
+
+type Inner = struct {
+    a uint32;
+    @available(added=2)
+    b uint32;
+};
+
+type Outer = struct {
+    inner Inner;
+};
+
+type Renamed = struct {
+    @available(replaced=2, renamed="new")
+    old uint32;
+    @available(added=2)
+    new uint32;
+};
+"#
+        ),
+        vec![
+            (Compatibility::AbiBreaking, "example/Inner.b".to_string()),
+            // Only the layout of `Outer` changed, through `Inner`.
+            (Compatibility::AbiBreaking, "example/Outer".to_string()),
+            (
+                Compatibility::SourceBreaking,
+                "example/Renamed.old".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn good_strictness_and_members() {
+    assert_eq!(
+        changes(
+            r#"@available(added=1)
+library example;
+
+type Strict = strict enum {
+    A = 1;
+    @available(added=2)
+    B = 2;
+};
+
+type Flexible = flexible bits {
+    A = 1;
+    @available(added=2)
+    B = 2;
+    @available(removed=2)
+    C = 4;
+};
+
+@available(replaced=2)
+type Flipped = strict union {
+    1: a uint32;
+};
+
+@available(added=2)
+type Flipped = flexible union {
+    1: a uint32;
+};
+"#
+        ),
+        vec![
+            (Compatibility::Safe, "example/Flexible.B".to_string()),
+            (
+                Compatibility::SourceBreaking,
+                "example/Flexible.C".to_string()
+            ),
+            (Compatibility::SourceBreaking, "example/Flipped".to_string()),
+            (Compatibility::AbiBreaking, "example/Strict.B".to_string()),
+        ]
+    );
+}
+
+/// `source`, compiled on its own.
+fn library(source: &str) -> Root {
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", source);
+    let output = api::compile(&sources, &CompileOptions::default()).unwrap();
+    assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+    output.root.unwrap()
+}
+
+/// The changes from `old` to `new`, as `(compatibility, name)`.
+fn library_changes(old: &str, new: &str) -> Vec<(Compatibility, String)> {
+    check(&library(old), &library(new))
+        .into_iter()
+        .map(|c| (c.compatibility, c.name))
+        .collect()
+}
+
+#[test]
+fn good_ordinal_reuse() {
+    // A single versioned library can't reuse an ordinal, so compare two.
+    assert_eq!(
+        library_changes(
+            r#"library example;
+
+type T = table {
+    1: a uint32;
+};
+"#,
+            r#"library example;
+
+type T = table {
+    1: b string;
+    2: c uint32;
+};
+"#
+        ),
+        vec![
+            (Compatibility::AbiBreaking, "example/T.a".to_string()),
+            (Compatibility::Safe, "example/T.c".to_string()),
+        ]
+    );
+}
+
+/// The JSON IR of `source`, with the table or union members at `reserved`
+/// replaced by reserved ones, as in IR from before `reserved` was removed
+/// from the language.
+fn ir(source: &str, reserved: &[(&str, u64)]) -> serde_json::Value {
+    let mut ir = serde_json::to_value(JsonRoot::from(&library(source))).unwrap();
+    for list in ["table_declarations", "union_declarations"] {
+        for decl in ir[list].as_array_mut().unwrap() {
+            let name = decl["name"].as_str().unwrap().to_string();
+            for member in decl["members"].as_array_mut().unwrap() {
+                let ordinal = member["ordinal"].as_u64().unwrap();
+                if reserved.contains(&(name.as_str(), ordinal)) {
+                    *member = serde_json::json!({"ordinal": ordinal, "reserved": true});
+                }
+            }
+        }
+    }
+    ir
+}
+
+/// The changes from the `old` to the `new` JSON IR, as
+/// `(compatibility, name)`.
+fn ir_changes(old: &serde_json::Value, new: &serde_json::Value) -> Vec<(Compatibility, String)> {
+    let output = api::compat(
+        &Sources::new(),
+        CompatLibrary::Ir(old),
+        CompatLibrary::Ir(new),
+    )
+    .unwrap();
+    output
+        .changes
+        .unwrap()
+        .into_iter()
+        .map(|c| (c.compatibility, c.name))
+        .collect()
+}
+
+#[test]
+fn good_reserved_ordinal_reused() {
+    let old = ir(
+        r#"library example;
+
+type T = table {
+    1: a uint32;
+    2: c uint32;
+};
+
+type U = flexible union {
+    1: a uint32;
+    2: c uint32;
+};
+"#,
+        &[("example/T", 1), ("example/U", 1)],
+    );
+    let new = ir(
+        r#"library example;
+
+type T = table {
+    1: b string;
+    2: c uint32;
+};
+
+type U = flexible union {
+    1: b string;
+    2: c uint32;
+};
+"#,
+        &[],
+    );
+    assert_eq!(
+        ir_changes(&old, &new),
+        vec![
+            (Compatibility::AbiBreaking, "example/T.b".to_string()),
+            (Compatibility::AbiBreaking, "example/U.b".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn good_ordinal_reserved() {
+    // Reserving the ordinal of a member removes it; keeping an ordinal
+    // reserved changes nothing.
+    let source = r#"library example;
+
+type T = table {
+    1: a uint32;
+    2: c uint32;
+};
+"#;
+    let old = ir(source, &[("example/T", 1)]);
+    let new = ir(source, &[("example/T", 1), ("example/T", 2)]);
+    assert_eq!(
+        ir_changes(&old, &new),
+        vec![(Compatibility::SourceBreaking, "example/T.c".to_string())]
+    );
+    assert_eq!(ir_changes(&old, &old), vec![]);
+}
+
+#[test]
+fn good_protocol_methods() {
+    assert_eq!(
+        changes(
+            r#"@available(added=1)
+library example;
+
+closed protocol P {
+    @available(removed=2)
+    strict Removed();
+    @available(added=2)
+    strict Added();
+    @available(replaced=2, renamed="New")
+    strict Old();
+    @available(added=2)
+    @selector("example/P.Old")
+    strict New();
+    @available(replaced=2)
+    strict Payload(struct { a uint32; });
+    @available(added=2)
+    strict Payload(struct { a uint64; });
+};
+"#
+        ),
+        vec![
+            (Compatibility::SourceBreaking, "example/P.Added".to_string()),
+            (Compatibility::SourceBreaking, "example/P.Old".to_string()),
+            (Compatibility::AbiBreaking, "example/P.Removed".to_string()),
+            // The payload is reported where it's declared, not in the method.
+            (
+                Compatibility::AbiBreaking,
+                "example/PPayloadRequest.a".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn good_compat_command_with_ir_files() {
+    let dir = tempdir().unwrap();
+    let write_ir = |name: &str, source: &str| {
+        let path = dir.path().join(name);
+        let json = serde_json::to_string(&JsonRoot::from(&library(source))).unwrap();
+        fs::write(&path, json).unwrap();
+        path.to_string_lossy().to_string()
+    };
+    let old_source = r#"library example;
+
+type E = strict enum {
+    A = 1;
+};
+"#;
+    let new_source = r#"library example;
+
+type E = strict enum {
+    A = 1;
+    B = 2;
+};
+"#;
+    let args = CompatArgs {
+        old_ir: Some(write_ir("old.fidl.json", old_source)),
+        new_ir: Some(write_ir("new.fidl.json", new_source)),
+        format: "text".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(
+        run_command(&Command::Compat(args.clone())),
+        Err("Found 1 breaking change(s)".to_string())
+    );
+
+    // The IR of the old library against its sources.
+    let path = dir.path().join("example.fidl");
+    fs::write(&path, old_source).unwrap();
+    let args = CompatArgs {
+        new_ir: None,
+        files: vec![path.to_string_lossy().to_string()],
+        ..args
+    };
+    assert_eq!(run_command(&Command::Compat(args)), Ok(()));
+}
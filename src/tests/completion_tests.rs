@@ -0,0 +1,180 @@
+use crate::api::{Sources, complete};
+use crate::completion::CompletionKind;
+
+const DEP: &str = r#"library fuchsia.dep;
+
+type Thing = struct {};
+
+type Mode = flexible bits {
+    READ = 1;
+    WRITE = 2;
+};
+"#;
+
+const OTHER: &str = r#"library example;
+
+type Color = strict enum {
+    RED = 1;
+    GREEN = 2;
+};
+
+protocol Painter {};
+"#;
+
+/// Completes at the `|` in `text`, and returns the labels of the given kind.
+fn labels(text: &str, kind: CompletionKind) -> Vec<String> {
+    let offset = text.find('|').unwrap();
+    let text = text.replace('|', "");
+    let line = text[..offset].matches('\n').count() + 1;
+    let character = offset - text[..offset].rfind('\n').map_or(0, |i| i + 1);
+
+    let mut sources = Sources::new();
+    sources.add_dependency_file("dep.fidl", DEP);
+    sources.add_file("other.fidl", OTHER);
+    sources.add_file("example.fidl", text);
+    complete(&sources, "example.fidl", line, character)
+        .into_iter()
+        .filter(|c| c.kind == kind)
+        .map(|c| c.label)
+        .collect()
+}
+
+#[test]
+fn good_declaration_keywords() {
+    assert_eq!(
+        labels("|", CompletionKind::Keyword),
+        vec![
+            "ajar",
+            "alias",
+            "closed",
+            "const",
+            "library",
+            "open",
+            "protocol",
+            "resource_definition",
+            "service",
+            "type",
+            "using"
+        ]
+    );
+    assert_eq!(
+        labels("library example;\n\nty|", CompletionKind::Keyword),
+        vec!["type"]
+    );
+    assert_eq!(
+        labels("library example;\nopen |", CompletionKind::Keyword),
+        vec!["protocol"]
+    );
+}
+
+#[test]
+fn good_layout_keywords() {
+    let text = "library example;\ntype Foo = |";
+    assert_eq!(
+        labels(text, CompletionKind::Keyword),
+        vec![
+            "bits", "enum", "flexible", "resource", "strict", "struct", "table", "union"
+        ]
+    );
+    let text = "library example;\ntype Foo = flexible re|";
+    assert_eq!(labels(text, CompletionKind::Keyword), vec!["resource"]);
+    assert_eq!(labels(text, CompletionKind::Type), Vec::<String>::new());
+}
+
+#[test]
+fn good_types_in_broken_file() {
+    let text = r#"library example;
+
+using fuchsia.dep as dep;
+
+type Broken = struct {
+    a ;
+};
+
+type Foo = struct {
+    color Co|
+"#;
+    assert_eq!(labels(text, CompletionKind::Type), vec!["Color"]);
+
+    let text = "library example;\nusing fuchsia.dep as dep;\ntype Foo = table {\n    1: x |\n};\n";
+    let types = labels(text, CompletionKind::Type);
+    assert!(types.contains(&"uint32".to_string()));
+    assert!(types.contains(&"Color".to_string()));
+    assert!(types.contains(&"Painter".to_string()));
+    assert!(!types.contains(&"Thing".to_string()));
+    assert_eq!(labels(text, CompletionKind::Library), vec!["dep"]);
+
+    let text = "library example;\nconst MAX uint32 = 3;\nalias Names = vector<|";
+    assert!(labels(text, CompletionKind::Type).contains(&"string".to_string()));
+}
+
+#[test]
+fn good_dotted_names() {
+    let text = "library example;\nconst C Color = Color.|";
+    assert_eq!(labels(text, CompletionKind::Member), vec!["GREEN", "RED"]);
+
+    let text = "library example;\nusing fuchsia.dep as dep;\nconst M dep.Mode = dep.Mode.W|";
+    assert_eq!(labels(text, CompletionKind::Member), vec!["WRITE"]);
+
+    let text = "library example;\nusing fuchsia.dep as dep;\ntype S = struct { t dep.|";
+    assert_eq!(labels(text, CompletionKind::Type), vec!["Mode", "Thing"]);
+
+    let text = "library example;\nusing fuchsia.dep;\ntype S = struct { t fuchsia.|";
+    assert_eq!(labels(text, CompletionKind::Library), vec!["dep"]);
+}
+
+#[test]
+fn good_protocol_members() {
+    let text = "library example;\nprotocol P {\n    |";
+    assert_eq!(
+        labels(text, CompletionKind::Keyword),
+        vec!["compose", "flexible", "strict"]
+    );
+    let text = "library example;\nprotocol P {\n    compose Pa|";
+    assert_eq!(labels(text, CompletionKind::Type), vec!["Painter"]);
+    let text = "library example;\nprotocol P {\n    M(|";
+    assert!(labels(text, CompletionKind::Keyword).contains(&"struct".to_string()));
+}
+
+#[test]
+fn good_attributes() {
+    let text = "library example;\n@disc|\nprotocol P {};\n";
+    assert_eq!(
+        labels(text, CompletionKind::Attribute),
+        vec!["discoverable"]
+    );
+
+    let text = "@available(added=1, |)\nlibrary example;\n";
+    let arguments = labels(text, CompletionKind::AttributeArgument);
+    assert!(arguments.contains(&"removed".to_string()));
+    assert!(arguments.contains(&"platform".to_string()));
+
+    // After an attribute, the declaration keywords.
+    let text = "library example;\n@discoverable\n|";
+    assert!(labels(text, CompletionKind::Keyword).contains(&"protocol".to_string()));
+}
+
+#[test]
+fn good_nothing_in_member_names() {
+    assert!(
+        labels(
+            "library example;\ntype S = struct {\n    |",
+            CompletionKind::Keyword
+        )
+        .is_empty()
+    );
+    assert!(
+        labels(
+            "library example;\ntype E = enum {\n    A|",
+            CompletionKind::Type
+        )
+        .is_empty()
+    );
+    assert!(
+        labels(
+            "library example;\nconst S string = \"a|b\";",
+            CompletionKind::Type
+        )
+        .is_empty()
+    );
+}
@@ -0,0 +1,175 @@
+use crate::diagnostics::Error;
+use crate::json_generator::JsonRoot;
+use crate::tests::test_library::{LookupHelpers, SharedAmongstLibraries, TestLibrary};
+
+const DEPENDENCY: &str = r#"library dep;
+
+const LIMIT uint32 = 4;
+const NAME string = "hi";
+
+type Color = strict enum : uint8 {
+    RED = 1;
+    BLUE = 2;
+};
+
+type Flags = flexible bits {
+    A = 1;
+    B = 2;
+};
+
+type Point = struct {
+    x int32;
+    y int32;
+};
+
+type Info = table {
+    1: name string:LIMIT;
+};
+
+type Choice = flexible union {
+    1: p Point;
+    2: n uint64;
+};
+
+type Res = resource struct {
+    v vector<uint8>;
+};
+
+alias Bytes = vector<uint8>:LIMIT;
+
+protocol Reader {
+    Read() -> (struct { data Bytes; });
+};
+"#;
+
+const EXAMPLE: &str = r#"library example;
+
+using dep;
+
+const MAIN_LIMIT uint32 = dep.LIMIT;
+const MAIN_NAME string = dep.NAME;
+const DEFAULT_COLOR dep.Color = dep.Color.BLUE;
+const FLAGS dep.Flags = dep.Flags.A | dep.Flags.B;
+
+type Holder = struct {
+    p dep.Point;
+    i dep.Info;
+    c dep.Choice;
+    color dep.Color;
+    flags dep.Flags;
+    maybe box<dep.Point>;
+    points vector<dep.Point>:dep.LIMIT;
+    bytes dep.Bytes;
+};
+
+type ResHolder = resource struct {
+    r dep.Res;
+    client client_end:dep.Reader;
+};
+"#;
+
+fn dependency_ir() -> serde_json::Value {
+    let mut dep = TestLibrary::new();
+    dep.add_source_file("dep.fidl", DEPENDENCY);
+    let root = dep.compile().expect("dependency compiled");
+    serde_json::to_value(JsonRoot::from(&root)).unwrap()
+}
+
+#[test]
+fn good_matches_compiling_from_source() {
+    let mut shared = SharedAmongstLibraries::new();
+    {
+        let mut dep = TestLibrary::with_shared(&mut shared);
+        dep.add_source_file("dep.fidl", DEPENDENCY);
+        dep.compile().expect("dependency compiled");
+    }
+    let mut from_source = TestLibrary::with_shared(&mut shared);
+    from_source.add_source_file("example.fidl", EXAMPLE);
+    let from_source = from_source.compile().expect("compiled from source");
+
+    let mut from_ir = TestLibrary::new();
+    from_ir.add_dependency_ir(dependency_ir());
+    from_ir.add_source_file("example.fidl", EXAMPLE);
+    let from_ir = from_ir.compile().expect("compiled from IR");
+
+    assert_eq!(
+        serde_json::to_value(JsonRoot::from(&from_ir)).unwrap(),
+        serde_json::to_value(JsonRoot::from(&from_source)).unwrap()
+    );
+}
+
+#[test]
+fn good_constants_from_ir() {
+    let mut library = TestLibrary::new();
+    library.add_dependency_ir(dependency_ir());
+    library.add_source_file("example.fidl", EXAMPLE);
+    let root = library.compile().expect("compilation failed");
+
+    let limit = root.lookup_constant("example/MAIN_LIMIT").unwrap();
+    assert_eq!(limit.value.value, "\"4\"");
+    assert_eq!(limit.value.identifier.as_deref(), Some("dep/LIMIT"));
+    let name = root.lookup_constant("example/MAIN_NAME").unwrap();
+    assert_eq!(name.value.value, "\"hi\"");
+    let color = root.lookup_constant("example/DEFAULT_COLOR").unwrap();
+    assert_eq!(color.value.value, "\"2\"");
+    assert_eq!(color.value.identifier.as_deref(), Some("dep/Color.BLUE"));
+    let flags = root.lookup_constant("example/FLAGS").unwrap();
+    assert_eq!(flags.value.value, "\"3\"");
+}
+
+#[test]
+fn bad_unknown_member_from_ir() {
+    let mut library = TestLibrary::new();
+    library.add_dependency_ir(dependency_ir());
+    library.add_source_file(
+        "example.fidl",
+        "library example;\nusing dep;\nconst C dep.Color = dep.Color.GREEN;\n",
+    );
+    library.expect_fail(Error::ErrCouldNotResolveMember("enum".into()));
+    assert!(library.check_compile());
+}
+
+#[test]
+fn bad_cannot_box_enum_from_ir() {
+    let mut library = TestLibrary::new();
+    library.add_dependency_ir(dependency_ir());
+    library.add_source_file(
+        "example.fidl",
+        "library example;\nusing dep;\ntype S = struct { c box<dep.Color>; };\n",
+    );
+    library.expect_fail(Error::ErrCannotBeBoxedNorOptional("dep.Color".into()));
+    assert!(library.check_compile());
+}
+
+#[test]
+fn good_compose_protocol_from_ir() {
+    let mut library = TestLibrary::new();
+    library.add_dependency_ir(dependency_ir());
+    library.add_source_file(
+        "example.fidl",
+        "library example;\nusing dep;\nprotocol P { compose dep.Reader; };\n",
+    );
+    let root = library.compile().expect("compilation failed");
+
+    let protocol = root.lookup_protocol("example/P").unwrap();
+    assert_eq!(protocol.methods.len(), 1);
+    assert_eq!(protocol.methods[0].name.as_string(), "Read");
+    assert!(protocol.methods[0].is_composed);
+    assert_eq!(root.external_struct_declarations.len(), 1);
+    assert_eq!(
+        root.external_struct_declarations[0].name.as_string(),
+        "dep/Reader_Read_Response"
+    );
+}
+
+#[test]
+fn bad_duplicate_dependency_ir() {
+    let mut library = TestLibrary::new();
+    library.add_dependency_ir(dependency_ir());
+    library.add_dependency_ir(dependency_ir());
+    library.add_source_file("example.fidl", "library example;\n");
+    assert_eq!(
+        library.compile().unwrap_err(),
+        "duplicate dependency IR for library dep"
+    );
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeSet;
+
+use crate::api::{self, CompileOptions, Sources};
+use crate::diagnostics::Error;
+use crate::json_generator::JsonRoot;
+use crate::versioning_types::{Platform, Version, VersionSelection};
+
+const DEPENDENCY: &str = r#"@available(added=1)
+library dep;
+
+@available(deprecated=2, note="use New instead")
+type Old = table {
+    1: a uint32;
+};
+
+type New = table {
+    1: a uint32;
+};
+
+type Color = strict enum {
+    RED = 1;
+    @available(deprecated=2)
+    BLUE = 2;
+};
+"#;
+
+/// Compiles `source` against the `dep` library, returning the message,
+/// notes and line of each warning about a deprecated reference, by line.
+fn warnings(source: &str, options: &CompileOptions) -> Vec<(String, Vec<String>, usize)> {
+    let mut sources = Sources::new();
+    sources.add_dependency_file("dep.fidl", DEPENDENCY);
+    sources.add_file("example.fidl", source);
+    let output = api::compile(&sources, options).unwrap();
+    assert!(output.root.is_some(), "{:?}", output.diagnostics);
+    let mut warnings: Vec<_> = output
+        .diagnostics
+        .into_iter()
+        .filter(|d| matches!(d.error, Error::WarnReferenceToDeprecated(..)))
+        .map(|d| (d.message, d.notes, d.location.unwrap().start_line))
+        .collect();
+    warnings.sort_by_key(|(_, _, line)| *line);
+    warnings
+}
+
+fn at(platform: &str, version: u32) -> CompileOptions {
+    let mut options = CompileOptions::default();
+    let mut selection = VersionSelection::new();
+    selection.insert(
+        Platform::parse(platform).unwrap(),
+        BTreeSet::from([Version::from_number(version).unwrap()]),
+    );
+    options.version_selection = selection;
+    options
+}
+
+const EXAMPLE: &str = r#"@available(added=1)
+library example;
+
+using dep;
+
+type S = struct {
+    old dep.Old;
+    new dep.New;
+};
+
+const C dep.Color = dep.Color.BLUE;
+"#;
+
+#[test]
+fn warn_reference_to_dependency() {
+    assert_eq!(
+        warnings(EXAMPLE, &CompileOptions::default()),
+        vec![
+            (
+                "reference to 'dep/Old', which is deprecated; either remove this reference or \
+                 mark the element that makes it as deprecated"
+                    .to_string(),
+                vec!["'dep/Old' is deprecated: use New instead".to_string()],
+                7
+            ),
+            (
+                "reference to 'dep/Color.BLUE', which is deprecated; either remove this \
+                 reference or mark the element that makes it as deprecated"
+                    .to_string(),
+                vec![],
+                11
+            ),
+        ]
+    );
+}
+
+#[test]
+fn good_not_yet_deprecated() {
+    assert_eq!(warnings(EXAMPLE, &at("dep", 1)), vec![]);
+}
+
+#[test]
+fn good_allow_deprecated() {
+    let options = CompileOptions {
+        allow_deprecated: true,
+        ..Default::default()
+    };
+    assert_eq!(warnings(EXAMPLE, &options), vec![]);
+}
+
+#[test]
+fn good_deprecated_referrer() {
+    let source = r#"@available(added=1)
+library example;
+
+using dep;
+
+@available(deprecated=1)
+type S = struct {
+    old dep.Old;
+};
+
+type T = struct {
+    @available(deprecated=1)
+    old dep.Old;
+    @available(removed=2)
+    older dep.Old;
+};
+"#;
+    assert_eq!(warnings(source, &at("example", 2)), vec![]);
+}
+
+#[test]
+fn warn_reference_within_library() {
+    let mut sources = Sources::new();
+    sources.add_file(
+        "example.fidl",
+        r#"@available(added=1)
+library example;
+
+@available(deprecated=2)
+type Old = struct {};
+
+type S = struct {
+    old Old;
+};
+"#,
+    );
+    let output = api::compile(&sources, &at("example", 1)).unwrap();
+    assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+
+    let output = api::compile(&sources, &at("example", 2)).unwrap();
+    assert_eq!(output.diagnostics.len(), 1);
+    let warning = &output.diagnostics[0];
+    assert!(matches!(
+        warning.error,
+        Error::WarnReferenceToDeprecated(..)
+    ));
+    assert_eq!(warning.labels.len(), 1);
+    assert_eq!(warning.labels[0].0, "'example/Old' is declared here");
+    assert_eq!(warning.labels[0].1.start_line, 5);
+
+    let options = CompileOptions {
+        werror: true,
+        ..at("example", 2)
+    };
+    let output = api::compile(&sources, &options).unwrap();
+    assert!(output.root.is_none());
+}
+
+#[test]
+fn warn_reference_to_precompiled_dependency() {
+    let mut dep = Sources::new();
+    dep.add_file("dep.fidl", DEPENDENCY);
+    let root = api::compile(&dep, &CompileOptions::default())
+        .unwrap()
+        .root
+        .unwrap();
+    let options = CompileOptions {
+        dependency_irs: vec![serde_json::to_value(JsonRoot::from(&root)).unwrap()],
+        ..Default::default()
+    };
+
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", EXAMPLE);
+    let output = api::compile(&sources, &options).unwrap();
+    let mut warnings: Vec<(&[String], usize)> = output
+        .diagnostics
+        .iter()
+        .filter(|d| matches!(d.error, Error::WarnReferenceToDeprecated(..)))
+        .map(|d| (d.notes.as_slice(), d.location.as_ref().unwrap().start_line))
+        .collect();
+    warnings.sort_by_key(|(_, line)| *line);
+    assert_eq!(
+        warnings,
+        vec![
+            (
+                &["'dep/Old' is deprecated: use New instead".to_string()][..],
+                7
+            ),
+            (&[][..], 11),
+        ]
+    );
+}
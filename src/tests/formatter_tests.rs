@@ -0,0 +1,201 @@
+use crate::cli::{Command, FormatArgs, run_command};
+use crate::diagnostics::Error;
+use std::fs;
+use tempfile::tempdir;
+
+fn format(text: &str) -> String {
+    crate::format("example.fidl", text).expect("formatting failed")
+}
+
+#[track_caller]
+fn assert_formats(input: &str, expected: &str) {
+    let formatted = format(input);
+    assert_eq!(formatted, expected);
+    assert_eq!(format(&formatted), formatted, "formatting isn't idempotent");
+}
+
+#[test]
+fn good_canonical_spacing_and_indentation() {
+    assert_formats(
+        r#"library   example ;
+type Foo=struct{a int32;b vector<uint8>:< MAX , optional >;c string :256;d box<Foo>;};
+type E = strict enum:uint8 { A=1; B = 2|4; };
+type T = table { 1 : x uint32; };
+const X uint32=5;
+alias Y = array<uint8,4>;
+type Empty = struct{};
+"#,
+        r#"library example;
+
+type Foo = struct {
+    a int32;
+    b vector<uint8>:<MAX, optional>;
+    c string:256;
+    d box<Foo>;
+};
+
+type E = strict enum : uint8 {
+    A = 1;
+    B = 2 | 4;
+};
+
+type T = table {
+    1: x uint32;
+};
+
+const X uint32 = 5;
+
+alias Y = array<uint8, 4>;
+
+type Empty = struct {};
+"#,
+    );
+}
+
+#[test]
+fn good_protocols_and_attributes() {
+    assert_formats(
+        r#"library example;
+@available( added = 1 )  @discoverable protocol P{
+compose Q;
+Method( struct { x bool; } )->( struct {} ) error uint32;
+-> OnEvent(table { 1: x uint32; });
+flexible(removed=2) strict Other();
+};
+protocol Q {};
+"#,
+        r#"library example;
+
+@available(added=1)
+@discoverable
+protocol P {
+    compose Q;
+    Method(struct {
+        x bool;
+    }) -> (struct {}) error uint32;
+    -> OnEvent(table {
+        1: x uint32;
+    });
+    flexible(removed=2) strict Other();
+};
+
+protocol Q {};
+"#,
+    );
+}
+
+#[test]
+fn good_keeps_comments_and_blank_lines() {
+    assert_formats(
+        r#"// Copyright header.
+
+library example; // the library
+/// Doc comment.
+///   Indented doc comment.
+type Foo = struct {
+
+
+  a int32;   // trailing
+  // before b
+
+  b bool;
+  // last
+};
+
+
+
+// end of file
+"#,
+        r#"// Copyright header.
+
+library example; // the library
+
+/// Doc comment.
+///   Indented doc comment.
+type Foo = struct {
+    a int32; // trailing
+    // before b
+
+    b bool;
+    // last
+};
+
+// end of file
+"#,
+    );
+}
+
+#[test]
+fn good_moves_using_to_top() {
+    assert_formats(
+        r#"library example;
+type Foo = struct {};
+// Needed for handles.
+using zx;
+using dep as d;
+"#,
+        r#"library example;
+
+// Needed for handles.
+using zx;
+using dep as d;
+
+type Foo = struct {};
+"#,
+    );
+}
+
+#[test]
+fn good_comment_within_declaration() {
+    assert_formats(
+        "library example;\ntype Foo = // why\n  struct {};\n",
+        "library example;\n\ntype Foo = // why\n    struct {};\n",
+    );
+}
+
+#[test]
+fn good_crlf() {
+    assert_formats(
+        "library example;\r\n\r\nconst C bool = true; // c\r\n",
+        "library example;\n\nconst C bool = true; // c\n",
+    );
+}
+
+#[test]
+fn bad_syntax_error() {
+    let diagnostics = crate::format("example.fidl", "library example;\ntype = struct {};\n")
+        .expect_err("formatting succeeded");
+    assert!(
+        diagnostics
+            .iter()
+            .any(|d| matches!(d.error, Error::ErrUnexpectedTokenOfKind(..)))
+    );
+}
+
+#[test]
+fn good_cli_check_and_in_place() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("example.fidl");
+    let path_str = path.to_str().unwrap().to_string();
+    fs::write(&path, "library example;\nconst C bool=true;\n").unwrap();
+
+    let check = Command::Format(FormatArgs {
+        check: true,
+        files: vec![path_str.clone()],
+        ..Default::default()
+    });
+    let err = run_command(&check).unwrap_err();
+    assert!(err.contains(&path_str), "{}", err);
+
+    let in_place = Command::Format(FormatArgs {
+        in_place: true,
+        files: vec![path_str],
+        ..Default::default()
+    });
+    run_command(&in_place).unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "library example;\n\nconst C bool = true;\n"
+    );
+    run_command(&check).unwrap();
+}
@@ -0,0 +1,206 @@
+use crate::api::{self, CompileOptions, Sources, hover};
+use crate::hover::{Hover, HoverAvailability};
+use crate::json_generator::JsonRoot;
+use crate::versioning_types::{Platform, Version};
+use std::collections::BTreeSet;
+
+const EXAMPLE: &str = r#"library example;
+
+/// A thing.
+type Thing = resource struct {
+    /// The first.
+    a uint32;
+    b vector<uint8>;
+};
+
+type Holder = struct {
+    thing Thing;
+};
+"#;
+
+fn hover_in(text: &str, options: &CompileOptions, line: usize, character: usize) -> Option<Hover> {
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", text);
+    hover(&sources, options, "example.fidl", line, character).unwrap()
+}
+
+fn hover_at(line: usize, character: usize) -> Option<Hover> {
+    hover_in(EXAMPLE, &CompileOptions::default(), line, character)
+}
+
+#[test]
+fn good_declaration() {
+    let hover = hover_at(4, 6).unwrap();
+    assert_eq!(hover.name, "example/Thing");
+    assert_eq!(hover.kind, "struct");
+    assert_eq!(
+        (
+            hover.range.start_line,
+            hover.range.start_char,
+            hover.range.end_char
+        ),
+        (4, 5, 10)
+    );
+    assert_eq!(hover.resource, Some(true));
+    assert_eq!(hover.availability, None);
+    assert_eq!(hover.doc.as_deref(), Some("A thing."));
+    let shape = hover.type_shape.as_ref().unwrap();
+    assert_eq!(
+        (
+            shape.inline_size,
+            shape.alignment,
+            shape.depth,
+            shape.max_handles
+        ),
+        (24, 8, 1, 0)
+    );
+    assert_eq!(shape.max_out_of_line, u32::MAX);
+    assert_eq!(
+        hover.markdown(),
+        "```fidl\nresource struct example/Thing\n```\n\nA thing.\n\n\
+         | Inline size | Alignment | Depth | Max handles | Max out-of-line |\n\
+         |---|---|---|---|---|\n\
+         | 24 | 8 | 1 | 0 | unbounded |"
+    );
+}
+
+#[test]
+fn good_reference() {
+    // `Thing` in `thing Thing;`.
+    let hover = hover_at(11, 12).unwrap();
+    assert_eq!(hover.name, "example/Thing");
+    assert_eq!((hover.range.start_line, hover.range.start_char), (11, 10));
+    assert!(hover.type_shape.is_some());
+}
+
+#[test]
+fn good_member() {
+    let hover = hover_at(6, 4).unwrap();
+    assert_eq!(hover.name, "example/Thing.a");
+    assert_eq!(hover.kind, "struct member");
+    assert_eq!(hover.doc.as_deref(), Some("The first."));
+    assert!(hover.type_shape.is_none());
+    assert_eq!(hover.resource, None);
+}
+
+#[test]
+fn good_nothing_to_show() {
+    // Whitespace, a keyword and a builtin type.
+    assert!(hover_at(2, 0).is_none());
+    assert!(hover_at(4, 0).is_none());
+    assert!(hover_at(6, 7).is_none());
+}
+
+#[test]
+fn good_availability() {
+    let text = r#"@available(added=1)
+library example;
+
+@available(added=2, deprecated=3, removed=4)
+type Old = struct {};
+
+type New = table {
+    @available(added=2)
+    1: x uint32;
+};
+"#;
+    let mut options = CompileOptions::default();
+    options.version_selection.insert(
+        Platform::parse("example").unwrap(),
+        BTreeSet::from([Version::from_number(3).unwrap()]),
+    );
+
+    let hover = hover_in(text, &options, 5, 6).unwrap();
+    assert_eq!(
+        hover.availability,
+        Some(HoverAvailability {
+            platform: "example".to_string(),
+            added: Version::from_number(2).unwrap(),
+            deprecated: Version::from_number(3),
+            removed: Version::from_number(4),
+        })
+    );
+    assert!(
+        hover
+            .markdown()
+            .ends_with("Added in `example` 2, deprecated in 3, removed in 4.")
+    );
+
+    let hover = hover_in(text, &options, 9, 7).unwrap();
+    assert_eq!(hover.kind, "table field");
+    let availability = hover.availability.unwrap();
+    assert_eq!(availability.added, Version::from_number(2).unwrap());
+    assert_eq!(availability.removed, None);
+
+    let hover = hover_in(text, &options, 7, 6).unwrap();
+    assert_eq!(
+        hover.availability.unwrap().added,
+        Version::from_number(1).unwrap()
+    );
+}
+
+#[test]
+fn good_reference_to_precompiled() {
+    let mut dep = Sources::new();
+    dep.add_file(
+        "dep.fidl",
+        r#"@available(added=1)
+library dep;
+
+/// Held.
+@available(added=2, deprecated=3)
+type Thing = resource table {};
+
+type Color = enum {
+    /// Red.
+    @available(added=3)
+    RED = 1;
+};
+"#,
+    );
+    let root = api::compile(&dep, &CompileOptions::default())
+        .unwrap()
+        .root
+        .unwrap();
+    let options = CompileOptions {
+        dependency_irs: vec![serde_json::to_value(JsonRoot::from(&root)).unwrap()],
+        ..Default::default()
+    };
+    let text = r#"library example;
+
+using dep;
+
+type Holder = resource struct {
+    thing dep.Thing;
+};
+
+const RED dep.Color = dep.Color.RED;
+"#;
+
+    // `Thing` in `dep.Thing`.
+    let hover = hover_in(text, &options, 6, 16).unwrap();
+    assert_eq!(hover.name, "dep/Thing");
+    assert_eq!(hover.kind, "table");
+    assert_eq!(hover.resource, Some(true));
+    assert_eq!(hover.doc.as_deref(), Some("Held."));
+    assert!(hover.type_shape.is_some());
+    assert_eq!(
+        hover.availability,
+        Some(HoverAvailability {
+            platform: "dep".to_string(),
+            added: Version::from_number(2).unwrap(),
+            deprecated: Version::from_number(3),
+            removed: None,
+        })
+    );
+
+    // `RED` in `dep.Color.RED`.
+    let hover = hover_in(text, &options, 9, 33).unwrap();
+    assert_eq!(hover.name, "dep/Color.RED");
+    assert_eq!(hover.kind, "member");
+    assert_eq!(hover.doc.as_deref(), Some("Red."));
+    assert_eq!(
+        hover.availability.unwrap().added,
+        Version::from_number(3).unwrap()
+    );
+}
@@ -0,0 +1,90 @@
+use crate::library_path::{LibraryPath, LibrarySearch, MANIFEST};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+#[test]
+fn candidates_in_search_order() {
+    let path = LibraryPath::new(["one", "two"]);
+    assert_eq!(
+        path.candidates("fuchsia.foo"),
+        vec![
+            PathBuf::from("one/fuchsia.foo"),
+            PathBuf::from("one/fuchsia/foo"),
+            PathBuf::from("two/fuchsia.foo"),
+            PathBuf::from("two/fuchsia/foo"),
+        ]
+    );
+    assert_eq!(
+        path.candidates("single"),
+        vec![PathBuf::from("one/single"), PathBuf::from("two/single")]
+    );
+}
+
+#[test]
+fn finds_fidl_files_in_library_directory() {
+    let root = tempdir().unwrap();
+    let dir = root.path().join("fuchsia.foo");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("b.fidl"), "library fuchsia.foo;").unwrap();
+    fs::write(dir.join("a.fidl"), "library fuchsia.foo;").unwrap();
+    fs::write(dir.join("README.md"), "").unwrap();
+
+    let path = LibraryPath::new([root.path()]);
+    assert_eq!(
+        path.find("fuchsia.foo").unwrap(),
+        LibrarySearch::Found(vec![
+            dir.join("a.fidl").display().to_string(),
+            dir.join("b.fidl").display().to_string(),
+        ])
+    );
+}
+
+#[test]
+fn finds_sources_listed_in_manifest() {
+    let root = tempdir().unwrap();
+    let dir = root.path().join("fuchsia").join("foo");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/foo.fidl"), "library fuchsia.foo;").unwrap();
+    fs::write(dir.join("unlisted.fidl"), "library fuchsia.foo;").unwrap();
+    fs::write(
+        dir.join(MANIFEST),
+        r#"{"name": "fuchsia.foo", "sources": ["src/foo.fidl"]}"#,
+    )
+    .unwrap();
+
+    let path = LibraryPath::new([root.path()]);
+    assert_eq!(
+        path.find("fuchsia.foo").unwrap(),
+        LibrarySearch::Found(vec![dir.join("src/foo.fidl").display().to_string()])
+    );
+}
+
+#[test]
+fn manifest_for_another_library_is_an_error() {
+    let root = tempdir().unwrap();
+    let dir = root.path().join("fuchsia.foo");
+    fs::create_dir(&dir).unwrap();
+    fs::write(
+        dir.join(MANIFEST),
+        r#"{"name": "fuchsia.bar", "sources": []}"#,
+    )
+    .unwrap();
+
+    let path = LibraryPath::new([root.path()]);
+    let err = path.find("fuchsia.foo").unwrap_err();
+    assert!(err.contains("describes library fuchsia.bar"), "{}", err);
+}
+
+#[test]
+fn reports_searched_directories() {
+    let root = tempdir().unwrap();
+    let path = LibraryPath::new([root.path()]);
+    assert_eq!(
+        path.find("fuchsia.foo").unwrap(),
+        LibrarySearch::NotFound(vec![
+            root.path().join("fuchsia.foo").display().to_string(),
+            root.path().join("fuchsia/foo").display().to_string(),
+        ])
+    );
+}
@@ -0,0 +1,260 @@
+use crate::api::{CompileOptions, Sources};
+use crate::cli::{Command, LintArgs, run_command};
+use crate::diagnostics::{Error, ErrorKind};
+use crate::linter::{LintCheck, LintOptions};
+use std::fs;
+use tempfile::tempdir;
+
+fn lint_with(text: &str, options: &LintOptions) -> Vec<(String, String)> {
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", text);
+    let output = crate::lint(&sources, &CompileOptions::default(), options).unwrap();
+    output
+        .diagnostics
+        .iter()
+        .map(|d| {
+            assert_eq!(d.kind(), ErrorKind::Warning, "{}", d.message);
+            (d.id(), d.message.clone())
+        })
+        .collect()
+}
+
+fn lint(text: &str) -> Vec<(String, String)> {
+    lint_with(text, &LintOptions::default())
+}
+
+fn ids(findings: &[(String, String)]) -> Vec<&str> {
+    findings.iter().map(|(id, _)| id.as_str()).collect()
+}
+
+#[test]
+fn good_clean_library() {
+    let findings = lint(
+        r#"library fuchsia.example;
+
+/// The maximum name length.
+const MAX_NAME uint32 = 32;
+
+/// A color.
+type Color = flexible enum {
+    DARK_RED = 1;
+};
+
+/// A thing.
+type Thing = table {
+    1: display_name string:MAX_NAME;
+};
+
+/// Does things.
+protocol Doer {
+    /// Does a thing.
+    Do(struct {
+        names vector<string:MAX_NAME>:16;
+    }) -> (struct {
+        thing Thing;
+    }) error uint32;
+};
+"#,
+    );
+    assert_eq!(findings, vec![]);
+}
+
+#[test]
+fn bad_invalid_case() {
+    let findings = lint(
+        r#"library fuchsia.example;
+
+/// c
+const kMaxSize uint32 = 1;
+
+/// s
+type my_struct = struct {
+    FooBar bool;
+};
+
+/// e
+type E = flexible enum {
+    first = 1;
+};
+"#,
+    );
+    assert_eq!(
+        findings,
+        vec![
+            (
+                "fi-1027".to_string(),
+                "const 'kMaxSize' should be UPPER_SNAKE_CASE, like 'MAX_SIZE'".to_string()
+            ),
+            (
+                "fi-1027".to_string(),
+                "struct 'my_struct' should be UpperCamelCase, like 'MyStruct'".to_string()
+            ),
+            (
+                "fi-1027".to_string(),
+                "struct member 'FooBar' should be lower_snake_case, like 'foo_bar'".to_string()
+            ),
+            (
+                "fi-1027".to_string(),
+                "enum member 'first' should be UPPER_SNAKE_CASE, like 'FIRST'".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn bad_library_prefix() {
+    let findings = lint("library example;\n");
+    assert_eq!(
+        findings,
+        vec![(
+            "fi-1028".to_string(),
+            "library 'example' should start with one of: fuchsia, fidl, test".to_string()
+        )]
+    );
+
+    let options = LintOptions {
+        library_prefixes: vec!["example".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(lint_with("library example;\n", &options), vec![]);
+}
+
+#[test]
+fn bad_unbounded_payload() {
+    let findings = lint(
+        r#"library fuchsia.example;
+
+/// p
+protocol P {
+    /// m
+    Method(struct {
+        data vector<uint8>;
+        names vector<string:10>:10;
+        bounded string:10;
+    }) -> (struct {
+        reply string;
+    });
+};
+"#,
+    );
+    assert_eq!(
+        findings,
+        vec![
+            (
+                "fi-1029".to_string(),
+                "unbounded vector in member 'data' of the payload of 'P.Method'; give it a maximum size"
+                    .to_string()
+            ),
+            (
+                "fi-1029".to_string(),
+                "unbounded string in member 'reply' of the payload of 'P.Method'; give it a maximum size"
+                    .to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn bad_missing_doc_comment() {
+    let findings = lint(
+        r#"library fuchsia.example;
+
+type S = struct {};
+
+/// Documented.
+protocol P {
+    M();
+};
+"#,
+    );
+    assert_eq!(
+        findings,
+        vec![
+            (
+                "fi-1030".to_string(),
+                "struct 'S' should have a doc comment".to_string()
+            ),
+            (
+                "fi-1030".to_string(),
+                "method 'M' should have a doc comment".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn bad_strict_and_discouraged_type() {
+    let findings = lint(
+        r#"library fuchsia.example;
+
+/// s
+type S = struct {
+    ratio float32;
+    kind strict enum {
+        A = 1;
+    };
+};
+"#,
+    );
+    assert_eq!(
+        findings,
+        vec![
+            (
+                "fi-1032".to_string(),
+                "type 'float32' is discouraged; prefer float64".to_string()
+            ),
+            (
+                "fi-1031".to_string(),
+                "enum 'kind' is strict; prefer flexible unless its members can never change"
+                    .to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn good_excluded_checks() {
+    let text = "library example;\n\ntype S = struct {\n    ratio float32;\n};\n";
+    assert_eq!(ids(&lint(text)), vec!["fi-1028", "fi-1030", "fi-1032"]);
+
+    let mut options = LintOptions::default();
+    for check in ["library-prefix", "fi-1030"] {
+        options.excluded.insert(check.parse().unwrap());
+    }
+    assert_eq!(ids(&lint_with(text, &options)), vec!["fi-1032"]);
+    assert!("no-such-check".parse::<LintCheck>().is_err());
+}
+
+#[test]
+fn good_check_ids_match_errors() {
+    let errors = [
+        Error::WarnLintInvalidCase("".into(), "".into(), "".into(), "".into()),
+        Error::WarnLintLibraryPrefix("".into(), "".into()),
+        Error::WarnLintUnboundedPayload("".into(), "".into(), "".into()),
+        Error::WarnLintMissingDocComment("".into(), "".into()),
+        Error::WarnLintPreferFlexible("".into(), "".into()),
+        Error::WarnLintDiscouragedType("".into(), "".into()),
+    ];
+    for (check, error) in LintCheck::ALL.iter().zip(errors) {
+        assert_eq!(check.error_id(), error.format_id().to_string());
+        assert_eq!(check.name().parse::<LintCheck>(), Ok(*check));
+    }
+}
+
+#[test]
+fn good_cli_lint() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("example.fidl");
+    let path_str = path.to_str().unwrap().to_string();
+    fs::write(&path, "library example;\n").unwrap();
+
+    let mut args = LintArgs {
+        files: vec![path_str],
+        ..Default::default()
+    };
+    let err = run_command(&Command::Lint(args.clone())).unwrap_err();
+    assert!(err.contains("1 problem"), "{}", err);
+
+    args.exclude_check = vec!["library-prefix".to_string()];
+    run_command(&Command::Lint(args)).unwrap();
+}
@@ -0,0 +1,97 @@
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::raw_ast::File;
+use crate::reporter::Reporter;
+use crate::source_file::SourceFile;
+use crate::token::TokenKind;
+
+fn parse_lossless<'a>(source: &'a SourceFile, reporter: &'a Reporter<'a>) -> File<'a> {
+    let mut lexer = Lexer::new(source, reporter);
+    let mut parser = Parser::new_lossless(&mut lexer, reporter);
+    parser.consume_token(TokenKind::StartOfFile);
+    parser.parse_file().expect("parse failed")
+}
+
+fn assert_round_trip(text: &str) {
+    let source = SourceFile::new("example.fidl".to_string(), text.to_string());
+    let reporter = Reporter::new();
+    let file = parse_lossless(&source, &reporter);
+    assert_eq!(file.to_source(), text);
+}
+
+#[test]
+fn good_round_trip_comments_and_blank_lines() {
+    assert_round_trip(
+        r#"// Copyright header.
+
+// Another comment.
+library example; // trailing comment
+
+using zx;
+
+/// A doc comment.
+//// Not a doc comment.
+@available(added=1)
+type Foo = struct {
+
+
+    a int32;    // after a member
+    // before a member
+    b vector<uint8>:MAX;
+};
+
+protocol P {
+    Method(struct { x bool; }) -> () error uint32;
+};
+// comment at the end of the file
+"#,
+    );
+}
+
+#[test]
+fn good_round_trip_crlf_and_tabs() {
+    assert_round_trip("library example;\r\n\r\n\tconst C uint32 = 1;\t// c\r\n");
+}
+
+#[test]
+fn good_round_trip_no_trailing_newline() {
+    assert_round_trip("library example;\n// done");
+}
+
+#[test]
+fn good_round_trip_parse_error() {
+    assert_round_trip("library example;\n\ntype = struct {};\n// x\n");
+}
+
+#[test]
+fn good_token_trivia() {
+    let text = "// header\nlibrary example; // the library\n\n/// Doc.\nconst C bool = true;\n";
+    let source = SourceFile::new("example.fidl".to_string(), text.to_string());
+    let reporter = Reporter::new();
+    let file = parse_lossless(&source, &reporter);
+
+    let library = &file.tokens[1];
+    assert_eq!(library.span.data, "library");
+    assert_eq!(library.leading_trivia, "// header\n");
+    let semicolon = &file.tokens[3];
+    assert_eq!(semicolon.kind, TokenKind::Semicolon);
+    assert_eq!(semicolon.leading_trivia, "");
+    assert_eq!(semicolon.trailing_trivia, " // the library");
+    let doc = &file.tokens[4];
+    assert_eq!(doc.kind, TokenKind::DocComment);
+    assert_eq!(doc.leading_trivia, "\n\n");
+    let eof = file.tokens.last().unwrap();
+    assert_eq!(eof.kind, TokenKind::EndOfFile);
+    assert_eq!(eof.leading_trivia, "\n");
+}
+
+#[test]
+fn good_tokens_not_kept_by_default() {
+    let source = SourceFile::new("example.fidl".to_string(), "library example;\n".to_string());
+    let reporter = Reporter::new();
+    let mut lexer = Lexer::new(&source, &reporter);
+    let mut parser = Parser::new(&mut lexer, &reporter);
+    parser.consume_token(TokenKind::StartOfFile);
+    let file = parser.parse_file().unwrap();
+    assert!(file.tokens.is_empty());
+}
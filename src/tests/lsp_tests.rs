@@ -0,0 +1,416 @@
+use crate::lsp::{self, Server, read_message, write_message};
+use serde_json::{Value, json};
+use std::io::Cursor;
+
+const EXAMPLE_URI: &str = "file:///example.fidl";
+const DEP_URI: &str = "file:///dep.fidl";
+
+const EXAMPLE: &str = r#"library fuchsia.example;
+
+using fuchsia.dep;
+
+/// A color.
+type Color = strict enum {
+    RED = 1;
+};
+
+const DEFAULT Color = Color.RED;
+
+type Holder = struct {
+    thing fuchsia.dep.Thing;
+};
+"#;
+
+const DEP: &str = "library fuchsia.dep;\n\ntype Thing = struct {};\n";
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn did_open(uri: &str, text: &str) -> Value {
+    notification(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": uri, "languageId": "fidl", "version": 1, "text": text } }),
+    )
+}
+
+fn position(id: u64, method: &str, line: u64, character: u64) -> Value {
+    request(
+        id,
+        method,
+        json!({
+            "textDocument": { "uri": EXAMPLE_URI },
+            "position": { "line": line, "character": character },
+        }),
+    )
+}
+
+fn response(server: &mut Server, message: Value) -> Value {
+    let replies = server.handle(&message);
+    assert_eq!(replies.len(), 1, "{:?}", replies);
+    replies[0]["result"].clone()
+}
+
+fn open_example() -> Server {
+    let mut server = Server::new();
+    server.handle(&did_open(DEP_URI, DEP));
+    server.handle(&did_open(EXAMPLE_URI, EXAMPLE));
+    server
+}
+
+#[test]
+fn good_session_over_stdio() {
+    let mut input = Vec::new();
+    for message in [
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        did_open(
+            EXAMPLE_URI,
+            "library example;\ntype Foo = struct { a Bar; };\n",
+        ),
+        request(2, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ] {
+        write_message(&mut input, &message).unwrap();
+    }
+    let mut output = Vec::new();
+    lsp::run(Cursor::new(input), &mut output).unwrap();
+
+    let mut reader = Cursor::new(output);
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+    assert_eq!(messages.len(), 3, "{:?}", messages);
+    assert_eq!(messages[0]["id"], 1);
+    assert_eq!(
+        messages[0]["result"]["capabilities"]["definitionProvider"],
+        true
+    );
+    assert_eq!(messages[1]["method"], "textDocument/publishDiagnostics");
+    let diagnostics = &messages[1]["params"]["diagnostics"];
+    assert_eq!(diagnostics[0]["code"], "fi-0052");
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({ "start": { "line": 1, "character": 22 }, "end": { "line": 1, "character": 25 } })
+    );
+    assert_eq!(
+        messages[2],
+        json!({ "jsonrpc": "2.0", "id": 2, "result": null })
+    );
+}
+
+#[test]
+fn bad_exit_without_shutdown() {
+    let mut input = Vec::new();
+    write_message(&mut input, &notification("exit", Value::Null)).unwrap();
+    assert!(lsp::run(Cursor::new(input), Vec::new()).is_err());
+}
+
+#[test]
+fn good_diagnostics_follow_changes() {
+    let mut server = open_example();
+    let replies = server.handle(&notification(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": EXAMPLE_URI, "version": 2 },
+            "contentChanges": [{ "text": "library fuchsia.example;\nconst C bool = 1;\n" }],
+        }),
+    ));
+    let example = replies
+        .iter()
+        .find(|r| r["params"]["uri"] == EXAMPLE_URI)
+        .unwrap();
+    assert_eq!(
+        example["params"]["diagnostics"].as_array().unwrap().len(),
+        1
+    );
+
+    let replies = server.handle(&notification(
+        "textDocument/didClose",
+        json!({ "textDocument": { "uri": EXAMPLE_URI } }),
+    ));
+    assert_eq!(
+        replies.last().unwrap()["params"],
+        json!({ "uri": EXAMPLE_URI, "diagnostics": [] })
+    );
+}
+
+#[test]
+fn good_definition() {
+    let mut server = open_example();
+    // `Color` in `const DEFAULT Color`.
+    let result = response(&mut server, position(1, "textDocument/definition", 9, 16));
+    assert_eq!(
+        result,
+        json!({
+            "uri": EXAMPLE_URI,
+            "range": { "start": { "line": 5, "character": 5 }, "end": { "line": 5, "character": 10 } },
+        })
+    );
+
+    // `Color.RED`.
+    let result = response(&mut server, position(2, "textDocument/definition", 9, 29));
+    assert_eq!(
+        result["range"]["start"],
+        json!({ "line": 6, "character": 4 })
+    );
+
+    // `Thing` in `fuchsia.dep.Thing`, in another document.
+    let result = response(&mut server, position(3, "textDocument/definition", 12, 22));
+    assert_eq!(result["uri"], DEP_URI);
+    assert_eq!(
+        result["range"]["start"],
+        json!({ "line": 2, "character": 5 })
+    );
+
+    // Whitespace.
+    let result = response(&mut server, position(4, "textDocument/definition", 1, 0));
+    assert_eq!(result, Value::Null);
+}
+
+#[test]
+fn good_references() {
+    let mut server = open_example();
+    let references = |server: &mut Server, id, line, character, include_declaration| {
+        let mut message = position(id, "textDocument/references", line, character);
+        message["params"]["context"] = json!({ "includeDeclaration": include_declaration });
+        let result = response(server, message);
+        result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| {
+                (
+                    l["uri"].as_str().unwrap().to_string(),
+                    l["range"]["start"].clone(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // From the declaration of `Color`.
+    assert_eq!(
+        references(&mut server, 1, 5, 6, true),
+        vec![
+            (
+                EXAMPLE_URI.to_string(),
+                json!({ "line": 5, "character": 5 })
+            ),
+            (
+                EXAMPLE_URI.to_string(),
+                json!({ "line": 9, "character": 14 })
+            ),
+        ]
+    );
+    // From a use of `Color.RED`.
+    assert_eq!(
+        references(&mut server, 2, 9, 25, false),
+        vec![(
+            EXAMPLE_URI.to_string(),
+            json!({ "line": 9, "character": 22 })
+        )]
+    );
+    // The library part of `fuchsia.dep.Thing` refers to the library.
+    assert_eq!(
+        references(&mut server, 3, 12, 12, false),
+        vec![
+            (
+                EXAMPLE_URI.to_string(),
+                json!({ "line": 2, "character": 6 })
+            ),
+            (
+                EXAMPLE_URI.to_string(),
+                json!({ "line": 12, "character": 10 })
+            ),
+        ]
+    );
+}
+
+#[test]
+fn good_hover() {
+    let mut server = open_example();
+    let result = response(&mut server, position(1, "textDocument/hover", 9, 16));
+    assert_eq!(
+        result["contents"]["value"],
+        "```fidl\nenum fuchsia.example/Color\n```\n\nA color.\n\n\
+         | Inline size | Alignment | Depth | Max handles | Max out-of-line |\n\
+         |---|---|---|---|---|\n\
+         | 4 | 4 | 0 | 0 | 0 |"
+    );
+    assert_eq!(
+        result["range"],
+        json!({ "start": { "line": 9, "character": 14 }, "end": { "line": 9, "character": 19 } })
+    );
+}
+
+#[test]
+fn good_completion() {
+    let mut server = open_example();
+    // After `Color.` in `const DEFAULT Color = Color.RED;`.
+    let result = response(&mut server, position(1, "textDocument/completion", 9, 28));
+    assert_eq!(
+        result,
+        json!([{ "label": "RED", "kind": 20, "detail": "enum member" }])
+    );
+}
+
+#[test]
+fn good_rename() {
+    let mut server = open_example();
+    let rename = |id, line, character, new_name: &str| {
+        let mut message = position(id, "textDocument/rename", line, character);
+        message["params"]["newName"] = json!(new_name);
+        message
+    };
+
+    // `Thing` in `fuchsia.dep.Thing`, declared in another document.
+    let result = response(&mut server, rename(1, 12, 22, "Widget"));
+    assert_eq!(
+        result["changes"][DEP_URI],
+        json!([{
+            "range": { "start": { "line": 2, "character": 5 }, "end": { "line": 2, "character": 10 } },
+            "newText": "Widget",
+        }])
+    );
+    assert_eq!(
+        result["changes"][EXAMPLE_URI][0]["range"]["start"],
+        json!({ "line": 12, "character": 22 })
+    );
+
+    // `Color` would collide with `Holder`'s canonical form.
+    let replies = server.handle(&rename(2, 5, 6, "holder"));
+    assert_eq!(replies[0]["error"]["code"], -32803);
+}
+
+#[test]
+fn good_document_symbols() {
+    let mut server = open_example();
+    let result = response(
+        &mut server,
+        request(
+            1,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": EXAMPLE_URI } }),
+        ),
+    );
+    let names: Vec<&str> = result
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["Color", "DEFAULT", "Holder"]);
+    assert_eq!(result[0]["children"][0]["name"], "RED");
+    assert_eq!(result[2]["children"][0]["name"], "thing");
+}
+
+#[test]
+fn good_semantic_tokens() {
+    let mut server = Server::new();
+    server.handle(&did_open(
+        EXAMPLE_URI,
+        "library example;\nconst A string = \"\u{e9}\"; const B bool = true;\n",
+    ));
+    let result = response(
+        &mut server,
+        request(
+            1,
+            "textDocument/semanticTokens/full",
+            json!({ "textDocument": { "uri": EXAMPLE_URI } }),
+        ),
+    );
+    assert_eq!(
+        result["data"],
+        json!([
+            0, 0, 7, 0, 0, // library
+            0, 8, 7, 2, 0, // example
+            1, 0, 5, 0, 0, // const
+            0, 6, 1, 3, 1, // A
+            0, 2, 6, 3, 0, // string
+            0, 9, 3, 9, 0, // "é", in UTF-16 code units
+            0, 5, 5, 0, 0, // const
+            0, 6, 1, 3, 1, // B
+            0, 2, 4, 3, 0, // bool
+            0, 7, 4, 0, 0, // true
+        ])
+    );
+}
+
+#[test]
+fn bad_unknown_method() {
+    let mut server = Server::new();
+    let replies = server.handle(&request(1, "workspace/unknown", json!({})));
+    assert_eq!(replies[0]["error"]["code"], -32601);
+    assert!(
+        server
+            .handle(&notification("$/unknown", json!({})))
+            .is_empty()
+    );
+}
+
+#[test]
+fn good_semantic_tokens_split_per_line() {
+    let mut server = Server::new();
+    server.handle(&did_open(
+        EXAMPLE_URI,
+        "library example;\nconst A string = \"x\ny\";\n",
+    ));
+    let result = response(
+        &mut server,
+        request(
+            1,
+            "textDocument/semanticTokens/full",
+            json!({ "textDocument": { "uri": EXAMPLE_URI } }),
+        ),
+    );
+    assert_eq!(
+        result["data"],
+        json!([
+            0, 0, 7, 0, 0, // library
+            0, 8, 7, 2, 0, // example
+            1, 0, 5, 0, 0, // const
+            0, 6, 1, 3, 1, // A
+            0, 2, 6, 3, 0, // string
+            0, 9, 2, 9, 0, // "x
+            1, 0, 2, 9, 0, // y"
+        ])
+    );
+}
+
+#[test]
+fn bad_malformed_message_gets_parse_error() {
+    let mut input = b"Content-Length: 9\r\n\r\n{not json".to_vec();
+    for message in [
+        request(1, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ] {
+        write_message(&mut input, &message).unwrap();
+    }
+    let mut output = Vec::new();
+    lsp::run(Cursor::new(input), &mut output).unwrap();
+
+    let mut reader = Cursor::new(output);
+    let parse_error = read_message(&mut reader).unwrap().unwrap();
+    assert_eq!(parse_error["id"], Value::Null);
+    assert_eq!(parse_error["error"]["code"], -32700);
+    let shutdown = read_message(&mut reader).unwrap().unwrap();
+    assert_eq!(shutdown["id"], 1);
+}
+
+#[test]
+fn good_only_imported_libraries_are_dependencies() {
+    let mut server = open_example();
+    // A library that fuchsia.example doesn't import, which doesn't compile.
+    server.handle(&did_open(
+        "file:///unrelated.fidl",
+        "library fuchsia.unrelated;\n\ntype Broken = struct {\n    a Missing;\n};\n",
+    ));
+    let result = response(&mut server, position(1, "textDocument/definition", 9, 16));
+    assert_eq!(result["uri"], EXAMPLE_URI);
+}
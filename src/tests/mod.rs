@@ -1,12 +1,17 @@
 pub mod alias_tests;
+pub mod api_tests;
 pub mod array_tests;
 pub mod attributes_tests;
 pub mod bits_tests;
 pub mod canonical_names_tests;
 pub mod cli_tests;
 pub mod compare_generation_tests;
+mod compat_tests;
+mod completion_tests;
 pub mod consts_tests;
 pub mod declaration_order_tests;
+pub mod dependency_ir_tests;
+mod deprecation_tests;
 pub mod direct_dependencies_tests;
 pub mod enums_tests;
 pub mod errcat;
@@ -16,28 +21,40 @@ pub mod errors_tests;
 pub mod experimental_flags_tests;
 pub mod flat_ast_tests;
 pub mod flexible_tests;
+pub mod formatter_tests;
 pub mod generated_name_tests;
 pub mod handle_tests;
+mod hover_tests;
+pub mod library_path_tests;
 pub mod library_tests;
+mod linter_tests;
+pub mod lossless_tests;
+mod lsp_tests;
 pub mod method_tests;
 pub mod new_type_tests;
 pub mod ordinals_tests;
+mod outline_tests;
 pub mod overlay_tests;
 pub mod parsing_tests;
+mod projection_tests;
 pub mod protocol_tests;
 pub mod recoverable_compilation_tests;
 pub mod recoverable_parsing_tests;
+mod rename_tests;
 pub mod reporter_tests;
 pub mod resource_tests;
 pub mod resourceness_tests;
 pub mod sdk_fidl;
+mod semantic_tokens_tests;
 pub mod service_tests;
+mod source_file_tests;
 pub mod span_tests;
 pub mod strictness_tests;
 pub mod string_array_tests;
 pub mod structs_tests;
 pub mod table_tests;
 pub mod test_library;
+mod timeline_tests;
 pub mod transport_tests;
 pub mod types_tests;
 pub mod typeshape_tests;
@@ -53,3 +70,4 @@ mod versioning_overlap_tests;
 mod versioning_platform_tests;
 mod versioning_replacement_tests;
 mod versioning_types_tests;
+mod xref_tests;
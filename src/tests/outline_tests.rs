@@ -0,0 +1,81 @@
+use crate::api::outline;
+use crate::outline::OutlineSymbol;
+
+/// Each symbol as its kind and name, with its children's indented below it.
+fn tree(symbol: &OutlineSymbol, depth: usize, lines: &mut Vec<String>) {
+    lines.push(format!(
+        "{}{} {}",
+        "  ".repeat(depth),
+        symbol.kind,
+        symbol.name
+    ));
+    for child in &symbol.children {
+        tree(child, depth + 1, lines);
+    }
+}
+
+#[test]
+fn good_outline() {
+    let contents = r#"library example;
+
+const MAX uint32 = 10;
+
+type Color = flexible enum {
+    RED = 1;
+};
+
+protocol Painter {
+    Paint(struct { color Color; });
+};
+
+/// A holder.
+type Holder = table {
+    1: color Color;
+    2: reserved;
+};
+
+alias Colors = vector<Color>:MAX;
+"#;
+    let outline = outline("example.fidl", contents).unwrap();
+    let mut lines = Vec::new();
+    tree(&outline, 0, &mut lines);
+    assert_eq!(
+        lines,
+        vec![
+            "library example",
+            "  const MAX",
+            "  enum Color",
+            "    member RED",
+            "  protocol Painter",
+            "    method Paint",
+            "  table Holder",
+            "    member color",
+            "  alias Colors",
+        ]
+    );
+
+    let holder = &outline.children[3];
+    assert_eq!((holder.range.start_line, holder.range.end_line), (13, 17));
+    assert_eq!(
+        (
+            holder.selection_range.start_line,
+            holder.selection_range.start_char,
+            holder.selection_range.end_char
+        ),
+        (14, 5, 11)
+    );
+}
+
+#[test]
+fn good_recovered_declarations() {
+    // The parser skips the broken declaration and keeps the rest.
+    let contents = "library example;\ntype Broken = struct {\n    a;\n};\ntype Fine = struct {};\n";
+    let outline = outline("example.fidl", contents).unwrap();
+    let names: Vec<&str> = outline.children.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["Fine"]);
+}
+
+#[test]
+fn bad_no_library() {
+    assert_eq!(outline("example.fidl", "type Foo = struct {};\n"), None);
+}
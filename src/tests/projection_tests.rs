@@ -0,0 +1,228 @@
+use std::collections::BTreeSet;
+use std::fs;
+
+use tempfile::tempdir;
+
+use crate::api::{self, CompileOptions, Sources};
+use crate::cli::{Command, ProjectArgs, run_command};
+use crate::versioning_types::{Platform, Version, VersionSelection};
+
+const SOURCE: &str = r#"@available(added=1)
+library example;
+
+/// Kept, with its doc comment.
+type Color = strict(removed=3) flexible(added=3) enum {
+    RED = 1;
+    @available(added=2)
+    GREEN = 2;
+    @available(removed=2)
+    BLUE = 3;
+};
+
+@available(deprecated=2, removed=3)
+type Old = struct {};
+
+@available(replaced=2)
+type Shape = struct {
+    x uint32;
+};
+
+@available(added=2)
+type Shape = table {
+    1: x uint32;
+    @available(added=3)
+    2: y uint32;
+};
+
+closed protocol P {
+    @available(removed=2)
+    strict Gone();
+    strict Kept(struct {
+        @available(added=2)
+        a uint32;
+    });
+};
+"#;
+
+/// `source` projected at `versions`, which are separated by commas.
+fn project(source: &str, versions: &str) -> String {
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", source);
+    let mut options = CompileOptions::default();
+    let mut selection = VersionSelection::new();
+    selection.insert(
+        Platform::parse("example").unwrap(),
+        versions
+            .split(',')
+            .map(|v| Version::parse(v).unwrap())
+            .collect::<BTreeSet<_>>(),
+    );
+    options.version_selection = selection;
+    let output = api::project(&sources, &options).unwrap();
+    assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+    let mut files = output.files.unwrap();
+    assert_eq!(files.len(), 1);
+    files.pop().unwrap().1
+}
+
+#[test]
+fn good_first_version() {
+    assert_eq!(
+        project(SOURCE, "1"),
+        r#"library example;
+
+/// Kept, with its doc comment.
+type Color = strict enum {
+    RED = 1;
+    BLUE = 3;
+};
+
+type Old = struct {};
+
+type Shape = struct {
+    x uint32;
+};
+
+closed protocol P {
+    strict Gone();
+    strict Kept(struct {});
+};
+"#
+    );
+}
+
+#[test]
+fn good_later_versions() {
+    assert_eq!(
+        project(SOURCE, "2"),
+        r#"library example;
+
+/// Kept, with its doc comment.
+type Color = strict enum {
+    RED = 1;
+    GREEN = 2;
+};
+
+type Old = struct {};
+
+type Shape = table {
+    1: x uint32;
+};
+
+closed protocol P {
+    strict Kept(struct {
+        a uint32;
+    });
+};
+"#
+    );
+    assert_eq!(
+        project(SOURCE, "HEAD"),
+        r#"library example;
+
+/// Kept, with its doc comment.
+type Color = flexible enum {
+    RED = 1;
+    GREEN = 2;
+};
+
+type Shape = table {
+    1: x uint32;
+    2: y uint32;
+};
+
+closed protocol P {
+    strict Kept(struct {
+        a uint32;
+    });
+};
+"#
+    );
+}
+
+#[test]
+fn good_renamed_member() {
+    let source = r#"@available(added=1)
+library example;
+
+type S = struct {
+    @available(removed=2, renamed="new_name")
+    old_name uint32;
+};
+"#;
+    assert!(project(source, "1").contains("old_name uint32;"));
+    // Renamed members live on under the new name in the legacy version,
+    // which selecting several versions compiles at.
+    let legacy = project(source, "1,HEAD");
+    assert!(legacy.contains("new_name uint32;"), "{}", legacy);
+    assert!(!project(source, "2").contains("_name"));
+}
+
+#[test]
+fn good_project_command() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("example.fidl");
+    fs::write(&path, SOURCE).unwrap();
+    let out_dir = dir.path().join("out");
+    let args = ProjectArgs {
+        available: vec!["example:HEAD".to_string()],
+        out_dir: Some(out_dir.to_string_lossy().to_string()),
+        files: vec![path.to_string_lossy().to_string()],
+        ..Default::default()
+    };
+    assert_eq!(run_command(&Command::Project(args)), Ok(()));
+    let projected = fs::read_to_string(out_dir.join("example.fidl")).unwrap();
+    assert_eq!(projected, project(SOURCE, "HEAD"));
+}
+
+#[test]
+fn good_project_command_keeps_directories() {
+    let dir = tempdir().unwrap();
+    let files: Vec<String> = ["a", "b"]
+        .iter()
+        .map(|sub| {
+            let path = dir.path().join("src").join(sub).join("example.fidl");
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(
+                &path,
+                format!(
+                    "library example;\n\ntype {} = struct {{}};\n",
+                    sub.to_uppercase()
+                ),
+            )
+            .unwrap();
+            path.to_string_lossy().to_string()
+        })
+        .collect();
+    let out_dir = dir.path().join("out");
+    let args = ProjectArgs {
+        available: vec!["example:HEAD".to_string()],
+        out_dir: Some(out_dir.to_string_lossy().to_string()),
+        files,
+        ..Default::default()
+    };
+    assert_eq!(run_command(&Command::Project(args)), Ok(()));
+    let a = fs::read_to_string(out_dir.join("a/example.fidl")).unwrap();
+    let b = fs::read_to_string(out_dir.join("b/example.fidl")).unwrap();
+    assert!(a.contains("type A = struct"), "{}", a);
+    assert!(b.contains("type B = struct"), "{}", b);
+}
+
+#[test]
+fn bad_project_command_conflicting_out_paths() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("example.fidl");
+    fs::write(&path, "library example;\n").unwrap();
+    let dotted = dir.path().join(".").join("example.fidl");
+    let args = ProjectArgs {
+        available: vec!["example:HEAD".to_string()],
+        out_dir: Some(dir.path().join("out").to_string_lossy().to_string()),
+        files: vec![
+            path.to_string_lossy().to_string(),
+            dotted.to_string_lossy().to_string(),
+        ],
+        ..Default::default()
+    };
+    let result = run_command(&Command::Project(args));
+    assert!(result.is_err_and(|e| e.contains("would be written to example.fidl")));
+}
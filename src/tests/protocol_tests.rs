@@ -769,6 +769,30 @@ fn bad_cannot_mutually_compose() {
     assert!(library.check_compile());
 }
 
+#[test]
+fn bad_mutually_composed_has_no_optional_note() {
+    let mut library = TestLibrary::new();
+    library.add_source_file(
+        "example.fidl",
+        r#"
+library example;
+
+protocol A {
+    compose B;
+};
+
+protocol B {
+    compose A;
+};
+"#,
+    );
+    library.expect_fail(Error::ErrIncludeCycle(
+        "protocol 'A' -> protocol 'B' -> protocol 'A'".into(),
+    ));
+    assert!(library.check_compile());
+    assert!(library.reporter().diagnostics()[0].notes.is_empty());
+}
+
 #[test]
 #[ignore]
 fn bad_cannot_compose_same_protocol_twice() {
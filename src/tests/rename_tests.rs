@@ -0,0 +1,164 @@
+use crate::api::{CompileOptions, RenameOutput, Sources, rename};
+
+const EXAMPLE: &str = r#"library example;
+
+type Color = strict enum {
+    RED = 1;
+    GREEN = 2;
+};
+
+const DEFAULT Color = Color.RED;
+
+type Holder = struct {
+    color Color;
+};
+
+protocol Base {};
+
+protocol Derived {
+    compose Base;
+};
+"#;
+
+fn rename_in(sources: &Sources, name: &str, new_name: &str) -> RenameOutput {
+    rename(sources, &[], &CompileOptions::default(), name, new_name).unwrap()
+}
+
+fn example() -> Sources {
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", EXAMPLE);
+    sources
+}
+
+/// Each edit as its 1-based line and 0-based character.
+fn positions(output: &RenameOutput) -> Vec<(usize, usize)> {
+    assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+    output
+        .edits
+        .iter()
+        .map(|e| (e.location.start_line, e.location.start_char))
+        .collect()
+}
+
+#[test]
+fn good_declaration() {
+    let output = rename_in(&example(), "example/Color", "Colour");
+    // The declaration, `DEFAULT Color`, `Color.RED` and `color Color`.
+    assert_eq!(positions(&output), vec![(3, 5), (8, 14), (8, 22), (11, 10)]);
+    assert!(output.edits.iter().all(|e| e.new_text == "Colour"));
+    assert_eq!(output.edits[2].location.end_char, 27);
+}
+
+#[test]
+fn good_member() {
+    let output = rename_in(&example(), "example/Color.RED", "CRIMSON");
+    assert_eq!(positions(&output), vec![(4, 4), (8, 28)]);
+}
+
+#[test]
+fn good_compose() {
+    let output = rename_in(&example(), "example/Base", "Root");
+    assert_eq!(positions(&output), vec![(14, 9), (17, 12)]);
+}
+
+#[test]
+fn good_dependent_library() {
+    let mut sources = Sources::new();
+    sources.add_dependency_file("dep.fidl", "library dep;\n\ntype Thing = struct {};\n");
+    sources.add_file(
+        "example.fidl",
+        "library example;\n\nusing dep;\n\ntype Holder = struct {\n    thing dep.Thing;\n};\n",
+    );
+    let output = rename_in(&sources, "dep/Thing", "Widget");
+    let edits: Vec<_> = output
+        .edits
+        .iter()
+        .map(|e| {
+            (
+                e.location.filename.as_str(),
+                e.location.start_line,
+                e.location.start_char,
+            )
+        })
+        .collect();
+    assert_eq!(edits, vec![("dep.fidl", 3, 5), ("example.fidl", 6, 14)]);
+}
+
+#[test]
+fn good_dependents() {
+    const DEP: &str = "library dep;\n\ntype Thing = struct {};\n";
+    let mut dep = Sources::new();
+    dep.add_file("dep.fidl", DEP);
+    let mut example = Sources::new();
+    example.add_dependency_file("dep.fidl", DEP);
+    example.add_file(
+        "example.fidl",
+        "library example;\n\nusing dep;\n\ntype Holder = struct {\n    thing dep.Thing;\n};\n",
+    );
+    let mut unrelated = Sources::new();
+    unrelated.add_file("other.fidl", "library other;\n\ntype Thing = struct {};\n");
+
+    let output = rename(
+        &dep,
+        &[example, unrelated],
+        &CompileOptions::default(),
+        "dep/Thing",
+        "Widget",
+    )
+    .unwrap();
+    let edits: Vec<_> = output
+        .edits
+        .iter()
+        .map(|e| {
+            (
+                e.location.filename.as_str(),
+                e.location.start_line,
+                e.location.start_char,
+            )
+        })
+        .collect();
+    // The declaration is edited once, though both libraries compile it.
+    assert_eq!(edits, vec![("dep.fidl", 3, 5), ("example.fidl", 6, 14)]);
+}
+
+#[test]
+fn bad_canonical_collision() {
+    let output = rename_in(&example(), "example/Color.RED", "green");
+    assert!(output.edits.is_empty());
+    assert_eq!(output.diagnostics.len(), 1);
+    let diagnostic = &output.diagnostics[0];
+    assert_eq!(diagnostic.id(), "fi-0035");
+    assert_eq!(
+        diagnostic.notes,
+        vec!["'green' and 'GREEN' both have the canonical form 'green'"]
+    );
+    assert_eq!(diagnostic.labels[0].1.start_line, 5);
+}
+
+#[test]
+fn bad_collision() {
+    let output = rename_in(&example(), "example/Holder", "Derived");
+    assert!(output.edits.is_empty());
+    assert_eq!(output.diagnostics[0].id(), "fi-0034");
+}
+
+#[test]
+fn bad_invalid_identifier() {
+    let output = rename_in(&example(), "example/Color", "Not_");
+    assert!(output.edits.is_empty());
+    assert_eq!(output.diagnostics[0].id(), "fi-0010");
+}
+
+#[test]
+fn bad_unknown_name() {
+    assert!(
+        rename(
+            &example(),
+            &[],
+            &CompileOptions::default(),
+            "example/Nope",
+            "Yes"
+        )
+        .is_err()
+    );
+}
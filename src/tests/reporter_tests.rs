@@ -1,4 +1,4 @@
-use crate::diagnostics::Error;
+use crate::diagnostics::{Error, Label};
 use crate::reporter::Reporter;
 use crate::source_file::SourceFile;
 use crate::source_span::SourceSpan;
@@ -92,3 +92,107 @@ fn report_error_with_reused_format_params() {
     assert_eq!(errors.len(), 1);
     // Supposed to assert positional swaps, etc.
 }
+
+#[test]
+fn report_json_format() {
+    let reporter = Reporter::new();
+    let file = SourceFile::new("fake.fidl".to_string(), "library x;\nspan text".to_string());
+    let span = SourceSpan::new(&file.data()[11..15], &file);
+
+    reporter.fail(Error::ErrInvalidCharacter("param1".into()), span);
+    reporter.fail(
+        Error::WarnAttributeTypo("param1".into(), "param2".into()),
+        span,
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&reporter.reports_json()).unwrap();
+    let diags = json.as_array().unwrap();
+    assert_eq!(diags.len(), 2);
+    assert_eq!(diags[0]["category"], "fidlc/error");
+    assert_eq!(diags[0]["error_id"], "fi-0001");
+    assert!(diags[0]["message"].as_str().unwrap().contains("param1"));
+    assert_eq!(diags[0]["path"], "fake.fidl");
+    assert_eq!(diags[0]["start_line"], 2);
+    assert_eq!(diags[0]["start_char"], 0);
+    assert_eq!(diags[0]["end_line"], 2);
+    assert_eq!(diags[0]["end_char"], 4);
+    assert_eq!(diags[0]["start_offset"], 11);
+    assert_eq!(diags[0]["end_offset"], 15);
+    assert_eq!(diags[1]["category"], "fidlc/warning");
+}
+
+#[test]
+fn report_text_format_with_squiggle() {
+    let reporter = Reporter::new();
+    let file = SourceFile::new(
+        "fake.fidl".to_string(),
+        "library x;\n\tconst FOO bar;\n".to_string(),
+    );
+    let span = SourceSpan::new(&file.data()[18..21], &file);
+    reporter.fail(Error::ErrInvalidCharacter("param1".into()), span);
+
+    let text = reporter.reports_text(false);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines[0],
+        "fake.fidl:2:8: error[fi-0001]: invalid character 'param1'"
+    );
+    assert_eq!(lines[1], "\tconst FOO bar;");
+    assert_eq!(lines[2], "\t      ^~~");
+
+    let colored = reporter.reports_text(true);
+    assert!(colored.contains("\x1b[1;31merror[fi-0001]"));
+    assert!(colored.contains("\x1b[1;32m\t      ^~~\x1b[0m"));
+}
+
+#[test]
+fn report_labels_and_notes() {
+    let reporter = Reporter::new();
+    let file = SourceFile::new(
+        "fake.fidl".to_string(),
+        "library x;\ntype Foo = struct {};\ntype Foo = table {};\n".to_string(),
+    );
+    let prev = SourceSpan::new(&file.data()[16..19], &file);
+    let span = SourceSpan::new(&file.data()[38..41], &file);
+    reporter.fail_with(
+        Error::ErrInvalidCharacter("param1".into()),
+        span,
+        vec![Label::new(prev, "previous declaration here")],
+        vec!["some note".to_string()],
+    );
+
+    let text = reporter.reports_text(false);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines[0],
+        "fake.fidl:3:6: error[fi-0001]: invalid character 'param1'"
+    );
+    assert_eq!(lines[1], "type Foo = table {};");
+    assert_eq!(lines[2], "     ^~~");
+    assert_eq!(lines[3], "fake.fidl:2:6: note: previous declaration here");
+    assert_eq!(lines[4], "type Foo = struct {};");
+    assert_eq!(lines[5], "     ^~~");
+    assert_eq!(lines[6], "  = note: some note");
+
+    let json: serde_json::Value = serde_json::from_str(&reporter.reports_json()).unwrap();
+    let diag = &json.as_array().unwrap()[0];
+    assert_eq!(diag["labels"][0]["message"], "previous declaration here");
+    assert_eq!(diag["labels"][0]["path"], "fake.fidl");
+    assert_eq!(diag["labels"][0]["start_line"], 2);
+    assert_eq!(diag["labels"][0]["start_char"], 5);
+    assert_eq!(diag["labels"][0]["end_char"], 8);
+    assert_eq!(diag["notes"][0], "some note");
+}
+
+#[test]
+fn report_json_omits_empty_labels_and_notes() {
+    let reporter = Reporter::new();
+    let file = SourceFile::new("fake.fidl".to_string(), "span text".to_string());
+    let span = SourceSpan::new(file.data(), &file);
+    reporter.fail(Error::ErrInvalidCharacter("param1".into()), span);
+
+    let json: serde_json::Value = serde_json::from_str(&reporter.reports_json()).unwrap();
+    let diag = &json.as_array().unwrap()[0];
+    assert!(diag.get("labels").is_none());
+    assert!(diag.get("notes").is_none());
+}
@@ -0,0 +1,102 @@
+use crate::api::semantic_tokens;
+use crate::semantic_tokens::SemanticTokenKind::{self, *};
+
+/// The text and kind of each token that isn't punctuation.
+fn classes(contents: &str) -> Vec<(String, SemanticTokenKind)> {
+    semantic_tokens("example.fidl", contents)
+        .into_iter()
+        .filter(|t| t.kind != Punctuation)
+        .map(|t| {
+            let line = contents.lines().nth(t.location.start_line - 1).unwrap();
+            (
+                line[t.location.start_char..t.location.end_char].to_string(),
+                t.kind,
+            )
+        })
+        .collect()
+}
+
+fn class(contents: &str, text: &str) -> SemanticTokenKind {
+    classes(contents)
+        .into_iter()
+        .find(|(t, _)| t == text)
+        .unwrap_or_else(|| panic!("no token {:?}", text))
+        .1
+}
+
+#[test]
+fn good_declarations() {
+    let contents = r#"library example;
+
+using dep as d;
+
+/// A thing.
+@available(added=1)
+type Thing = strict resource struct {
+    a vector<d.Other>:MAX;
+    struct uint32 = 5;
+};
+
+protocol P {
+    compose d.Base;
+    flexible Get() -> (Thing);
+};
+"#;
+    assert_eq!(
+        classes(contents)[..6],
+        [
+            ("library".to_string(), Keyword),
+            ("example".to_string(), Library),
+            ("using".to_string(), Keyword),
+            ("dep".to_string(), Library),
+            ("as".to_string(), Keyword),
+            ("d".to_string(), Library),
+        ]
+    );
+    assert_eq!(class(contents, "/// A thing."), DocComment);
+    assert_eq!(class(contents, "available"), Attribute);
+    assert_eq!(class(contents, "added"), Attribute);
+    assert_eq!(class(contents, "1"), NumericLiteral);
+    assert_eq!(class(contents, "Thing"), DeclarationName);
+    assert_eq!(class(contents, "strict"), Modifier);
+    assert_eq!(class(contents, "resource"), Modifier);
+    assert_eq!(class(contents, "a"), MemberName);
+    assert_eq!(class(contents, "vector"), TypeReference);
+    assert_eq!(class(contents, "Other"), TypeReference);
+    assert_eq!(class(contents, "MAX"), ConstantReference);
+    // `struct` as a member name, not the layout keyword before it.
+    let structs: Vec<_> = classes(contents)
+        .into_iter()
+        .filter(|(t, _)| t == "struct")
+        .map(|(_, kind)| kind)
+        .collect();
+    assert_eq!(structs, vec![Keyword, MemberName]);
+    assert_eq!(class(contents, "compose"), Keyword);
+    assert_eq!(class(contents, "Base"), TypeReference);
+    assert_eq!(class(contents, "flexible"), Modifier);
+    assert_eq!(class(contents, "Get"), MemberName);
+}
+
+#[test]
+fn good_constants() {
+    let contents =
+        "library example;\nconst A string = \"a\";\nconst B bool = true;\nconst C uint32 = A;\n";
+    assert_eq!(class(contents, "\"a\""), StringLiteral);
+    assert_eq!(class(contents, "true"), Keyword);
+    assert_eq!(class(contents, "string"), TypeReference);
+    let a: Vec<_> = classes(contents)
+        .into_iter()
+        .filter(|(t, _)| t == "A")
+        .map(|(_, kind)| kind)
+        .collect();
+    assert_eq!(a, vec![DeclarationName, ConstantReference]);
+}
+
+#[test]
+fn good_without_parse() {
+    // Identifiers are classed by their subkind when the file doesn't parse.
+    let contents = "library example;\ntype Foo = strict struct {\n";
+    assert_eq!(class(contents, "type"), Keyword);
+    assert_eq!(class(contents, "strict"), Modifier);
+    assert_eq!(class(contents, "Foo"), Identifier);
+}
@@ -0,0 +1,73 @@
+use crate::source_file::{ColumnUnit, Position, SourceFile};
+use crate::source_span::SourceSpan;
+
+fn position(line: usize, column: usize) -> Option<Position> {
+    Some(Position { line, column })
+}
+
+#[test]
+fn good_multibyte_columns() {
+    // `é` is two bytes and one UTF-16 code unit; `𝄞` is four bytes and two
+    // UTF-16 code units.
+    let file = SourceFile::new("f".to_string(), "a\nxé𝄞y\n".to_string());
+    let y = file.data().find('y').unwrap();
+    assert_eq!(file.position_at(y, ColumnUnit::Byte), position(2, 8));
+    assert_eq!(file.position_at(y, ColumnUnit::Char), position(2, 4));
+    assert_eq!(file.position_at(y, ColumnUnit::Utf16), position(2, 5));
+    assert_eq!(file.offset_at(2, 8, ColumnUnit::Byte), Some(y));
+    assert_eq!(file.offset_at(2, 4, ColumnUnit::Char), Some(y));
+    assert_eq!(file.offset_at(2, 5, ColumnUnit::Utf16), Some(y));
+
+    // Inside a character.
+    assert_eq!(file.position_at(y - 1, ColumnUnit::Byte), None);
+    assert_eq!(file.offset_at(2, 4, ColumnUnit::Utf16), Some(y - 4));
+}
+
+#[test]
+fn good_line_ends() {
+    let file = SourceFile::new("f".to_string(), "ab\n\ncd\n".to_string());
+    assert_eq!(file.position_at(2, ColumnUnit::Byte), position(1, 3));
+    assert_eq!(file.position_at(3, ColumnUnit::Byte), position(2, 1));
+    assert_eq!(file.position_at(4, ColumnUnit::Utf16), position(3, 1));
+    // The end of the file is on the empty line after the final newline.
+    assert_eq!(file.position_at(7, ColumnUnit::Utf16), position(4, 1));
+    assert_eq!(file.position_at(8, ColumnUnit::Utf16), None);
+
+    // Columns past the end of a line clamp to it.
+    assert_eq!(file.offset_at(1, 10, ColumnUnit::Utf16), Some(2));
+    assert_eq!(file.offset_at(4, 1, ColumnUnit::Utf16), Some(7));
+    assert_eq!(file.offset_at(5, 1, ColumnUnit::Utf16), None);
+    assert_eq!(file.offset_at(0, 1, ColumnUnit::Utf16), None);
+}
+
+#[test]
+fn good_no_final_newline() {
+    let file = SourceFile::new("f".to_string(), "ab\ncd".to_string());
+    assert_eq!(file.position_at(5, ColumnUnit::Char), position(2, 3));
+    assert_eq!(file.offset_at(3, 1, ColumnUnit::Char), None);
+}
+
+#[test]
+fn good_empty_file() {
+    let file = SourceFile::new("f".to_string(), String::new());
+    assert_eq!(file.position_at(0, ColumnUnit::Utf16), position(1, 1));
+    assert_eq!(file.offset_at(1, 1, ColumnUnit::Utf16), Some(0));
+    let span = SourceSpan::new(file.data(), &file);
+    assert_eq!(span.position(), Position { line: 1, column: 1 });
+}
+
+#[test]
+fn good_line_containing_many_lines() {
+    let data: String = (0..1000).map(|i| format!("line {}\n", i)).collect();
+    let file = SourceFile::new("f".to_string(), data);
+    let start = file.data().find("line 637").unwrap();
+    let span = SourceSpan::new(&file.data()[start + 5..start + 8], &file);
+    assert_eq!(
+        span.position(),
+        Position {
+            line: 638,
+            column: 6
+        }
+    );
+    assert_eq!(file.line_containing(span.data).unwrap().0, "line 637");
+}
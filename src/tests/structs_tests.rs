@@ -372,6 +372,28 @@ type MySelf = struct {
     assert!(lib.check_compile());
 }
 
+#[test]
+fn bad_recursive_suggests_optional() {
+    let mut lib = TestLibrary::new();
+    lib.add_source_file(
+        "example.fidl",
+        r#"
+library example;
+
+type MySelf = struct {
+    me MySelf;
+};
+"#,
+    );
+    assert!(lib.compile().is_err());
+    let diagnostics = lib.reporter().diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].notes,
+        vec!["make one of the references optional or boxed to break the cycle".to_string()]
+    );
+}
+
 #[test]
 fn good_recursive_optional_vector() {
     let mut lib = TestLibrary::new();
@@ -93,6 +93,7 @@ pub struct TestLibrary<'a> {
     reporter: Reporter<'a>,
     source_files: Vec<SourceFile>,
     dependency_files: Vec<SourceFile>,
+    dependency_irs: Vec<serde_json::Value>,
     #[allow(dead_code)]
     generated_source_file: VirtualSourceFile,
     pub experimental_flags: Vec<String>,
@@ -114,6 +115,7 @@ impl<'a> TestLibrary<'a> {
             reporter: Reporter::new(),
             source_files: Vec::new(),
             dependency_files: Vec::new(),
+            dependency_irs: Vec::new(),
             generated_source_file: VirtualSourceFile::new("generated".to_string()),
             experimental_flags: Vec::new(),
             select_versions: Vec::new(),
@@ -164,6 +166,10 @@ impl<'a> TestLibrary<'a> {
             .push(SourceFile::new(filename.to_string(), contents.to_string()));
     }
 
+    pub fn add_dependency_ir(&mut self, ir: serde_json::Value) {
+        self.dependency_irs.push(ir);
+    }
+
     pub fn add_attribute_schema(&mut self, name: &str, schema: AttributeSchema) {
         self.custom_schemas.insert(name.to_string(), schema);
     }
@@ -295,6 +301,9 @@ resource_definition handle : uint32 {
             .attribute_schemas
             .schemas
             .extend(self.custom_schemas.clone());
+        for ir in &self.dependency_irs {
+            compiler.add_dependency_ir(ir)?;
+        }
         let mut main_asts = Vec::new();
 
         for file in &self.source_files {
@@ -302,8 +311,6 @@ resource_definition handle : uint32 {
             let mut parser = Parser::new(&mut lexer, &self.reporter);
             if let Some(ast) = parser.parse_file() {
                 main_asts.push(ast);
-            } else {
-                return Err("Parsing failed".to_string());
             }
         }
 
@@ -313,8 +320,6 @@ resource_definition handle : uint32 {
             let mut parser = Parser::new(&mut lexer, &self.reporter);
             if let Some(ast) = parser.parse_file() {
                 dep_asts.push(ast);
-            } else {
-                return Err("Parsing failed".to_string());
             }
         }
 
@@ -0,0 +1,231 @@
+use std::fs;
+
+use tempfile::tempdir;
+
+use crate::api::{self, CompileOptions, Sources};
+use crate::cli::{Command, TimelineArgs, run_command};
+use crate::reporter::format_diagnostic;
+use crate::source_file::SourceFile;
+use crate::timeline::{Timeline, TimelineProblem};
+
+fn timeline(source: &str) -> Timeline {
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", source);
+    api::timeline(&sources, &CompileOptions::default())
+        .unwrap()
+        .timeline
+}
+
+/// Each element as `name added deprecated removed replaced`, with `-` for
+/// versions that aren't set.
+fn rows(timeline: &Timeline) -> Vec<String> {
+    let cell = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+    timeline
+        .elements
+        .iter()
+        .map(|e| {
+            format!(
+                "{} {} {} {} {}",
+                e.name,
+                cell(&e.added),
+                cell(&e.deprecated),
+                cell(&e.removed),
+                cell(&e.replaced)
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn good_inherited_availability() {
+    let timeline = timeline(
+        r#"@available(added=1)
+library example;
+
+@available(added=2, deprecated=3, removed=4, note="use Bar")
+type Foo = struct {
+    a uint32;
+    @available(added=3)
+    b uint32;
+};
+
+closed protocol P {
+    @available(deprecated=2, removed=3)
+    strict M();
+};
+"#,
+    );
+    assert_eq!(timeline.library, "example");
+    assert_eq!(timeline.platform.as_deref(), Some("example"));
+    assert_eq!(
+        rows(&timeline),
+        vec![
+            "example/Foo 2 3 4 -",
+            "example/Foo.a 2 3 4 -",
+            "example/Foo.b 3 3 4 -",
+            "example/P 1 - - -",
+            "example/P.M 1 2 3 -",
+        ]
+    );
+    assert_eq!(timeline.elements[0].note.as_deref(), Some("use Bar"));
+    assert_eq!(timeline.elements[1].note, None);
+    assert_eq!(timeline.problems().count(), 0);
+}
+
+#[test]
+fn good_replaced_and_reused_names() {
+    let timeline = timeline(
+        r#"@available(added=1)
+library example;
+
+@available(replaced=2)
+type Foo = struct {};
+
+@available(added=2)
+type Foo = table {
+    @available(removed=3)
+    1: a uint32;
+};
+"#,
+    );
+    assert_eq!(
+        rows(&timeline),
+        vec![
+            "example/Foo 1 - - 2",
+            "example/Foo 2 - - -",
+            "example/Foo.a 2 - 3 -",
+        ]
+    );
+    let problems: Vec<(&str, &[TimelineProblem])> = timeline
+        .problems()
+        .map(|e| (e.name.as_str(), e.problems.as_slice()))
+        .collect();
+    assert_eq!(
+        problems,
+        vec![(
+            "example/Foo.a",
+            &[TimelineProblem::RemovedWithoutDeprecation][..]
+        )]
+    );
+}
+
+#[test]
+fn bad_replaced_without_addition() {
+    let mut sources = Sources::new();
+    sources.add_file(
+        "example.fidl",
+        r#"@available(added=1)
+library example;
+
+@available(replaced=2)
+type Foo = struct {};
+"#,
+    );
+    let output = api::timeline(&sources, &CompileOptions::default()).unwrap();
+    // The compiler reports it too, but the timeline is still complete.
+    assert!(!output.diagnostics.is_empty());
+    let foo = &output.timeline.elements[0];
+    assert_eq!(foo.name, "example/Foo");
+    assert_eq!(foo.problems, vec![TimelineProblem::ReplacedWithoutAddition]);
+}
+
+#[test]
+fn good_unversioned() {
+    let timeline = timeline(
+        r#"library example;
+
+type Foo = struct {
+    a uint32;
+};
+"#,
+    );
+    assert_eq!(timeline.platform, None);
+    assert_eq!(
+        rows(&timeline),
+        vec!["example/Foo - - - -", "example/Foo.a - - - -"]
+    );
+}
+
+#[test]
+fn good_markdown() {
+    let timeline = timeline(
+        r#"@available(added=1)
+library example;
+
+@available(removed=2, note="gone | for good")
+const C uint32 = 1;
+"#,
+    );
+    assert_eq!(
+        timeline.markdown(),
+        "# Availability of `example`
+
+Platform: `example`
+
+| Name | Kind | Added | Deprecated | Removed | Replaced | Note |
+|---|---|---|---|---|---|---|
+| `example/C` | const | 1 |  | 2 |  | gone \\| for good |
+
+## Problems
+
+- `example/C`: removed without being deprecated
+"
+    );
+}
+
+#[test]
+fn bad_timeline_command_with_errors() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("example.fidl");
+    fs::write(
+        &path,
+        r#"@available(added=1)
+library example;
+
+type Foo = struct {
+    a Missing;
+};
+"#,
+    )
+    .unwrap();
+    let args = TimelineArgs {
+        format: "markdown".to_string(),
+        files: vec![path.to_string_lossy().to_string()],
+        ..Default::default()
+    };
+    assert_eq!(
+        run_command(&Command::Timeline(args)),
+        Err("Compilation failed".to_string())
+    );
+}
+
+#[test]
+fn good_diagnostics_render_like_the_reporter() {
+    let mut sources = Sources::new();
+    let source = r#"@available(added=1)
+library example;
+
+@available(replaced=2)
+type Foo = struct {};
+"#;
+    sources.add_file("example.fidl", source);
+    let output = api::timeline(&sources, &CompileOptions::default()).unwrap();
+    let source_files = [SourceFile::new(
+        "example.fidl".to_string(),
+        source.to_string(),
+    )];
+    let rendered = format_diagnostic(&output.diagnostics[0].to_reported(&source_files), false);
+    assert!(
+        rendered.starts_with(&format!(
+            "example.fidl:5:6: error[{}]: ",
+            output.diagnostics[0].id()
+        )),
+        "{}",
+        rendered
+    );
+    assert!(
+        rendered.contains("\ntype Foo = struct {};\n     ^~~"),
+        "{}",
+        rendered
+    );
+}
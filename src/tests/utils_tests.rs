@@ -1,31 +1,10 @@
+use crate::utils::{
+    is_lower_camel_case, is_lower_snake_case, is_upper_camel_case, is_upper_snake_case,
+    split_identifier_words, to_lower_camel_case, to_lower_snake_case, to_upper_camel_case,
+    to_upper_snake_case,
+};
+
 // Dummy implementations to allow test compilation
-fn split_identifier_words(_s: &str) -> Vec<String> {
-    unimplemented!()
-}
-fn is_upper_camel_case(_s: &str) -> bool {
-    unimplemented!()
-}
-fn to_upper_camel_case(_s: &str) -> String {
-    unimplemented!()
-}
-fn is_lower_camel_case(_s: &str) -> bool {
-    unimplemented!()
-}
-fn to_lower_camel_case(_s: &str) -> String {
-    unimplemented!()
-}
-fn is_upper_snake_case(_s: &str) -> bool {
-    unimplemented!()
-}
-fn to_upper_snake_case(_s: &str) -> String {
-    unimplemented!()
-}
-fn is_lower_snake_case(_s: &str) -> bool {
-    unimplemented!()
-}
-fn to_lower_snake_case(_s: &str) -> String {
-    unimplemented!()
-}
 fn is_valid_library_component(_s: &str) -> bool {
     unimplemented!()
 }
@@ -55,7 +34,6 @@ fn string_literal_length(_s: &str) -> usize {
 }
 
 #[test]
-#[ignore]
 fn test_id_to_words() {
     assert_eq!(
         split_identifier_words("agent_request_count").join(" "),
@@ -114,7 +92,6 @@ fn test_id_to_words() {
 }
 
 #[test]
-#[ignore]
 fn test_upper_camel_case() {
     // From: "x", To: "X"
     assert_eq!(to_upper_camel_case("x"), "X");
@@ -229,7 +206,6 @@ fn test_upper_camel_case() {
 }
 
 #[test]
-#[ignore]
 fn test_lower_camel_case() {
     // From: "X", To: "x"
     assert_eq!(to_lower_camel_case("X"), "x");
@@ -345,7 +321,6 @@ fn test_lower_camel_case() {
 }
 
 #[test]
-#[ignore]
 fn test_upper_snake_case() {
     // From: "x", To: "X"
     assert_eq!(to_upper_snake_case("x"), "X");
@@ -458,7 +433,6 @@ fn test_upper_snake_case() {
 }
 
 #[test]
-#[ignore]
 fn test_lower_snake_case() {
     // From: "X", To: "x"
     assert_eq!(to_lower_snake_case("X"), "x");
@@ -1,19 +1,121 @@
-#![allow(unused_imports, dead_code)]
+use crate::api::{self, CompileOptions, Sources};
+use crate::decomposition::{Decomposition, json_declarations};
+use crate::json_generator::JsonRoot;
 use crate::tests::test_library::TestLibrary;
+use crate::versioning_types::{Platform, Version, VersionSelection};
+use serde_json::Value;
 
-fn assert_equivalent(left_fidl: &str, right_fidl: &str, versions_str: &str) {
-    // left_fidl and right_fidl generate same JSON after scrubbing platform/locations/declaration_order
-    let mut left_lib = TestLibrary::new();
-    left_lib.add_source_file("example.fidl", left_fidl);
-    left_lib.select_version("example", versions_str);
-    left_lib.compile().unwrap();
+/// `fidl` decomposed once, with nothing selected.
+struct Decomposed<'a> {
+    fidl: &'a str,
+    decomposition: Decomposition,
+}
+
+#[track_caller]
+fn decompose<'a>(fidl: &'a str) -> Decomposed<'a> {
+    Decomposed {
+        fidl,
+        decomposition: decompose_with(fidl, &VersionSelection::new()),
+    }
+}
+
+#[track_caller]
+fn decompose_with(fidl: &str, selection: &VersionSelection) -> Decomposition {
+    let mut sources = Sources::new();
+    sources.add_file("example.fidl", fidl);
+    let options = CompileOptions {
+        version_selection: selection.clone(),
+        ..Default::default()
+    };
+    let output = api::decompose(&sources, &options).unwrap();
+    output
+        .decomposition
+        .unwrap_or_else(|| panic!("decomposition failed: {:?}", output.diagnostics))
+}
+
+/// Asserts that `left`, selected at `versions_str`, has the same declarations
+/// as `right_fidl` compiled at `versions_str`. Locations, `@available`
+/// attributes and declaration order don't count.
+///
+/// Several versions can't be selected from `left`, which wasn't decomposed
+/// with them; they are compared with the `LEGACY` range of the same source
+/// decomposed with them selected.
+#[track_caller]
+fn assert_equivalent(left: &Decomposed<'_>, right_fidl: &str, versions_str: &str) {
+    let mut selection = VersionSelection::new();
+    selection.insert(
+        Platform::parse("example").unwrap(),
+        versions_str
+            .split(',')
+            .map(|v| Version::parse(v).unwrap())
+            .collect(),
+    );
+    let selected = left.decomposition.select(&selection);
+    let legacy;
+    let selected = if versions_str.contains(',') && left.decomposition.platform().is_some() {
+        assert!(
+            selected.is_err(),
+            "selected {} from one decomposition",
+            versions_str
+        );
+        legacy = decompose_with(left.fidl, &selection);
+        legacy.select(&selection).unwrap()
+    } else {
+        selected.unwrap()
+    };
+    let left: Vec<(String, Value)> = selected
+        .into_iter()
+        .map(|d| (d.name.clone(), scrub(d.json.clone())))
+        .collect();
 
     let mut right_lib = TestLibrary::new();
     right_lib.add_source_file("example.fidl", right_fidl);
     right_lib.select_version("example", versions_str);
-    right_lib.compile().unwrap();
+    let root = right_lib.compile().unwrap();
+    let mut right: Vec<(String, Value)> = json_declarations(&JsonRoot::from(&root))
+        .into_iter()
+        .map(|(name, _, json)| (name, scrub(json)))
+        .collect();
+    right.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for l in &left {
+        if !right.contains(l) {}
+    }
+    for r in &right {
+        if !left.contains(r) {}
+    }
+    assert_eq!(left, right, "at version {}", versions_str);
+}
 
-    // Ignore JSON equivalence for now since scrubbing logic isn't ported
+fn scrub(json: Value) -> Value {
+    match json {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .filter(|(key, _)| key != "location")
+                .filter_map(|(key, value)| {
+                    let value = if key == "maybe_attributes" {
+                        let attributes: Vec<Value> = value
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .filter(|a| a["name"] != "available")
+                            .cloned()
+                            .collect();
+                        if attributes.is_empty() {
+                            return None;
+                        }
+                        Value::Array(attributes)
+                    } else {
+                        value
+                    };
+                    Some((key, scrub(value)))
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(scrub).collect()),
+        other => other,
+    }
 }
 
 #[test]
@@ -23,11 +125,13 @@ fn equivalenttoself() {
 library example;
 "#;
 
-    assert_equivalent(&fidl, &fidl, "1");
-    assert_equivalent(&fidl, &fidl, "2");
-    assert_equivalent(&fidl, &fidl, "HEAD");
-    assert_equivalent(&fidl, &fidl, "1,HEAD");
-    assert_equivalent(&fidl, &fidl, "1,2,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &fidl, "1");
+    assert_equivalent(&decomposed, &fidl, "2");
+    assert_equivalent(&decomposed, &fidl, "HEAD");
+    assert_equivalent(&decomposed, &fidl, "1,HEAD");
+    assert_equivalent(&decomposed, &fidl, "1,2,HEAD");
 }
 
 #[test]
@@ -45,11 +149,13 @@ library example;
 type Foo = struct {};
 "#;
 
-    assert_equivalent(&unversioned, &versioned, "1");
-    assert_equivalent(&unversioned, &versioned, "2");
-    assert_equivalent(&unversioned, &versioned, "HEAD");
-    assert_equivalent(&unversioned, &versioned, "1,HEAD");
-    assert_equivalent(&unversioned, &versioned, "1,2,HEAD");
+    let decomposed = decompose(&unversioned);
+
+    assert_equivalent(&decomposed, &versioned, "1");
+    assert_equivalent(&decomposed, &versioned, "2");
+    assert_equivalent(&decomposed, &versioned, "HEAD");
+    assert_equivalent(&decomposed, &versioned, "1,HEAD");
+    assert_equivalent(&decomposed, &versioned, "1,2,HEAD");
 }
 
 #[test]
@@ -78,12 +184,14 @@ type Foo = struct {};
 library example;
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2, "2");
-    assert_equivalent(&fidl, &v3_onward, "3");
-    assert_equivalent(&fidl, &v3_onward, "HEAD");
-    assert_equivalent(&fidl, &v3_onward, "1,3,HEAD");
-    assert_equivalent(&fidl, &v2, "1,2,3,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2, "2");
+    assert_equivalent(&decomposed, &v3_onward, "3");
+    assert_equivalent(&decomposed, &v3_onward, "HEAD");
+    assert_equivalent(&decomposed, &v3_onward, "1,3,HEAD");
+    assert_equivalent(&decomposed, &v2, "1,2,3,HEAD");
 }
 
 #[test]
@@ -114,11 +222,13 @@ type TopLevel = struct {
 };
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2_onward, "2");
-    assert_equivalent(&fidl, &v2_onward, "HEAD");
-    assert_equivalent(&fidl, &v2_onward, "1,HEAD");
-    assert_equivalent(&fidl, &v2_onward, "1,2,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2_onward, "2");
+    assert_equivalent(&decomposed, &v2_onward, "HEAD");
+    assert_equivalent(&decomposed, &v2_onward, "1,HEAD");
+    assert_equivalent(&decomposed, &v2_onward, "1,2,HEAD");
 }
 
 #[test]
@@ -144,11 +254,13 @@ library example;
 type TopLevel = resource struct {};
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2_onward, "2");
-    assert_equivalent(&fidl, &v2_onward, "HEAD");
-    assert_equivalent(&fidl, &v2_onward, "1,HEAD");
-    assert_equivalent(&fidl, &v2_onward, "1,2,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2_onward, "2");
+    assert_equivalent(&decomposed, &v2_onward, "HEAD");
+    assert_equivalent(&decomposed, &v2_onward, "1,HEAD");
+    assert_equivalent(&decomposed, &v2_onward, "1,2,HEAD");
 }
 
 #[test]
@@ -191,11 +303,13 @@ type That = struct {
 };
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2_onward, "2");
-    assert_equivalent(&fidl, &v2_onward, "HEAD");
-    assert_equivalent(&fidl, &v2_onward, "1,HEAD");
-    assert_equivalent(&fidl, &v2_onward, "1,2,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2_onward, "2");
+    assert_equivalent(&decomposed, &v2_onward, "HEAD");
+    assert_equivalent(&decomposed, &v2_onward, "1,HEAD");
+    assert_equivalent(&decomposed, &v2_onward, "1,2,HEAD");
 }
 
 #[test]
@@ -246,12 +360,14 @@ type This = struct {
 type That = struct {};
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2, "2");
-    assert_equivalent(&fidl, &v3_onward, "3");
-    assert_equivalent(&fidl, &v3_onward, "HEAD");
-    assert_equivalent(&fidl, &v3_onward, "1,HEAD");
-    assert_equivalent(&fidl, &v3_onward, "1,2,3,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2, "2");
+    assert_equivalent(&decomposed, &v3_onward, "3");
+    assert_equivalent(&decomposed, &v3_onward, "HEAD");
+    assert_equivalent(&decomposed, &v3_onward, "1,HEAD");
+    assert_equivalent(&decomposed, &v3_onward, "1,2,3,HEAD");
 }
 
 #[test]
@@ -387,12 +503,14 @@ type Expr = flexible union {
 };
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2, "2");
-    assert_equivalent(&fidl, &v3, "3");
-    assert_equivalent(&fidl, &v4_onward, "4");
-    assert_equivalent(&fidl, &v4_onward, "HEAD");
-    assert_equivalent(&fidl, &all_versions, "1,2,3,4,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2, "2");
+    assert_equivalent(&decomposed, &v3, "3");
+    assert_equivalent(&decomposed, &v4_onward, "4");
+    assert_equivalent(&decomposed, &v4_onward, "HEAD");
+    assert_equivalent(&decomposed, &all_versions, "1,2,3,4,HEAD");
 }
 
 #[test]
@@ -505,13 +623,15 @@ type Bar = table {
 };
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2, "2");
-    assert_equivalent(&fidl, &v3, "3");
-    assert_equivalent(&fidl, &v4, "4");
-    assert_equivalent(&fidl, &v5_onward, "5");
-    assert_equivalent(&fidl, &v5_onward, "HEAD");
-    assert_equivalent(&fidl, &all_versions, "1,2,3,4,5,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2, "2");
+    assert_equivalent(&decomposed, &v3, "3");
+    assert_equivalent(&decomposed, &v4, "4");
+    assert_equivalent(&decomposed, &v5_onward, "5");
+    assert_equivalent(&decomposed, &v5_onward, "HEAD");
+    assert_equivalent(&decomposed, &all_versions, "1,2,3,4,5,HEAD");
 }
 
 #[test]
@@ -571,13 +691,15 @@ type Foo = table {
 };
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2, "2");
-    assert_equivalent(&fidl, &v3, "3");
-    assert_equivalent(&fidl, &v4_onward, "4");
-    assert_equivalent(&fidl, &v4_onward, "HEAD");
-    assert_equivalent(&fidl, &v4_onward, "1,HEAD");
-    assert_equivalent(&fidl, &v4_onward, "1,2,3,4,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2, "2");
+    assert_equivalent(&decomposed, &v3, "3");
+    assert_equivalent(&decomposed, &v4_onward, "4");
+    assert_equivalent(&decomposed, &v4_onward, "HEAD");
+    assert_equivalent(&decomposed, &v4_onward, "1,HEAD");
+    assert_equivalent(&decomposed, &v4_onward, "1,2,3,4,HEAD");
 }
 
 #[test]
@@ -643,12 +765,14 @@ type X = struct {
 type Y = flexible enum { A = 1; };
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2, "2");
-    assert_equivalent(&fidl, &v3, "3");
-    assert_equivalent(&fidl, &v4_onward, "4");
-    assert_equivalent(&fidl, &v4_onward, "HEAD");
-    assert_equivalent(&fidl, &all_versions, "1,2,3,4,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2, "2");
+    assert_equivalent(&decomposed, &v3, "3");
+    assert_equivalent(&decomposed, &v4_onward, "4");
+    assert_equivalent(&decomposed, &v4_onward, "HEAD");
+    assert_equivalent(&decomposed, &all_versions, "1,2,3,4,HEAD");
 }
 
 #[test]
@@ -718,15 +842,17 @@ type Foo = struct {};
 library example;
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2, "2");
-    assert_equivalent(&fidl, &v3, "3");
-    assert_equivalent(&fidl, &v4_to_5, "4");
-    assert_equivalent(&fidl, &v4_to_5, "5");
-    assert_equivalent(&fidl, &v6, "6");
-    assert_equivalent(&fidl, &v7_onward, "7");
-    assert_equivalent(&fidl, &v7_onward, "HEAD");
-    assert_equivalent(&fidl, &v4_to_5, "4,5,6,7,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2, "2");
+    assert_equivalent(&decomposed, &v3, "3");
+    assert_equivalent(&decomposed, &v4_to_5, "4");
+    assert_equivalent(&decomposed, &v4_to_5, "5");
+    assert_equivalent(&decomposed, &v6, "6");
+    assert_equivalent(&decomposed, &v7_onward, "7");
+    assert_equivalent(&decomposed, &v7_onward, "HEAD");
+    assert_equivalent(&decomposed, &v4_to_5, "4,5,6,7,HEAD");
 }
 
 #[test]
@@ -816,17 +942,18 @@ type Foo = table {
 type Bar = table {};
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2, "2");
-    assert_equivalent(&fidl, &v3, "3");
-    assert_equivalent(&fidl, &v4, "4");
-    assert_equivalent(&fidl, &v5_onward, "5");
-    assert_equivalent(&fidl, &v5_onward, "HEAD");
-    assert_equivalent(&fidl, &all_versions, "1,2,3,4,5,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2, "2");
+    assert_equivalent(&decomposed, &v3, "3");
+    assert_equivalent(&decomposed, &v4, "4");
+    assert_equivalent(&decomposed, &v5_onward, "5");
+    assert_equivalent(&decomposed, &v5_onward, "HEAD");
+    assert_equivalent(&decomposed, &all_versions, "1,2,3,4,5,HEAD");
 }
 
 #[test]
-#[ignore]
 fn allelementssplitbymembership() {
     let fidl = r#"
 @available(added=1)
@@ -1052,17 +1179,18 @@ resource_definition Resource : uint32 {
 library example;
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2, "2");
-    assert_equivalent(&fidl, &v3, "3");
-    assert_equivalent(&fidl, &v4, "4");
-    assert_equivalent(&fidl, &v5_onward, "5");
-    assert_equivalent(&fidl, &v5_onward, "HEAD");
-    assert_equivalent(&fidl, &v3, "1,2,3,4,5,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2, "2");
+    assert_equivalent(&decomposed, &v3, "3");
+    assert_equivalent(&decomposed, &v4, "4");
+    assert_equivalent(&decomposed, &v5_onward, "5");
+    assert_equivalent(&decomposed, &v5_onward, "HEAD");
+    assert_equivalent(&decomposed, &v3, "1,2,3,4,5,HEAD");
 }
 
 #[test]
-#[ignore]
 fn allelementssplitbyreference() {
     let fidl_prefix = r#"
 @available(added=1)
@@ -1247,14 +1375,15 @@ protocol AnonymousLayoutsInProtocol {
     let v1 = format!("{}{}", v1_prefix, common_suffix);
     let v2_onward = format!("{}{}", v2_onward_prefix, common_suffix);
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2_onward, "2");
-    assert_equivalent(&fidl, &v2_onward, "HEAD");
-    assert_equivalent(&fidl, &v2_onward, "1,2,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2_onward, "2");
+    assert_equivalent(&decomposed, &v2_onward, "HEAD");
+    assert_equivalent(&decomposed, &v2_onward, "1,2,HEAD");
 }
 
 #[test]
-#[ignore]
 fn complicated() {
     let fidl = r#"
 @available(added=1)
@@ -1554,15 +1683,17 @@ protocol AB {
 };
 "#;
 
-    assert_equivalent(&fidl, &v1_to_2, "1");
-    assert_equivalent(&fidl, &v1_to_2, "2");
-    assert_equivalent(&fidl, &v3, "3");
-    assert_equivalent(&fidl, &v4, "4");
-    assert_equivalent(&fidl, &v5, "5");
-    assert_equivalent(&fidl, &v6, "6");
-    assert_equivalent(&fidl, &v7_onward, "7");
-    assert_equivalent(&fidl, &v7_onward, "HEAD");
-    assert_equivalent(&fidl, &all_versions, "1,2,3,4,5,6,7,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1_to_2, "1");
+    assert_equivalent(&decomposed, &v1_to_2, "2");
+    assert_equivalent(&decomposed, &v3, "3");
+    assert_equivalent(&decomposed, &v4, "4");
+    assert_equivalent(&decomposed, &v5, "5");
+    assert_equivalent(&decomposed, &v6, "6");
+    assert_equivalent(&decomposed, &v7_onward, "7");
+    assert_equivalent(&decomposed, &v7_onward, "HEAD");
+    assert_equivalent(&decomposed, &all_versions, "1,2,3,4,5,6,7,HEAD");
 }
 
 #[test]
@@ -1605,10 +1736,12 @@ type Foo = struct {
 };
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2_onward, "2");
-    assert_equivalent(&fidl, &v2_onward, "HEAD");
-    assert_equivalent(&fidl, &v2_onward, "1,2,HEAD");
+    let decomposed = decompose(&fidl);
+
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2_onward, "2");
+    assert_equivalent(&decomposed, &v2_onward, "HEAD");
+    assert_equivalent(&decomposed, &v2_onward, "1,2,HEAD");
 }
 
 #[test]
@@ -1651,1210 +1784,10 @@ type Foo = struct {
 type Bar = struct {};
 "#;
 
-    assert_equivalent(&fidl, &v1, "1");
-    assert_equivalent(&fidl, &v2_onward, "2");
-    assert_equivalent(&fidl, &v2_onward, "HEAD");
-    assert_equivalent(&fidl, &v2_onward, "1,2,HEAD");
-}
-
-#[test]
-#[ignore]
-fn equivalent_to_self() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn unversioned_library() {
-    let _unversioned = r#"
-library example;
-
-type Foo = struct {};
-"#;
-
-    let _versioned = r#"
-@available(added=1)
-library example;
-
-type Foo = struct {};
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn absent_library_is_empty() {
-    let _fidl = r#"
-@available(added=2, removed=3)
-library example;
-
-type Foo = struct {};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-"#;
-
-    let _v2 = r#"
-@available(added=2, removed=3)
-library example;
-
-type Foo = struct {};
-"#;
-
-    let _v3_onward = r#"
-@available(added=3)
-library example;
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn split_by_membership() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-type TopLevel = struct {
-    @available(added=2)
-    first uint32;
-};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-type TopLevel = struct {};
-"#;
-
-    let _v2_onward = r#"
-@available(added=2)
-library example;
-
-type TopLevel = struct {
-    first uint32;
-};
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn split_by_modifier() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-type TopLevel = resource(added=2) struct {};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-type TopLevel = struct {};
-"#;
-
-    let _v2_onward = r#"
-@available(added=2)
-library example;
-
-type TopLevel = resource struct {};
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn split_by_reference() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-type This = struct {
-    this_member That;
-};
-
-type That = struct {
-    @available(added=2)
-    that_member uint32;
-};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-type This = struct {
-    this_member That;
-};
-
-type That = struct {};
-"#;
-
-    let _v2_onward = r#"
-@available(added=2)
-library example;
-
-type This = struct {
-    this_member That;
-};
-
-type That = struct {
-    that_member uint32;
-};
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn split_by_two_members() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-type This = struct {
-    @available(added=2)
-    first That;
-    @available(added=3)
-    second That;
-};
-
-type That = struct {};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-type This = struct {};
-
-type That = struct {};
-"#;
-
-    let _v2 = r#"
-@available(added=2, removed=3)
-library example;
-
-type This = struct {
-    first That;
-};
-
-type That = struct {};
-"#;
-
-    let _v3_onward = r#"
-@available(added=3)
-library example;
-
-type This = struct {
-    first That;
-    second That;
-};
-
-type That = struct {};
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn mutual_recursion() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-@available(added=2)
-type Foo = table {
-    1: str string;
-    @available(added=3)
-    // Struct wrapper needed because tables aren't allowed to be boxed.
-    2: bars vector<box<struct { bar Bar; }>>;
-};
-
-@available(added=2)
-type Bar = table {
-    @available(removed=5)
-    // OuterStruct needed because aren't allowed to contain boxes.
-    // InnerStruct needed because tables aren't allowed to be boxed.
-    1: foo @generated_name("OuterStruct") struct {
-        inner box<@generated_name("InnerStruct") struct { foo Foo; }>;
-    };
-    @available(added=4)
-    2: str string;
-};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-"#;
-
-    let _v2 = r#"
-@available(added=2, removed=3)
-library example;
-
-type Foo = table {
-    1: str string;
-};
-
-type Bar = table {
-    1: foo @generated_name("OuterStruct") struct {
-        inner box<@generated_name("InnerStruct") struct { foo Foo; }>;
-    };
-};
-"#;
-
-    let _v3 = r#"
-@available(added=3, removed=4)
-library example;
-
-type Foo = table {
-    1: str string;
-    2: bars vector<box<struct { bar Bar; }>>;
-};
-
-type Bar = table {
-    1: foo @generated_name("OuterStruct") struct {
-        inner box<@generated_name("InnerStruct") struct { foo Foo; }>;
-    };
-};
-"#;
-
-    let _v4 = r#"
-@available(added=4, removed=5)
-library example;
-
-type Foo = table {
-    1: str string;
-    2: bars vector<box<struct { bar Bar; }>>;
-};
-
-type Bar = table {
-    1: foo @generated_name("OuterStruct") struct {
-        inner box<@generated_name("InnerStruct") struct { foo Foo; }>;
-    };
-    2: str string;
-};
-"#;
-
-    let _v5_onward = r#"
-@available(added=5)
-library example;
-
-type Foo = table {
-    1: str string;
-    2: bars vector<box<struct { bar Bar; }>>;
-};
-
-type Bar = table {
-    2: str string;
-};
-"#;
-
-    let _all_versions = r#"
-library example;
-
-type Foo = table {
-    1: str string;
-    2: bars vector<box<struct { bar Bar; }>>;
-};
-
-type Bar = table {
-    1: foo @generated_name("OuterStruct") struct {
-        inner box<@generated_name("InnerStruct") struct { foo Foo; }>;
-    };
-    2: str string;
-};
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn misaligned_swapping() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-@available(replaced=4)
-const LEN uint64 = 16;
-@available(added=4)
-const LEN uint64 = 32;
-
-@available(added=2)
-type Foo = table {
-    @available(replaced=3)
-    1: bar string;
-    @available(added=3)
-    1: bar string:LEN;
-};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-const LEN uint64 = 16;
-"#;
-
-    let _v2 = r#"
-@available(added=2, removed=3)
-library example;
-
-const LEN uint64 = 16;
-type Foo = table {
-    1: bar string;
-};
-"#;
-
-    let _v3 = r#"
-@available(added=3, removed=4)
-library example;
-
-const LEN uint64 = 16;
-type Foo = table {
-    1: bar string:LEN;
-};
-"#;
-
-    let _v4_onward = r#"
-@available(added=4)
-library example;
-
-const LEN uint64 = 32;
-type Foo = table {
-    1: bar string:LEN;
-};
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn strict_to_flexible() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-type X = struct {
-    @available(added=2, removed=4)
-    y Y;
-};
-
-@available(added=2)
-type Y = strict(removed=3) flexible(added=3) enum { A = 1; };
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-type X = struct {};
-"#;
-
-    let _v2 = r#"
-@available(added=2, removed=3)
-library example;
-
-type X = struct {
-    y Y;
-};
-
-type Y = strict enum { A = 1; };
-"#;
-
-    let _v3 = r#"
-@available(added=3, removed=4)
-library example;
-
-type X = struct {
-    y Y;
-};
-
-type Y = flexible enum { A = 1; };
-"#;
-
-    let _v4_onward = r#"
-@available(added=4)
-library example;
-
-type X = struct {};
-
-type Y = flexible enum { A = 1; };
-"#;
-
-    let _all_versions = r#"
-library example;
-
-type X = struct {
-    y Y;
-};
-
-type Y = flexible enum { A = 1; };
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn name_reuse() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-@available(added=2, removed=3)
-type Foo = struct {
-    bar Bar;
-};
-@available(added=1, replaced=4)
-type Bar = struct {};
-
-@available(added=4, removed=7)
-type Foo = struct {};
-@available(added=4, removed=6)
-type Bar = struct {
-    foo Foo;
-};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-type Bar = struct {};
-"#;
-
-    let _v2 = r#"
-@available(added=2, removed=3)
-library example;
-
-type Foo = struct {
-    bar Bar;
-};
-type Bar = struct {};
-"#;
-
-    let _v3 = r#"
-@available(added=3, removed=4)
-library example;
-
-type Bar = struct {};
-"#;
-
-    let _v4_to_5 = r#"
-@available(added=4, removed=6)
-library example;
-
-type Foo = struct {};
-type Bar = struct {
-    foo Foo;
-};
-"#;
-
-    let _v6 = r#"
-@available(added=6, removed=7)
-library example;
-
-type Foo = struct {};
-"#;
-
-    let _v7_onward = r#"
-@available(added=7)
-library example;
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn consts_and_constraints() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-@available(removed=4)
-const LEN uint64 = 10;
-
-type Foo = table {
-    @available(replaced=3)
-    1: bar Bar;
-    @available(added=3, replaced=4)
-    1: bar string:LEN;
-    @available(added=4, removed=5)
-    1: bar Bar;
-};
-
-@available(replaced=2)
-type Bar = struct {};
-@available(added=2)
-type Bar = table {};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-const LEN uint64 = 10;
-type Foo = table {
-    1: bar Bar;
-};
-type Bar = struct {};
-"#;
-
-    let _v2 = r#"
-@available(added=2, removed=3)
-library example;
-
-const LEN uint64 = 10;
-type Foo = table {
-    1: bar Bar;
-};
-type Bar = table {};
-"#;
-
-    let _v3 = r#"
-@available(added=3, removed=4)
-library example;
-
-const LEN uint64 = 10;
-type Foo = table {
-    1: bar string:LEN;
-};
-type Bar = table {};
-"#;
-
-    let _v4 = r#"
-@available(added=4, removed=5)
-library example;
-
-type Foo = table {
-    1: bar Bar;
-};
-type Bar = table {};
-"#;
-
-    let _v5_onward = r#"
-@available(added=5)
-library example;
-
-type Foo = table {};
-type Bar = table {};
-"#;
-
-    let _all_versions = r#"
-library example;
-
-const LEN uint64 = 10;
-
-type Foo = table {
-    1: bar Bar;
-};
-
-type Bar = table {};
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn all_elements_split_by_membership() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-@available(added=2, removed=5)
-type Bits = bits {
-    FIRST = 1;
-    @available(added=3, removed=4)
-    SECOND = 2;
-};
-
-@available(added=2, removed=5)
-type Enum = enum {
-    FIRST = 1;
-    @available(added=3, removed=4)
-    SECOND = 2;
-};
-
-@available(added=2, removed=5)
-type Struct = struct {
-    first string;
-    @available(added=3, removed=4)
-    second string;
-};
-
-@available(added=2, removed=5)
-type Table = table {
-    1: first string;
-    @available(added=3, removed=4)
-    2: second string;
-};
-
-@available(added=2, removed=5)
-type Union = union {
-    1: first string;
-    @available(added=3, removed=4)
-    2: second string;
-};
-
-@available(added=2, removed=5)
-protocol TargetProtocol {};
-
-@available(added=2, removed=5)
-protocol ProtocolComposition {
-    @available(added=3, removed=4)
-    compose TargetProtocol;
-};
-
-@available(added=2, removed=5)
-protocol ProtocolMethods {
-    @available(added=3, removed=4)
-    Method() . ();
-};
-
-@available(added=2, removed=5)
-service Service {
-    first client_end:TargetProtocol;
-    @available(added=3, removed=4)
-    second client_end:TargetProtocol;
-};
-
-@available(added=2, removed=5)
-resource_definition Resource : uint32 {
-    properties {
-        first uint32;
-        @available(added=3, removed=4)
-        second uint32;
-        // This property is required for compilation, but is not otherwise under test.
-        subtype flexible enum : uint32 {};
-    };
-};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-"#;
-
-    let _v2 = r#"
-@available(added=2, removed=3)
-library example;
-
-type Bits = bits {
-    FIRST = 1;
-};
-
-type Enum = enum {
-    FIRST = 1;
-};
-
-type Struct = struct {
-    first string;
-};
-
-type Table = table {
-    1: first string;
-};
-
-type Union = union {
-    1: first string;
-};
-
-protocol TargetProtocol {};
-
-protocol ProtocolComposition {};
-
-protocol ProtocolMethods {};
-
-service Service {
-    first client_end:TargetProtocol;
-};
-
-resource_definition Resource : uint32 {
-    properties {
-        first uint32;
-        // This property is required for compilation, but is not otherwise under test.
-        subtype flexible enum : uint32 {};
-    };
-};
-"#;
-
-    let _v3 = r#"
-@available(added=3, removed=4)
-library example;
-
-type Bits = bits {
-    FIRST = 1;
-    SECOND = 2;
-};
-
-type Enum = enum {
-    FIRST = 1;
-    SECOND = 2;
-};
-
-type Struct = struct {
-    first string;
-    second string;
-};
-
-type Table = table {
-    1: first string;
-    2: second string;
-};
-
-type Union = union {
-    1: first string;
-    2: second string;
-};
-
-protocol TargetProtocol {};
-
-protocol ProtocolComposition {
-    compose TargetProtocol;
-};
-
-protocol ProtocolMethods {
-    Method() . ();
-};
-
-service Service {
-    first client_end:TargetProtocol;
-    second client_end:TargetProtocol;
-};
-
-resource_definition Resource : uint32 {
-    properties {
-        first uint32;
-        second uint32;
-        // This property is required for compilation, but is not otherwise under test.
-        subtype flexible enum : uint32 {};
-    };
-};
-"#;
-
-    let _v4 = r#"
-@available(added=4, removed=5)
-library example;
-
-type Bits = bits {
-    FIRST = 1;
-};
-
-type Enum = enum {
-    FIRST = 1;
-};
-
-type Struct = struct {
-    first string;
-};
-
-
-type Table = table {
-    1: first string;
-};
-
-type Union = union {
-    1: first string;
-};
-
-protocol TargetProtocol {};
-
-protocol ProtocolComposition {};
-
-protocol ProtocolMethods {};
-
-service Service {
-    first client_end:TargetProtocol;
-};
-
-resource_definition Resource : uint32 {
-    properties {
-        first uint32;
-        // This property is required for compilation, but is not otherwise under test.
-        subtype flexible enum : uint32 {};
-    };
-};
-"#;
-
-    let _v5_onward = r#"
-@available(added=5)
-library example;
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn all_elements_split_by_reference() {
-    let fidl_prefix = r#"
-@available(added=1)
-library example;
-
-@available(replaced=2)
-const VALUE uint32 = 1;
-@available(added=2)
-const VALUE uint32 = 2;
-
-@available(replaced=2)
-type Type = struct {
-    value bool;
-};
-@available(added=2)
-type Type = table {
-    1: value bool;
-};
-
-// Need unsigned integers for bits underlying type.
-@available(replaced=2)
-alias IntegerType = uint32;
-@available(added=2)
-alias IntegerType = uint64;
-
-// Need uint32/int32 for error type.
-@available(replaced=2)
-alias Error::ErrorIntegerType = uint32;
-@available(added=2)
-alias Error::ErrorIntegerType = int32;
-
-@available(replaced=2)
-protocol TargetProtocol {};
-@available(added=2)
-protocol TargetProtocol {
-    Method();
-};
-"#;
-
-    let v1_prefix = r#"
-@available(added=1, removed=2)
-library example;
-
-const VALUE uint32 = 1;
-
-type Type = struct {
-    value bool;
-};
-
-alias IntegerType = uint32;
-
-alias Error::ErrorIntegerType = uint32;
-
-protocol TargetProtocol {};
-"#;
-
-    let v2_onward_prefix = r#"
-@available(added=2)
-library example;
-
-const VALUE uint32 = 2;
-
-type Type = table {
-    1: value bool;
-};
-
-alias IntegerType = uint64;
-
-alias Error::ErrorIntegerType = int32;
-
-protocol TargetProtocol { Method(); };
-"#;
-
-    let common_suffix = r#"
-const CONST uint32 = VALUE;
-
-alias Alias = Type;
-
-// TODO(https://fxbug.dev/42158155): Uncomment.
-// type Newtype = Type;
-
-type BitsUnderlying = bits : IntegerType {
-    MEMBER = 1;
-};
-
-type BitsMemberValue = bits {
-    MEMBER = VALUE;
-};
-
-type EnumUnderlying = enum : IntegerType {
-    MEMBER = 1;
-};
-
-type EnumMemberValue = enum {
-    MEMBER = VALUE;
-};
-
-type StructMemberType = struct {
-    member Type;
-};
-
-type StructMemberDefault = struct {
-    @allow_deprecated_struct_defaults
-    member uint32 = VALUE;
-};
-
-type Table = table {
-    1: member Type;
-};
-
-type Union = union {
-    1: member Type;
-};
-
-protocol ProtocolComposition {
-    compose TargetProtocol;
-};
-
-protocol ProtocolMethodRequest {
-    Method(Type);
-};
-
-protocol ProtocolMethodResponse {
-    Method() . (Type);
-};
-
-protocol ProtocolEvent {
-    . Event(Type);
-};
-
-protocol ProtocolSuccess {
-    Method() . (Type) error uint32;
-};
-
-protocol ProtocolError::Error {
-    Method() . () error Error::ErrorIntegerType;
-};
-
-service Service {
-    member client_end:TargetProtocol;
-};
-
-resource_definition Resource : uint32 {
-    properties {
-        first IntegerType;
-        // This property is required for compilation, but is not otherwise under test.
-        subtype flexible enum : uint32 {};
-    };
-};
-
-type NestedTypes = struct {
-    first vector<Type>;
-    second vector<array<Type, 3>>;
-};
-
-type LayoutParameters = struct {
-    member array<bool, VALUE>;
-};
-
-type Constraints = struct {
-    member vector<bool>:VALUE;
-};
-
-type AnonymousLayouts = struct {
-    first_member table {
-        1: second_member union {
-            1: third_member Type;
-        };
-    };
-};
-
-protocol AnonymousLayoutsInProtocol {
-    Request(struct { member Type; });
-    Response() . (struct { member Type; });
-    . Event(struct { member Type; });
-    Success() . (struct { member Type; }) error uint32;
-    Error::Error() . () error Error::ErrorIntegerType;
-};
-"#;
-
-    let _fidl = String::from(fidl_prefix) + common_suffix;
-    let _v1 = String::from(v1_prefix) + common_suffix;
-    let _v2_onward = String::from(v2_onward_prefix) + common_suffix;
-
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn convert_named_to_anonymous() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-@available(replaced=2)
-type Foo = struct {
-    member Bar;
-};
-
-@available(replaced=2)
-type Bar = struct {};
-
-@available(added=2)
-type Foo = struct {
-    member @generated_name("Bar") struct {};
-};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-type Foo = struct {
-    member Bar;
-};
-
-type Bar = struct {};
-"#;
-
-    let _v2_onward = r#"
-@available(added=2)
-library example;
-
-type Foo = struct {
-    member @generated_name("Bar") struct {};
-};
-"#;
-
-    // test check
-    // test check
-    // test check
-    // test check
-}
-
-#[test]
-#[ignore]
-fn convert_anonymous_to_named() {
-    let _fidl = r#"
-@available(added=1)
-library example;
-
-@available(replaced=2)
-type Foo = struct {
-    member @generated_name("Bar") struct {};
-};
-
-@available(added=2)
-type Foo = struct {
-    member Bar;
-};
-
-@available(added=2)
-type Bar = struct {};
-"#;
-
-    let _v1 = r#"
-@available(added=1, removed=2)
-library example;
-
-type Foo = struct {
-    member @generated_name("Bar") struct {};
-};
-"#;
-
-    let _v2_onward = r#"
-@available(added=2)
-library example;
-
-type Foo = struct {
-    member Bar;
-};
-
-type Bar = struct {};
-"#;
+    let decomposed = decompose(&fidl);
 
-    // test check
-    // test check
-    // test check
-    // test check
+    assert_equivalent(&decomposed, &v1, "1");
+    assert_equivalent(&decomposed, &v2_onward, "2");
+    assert_equivalent(&decomposed, &v2_onward, "HEAD");
+    assert_equivalent(&decomposed, &v2_onward, "1,2,HEAD");
 }
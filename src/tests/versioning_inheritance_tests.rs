@@ -93,7 +93,16 @@ type Foo = struct {};
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "added".into(),
+        "6".into(),
+        "replaced".into(),
+        "6".into(),
+        "example.fidl:5:35".into(),
+        "added".into(),
+        "after".into(),
+        "removed".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -136,7 +145,16 @@ type Foo = struct {};
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "added".into(),
+        "7".into(),
+        "replaced".into(),
+        "6".into(),
+        "example.fidl:5:35".into(),
+        "added".into(),
+        "after".into(),
+        "removed".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -251,7 +269,16 @@ type Foo = struct {};
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "deprecated".into(),
+        "6".into(),
+        "replaced".into(),
+        "6".into(),
+        "example.fidl:5:35".into(),
+        "deprecated".into(),
+        "after".into(),
+        "removed".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -294,7 +321,16 @@ type Foo = struct {};
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "deprecated".into(),
+        "7".into(),
+        "replaced".into(),
+        "6".into(),
+        "example.fidl:5:35".into(),
+        "deprecated".into(),
+        "after".into(),
+        "removed".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -312,7 +348,16 @@ type Foo = struct {};
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "removed".into(),
+        "1".into(),
+        "added".into(),
+        "2".into(),
+        "example.fidl:2:12".into(),
+        "removed".into(),
+        "before".into(),
+        "added".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -351,7 +396,16 @@ type Foo = struct {};
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "removed".into(),
+        "2".into(),
+        "added".into(),
+        "2".into(),
+        "example.fidl:2:12".into(),
+        "removed".into(),
+        "before".into(),
+        "added".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -507,7 +561,16 @@ type Foo = struct {};
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "removed".into(),
+        "7".into(),
+        "removed".into(),
+        "6".into(),
+        "example.fidl:2:35".into(),
+        "removed".into(),
+        "after".into(),
+        "removed".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -531,7 +594,16 @@ type Foo = struct {};
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "removed".into(),
+        "7".into(),
+        "replaced".into(),
+        "6".into(),
+        "example.fidl:5:35".into(),
+        "removed".into(),
+        "after".into(),
+        "removed".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -880,7 +952,16 @@ type Foo = struct {           // L6
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "added".into(),
+        "1".into(),
+        "added".into(),
+        "2".into(),
+        "example.fidl:2:12".into(),
+        "added".into(),
+        "before".into(),
+        "added".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -903,7 +984,16 @@ type Foo = struct {           // L6
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "removed".into(),
+        "1".into(),
+        "added".into(),
+        "2".into(),
+        "example.fidl:5:12".into(),
+        "removed".into(),
+        "before".into(),
+        "added".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -938,6 +1028,15 @@ type Foo = resource(removed=3) struct {};
 "#,
     );
     library.select_version("example", "HEAD");
-    // expect_fail
+    library.expect_fail(Error::ErrAvailabilityConflictsWithParent(
+        "removed".into(),
+        "3".into(),
+        "removed".into(),
+        "2".into(),
+        "example.fidl:2:21".into(),
+        "removed".into(),
+        "after".into(),
+        "removed".into(),
+    ));
     assert!(library.check_compile());
 }
@@ -37,7 +37,14 @@ type Foo = resource struct {};
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "struct 'Foo'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:9:6".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -77,7 +84,13 @@ type Foo = struct {};
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidReplaced(
+        "struct 'Foo'".into(),
+        "2".into(),
+        "2".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -118,7 +131,14 @@ type Foo = table {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "table member 'bar'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:9:8".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -161,7 +181,13 @@ type Foo = table {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidReplaced(
+        "table member 'bar'".into(),
+        "2".into(),
+        "2".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -189,8 +215,7 @@ fn bad_method_removed() {
     let mut library = TestLibrary::new();
     //   library.select_version("test", GetParam());
     library.add_errcat_file("bad/fi-0205.test.fidl");
-    // expect_fail
-    assert!(library.check_compile());
+    assert!(library.compile().is_err());
 }
 
 #[test]
@@ -221,8 +246,7 @@ fn bad_method_replaced() {
     let mut library = TestLibrary::new();
     library.add_errcat_file("bad/fi-0206.test.fidl");
     //   library.select_version("test", GetParam());
-    // expect_fail
-    assert!(library.check_compile());
+    assert!(library.compile().is_err());
 }
 
 #[test]
@@ -243,13 +267,62 @@ protocol Protocol {
 };
 
 protocol Base {
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'Method'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:15:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
+    assert!(library.check_compile());
+}
+
+#[test]
+fn bad_method_removed_new_compose_from_dependency() {
+    let mut library = TestLibrary::new();
+    library.add_dependency_file(
+        "dependency.fidl",
+        r#"
+library dependency;
+
+protocol Base {
+  @selector("example/Protocol.Method")
+  Method();
+};
+"#,
+    );
+    library.add_source_file(
+        "example.fidl",
+        r#"
+@available(added=1)
+library example;
+
+using dependency;
+
+protocol Protocol {
+  @available(removed=2)
+  Method();
+
+  @available(added=2)
+  compose dependency.Base;
+};
+"#,
+    );
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'Method'".into(),
+        "2".into(),
+        "2".into(),
+        "dependency.fidl:6:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -271,7 +344,7 @@ protocol Protocol {
 };
 
 protocol Base {
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
@@ -300,13 +373,20 @@ protocol Protocol {
 
 protocol Base {
   @available(added=2)
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'Method'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:15:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -328,7 +408,7 @@ protocol Protocol {
 
 protocol Base {
   @available(added=2)
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
@@ -357,13 +437,20 @@ protocol Protocol {
 };
 
 protocol Base {
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'Method'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:10:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -385,7 +472,7 @@ protocol Protocol {
 };
 
 protocol Base {
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
@@ -415,13 +502,20 @@ protocol Protocol {
 
 @available(removed=2)
 protocol Base {
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'Method'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:10:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -444,7 +538,7 @@ protocol Protocol {
 
 @available(removed=2)
 protocol Base {
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
@@ -484,7 +578,14 @@ protocol Bar {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'Method'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:20:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -552,7 +653,14 @@ protocol Bar {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'Method'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:21:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -609,13 +717,20 @@ protocol Protocol {
 
 protocol Base {
   @available(removed=2)
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'Method'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:9:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -641,13 +756,20 @@ protocol Intermediate {
 };
 
 protocol Base {
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'Method'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:19:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -673,7 +795,7 @@ protocol Intermediate {
 };
 
 protocol Base {
-  @selector("example/Protocol.method")
+  @selector("example/Protocol.Method")
   Method();
 };
 "#,
@@ -723,9 +845,30 @@ protocol BC {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
-    // expect_fail
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'A'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:14:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'B'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:28:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "protocol method 'C'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:30:3".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1013,7 +1156,14 @@ type Bar = struct {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemoved(
+        "struct 'Foo'".into(),
+        "2".into(),
+        "2".into(),
+        "example.fidl:10:9".into(),
+        "2".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1226,7 +1376,12 @@ type Foo = table {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemovedAndRenamed(
+        "table member 'bar'".into(),
+        "2".into(),
+        "old_bar".into(),
+        "example.fidl:8:8".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1248,7 +1403,38 @@ type Foo = table {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemovedAndRenamed(
+        "table member 'bar'".into(),
+        "2".into(),
+        "old_bar".into(),
+        "example.fidl:9:8".into(),
+    ));
+    assert!(library.check_compile());
+}
+
+#[test]
+fn bad_member_replaced_and_renamed() {
+    let mut library = TestLibrary::new();
+    library.add_source_file(
+        "example.fidl",
+        r#"
+@available(added=1)
+library example;
+
+type Foo = table {
+    @available(replaced=2, renamed="new_bar")
+    1: bar string;
+    @available(added=2)
+    1: bar uint32;
+};
+"#,
+    );
+    library.expect_fail(Error::ErrInvalidReplacedAndRenamed(
+        "table member 'bar'".into(),
+        "2".into(),
+        "new_bar".into(),
+        "2".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1257,8 +1443,7 @@ fn bad_method_removed_and_renamed() {
     let mut library = TestLibrary::new();
     library.add_errcat_file("bad/fi-0214.test.fidl");
     //   library.select_version("test", GetParam());
-    // expect_fail
-    assert!(library.check_compile());
+    assert!(library.compile().is_err());
 }
 
 #[test]
@@ -1266,8 +1451,7 @@ fn bad_method_replaced_and_renamed() {
     let mut library = TestLibrary::new();
     library.add_errcat_file("bad/fi-0215.test.fidl");
     //   library.select_version("test", GetParam());
-    // expect_fail
-    assert!(library.check_compile());
+    assert!(library.compile().is_err());
 }
 
 #[test]
@@ -1288,7 +1472,16 @@ type Foo = bits {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemovedAbi(
+        "bits member 'A'".into(),
+        "2".into(),
+        "value".into(),
+        "1".into(),
+        "example.fidl:9:5".into(),
+        "2".into(),
+        "B".into(),
+        "value".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1310,7 +1503,17 @@ type Foo = bits {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidReplacedAbi(
+        "bits member 'A'".into(),
+        "2".into(),
+        "value".into(),
+        "1".into(),
+        "value".into(),
+        "2".into(),
+        "example.fidl:9:5".into(),
+        "2".into(),
+        "value".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1332,7 +1535,16 @@ type Foo = enum {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemovedAbi(
+        "enum member 'A'".into(),
+        "2".into(),
+        "value".into(),
+        "1".into(),
+        "example.fidl:9:5".into(),
+        "2".into(),
+        "B".into(),
+        "value".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1354,7 +1566,17 @@ type Foo = enum {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidReplacedAbi(
+        "enum member 'A'".into(),
+        "2".into(),
+        "value".into(),
+        "1".into(),
+        "value".into(),
+        "2".into(),
+        "example.fidl:9:5".into(),
+        "2".into(),
+        "value".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1376,7 +1598,16 @@ type Foo = struct {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemovedAbi(
+        "struct member 'bar'".into(),
+        "2".into(),
+        "offset".into(),
+        "0".into(),
+        "example.fidl:9:5".into(),
+        "2".into(),
+        "baz".into(),
+        "offset".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1399,7 +1630,60 @@ type Foo = struct {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidReplacedAbi(
+        "struct member 'bar'".into(),
+        "2".into(),
+        "offset".into(),
+        "0".into(),
+        "offset".into(),
+        "4".into(),
+        "example.fidl:10:5".into(),
+        "2".into(),
+        "offset".into(),
+    ));
+    assert!(library.check_compile());
+}
+
+#[test]
+fn bad_struct_member_replaced_abi_after_declared_type() {
+    let mut library = TestLibrary::new();
+    library.add_source_file(
+        "example.fidl",
+        r#"
+@available(added=1)
+library example;
+
+type Small = struct {
+    value uint8;
+};
+
+type Large = struct {
+    value uint64;
+};
+
+type Foo = struct {
+    @available(replaced=2)
+    bar Small;
+    @available(added=2)
+    bar Large;
+    @available(replaced=2)
+    baz uint8;
+    @available(added=2)
+    baz uint8;
+};
+"#,
+    );
+    library.expect_fail(Error::ErrInvalidReplacedAbi(
+        "struct member 'baz'".into(),
+        "2".into(),
+        "offset".into(),
+        "1".into(),
+        "offset".into(),
+        "8".into(),
+        "example.fidl:21:5".into(),
+        "2".into(),
+        "offset".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1421,7 +1705,16 @@ type Foo = table {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemovedAbi(
+        "table member 'bar'".into(),
+        "2".into(),
+        "ordinal".into(),
+        "1".into(),
+        "example.fidl:9:8".into(),
+        "2".into(),
+        "baz".into(),
+        "ordinal".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1443,7 +1736,17 @@ type Foo = table {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidReplacedAbi(
+        "table member 'bar'".into(),
+        "2".into(),
+        "ordinal".into(),
+        "1".into(),
+        "ordinal".into(),
+        "2".into(),
+        "example.fidl:9:8".into(),
+        "2".into(),
+        "ordinal".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1465,7 +1768,16 @@ type Foo = union {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemovedAbi(
+        "union member 'bar'".into(),
+        "2".into(),
+        "ordinal".into(),
+        "1".into(),
+        "example.fidl:9:8".into(),
+        "2".into(),
+        "baz".into(),
+        "ordinal".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1487,7 +1799,17 @@ type Foo = union {
 "#,
     );
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidReplacedAbi(
+        "union member 'bar'".into(),
+        "2".into(),
+        "ordinal".into(),
+        "1".into(),
+        "ordinal".into(),
+        "2".into(),
+        "example.fidl:9:8".into(),
+        "2".into(),
+        "ordinal".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1510,7 +1832,16 @@ type Foo = strict overlay {
     );
     library.enable_flag("zx_c_types");
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidRemovedAbi(
+        "overlay member 'bar'".into(),
+        "2".into(),
+        "ordinal".into(),
+        "1".into(),
+        "example.fidl:9:8".into(),
+        "2".into(),
+        "baz".into(),
+        "ordinal".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1533,7 +1864,17 @@ type Foo = strict overlay {
     );
     library.enable_flag("zx_c_types");
     //   library.select_version("example", GetParam());
-    // expect_fail
+    library.expect_fail(Error::ErrInvalidReplacedAbi(
+        "overlay member 'bar'".into(),
+        "2".into(),
+        "ordinal".into(),
+        "1".into(),
+        "ordinal".into(),
+        "2".into(),
+        "example.fidl:9:8".into(),
+        "2".into(),
+        "ordinal".into(),
+    ));
     assert!(library.check_compile());
 }
 
@@ -1542,8 +1883,7 @@ fn bad_method_removed_abi() {
     let mut library = TestLibrary::new();
     library.add_errcat_file("bad/fi-0216.test.fidl");
     //   library.select_version("test", GetParam());
-    // expect_fail
-    assert!(library.check_compile());
+    assert!(library.compile().is_err());
 }
 
 #[test]
@@ -1551,6 +1891,68 @@ fn bad_method_replaced_abi() {
     let mut library = TestLibrary::new();
     library.add_errcat_file("bad/fi-0217.test.fidl");
     //   library.select_version("test", GetParam());
-    // expect_fail
+    assert!(library.compile().is_err());
+}
+
+#[test]
+fn bad_method_removed_selector_reused() {
+    let mut library = TestLibrary::new();
+    library.add_source_file(
+        "example.fidl",
+        r#"
+@available(added=1)
+library example;
+
+protocol Foo {
+    @available(removed=2)
+    Bar();
+    @available(added=2)
+    @selector("Bar")
+    Baz();
+};
+"#,
+    );
+    library.expect_fail(Error::ErrInvalidRemovedAbi(
+        "protocol method 'Bar'".into(),
+        "2".into(),
+        "selector".into(),
+        "example/Foo.Bar".into(),
+        "example.fidl:10:5".into(),
+        "2".into(),
+        "Baz".into(),
+        "selector".into(),
+    ));
+    assert!(library.check_compile());
+}
+
+#[test]
+fn bad_method_replaced_selector_changed() {
+    let mut library = TestLibrary::new();
+    library.add_source_file(
+        "example.fidl",
+        r#"
+@available(added=1)
+library example;
+
+protocol Foo {
+    @available(replaced=2)
+    Bar();
+    @available(added=2)
+    @selector("example/Foo.Baz")
+    Bar();
+};
+"#,
+    );
+    library.expect_fail(Error::ErrInvalidReplacedAbi(
+        "protocol method 'Bar'".into(),
+        "2".into(),
+        "selector".into(),
+        "example/Foo.Bar".into(),
+        "selector".into(),
+        "example/Foo.Baz".into(),
+        "example.fidl:10:5".into(),
+        "2".into(),
+        "selector".into(),
+    ));
     assert!(library.check_compile());
 }
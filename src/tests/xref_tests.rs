@@ -0,0 +1,126 @@
+use crate::api::{CompileOptions, CompileOutput, Sources, compile};
+use crate::compiler::{ReferenceKind, Referent};
+use crate::names::{OwnedLibraryName, OwnedQualifiedName};
+
+fn compile_example(text: &str) -> CompileOutput {
+    let mut sources = Sources::new();
+    sources.add_dependency_file(
+        "dep.fidl",
+        "library fuchsia.dep;\ntype Thing = struct {};\nprotocol Base {};\n",
+    );
+    sources.add_file("example.fidl", text);
+    let output = compile(&sources, &CompileOptions::default()).unwrap();
+    assert!(output.succeeded(), "{:?}", output.diagnostics);
+    output
+}
+
+fn declaration(name: &str) -> Referent {
+    Referent::Declaration(OwnedQualifiedName::parse(name))
+}
+
+/// The (kind, line, start, end) of each reference to `referent`.
+fn positions(
+    output: &CompileOutput,
+    referent: &Referent,
+) -> Vec<(ReferenceKind, usize, usize, usize)> {
+    output
+        .references_to(referent)
+        .map(|r| {
+            let l = &r.location;
+            assert_eq!(l.start_line, l.end_line);
+            (r.kind, l.start_line, l.start_char, l.end_char)
+        })
+        .collect()
+}
+
+#[test]
+fn good_types_and_constants() {
+    let output = compile_example(
+        r#"library example;
+
+type Color = strict enum {
+    RED = 1;
+};
+const LIMIT uint32 = 10;
+const DEFAULT Color = Color.RED;
+
+type S = struct {
+    color Color;
+    names vector<string:LIMIT>:LIMIT;
+    colors array<Color, 2>;
+};
+"#,
+    );
+    assert_eq!(
+        positions(&output, &declaration("example/Color")),
+        vec![
+            (ReferenceKind::Type, 7, 14, 19),
+            (ReferenceKind::Type, 10, 10, 15),
+            (ReferenceKind::Type, 12, 17, 22),
+        ]
+    );
+    assert_eq!(
+        positions(&output, &declaration("example/Color.RED")),
+        vec![(ReferenceKind::Constant, 7, 22, 31)]
+    );
+    assert_eq!(
+        positions(&output, &declaration("example/LIMIT")),
+        vec![
+            (ReferenceKind::Constant, 11, 24, 29),
+            (ReferenceKind::Constant, 11, 31, 36),
+        ]
+    );
+    // Builtins aren't references.
+    assert!(
+        output
+            .references
+            .iter()
+            .all(|r| matches!(r.referent, Referent::Declaration(_)))
+    );
+}
+
+#[test]
+fn good_dependency_and_alias() {
+    let output = compile_example(
+        r#"library example;
+
+using fuchsia.dep as dep;
+
+type S = struct {
+    thing dep.Thing;
+};
+
+protocol P {
+    compose dep.Base;
+};
+"#,
+    );
+    let library = Referent::Library(OwnedLibraryName::new("fuchsia.dep".to_string()));
+    assert_eq!(
+        positions(&output, &library),
+        vec![
+            (ReferenceKind::Library, 3, 6, 17),
+            (ReferenceKind::Library, 6, 10, 13),
+            (ReferenceKind::Library, 10, 12, 15),
+        ]
+    );
+    assert_eq!(
+        positions(&output, &declaration("fuchsia.dep/Thing")),
+        vec![(ReferenceKind::Type, 6, 10, 19)]
+    );
+    assert_eq!(
+        positions(&output, &declaration("fuchsia.dep/Base")),
+        vec![(ReferenceKind::Compose, 10, 12, 20)]
+    );
+
+    // The innermost reference wins.
+    let at = |character| {
+        output
+            .reference_at("example.fidl", 6, character)
+            .map(|r| &r.referent)
+    };
+    assert_eq!(at(11), Some(&library));
+    assert_eq!(at(16), Some(&declaration("fuchsia.dep/Thing")));
+    assert_eq!(at(4), None);
+    assert!(output.reference_at("dep.fidl", 2, 5).is_none());
+}
@@ -0,0 +1,246 @@
+//! When each declaration and member of a library is available.
+//!
+//! A [`Timeline`] lists every element with the versions at which it was
+//! added, deprecated, removed or replaced, after inheriting from its parent,
+//! as [`Compiler::decl_availability`] and [`Compiler::member_availability`]
+//! hold them. Only the elements in the selected version get there, so
+//! [`crate::api::timeline`] compiles the library at every version and adds
+//! what each compilation found.
+//!
+//! [`Compiler::decl_availability`]: crate::compiler::Compiler::decl_availability
+//! [`Compiler::member_availability`]: crate::compiler::Compiler::member_availability
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::api::Location;
+use crate::compiler::{Compiler, MemberKind};
+use crate::raw_ast::{self, AttributeList};
+use crate::versioning_types::{Availability, AvailabilityState, Ending, Version};
+
+/// Something a reviewer should look at in an element's availability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineProblem {
+    /// Removed without being deprecated first, so users had no warning.
+    RemovedWithoutDeprecation,
+    /// Replaced, but no element of the same name is added at that version.
+    ReplacedWithoutAddition,
+}
+
+impl fmt::Display for TimelineProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RemovedWithoutDeprecation => write!(f, "removed without being deprecated"),
+            Self::ReplacedWithoutAddition => write!(f, "replaced, but no replacement is added"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineElement {
+    /// The fully qualified name, like `fuchsia.example/Color` or
+    /// `fuchsia.example/Color.RED`.
+    pub name: String,
+    /// The kind of declaration or member, like `struct` or `table field`.
+    pub kind: String,
+    #[serde(skip)]
+    pub location: Location,
+    /// `None` if the library isn't versioned.
+    pub added: Option<String>,
+    pub deprecated: Option<String>,
+    /// `None` if it was never removed, or was replaced instead.
+    pub removed: Option<String>,
+    pub replaced: Option<String>,
+    /// The `note` of its own `@available` attribute.
+    pub note: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<TimelineProblem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Timeline {
+    pub library: String,
+    /// `None` if the library isn't versioned.
+    pub platform: Option<String>,
+    /// Every element, by name and then by where it is declared.
+    pub elements: Vec<TimelineElement>,
+}
+
+impl Timeline {
+    pub(crate) fn new(library: String, platform: Option<String>) -> Self {
+        Self {
+            library,
+            platform,
+            elements: Vec::new(),
+        }
+    }
+
+    /// Adds the elements available in the version `compiler` compiled the
+    /// library at, that an earlier compilation didn't already add.
+    pub(crate) fn add(&mut self, compiler: &Compiler<'_, '_>) {
+        let mut seen: HashSet<(String, usize, usize)> =
+            self.elements.iter().map(|e| key(&e.location)).collect();
+        let prefix = format!("{}/", compiler.library_name);
+        let mut add = |name: String,
+                       kind: String,
+                       id: &raw_ast::Identifier<'_>,
+                       attributes: Option<&AttributeList<'_>>,
+                       availability: Option<&Availability>| {
+            let Some(availability) = availability.filter(|a| {
+                matches!(
+                    a.state(),
+                    AvailabilityState::Inherited | AvailabilityState::Narrowed
+                )
+            }) else {
+                return;
+            };
+            let location = Location::new(&id.element.span());
+            if !seen.insert(key(&location)) {
+                return;
+            }
+            let ending = availability.ending();
+            let end = availability
+                .removed()
+                .filter(|&v| v != Version::POS_INF)
+                .map(|v| v.to_string());
+            self.elements.push(TimelineElement {
+                name,
+                kind,
+                location,
+                added: availability
+                    .added()
+                    .filter(|&v| v != Version::NEG_INF)
+                    .map(|v| v.to_string()),
+                deprecated: availability.deprecated().map(|v| v.to_string()),
+                removed: end.clone().filter(|_| ending != Ending::Replaced),
+                replaced: end.filter(|_| ending == Ending::Replaced),
+                note: note(attributes),
+                problems: if ending == Ending::Removed && availability.deprecated().is_none() {
+                    vec![TimelineProblem::RemovedWithoutDeprecation]
+                } else {
+                    Vec::new()
+                },
+            });
+        };
+
+        for (name, decl) in &compiler.raw_decls {
+            let name = name.to_string();
+            if !name.starts_with(&prefix) {
+                continue;
+            }
+            let kind = decl.kind();
+            // Anonymous layouts are listed by their members only.
+            if let Some(id) = decl.name() {
+                add(
+                    name.clone(),
+                    kind.to_string(),
+                    id,
+                    decl.attributes(),
+                    compiler.decl_availability.get(name.as_str()),
+                );
+            }
+            decl.for_each_member(|id, attributes, element| {
+                let ptr = element.span().data.as_ptr() as usize;
+                add(
+                    format!("{}.{}", name, id.data()),
+                    MemberKind::of(kind).to_string(),
+                    id,
+                    attributes,
+                    compiler.member_availability.get(&ptr),
+                );
+            });
+        }
+    }
+
+    /// Sorts the elements and flags replacements that have nothing to
+    /// replace them, once every version has been added.
+    pub(crate) fn finish(&mut self) {
+        let added: HashSet<(String, String)> = self
+            .elements
+            .iter()
+            .filter_map(|e| Some((e.name.clone(), e.added.clone()?)))
+            .collect();
+        for element in &mut self.elements {
+            if let Some(replaced) = &element.replaced
+                && !added.contains(&(element.name.clone(), replaced.clone()))
+            {
+                element
+                    .problems
+                    .push(TimelineProblem::ReplacedWithoutAddition);
+            }
+        }
+        self.elements
+            .sort_by(|a, b| (&a.name, key(&a.location)).cmp(&(&b.name, key(&b.location))));
+    }
+
+    /// The elements with problems.
+    pub fn problems(&self) -> impl Iterator<Item = &TimelineElement> {
+        self.elements.iter().filter(|e| !e.problems.is_empty())
+    }
+
+    /// The timeline as a Markdown table, followed by a list of the problems.
+    pub fn markdown(&self) -> String {
+        let mut markdown = format!("# Availability of `{}`\n\n", self.library);
+        match &self.platform {
+            Some(platform) => markdown.push_str(&format!("Platform: `{}`\n\n", platform)),
+            None => markdown.push_str("The library is unversioned.\n\n"),
+        }
+        markdown.push_str("| Name | Kind | Added | Deprecated | Removed | Replaced | Note |\n");
+        markdown.push_str("|---|---|---|---|---|---|---|\n");
+        let cell = |value: &Option<String>| value.clone().unwrap_or_default();
+        for e in &self.elements {
+            markdown.push_str(&format!(
+                "| `{}` | {} | {} | {} | {} | {} | {} |\n",
+                e.name,
+                e.kind,
+                cell(&e.added),
+                cell(&e.deprecated),
+                cell(&e.removed),
+                cell(&e.replaced),
+                cell(&e.note).replace('|', "\\|"),
+            ));
+        }
+        let problems: Vec<&TimelineElement> = self.problems().collect();
+        if !problems.is_empty() {
+            markdown.push_str("\n## Problems\n\n");
+            for e in problems {
+                for problem in &e.problems {
+                    markdown.push_str(&format!("- `{}`: {}\n", e.name, problem));
+                }
+            }
+        }
+        markdown
+    }
+}
+
+/// Identifies an element by where its name is, which is the same in every
+/// compilation.
+fn key(location: &Location) -> (String, usize, usize) {
+    (
+        location.filename.clone(),
+        location.start_line,
+        location.start_char,
+    )
+}
+
+/// The `note` of an element's own `@available` attribute.
+pub(crate) fn note(attributes: Option<&AttributeList<'_>>) -> Option<String> {
+    let available = attributes?
+        .attributes
+        .iter()
+        .find(|a| a.name.data() == "available")?;
+    available.args.iter().find_map(|arg| {
+        if arg.name.as_ref()?.data() != "note" {
+            return None;
+        }
+        match &arg.value {
+            raw_ast::Constant::Literal(lit) => {
+                Some(lit.literal.value.trim_matches('"').to_string())
+            }
+            _ => None,
+        }
+    })
+}
@@ -73,6 +73,12 @@ pub struct Token<'a> {
     pub kind: TokenKind,
     pub subkind: TokenSubkind,
     pub leading_newlines: u16,
+    /// Whitespace and regular comments between the previous token's trailing
+    /// trivia and this token.
+    pub leading_trivia: &'a str,
+    /// Whitespace and a regular comment following this token on the same
+    /// line, not including the newline.
+    pub trailing_trivia: &'a str,
 }
 
 impl<'a> Token<'a> {
@@ -87,6 +93,13 @@ impl<'a> Token<'a> {
             kind,
             subkind,
             leading_newlines,
+            leading_trivia: "",
+            trailing_trivia: "",
         }
     }
+
+    /// The token's text together with its trivia.
+    pub fn full_text(&self) -> String {
+        [self.leading_trivia, self.span.data, self.trailing_trivia].concat()
+    }
 }
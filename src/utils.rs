@@ -0,0 +1,98 @@
+//! Naming-case helpers, ported from fidlc's `utils.cc`.
+
+/// Splits an identifier in any case into lowercase words, e.g.
+/// `"URLLoader"` and `"url_loader"` both become `["url", "loader"]`. A
+/// leading `k` of a constant name like `kUrlLoader` is dropped.
+pub fn split_identifier_words(s: &str) -> Vec<String> {
+    let bytes = s.as_bytes();
+    let bytes = match bytes {
+        [b'k', second, ..] if second.is_ascii_uppercase() => &bytes[1..],
+        _ => bytes,
+    };
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut last_was_upper_or_begin = true;
+    for (i, &c) in bytes.iter().enumerate() {
+        if matches!(c, b'_' | b'-' | b'.') {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            last_was_upper_or_begin = true;
+            continue;
+        }
+        let next_is_lower = bytes.get(i + 1).is_some_and(u8::is_ascii_lowercase);
+        if c.is_ascii_uppercase() && (!last_was_upper_or_begin || next_is_lower) && !word.is_empty()
+        {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c.to_ascii_lowercase() as char);
+        last_was_upper_or_begin = c.is_ascii_uppercase();
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+/// Joins camel-case words, keeping a `_` between two numbers so that
+/// `android_8_0_0` becomes `Android8_0_0` rather than `Android800`.
+fn join_camel_case(words: impl Iterator<Item = String>) -> String {
+    let mut out = String::new();
+    for word in words {
+        let starts_with_digit = word.starts_with(|c: char| c.is_ascii_digit());
+        if starts_with_digit && out.ends_with(|c: char| c.is_ascii_digit()) {
+            out.push('_');
+        }
+        out.push_str(&word);
+    }
+    out
+}
+
+pub fn to_upper_camel_case(s: &str) -> String {
+    join_camel_case(split_identifier_words(s).iter().map(|w| capitalize(w)))
+}
+
+pub fn to_lower_camel_case(s: &str) -> String {
+    join_camel_case(
+        split_identifier_words(s)
+            .into_iter()
+            .enumerate()
+            .map(|(i, w)| if i == 0 { w } else { capitalize(&w) }),
+    )
+}
+
+pub fn to_upper_snake_case(s: &str) -> String {
+    split_identifier_words(s).join("_").to_ascii_uppercase()
+}
+
+pub fn to_lower_snake_case(s: &str) -> String {
+    split_identifier_words(s).join("_")
+}
+
+pub fn is_upper_camel_case(s: &str) -> bool {
+    !s.is_empty() && to_upper_camel_case(s) == s
+}
+
+pub fn is_lower_camel_case(s: &str) -> bool {
+    !s.is_empty() && to_lower_camel_case(s) == s
+}
+
+pub fn is_upper_snake_case(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_uppercase())
+        && s.bytes()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == b'_')
+}
+
+pub fn is_lower_snake_case(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_lowercase())
+        && s.bytes()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'_')
+}
@@ -274,6 +274,18 @@ impl Availability {
         self.state
     }
 
+    pub fn added(&self) -> Option<Version> {
+        self.added
+    }
+
+    pub fn deprecated(&self) -> Option<Version> {
+        self.deprecated
+    }
+
+    pub fn removed(&self) -> Option<Version> {
+        self.removed
+    }
+
     pub fn is_deprecated(&self) -> bool {
         assert!(self.state == AvailabilityState::Narrowed);
         self.deprecated.is_some()
@@ -480,11 +492,15 @@ impl Availability {
     }
 
     pub fn ending(&self) -> Ending {
-        assert!(self.state == AvailabilityState::Narrowed);
+        assert!(matches!(
+            self.state,
+            AvailabilityState::Inherited | AvailabilityState::Narrowed
+        ));
         self.ending.unwrap()
     }
 }
 
+#[derive(Clone)]
 pub struct VersionSelection {
     map: BTreeMap<Platform, BTreeSet<Version>>,
 }
@@ -517,6 +533,12 @@ impl VersionSelection {
         }
     }
 
+    /// Removes the versions selected for `platform`, so that others can be
+    /// inserted. Returns whether there were any.
+    pub fn remove(&mut self, platform: &Platform) -> bool {
+        self.map.remove(platform).is_some()
+    }
+
     pub fn lookup(&self, platform: &Platform) -> Version {
         if platform.is_unversioned() {
             Version::HEAD
@@ -531,6 +553,15 @@ impl VersionSelection {
         }
     }
 
+    /// The versions selected for `platform`, or just `HEAD` if there are
+    /// none.
+    pub fn lookup_all(&self, platform: &Platform) -> BTreeSet<Version> {
+        match self.map.get(platform) {
+            Some(versions) if !platform.is_unversioned() => versions.clone(),
+            _ => BTreeSet::from([Version::HEAD]),
+        }
+    }
+
     pub fn intersects(&self, platform: &Platform, set: &VersionSet) -> bool {
         if platform.is_unversioned() {
             set.contains(Version::HEAD)